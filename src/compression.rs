@@ -0,0 +1,171 @@
+use crate::{
+    effective::{effective_config, EffectiveSettings},
+    lint::{Config, Finding, LintConfig, LintRule, Severity},
+    names::DirectiveName,
+    proxy::APP_PASSES,
+    router::Router,
+    tls::is_tls,
+    types::Directive,
+    Structure,
+};
+
+/// Dynamic HTTPS responses compressed along with the cookies of the request, secrets in them leak
+/// to whoever can inject text and watch response sizes
+pub const BREACH: &str = "breach";
+
+/// Content types of responses likely to reflect input next to secrets, when `[breach] content_types` isn't set
+pub const DEFAULT_CONTENT_TYPES: &[&str] = &["text/html", "application/json", "application/xml", "text/plain"];
+
+/// The `breach` rule, with the content types it counts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreachRule {
+    /// Lowercased
+    pub content_types: Vec<String>,
+}
+
+impl Default for BreachRule {
+    fn default() -> Self {
+        Self { content_types: DEFAULT_CONTENT_TYPES.iter().map(|t| t.to_string()).collect() }
+    }
+}
+
+impl BreachRule {
+    /// Content types from `content_types` of the `[breach]` table, comma-separated
+    pub fn from_config(config: &LintConfig) -> Self {
+        match config.option(BREACH, "content_types") {
+            Some(types) => Self {
+                content_types: types
+                    .split(',')
+                    .map(|t| t.trim().to_ascii_lowercase())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+impl LintRule for BreachRule {
+    fn id(&self) -> &'static str {
+        BREACH
+    }
+
+    fn name(&self) -> &'static str {
+        "compression over TLS"
+    }
+
+    fn check<'c>(&self, config: &'c Config<'c>, router: &Router<'c>) -> Vec<Finding<'c>> {
+        check_breach(router, config.source, &self.content_types)
+    }
+}
+
+/// Locations of HTTPS servers passing to an app server with `gzip` or `brotli` on for one of `content_types`,
+/// unless the request cookies are kept from the app (`proxy_set_header Cookie ""`)
+pub fn check_breach<'a>(router: &Router<'a>, source: &str, content_types: &[String]) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    for scope in router.scopes().into_iter().filter(|scope| is_tls(scope.server.block) && !scope.locations.is_empty()) {
+        let Structure::Block { children, .. } = scope.block() else {
+            continue;
+        };
+        let Some(pass) = children.iter().find(|c| c.name().is_some_and(|name| APP_PASSES.contains(&name))) else {
+            continue;
+        };
+        let settings = effective_config(&router.contexts(scope.server, &scope.locations));
+        if strips_cookies(&settings) {
+            continue;
+        }
+        let compressions = [
+            (DirectiveName::Gzip, DirectiveName::GzipTypes),
+            (DirectiveName::Brotli, DirectiveName::BrotliTypes),
+        ];
+        for (enable, types) in compressions {
+            let compressed = compressed_types(&settings, enable, types, content_types);
+            if !compressed.is_empty() {
+                let message = format!(
+                    "`{enable} on` compresses {} responses of an app over HTTPS, secrets in them can leak through their size (BREACH)",
+                    compressed.iter().map(|t| format!("`{t}`")).collect::<Vec<_>>().join(", "),
+                );
+                findings.push(Finding::new(BREACH, Severity::Medium, pass, source, message));
+            }
+        }
+    }
+    findings
+}
+
+/// Which of `content_types` the compression is on for, `text/html` always is
+fn compressed_types<'t>(
+    settings: &EffectiveSettings,
+    enable: DirectiveName,
+    types: DirectiveName,
+    content_types: &'t [String],
+) -> Vec<&'t str> {
+    let enabled = settings.directives(enable).last().is_some_and(|directive| {
+        matches!(directive, Directive::Gzip { enabled: true } | Directive::Brotli { enabled: true })
+    });
+    if !enabled {
+        return Vec::new()
+    }
+    let listed = settings
+        .directives(types)
+        .flat_map(|directive| match directive {
+            Directive::GzipTypes { mime_types } | Directive::BrotliTypes { mime_types } => mime_types,
+            _ => Vec::new(),
+        })
+        .map(|t| t.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    content_types
+        .iter()
+        .filter(|t| *t == "text/html" || listed.iter().any(|listed| listed == "*" || listed == *t))
+        .map(String::as_str)
+        .collect()
+}
+
+/// `proxy_set_header Cookie ""` or `fastcgi_param HTTP_COOKIE ""`, responses can't depend on the cookies
+fn strips_cookies(settings: &EffectiveSettings) -> bool {
+    let empty = |name, header: &str| {
+        settings.statements(name).any(|statement| {
+            let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+            matches!(args.as_slice(), [_, name, value] if name.value().eq_ignore_ascii_case(header) && value.value().is_empty())
+        })
+    };
+    empty(DirectiveName::ProxySetHeader, "Cookie") || empty(DirectiveName::FastcgiParam, "HTTP_COOKIE")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CONFIG: &str = "http { gzip on; gzip_types application/json;\n\
+        server { listen 443 ssl;\n\
+        location /app { proxy_pass http://app; }\n\
+        location /anon { proxy_set_header Cookie \"\"; proxy_pass http://app; }\n\
+        location /static { root /srv; }\n\
+        location /php { gzip off; brotli on; brotli_types *; fastcgi_pass unix:/php.sock; }\n\
+        }\n\
+        server { listen 80; location / { proxy_pass http://app; } }\n\
+        }";
+
+    /// Lines and messages of the findings
+    fn findings(rule: &BreachRule) -> Vec<(usize, String)> {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        check_breach(&router, CONFIG, &rule.content_types).into_iter().map(|finding| (finding.span.unwrap().start.line, finding.message)).collect()
+    }
+
+    #[test]
+    fn compressed_app_responses_over_https() {
+        let message = |enable, types| format!("`{enable} on` compresses {types} responses of an app over HTTPS, secrets in them can leak through their size (BREACH)");
+        assert_eq!(
+            findings(&BreachRule::default()),
+            [
+                (3, message("gzip", "`text/html`, `application/json`")),
+                (6, message("brotli", "`text/html`, `application/json`, `application/xml`, `text/plain`")),
+            ]
+        );
+        let config = "[breach]\ncontent_types = \"Application/XML, \"".parse().unwrap();
+        let rule = BreachRule::from_config(&config);
+        assert_eq!(rule.content_types, ["application/xml"]);
+        assert_eq!(findings(&rule), [(6, message("brotli", "`application/xml`"))]);
+        assert_eq!(BreachRule::from_config(&LintConfig::default()), BreachRule::default());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{
+    effective::effective_config,
+    lint::{BuiltinRule, Check, Finding, Severity},
+    names::DirectiveName,
+    router::{Router, Scope},
+    types::{AddHeader, Condition, Directive},
+    Structure,
+};
+
+/// `Access-Control-Allow-Origin *` along with `Access-Control-Allow-Credentials true`
+pub const CORS_WILDCARD_CREDENTIALS: &str = "cors-wildcard-credentials";
+/// `add_header Access-Control-*` without `always`, left out of error responses
+pub const CORS_WITHOUT_ALWAYS: &str = "cors-without-always";
+/// Location sending CORS headers without answering preflight requests, when others of its server do
+pub const CORS_PREFLIGHT: &str = "cors-preflight";
+
+/// Prefix of the CORS response headers
+const CORS_PREFIX: &str = "access-control-";
+
+/// The rules of this module, for `lint::LintRunner`
+pub fn rules() -> Vec<BuiltinRule> {
+    vec![
+        BuiltinRule { id: CORS_WILDCARD_CREDENTIALS, name: "wildcard CORS origin with credentials", check: Check::Routed(check_cors) },
+        BuiltinRule { id: CORS_WITHOUT_ALWAYS, name: "CORS headers on success only", check: Check::Routed(check_cors) },
+        BuiltinRule { id: CORS_PREFLIGHT, name: "partial preflight handling", check: Check::Routed(check_cors) },
+    ]
+}
+
+/// CORS setup of an `http` server, or of one of its locations
+#[derive(Debug, Clone)]
+pub struct CorsSetup<'r, 'a> {
+    pub scope: Scope<'r, 'a>,
+    /// `add_header Access-Control-*` in effect, the ones of the innermost context defining any `add_header`
+    pub headers: Vec<(&'a Structure<'a>, AddHeader)>,
+    /// `if ($request_method = OPTIONS)` of the server or of one of the locations, the innermost one
+    pub preflight: Option<&'a Structure<'a>>,
+    /// `add_header Access-Control-*` of the preflight `if`
+    pub preflight_headers: Vec<(&'a Structure<'a>, AddHeader)>,
+}
+
+impl CorsSetup<'_, '_> {
+    /// Sends CORS headers, on regular or preflight responses
+    pub fn is_cors(&self) -> bool {
+        !self.headers.is_empty() || !self.preflight_headers.is_empty()
+    }
+}
+
+/// CORS setup of every server and of each of its locations, nested ones included
+pub fn cors_setups<'r, 'a>(router: &'r Router<'a>) -> Vec<CorsSetup<'r, 'a>> {
+    let mut setups = Vec::new();
+    for scope in router.scopes() {
+        let contexts = router.contexts(scope.server, &scope.locations);
+        let settings = effective_config(&contexts);
+        let headers = cors_headers(settings.statements(DirectiveName::AddHeader));
+        // `if` of the server run before location matching, the innermost one wins
+        let preflight = contexts.iter().rev().find_map(|context| preflight_if(context));
+        let preflight_headers = match preflight {
+            Some(Structure::Block { children, .. }) => cors_headers(children.iter()),
+            _ => Vec::new(),
+        };
+        setups.push(CorsSetup { scope, headers, preflight, preflight_headers });
+    }
+    setups
+}
+
+/// `add_header Access-Control-*` among the statements
+fn cors_headers<'a>(statements: impl Iterator<Item = &'a Structure<'a>>) -> Vec<(&'a Structure<'a>, AddHeader)> {
+    statements
+        .filter_map(|statement| match Directive::try_from(statement) {
+            Ok(Directive::AddHeader { name, value, always }) if name.to_ascii_lowercase().starts_with(CORS_PREFIX) => {
+                Some((statement, AddHeader { name, value, always }))
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// `if` block of a context holding for `OPTIONS` requests
+fn preflight_if<'a>(context: &'a Structure<'a>) -> Option<&'a Structure<'a>> {
+    let Structure::Block { children, .. } = context else {
+        return None
+    };
+    children.iter().filter(|child| matches!(child, Structure::Block { .. })).find(|child| {
+        match Directive::try_from(*child) {
+            Ok(Directive::If { condition: Condition::Compare { variable, value, negated: false } }) => {
+                variable == "request_method" && value.eq_ignore_ascii_case("OPTIONS")
+            },
+            Ok(Directive::If { condition: Condition::Match { variable, regex, negated: false } }) => {
+                variable == "request_method" && regex.is_match("OPTIONS")
+            },
+            _ => false,
+        }
+    })
+}
+
+/// Wildcard origins allowing credentials, CORS headers missing from error responses,
+/// and servers answering preflight requests in some of the locations sending CORS headers but not all
+pub fn check_cors<'a>(router: &Router<'a>, source: &str) -> Vec<Finding<'a>> {
+    let setups = cors_setups(router);
+    let mut findings = Vec::new();
+    // locations inheriting `add_header`s share their statements, each is reported once
+    let mut reported = Vec::<(&'static str, &Structure)>::new();
+    let mut report = |rule, severity, statement: &'a Structure<'a>, message: String| {
+        if !reported.iter().any(|(r, s)| *r == rule && std::ptr::eq(*s, statement)) {
+            reported.push((rule, statement));
+            findings.push(Finding::new(rule, severity, statement, source, message));
+        }
+    };
+    for setup in &setups {
+        for headers in [&setup.headers, &setup.preflight_headers] {
+            let header = |name: &str| headers.iter().find(|(_, h)| h.name.eq_ignore_ascii_case(name));
+            let origin = header("Access-Control-Allow-Origin").filter(|(_, h)| h.value == "*");
+            let credentials = header("Access-Control-Allow-Credentials").filter(|(_, h)| h.value.eq_ignore_ascii_case("true"));
+            if let (Some(_), Some((statement, _))) = (origin, credentials) {
+                let message = "`Access-Control-Allow-Origin *` with credentials, browsers reject the response; \
+                    reflecting `$http_origin` unchecked instead lets any site read it"
+                    .to_string();
+                report(CORS_WILDCARD_CREDENTIALS, Severity::Medium, statement, message);
+            }
+        }
+        // preflight responses are 204s or 200s, which `add_header` covers
+        for (statement, header) in setup.headers.iter().filter(|(_, header)| !header.always) {
+            let message = format!(
+                "`add_header {}` without `always` is only sent on successes and redirects, browsers hide error responses from the page",
+                header.name,
+            );
+            report(CORS_WITHOUT_ALWAYS, Severity::Low, statement, message);
+        }
+    }
+    for server in &router.servers.servers {
+        let cors = setups
+            .iter()
+            .filter(|setup| std::ptr::eq(setup.scope.server, server) && !setup.scope.locations.is_empty() && setup.is_cors())
+            .collect::<Vec<_>>();
+        if !cors.iter().any(|setup| setup.preflight.is_some()) {
+            continue;
+        }
+        for setup in cors.iter().filter(|setup| setup.preflight.is_none()) {
+            let block = setup.scope.block();
+            let (Structure::Statement { args } | Structure::Block { args, .. }) = block;
+            let path = args.iter().skip(1).map(|arg| arg.value()).collect::<Vec<_>>().join(" ");
+            let message = format!(
+                "`location {path}` sends CORS headers but doesn't answer `OPTIONS` preflight requests like other locations of its server",
+            );
+            report(CORS_PREFLIGHT, Severity::Medium, block, message);
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CONFIG: &str = "http {\n\
+        server { add_header Access-Control-Allow-Origin * always; add_header Access-Control-Allow-Credentials true always;\n\
+        location /a { root /srv; } }\n\
+        server { listen 81;\n\
+        location /b { add_header Access-Control-Allow-Origin https://app.example.com; if ($request_method = OPTIONS) { add_header Access-Control-Allow-Methods \"GET, POST\" always; return 204; } }\n\
+        location /c { add_header Access-Control-Allow-Origin https://app.example.com always; }\n\
+        location /d { root /srv; }\n\
+        } }";
+
+    #[test]
+    fn cors_setups_of_servers_and_locations() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        let setups = cors_setups(&router)
+            .iter()
+            .map(|setup| (setup.scope.locations.len(), setup.headers.len(), setup.preflight.is_some(), setup.preflight_headers.len(), setup.is_cors()))
+            .collect::<Vec<_>>();
+        assert_eq!(setups, [(0, 2, false, 0, true), (1, 2, false, 0, true), (0, 0, false, 0, false), (1, 1, true, 1, true), (1, 1, false, 0, true), (1, 0, false, 0, false)]);
+    }
+
+    #[test]
+    fn cors_findings() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        let findings = check_cors(&router, CONFIG).iter().map(|finding| (finding.rule, finding.span.unwrap().start.line)).collect::<Vec<_>>();
+        assert_eq!(findings, [(CORS_WILDCARD_CREDENTIALS, 2), (CORS_WITHOUT_ALWAYS, 5), (CORS_PREFLIGHT, 6)]);
+    }
+}
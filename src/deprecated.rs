@@ -0,0 +1,260 @@
+use std::fmt::Display;
+
+use crate::{
+    names::DirectiveName,
+    validate::{Context, Diagnostic, Problem, Version},
+    Structure, Token,
+};
+
+/// `listen ... http2` gave way to the `http2` directive
+const HTTP2_DIRECTIVE: Version = Version::new(1, 25, 1);
+
+/// Syntax nginx deprecated or dropped, that `modernize` rewrites
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Deprecation {
+    /// `ssl on;`, the `ssl` parameter of `listen` replaces it, `ssl off;` is just dropped
+    SslDirective { on: bool },
+    /// `listen 443 ssl http2;`, `http2 on;` replaces it from 1.25.1
+    ListenHttp2,
+    /// `listen 443 ssl spdy;`, SPDY gave way to HTTP/2 in 1.9.5
+    ListenSpdy,
+    /// `spdy_*` directives, renamed to their `http2_*` counterpart when there is one
+    SpdyDirective { name: DirectiveName, replacement: Option<DirectiveName> },
+    /// `SSLv2` and `SSLv3` in `ssl_protocols` or `proxy_ssl_protocols`, broken and unsupported by OpenSSL
+    InsecureProtocols { protocols: Vec<String> },
+}
+
+impl Display for Deprecation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SslDirective { on: true } => write!(f, "`ssl on` is deprecated, use the `ssl` parameter of `listen`"),
+            Self::SslDirective { on: false } => write!(f, "`ssl off` is deprecated and does nothing"),
+            Self::ListenHttp2 => write!(f, "the `http2` parameter of `listen` is deprecated, use `http2 on`"),
+            Self::ListenSpdy => write!(f, "the `spdy` parameter of `listen` is gone, use HTTP/2"),
+            Self::SpdyDirective { replacement: Some(replacement), .. } => write!(f, "SPDY is gone, use `{replacement}`"),
+            Self::SpdyDirective { replacement: None, .. } => write!(f, "SPDY is gone, the directive has no HTTP/2 counterpart"),
+            Self::InsecureProtocols { protocols } => write!(f, "{} are insecure and unsupported", protocols.join(", ")),
+        }
+    }
+}
+
+/// Deprecated syntax of a config deployed to the `target` nginx version
+pub fn deprecations<'a>(root: &'a Structure<'a>, target: Version) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    find_deprecations(root, Context::Main, target, &mut diagnostics);
+    diagnostics
+}
+
+fn find_deprecations<'a>(block: &'a Structure<'a>, context: Context, target: Version, diagnostics: &mut Vec<Diagnostic<'a>>) {
+    let Structure::Block { children, .. } = block else {
+        return
+    };
+    for child in children {
+        let Some(directive) = child.name() else {
+            continue;
+        };
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = child;
+        for deprecation in statement_deprecations(directive, args, context, target) {
+            diagnostics.push(Diagnostic::new(args[0].value(), child, Problem::Deprecated(deprecation)));
+        }
+        if let (Structure::Block { .. }, Some(inner)) = (child, context.enter(directive)) {
+            find_deprecations(child, inner, target, diagnostics);
+        }
+    }
+}
+
+fn statement_deprecations(directive: DirectiveName, args: &[Token], context: Context, target: Version) -> Vec<Deprecation> {
+    let values = || args.iter().skip(1).map(Token::value);
+    match directive {
+        DirectiveName::Ssl => vec![Deprecation::SslDirective { on: values().next() == Some("on") }],
+        DirectiveName::Listen if context == Context::Server => {
+            let mut deprecations = Vec::new();
+            if values().any(|v| v == "http2") && target >= HTTP2_DIRECTIVE {
+                deprecations.push(Deprecation::ListenHttp2);
+            }
+            if values().any(|v| v == "spdy") {
+                deprecations.push(Deprecation::ListenSpdy);
+            }
+            deprecations
+        },
+        DirectiveName::SslProtocols | DirectiveName::ProxySslProtocols => {
+            let protocols = values().filter(|v| matches!(*v, "SSLv2" | "SSLv3")).map(String::from).collect::<Vec<_>>();
+            match protocols.is_empty() {
+                true => Vec::new(),
+                false => vec![Deprecation::InsecureProtocols { protocols }],
+            }
+        },
+        name if name.as_str().starts_with("spdy_") => {
+            vec![Deprecation::SpdyDirective { name, replacement: spdy_replacement(name) }]
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// The `http2_*` directive taking over a `spdy_*` one
+fn spdy_replacement(name: DirectiveName) -> Option<DirectiveName> {
+    match name {
+        DirectiveName::SpdyChunkSize => Some(DirectiveName::Http2ChunkSize),
+        DirectiveName::SpdyMaxConcurrentStreams => Some(DirectiveName::Http2MaxConcurrentStreams),
+        DirectiveName::SpdyRecvBufferSize => Some(DirectiveName::Http2RecvBufferSize),
+        _ => None,
+    }
+}
+
+/// Rewrites the deprecated syntax of a config deployed to the `target` nginx version
+/// into its modern equivalent, returning what it changed
+///
+/// - `ssl on;` is dropped and the `listen`s of its server get the `ssl` parameter, those of every server
+///   under it when it's set in `http` or `mail`, but for the servers with their own `ssl off;`
+/// - `listen ... http2` loses the parameter and its server gets `http2 on;`, from 1.25.1
+/// - `listen ... spdy` becomes `http2` (or `http2 on;` from 1.25.1)
+/// - `spdy_*` directives are renamed to their `http2_*` counterpart, or dropped
+/// - `SSLv2` and `SSLv3` are dropped from `ssl_protocols`, leaving `TLSv1.2 TLSv1.3` when nothing remains
+///
+/// `format::print` writes the tree back out
+pub fn modernize(root: &mut Structure, target: Version) -> Vec<Deprecation> {
+    let mut fixed = Vec::new();
+    modernize_block(root, Context::Main, target, false, &mut fixed);
+    fixed
+}
+
+/// `inherited_ssl` is whether an enclosing block set `ssl on;`
+fn modernize_block(block: &mut Structure, context: Context, target: Version, inherited_ssl: bool, fixed: &mut Vec<Deprecation>) {
+    let Structure::Block { children, .. } = block else {
+        return
+    };
+    // the last `ssl` of the block wins, and is inherited by the blocks inside
+    let ssl_on = children
+        .iter()
+        .rfind(|child| child.name() == Some(DirectiveName::Ssl))
+        .map_or(inherited_ssl, |ssl| {
+            let (Structure::Statement { args } | Structure::Block { args, .. }) = ssl;
+            args.get(1).is_some_and(|on| on.value() == "on")
+        });
+    let mut http2_on = false;
+    let mut kept = Vec::with_capacity(children.len());
+    for mut child in std::mem::take(children) {
+        let Some(directive) = child.name() else {
+            kept.push(child);
+            continue;
+        };
+        let deprecations = statement_deprecations(directive, child.args(), context, target);
+        let mut keep = true;
+        for deprecation in &deprecations {
+            match deprecation {
+                Deprecation::SslDirective { .. } => keep = false,
+                Deprecation::ListenHttp2 => {
+                    child.args().retain(|arg| arg.value() != "http2");
+                    http2_on = true;
+                },
+                Deprecation::ListenSpdy if target >= HTTP2_DIRECTIVE => {
+                    child.args().retain(|arg| arg.value() != "spdy");
+                    http2_on = true;
+                },
+                Deprecation::ListenSpdy => {
+                    let args = child.args();
+                    let has_http2 = args.iter().any(|arg| arg.value() == "http2");
+                    args.retain(|arg| arg.value() != "spdy");
+                    if !has_http2 {
+                        args.push(Token::Word("http2"));
+                    }
+                },
+                Deprecation::SpdyDirective { replacement: Some(replacement), .. } => {
+                    child.args()[0] = Token::Word(replacement.as_str());
+                },
+                Deprecation::SpdyDirective { replacement: None, .. } => keep = false,
+                Deprecation::InsecureProtocols { .. } => {
+                    let args = child.args();
+                    args.retain(|arg| !matches!(arg.value(), "SSLv2" | "SSLv3"));
+                    if args.len() == 1 {
+                        args.extend([Token::Word("TLSv1.2"), Token::Word("TLSv1.3")]);
+                    }
+                },
+            }
+        }
+        fixed.extend(deprecations);
+        if let (Structure::Block { .. }, Some(inner)) = (&child, context.enter(directive)) {
+            modernize_block(&mut child, inner, target, ssl_on, fixed);
+        }
+        if keep {
+            kept.push(child);
+        }
+    }
+    *children = kept;
+
+    // only servers listen, `http` and `mail` hand the setting down to theirs
+    let ssl_on = ssl_on && matches!(context, Context::Server | Context::MailServer);
+    if ssl_on && context == Context::Server && !children.iter().any(|child| child.name() == Some(DirectiveName::Listen)) {
+        // the implicit `listen *:80` turned to TLS
+        children.insert(0, Structure::Statement { args: vec![Token::Word("listen"), Token::Word("80")] });
+    }
+    if ssl_on {
+        for listen in children.iter_mut().filter(|child| child.name() == Some(DirectiveName::Listen)) {
+            if !listen.args().iter().any(|arg| arg.value() == "ssl") {
+                listen.args().push(Token::Word("ssl"));
+            }
+        }
+    }
+    if http2_on && !children.iter().any(|child| child.name() == Some(DirectiveName::Http2)) {
+        let at = children.iter().rposition(|child| child.name() == Some(DirectiveName::Listen)).map_or(0, |at| at + 1);
+        children.insert(at, Structure::Statement { args: vec![Token::Word("http2"), Token::Word("on")] });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{print, FormatOptions};
+
+    fn modernized(source: &str, target: Version) -> String {
+        let mut root = Structure::parse(source).unwrap();
+        modernize(&mut root, target);
+        print(&root, FormatOptions::default())
+    }
+
+    #[test]
+    fn ssl_on_moves_to_listen() {
+        assert_eq!(
+            modernized("http { server { ssl on; listen 443; listen [::]:443; } }", Version::new(1, 24, 0)),
+            "http {\n    server {\n        listen 443 ssl;\n        listen [::]:443 ssl;\n    }\n}\n",
+        );
+        assert_eq!(
+            modernized("http { server { ssl on; server_name a; } }", Version::new(1, 24, 0)),
+            "http {\n    server {\n        listen 80 ssl;\n        server_name a;\n    }\n}\n",
+        );
+    }
+
+    #[test]
+    fn inherited_ssl_on_moves_to_the_servers() {
+        assert_eq!(
+            modernized("http { ssl on; server { listen 443; } server { ssl off; listen 80; } }", Version::new(1, 24, 0)),
+            "http {\n    server {\n        listen 443 ssl;\n    }\n    server {\n        listen 80;\n    }\n}\n",
+        );
+        assert_eq!(
+            modernized("mail { ssl on; server { listen 993; protocol imap; } }", Version::new(1, 24, 0)),
+            "mail {\n    server {\n        listen 993 ssl;\n        protocol imap;\n    }\n}\n",
+        );
+    }
+
+    #[test]
+    fn http2_and_spdy() {
+        assert_eq!(
+            modernized("http { server { listen 443 ssl http2; spdy_chunk_size 8k; } }", Version::new(1, 25, 1)),
+            "http {\n    server {\n        listen 443 ssl;\n        http2 on;\n        http2_chunk_size 8k;\n    }\n}\n",
+        );
+        assert_eq!(
+            modernized("http { server { listen 443 ssl spdy; ssl_protocols SSLv3; } }", Version::new(1, 24, 0)),
+            "http {\n    server {\n        listen 443 ssl http2;\n        ssl_protocols TLSv1.2 TLSv1.3;\n    }\n}\n",
+        );
+    }
+
+    #[test]
+    fn printed_trees_parse_back() {
+        let source = "http { server { location / { if ($request_method !~ ^(GET|POST)$) { return 405; } \
+                      content_by_lua_block { ngx.say(\"}\") } } } }";
+        let root = Structure::parse(source).unwrap();
+        let printed = print(&root, FormatOptions::default());
+        crate::format::check_round_trip(source, &printed).unwrap();
+        assert!(printed.contains("if ($request_method !~ ^(GET|POST)$) {\n"));
+    }
+}
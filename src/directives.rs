@@ -0,0 +1,614 @@
+use crate::{
+    names::DirectiveName,
+    validate::{ArgKind, Contexts, Obsolete, Signature, Version},
+};
+
+const ON_OFF_ALWAYS: &[&str] = &["on", "off", "always"];
+const ON_CLEAN_OFF: &[&str] = &["on", "clean", "off"];
+const TLS_PROTOCOLS: &[&str] = &["SSLv2", "SSLv3", "TLSv1", "TLSv1.1", "TLSv1.2", "TLSv1.3"];
+const HTTP_VERSIONS: &[&str] = &["1.0", "1.1"];
+const GZIP_PROXIED: &[&str] = &[
+    "off", "expired", "no-cache", "no-store", "private", "no_last_modified", "no_etag", "auth", "any",
+];
+const IF_MODIFIED_SINCE: &[&str] = &["off", "exact", "before"];
+const LOG_LEVELS: &[&str] = &["info", "notice", "warn", "error"];
+const SATISFY: &[&str] = &["all", "any"];
+const SSL_OCSP: &[&str] = &["on", "off", "leaf"];
+const SSL_VERIFY_CLIENT: &[&str] = &["on", "off", "optional", "optional_no_ca"];
+
+impl DirectiveName {
+    /// Contexts the directive is allowed in, across the modules defining it (`proxy_pass` is both
+    /// an http and a stream directive), `None` for directives whose contexts aren't known
+    ///
+    /// Generated from the nginx, NGINX Plus, njs, lua-nginx-module, ngx_brotli and geoip2 docs
+    pub fn contexts(&self) -> Option<Contexts> {
+        use DirectiveName::*;
+        match self {
+            AbsoluteRedirect | AddAfterBody | AddBeforeBody | AdditionTypes | Aio | AioWrite |
+            AncientBrowser | AncientBrowserValue | AuthDelay | AuthRequest | AuthRequestSet | Autoindex |
+            AutoindexExactSize | AutoindexFormat | AutoindexLocaltime | BrotliBuffers | BrotliCompLevel |
+            BrotliMinLength | BrotliStatic | BrotliTypes | BrotliWindow | CharsetTypes |
+            ChunkedTransferEncoding | ClientBodyBufferSize | ClientBodyInFileOnly | ClientBodyInSingleBuffer |
+            ClientBodyTempPath | ClientBodyTimeout | ClientMaxBodySize | CreateFullPutPath | DavAccess |
+            DavMethods | DefaultType | Directio | DirectioAlignment | DisableSymlinks | EarlyHints | Etag |
+            F4fBufferSize | FastcgiBind | FastcgiBufferSize | FastcgiBuffering | FastcgiBuffers |
+            FastcgiBusyBuffersSize | FastcgiCache | FastcgiCacheBackgroundUpdate | FastcgiCacheBypass |
+            FastcgiCacheKey | FastcgiCacheLock | FastcgiCacheLockAge | FastcgiCacheLockTimeout |
+            FastcgiCacheMaxRangeOffset | FastcgiCacheMethods | FastcgiCacheMinUses | FastcgiCachePurge |
+            FastcgiCacheRevalidate | FastcgiCacheUseStale | FastcgiCacheValid | FastcgiCatchStderr |
+            FastcgiConnectTimeout | FastcgiForceRanges | FastcgiHideHeader | FastcgiIgnoreClientAbort |
+            FastcgiIgnoreHeaders | FastcgiIndex | FastcgiInterceptErrors | FastcgiKeepConn |
+            FastcgiLimitRate | FastcgiMaxTempFileSize | FastcgiNextUpstream | FastcgiNextUpstreamTimeout |
+            FastcgiNextUpstreamTries | FastcgiNoCache | FastcgiParam | FastcgiPassHeader |
+            FastcgiPassRequestBody | FastcgiPassRequestHeaders | FastcgiReadTimeout |
+            FastcgiRequestBuffering | FastcgiSendLowat | FastcgiSendTimeout | FastcgiSocketKeepalive |
+            FastcgiStore | FastcgiStoreAccess | FastcgiTempFileWriteSize | FastcgiTempPath | GrpcBind |
+            GrpcBufferSize | GrpcConnectTimeout | GrpcHideHeader | GrpcIgnoreHeaders | GrpcInterceptErrors |
+            GrpcNextUpstream | GrpcNextUpstreamTimeout | GrpcNextUpstreamTries | GrpcPassHeader |
+            GrpcReadTimeout | GrpcSendTimeout | GrpcSetHeader | GrpcSocketKeepalive | GrpcSslCertificate |
+            GrpcSslCertificateKey | GrpcSslCiphers | GrpcSslConfCommand | GrpcSslCrl | GrpcSslName |
+            GrpcSslPasswordFile | GrpcSslProtocols | GrpcSslServerName | GrpcSslSessionReuse |
+            GrpcSslTrustedCertificate | GrpcSslVerify | GrpcSslVerifyDepth | Gunzip | GunzipBuffers |
+            GzipBuffers | GzipCompLevel | GzipDisable | GzipHash | GzipHttpVersion | GzipMinLength |
+            GzipNoBuffer | GzipProxied | GzipStatic | GzipTypes | GzipVary | GzipWindow | HlsBuffers |
+            HlsForwardArgs | HlsFragment | HlsMp4BufferSize | HlsMp4MaxBufferSize | Http2ChunkSize |
+            Http2Push | Http2PushPreload | Http3Push | Http3PushPreload | IfModifiedSince |
+            ImageFilterBuffer | ImageFilterInterlace | ImageFilterJpegQuality | ImageFilterSharpen |
+            ImageFilterTransparency | ImageFilterWebpQuality | Index | JsEngine | JsSet | JsVar |
+            KeepaliveDisable | LimitConnStatus | LimitReq | LimitReqDryRun | LimitReqLogLevel |
+            LimitReqStatus | LingeringClose | LingeringTime | LingeringTimeout | LogNotFound | LogSubrequest |
+            LuaSocketBufferSize | LuaSocketConnectTimeout | LuaSocketKeepaliveTimeout | LuaSocketLogErrors |
+            LuaSocketPoolSize | LuaSocketReadTimeout | LuaSocketSendTimeout | LuaSslCiphers |
+            LuaSslProtocols | LuaSslTrustedCertificate | LuaSslVerifyDepth | MaxRanges | MemcachedBind |
+            MemcachedBufferSize | MemcachedConnectTimeout | MemcachedForceRanges | MemcachedGzipFlag |
+            MemcachedNextUpstream | MemcachedNextUpstreamTimeout | MemcachedNextUpstreamTries |
+            MemcachedReadTimeout | MemcachedSendTimeout | MemcachedSocketKeepalive | MinDeleteDepth | Mirror |
+            MirrorRequestBody | ModernBrowser | ModernBrowserValue | Mp4BufferSize | Mp4LimitRate |
+            Mp4LimitRateAfter | Mp4MaxBufferSize | MsiePadding | MsieRefresh | OpenFileCache |
+            OpenFileCacheErrors | OpenFileCacheEvents | OpenFileCacheMinUses | OpenFileCacheRetest |
+            OpenFileCacheValid | OutputBuffers | PortInRedirect | PostAction | PostponeGzipping |
+            PostponeOutput | ProxyBuffering | ProxyBuffers | ProxyBusyBuffersSize | ProxyCache |
+            ProxyCacheBackgroundUpdate | ProxyCacheBypass | ProxyCacheConvertHead | ProxyCacheKey |
+            ProxyCacheLock | ProxyCacheLockAge | ProxyCacheLockTimeout | ProxyCacheMaxRangeOffset |
+            ProxyCacheMethods | ProxyCacheMinUses | ProxyCachePurge | ProxyCacheRevalidate |
+            ProxyCacheUseStale | ProxyCacheValid | ProxyCookieDomain | ProxyCookieFlags | ProxyCookiePath |
+            ProxyForceRanges | ProxyHeadersHashBucketSize | ProxyHeadersHashMaxSize | ProxyHideHeader |
+            ProxyHttpVersion | ProxyIgnoreClientAbort | ProxyIgnoreHeaders | ProxyInterceptErrors |
+            ProxyLimitRate | ProxyMaxTempFileSize | ProxyMethod | ProxyNoCache | ProxyPassHeader |
+            ProxyPassRequestBody | ProxyPassRequestHeaders | ProxyReadTimeout | ProxyRedirect |
+            ProxyRequestBuffering | ProxySendLowat | ProxySendTimeout | ProxySetBody | ProxySetHeader |
+            ProxyStore | ProxyStoreAccess | ProxyTempFileWriteSize | ProxyTempPath | ReadAhead |
+            RealIpHeader | RealIpRecursive | RecursiveErrorPages | ResetTimedoutConnection | Satisfy |
+            SatisfyAny | ScgiBind | ScgiBufferSize | ScgiBuffering | ScgiBuffers | ScgiBusyBuffersSize |
+            ScgiCache | ScgiCacheBackgroundUpdate | ScgiCacheBypass | ScgiCacheKey | ScgiCacheLock |
+            ScgiCacheLockAge | ScgiCacheLockTimeout | ScgiCacheMaxRangeOffset | ScgiCacheMethods |
+            ScgiCacheMinUses | ScgiCachePurge | ScgiCacheRevalidate | ScgiCacheUseStale | ScgiCacheValid |
+            ScgiConnectTimeout | ScgiForceRanges | ScgiHideHeader | ScgiIgnoreClientAbort |
+            ScgiIgnoreHeaders | ScgiInterceptErrors | ScgiLimitRate | ScgiMaxTempFileSize | ScgiNextUpstream |
+            ScgiNextUpstreamTimeout | ScgiNextUpstreamTries | ScgiNoCache | ScgiParam | ScgiPassHeader |
+            ScgiPassRequestBody | ScgiPassRequestHeaders | ScgiReadTimeout | ScgiRequestBuffering |
+            ScgiSendTimeout | ScgiSocketKeepalive | ScgiStore | ScgiStoreAccess | ScgiTempFileWriteSize |
+            ScgiTempPath | SecureLink | SecureLinkMd5 | SendLowat | SendTimeout | SendfileMaxChunk |
+            ServerNameInRedirect | ServerTokens | SessionLog | Slice | SsiIgnoreRecycledBuffers |
+            SsiLastModified | SsiMinFileChunk | SsiSilentErrors | SsiTypes | SsiValueLength | StatusFormat |
+            SubFilter | SubFilterLastModified | SubFilterOnce | SubFilterTypes | SubrequestOutputBufferSize |
+            TcpNopush | Types | TypesHashBucketSize | TypesHashMaxSize | Userid | UseridDomain |
+            UseridExpires | UseridMark | UseridName | UseridP3p | UseridPath | UseridService | UwsgiBind |
+            UwsgiBufferSize | UwsgiBuffering | UwsgiBuffers | UwsgiBusyBuffersSize | UwsgiCache |
+            UwsgiCacheBackgroundUpdate | UwsgiCacheBypass | UwsgiCacheKey | UwsgiCacheLock |
+            UwsgiCacheLockAge | UwsgiCacheLockTimeout | UwsgiCacheMaxRangeOffset | UwsgiCacheMethods |
+            UwsgiCacheMinUses | UwsgiCachePurge | UwsgiCacheRevalidate | UwsgiCacheUseStale |
+            UwsgiCacheValid | UwsgiConnectTimeout | UwsgiForceRanges | UwsgiHideHeader |
+            UwsgiIgnoreClientAbort | UwsgiIgnoreHeaders | UwsgiInterceptErrors | UwsgiLimitRate |
+            UwsgiMaxTempFileSize | UwsgiModifier1 | UwsgiModifier2 | UwsgiNextUpstream |
+            UwsgiNextUpstreamTimeout | UwsgiNextUpstreamTries | UwsgiNoCache | UwsgiParam | UwsgiPassHeader |
+            UwsgiPassRequestBody | UwsgiPassRequestHeaders | UwsgiReadTimeout | UwsgiRequestBuffering |
+            UwsgiSendTimeout | UwsgiSocketKeepalive | UwsgiSslCertificate | UwsgiSslCertificateKey |
+            UwsgiSslCiphers | UwsgiSslConfCommand | UwsgiSslCrl | UwsgiSslName | UwsgiSslPasswordFile |
+            UwsgiSslProtocols | UwsgiSslServerName | UwsgiSslSessionReuse | UwsgiSslTrustedCertificate |
+            UwsgiSslVerify | UwsgiSslVerifyDepth | UwsgiStore | UwsgiStoreAccess | UwsgiString |
+            UwsgiTempFileWriteSize | UwsgiTempPath | XmlEntities | XsltLastModified | XsltParam |
+            XsltStringParam | XsltTypes => Some(Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION),
+            AcceptMutex | AcceptMutexDelay | AcceptexRead | Connections | DebugConnection | DevpollChanges |
+            DevpollEvents | EpollEvents | EventportEvents | IocpThreads | KqueueChanges | KqueueEvents |
+            MultiAccept | PostAcceptex | RtsigOverflowEvents | RtsigOverflowTest | RtsigOverflowThreshold |
+            RtsigSigno | Use | WorkerAioRequests | WorkerConnections => Some(Contexts::EVENTS),
+            AccessByLua | AccessByLuaBlock | AccessByLuaFile | AddHeader | AddTrailer | BodyFilterByLua |
+            BodyFilterByLuaBlock | BodyFilterByLuaFile | Brotli | Charset | ErrorPage | Expires | Gzip |
+            HeaderFilterByLua | HeaderFilterByLuaBlock | HeaderFilterByLuaFile | LimitRate | LimitRateAfter |
+            LogByLua | LogByLuaBlock | LogByLuaFile | LuaCodeCache | LuaNeedRequestBody | OverrideCharset |
+            RewriteByLua | RewriteByLuaBlock | RewriteByLuaFile | Root | Sendfile | SourceCharset | Ssi => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::IF_IN_LOCATION),
+            AccessLog => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::IF_IN_LOCATION |
+                Contexts::LIMIT_EXCEPT | Contexts::STREAM | Contexts::STREAM_SERVER),
+            Alias | Api | Degrade | EmptyGif | F4f | FastcgiSplitPathInfo | Flv | Hls | ImageFilter |
+            Internal | LimitExcept | Mp4 | MysqlTest | RandomIndex | SecureLinkSecret | Status |
+            UpstreamConf | XsltStylesheet => Some(Contexts::LOCATION),
+            Allow | Deny => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::LIMIT_EXCEPT |
+                Contexts::STREAM | Contexts::STREAM_SERVER),
+            AuthBasic | AuthBasicUserFile | AuthJwt | AuthJwtKeyCache | AuthJwtKeyFile | AuthJwtKeyRequest |
+            AuthJwtLeeway | AuthJwtRequire | AuthJwtType => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::LIMIT_EXCEPT),
+            AuthHttp | AuthHttpHeader | AuthHttpPassClientCert | AuthHttpTimeout | Imap | ImapAuth |
+            ImapCapabilities | ImapClientBuffer | MaxErrors | Pop3Auth | Pop3Capabilities | Proxy |
+            ProxyBuffer | ProxyPassErrorMessage | ProxySmtpAuth | SmtpAuth | SmtpCapabilities |
+            SmtpClientBuffer | SmtpGreetingDelay | Starttls | Timeout | Xclient => Some(
+                Contexts::MAIL | Contexts::MAIL_SERVER),
+            AuthJwtClaimSet | AuthJwtHeaderSet | CharsetMap | Degradation | ExitWorkerByLuaBlock |
+            ExitWorkerByLuaFile | FastcgiCachePath | Geoip2Proxy | Geoip2ProxyRecursive | GeoipProxy |
+            GeoipProxyRecursive | Http2RecvBufferSize | InitByLua | InitByLuaBlock | InitByLuaFile |
+            InitWorkerByLua | InitWorkerByLuaBlock | InitWorkerByLuaFile | JsInclude | LimitReqZone |
+            LimitZone | LuaMaxPendingTimers | LuaMaxRunningTimers | LuaPackageCpath | LuaPackagePath |
+            LuaSharedDict | PerlModules | PerlRequire | PerlSet | ProxyCachePath | ScgiCachePath |
+            SessionLogFormat | SessionLogZone | SslSessionFetchByLuaBlock | SslSessionFetchByLuaFile |
+            SslSessionStoreByLuaBlock | SslSessionStoreByLuaFile | UwsgiCachePath => Some(Contexts::HTTP),
+            BalancerByLuaBlock | BalancerByLuaFile | IpHash | Keepalive | Ntlm | Queue | Sticky |
+            StickyCookieInsert => Some(Contexts::UPSTREAM),
+            Break | Return | Rewrite | Set | SetByLua | SetByLuaBlock | SetByLuaFile => Some(
+                Contexts::SERVER | Contexts::LOCATION | Contexts::IF_IN_SERVER | Contexts::IF_IN_LOCATION),
+            ClientHeaderBufferSize | ClientHeaderTimeout | ConnectionPoolSize | Http2 | Http2BodyPrereadSize |
+            Http2IdleTimeout | Http2MaxConcurrentStreams | Http2MaxFieldSize | Http2MaxHeaderSize |
+            Http2MaxRequests | Http2RecvTimeout | Http3 | Http3Hq | Http3MaxConcurrentPushes |
+            Http3MaxConcurrentStreams | Http3StreamBufferSize | IgnoreInvalidHeaders |
+            LargeClientHeaderBuffers | MergeSlashes | OptimizeServerNames | QuicActiveConnectionIdLimit |
+            QuicGso | QuicHostKey | QuicMtu | QuicRetry | RequestPoolSize | ServerRewriteByLuaBlock |
+            ServerRewriteByLuaFile | SpdyChunkSize | SpdyHeadersComp | SpdyKeepaliveTimeout |
+            SpdyMaxConcurrentStreams | SpdyPoolSize | SpdyRecvBufferSize | SpdyRecvTimeout |
+            SpdyStreamsIndexSize | SslBufferSize | SslClientHelloByLuaBlock | SslClientHelloByLuaFile |
+            SslEarlyData | SslOcsp | SslOcspCache | SslOcspResponder | SslRejectHandshake | SslStapling |
+            SslStaplingFile | SslStaplingResponder | SslStaplingVerify | UnderscoresInHeaders => Some(
+                Contexts::HTTP | Contexts::SERVER),
+            ContentByLua | ContentByLuaBlock | ContentByLuaFile | FastcgiPass | GrpcPass | MemcachedPass |
+            ScgiPass | UwsgiPass => Some(Contexts::LOCATION | Contexts::IF_IN_LOCATION),
+            Daemon | DebugPoints | Env | Events | GooglePerftoolsProfiles | Http | LoadModule | LockFile |
+            Mail | MasterProcess | Mgmt | PcreJit | Pid | QuicBpf | SslEngine | Stream | ThreadPool |
+            ThreadStackSize | TimerResolution | User | WorkerCpuAffinity | WorkerPriority | WorkerProcesses |
+            WorkerRlimitCore | WorkerRlimitNofile | WorkerRlimitSigpending | WorkerShutdownTimeout |
+            WorkerThreads | WorkingDirectory => Some(Contexts::MAIN),
+            ErrorLog => Some(
+                Contexts::MAIN | Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::MAIL |
+                Contexts::MAIL_SERVER | Contexts::STREAM | Contexts::STREAM_SERVER),
+            Geo | Geoip2 | GeoipCity | GeoipCountry | GeoipOrg | JsPath | JsSharedDictZone | Keyval |
+            KeyvalZone | LimitConnZone | LogFormat | Map | MapHashBucketSize | MapHashMaxSize | Match |
+            ServerNamesHashBucketSize | ServerNamesHashMaxSize | SplitClients | Upstream |
+            VariablesHashBucketSize | VariablesHashMaxSize => Some(Contexts::HTTP | Contexts::STREAM),
+            Hash | LeastConn | LeastTime | Random | State | Zone => Some(
+                Contexts::UPSTREAM | Contexts::STREAM_UPSTREAM),
+            HealthCheck | JsPeriodic => Some(Contexts::LOCATION | Contexts::STREAM_SERVER),
+            HealthCheckTimeout | JsAccess | JsFilter | JsPreread | PrereadBufferSize | PrereadTimeout |
+            ProxyDownloadRate | ProxyHalfClose | ProxyProtocolTimeout | ProxyRequests | ProxyResponses |
+            ProxySessionDrop | ProxySsl | ProxyUploadRate | SslAlpn | SslHandshakeTimeout | SslPreread |
+            ZoneSyncBuffers | ZoneSyncConnectRetryInterval | ZoneSyncConnectTimeout | ZoneSyncInterval |
+            ZoneSyncRecvBufferSize | ZoneSyncServer | ZoneSyncSsl | ZoneSyncSslCertificate |
+            ZoneSyncSslCertificateKey | ZoneSyncSslCiphers | ZoneSyncSslConfCommand | ZoneSyncSslCrl |
+            ZoneSyncSslName | ZoneSyncSslPasswordFile | ZoneSyncSslProtocols | ZoneSyncSslServerName |
+            ZoneSyncSslTrustedCertificate | ZoneSyncSslVerify | ZoneSyncSslVerifyDepth | ZoneSyncTimeout => Some(
+                Contexts::STREAM | Contexts::STREAM_SERVER),
+            If | Location | RefererHashBucketSize | RefererHashMaxSize | StubStatus | TryFiles | ValidReferers => Some(
+                Contexts::SERVER | Contexts::LOCATION),
+            Include => Some(Contexts::ANY),
+            JsBodyFilter | JsContent | JsHeaderFilter => Some(
+                Contexts::LOCATION | Contexts::IF_IN_LOCATION | Contexts::LIMIT_EXCEPT),
+            JsFetchBufferSize | JsFetchCiphers | JsFetchMaxResponseBufferSize | JsFetchProtocols |
+            JsFetchTimeout | JsFetchTrustedCertificate | JsFetchVerify | JsFetchVerifyDepth | JsImport |
+            JsPreloadObject | LimitConn | LimitConnDryRun | LimitConnLogLevel | OpenLogFileCache | ProxyBind |
+            ProxyBufferSize | ProxyConnectTimeout | ProxyNextUpstream | ProxyNextUpstreamTimeout |
+            ProxyNextUpstreamTries | ProxySocketKeepalive | ProxySslCertificate | ProxySslCertificateKey |
+            ProxySslCiphers | ProxySslConfCommand | ProxySslCrl | ProxySslName | ProxySslPasswordFile |
+            ProxySslProtocols | ProxySslServerName | ProxySslSessionReuse | ProxySslTrustedCertificate |
+            ProxySslVerify | ProxySslVerifyDepth | SetRealIpFrom | TcpNodelay => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::STREAM |
+                Contexts::STREAM_SERVER),
+            KeepaliveRequests | KeepaliveTime | KeepaliveTimeout => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::UPSTREAM),
+            Listen => Some(Contexts::SERVER | Contexts::MAIL_SERVER | Contexts::STREAM_SERVER),
+            Pass | ZoneSync => Some(Contexts::STREAM_SERVER),
+            Perl => Some(Contexts::LOCATION | Contexts::LIMIT_EXCEPT),
+            Protocol => Some(Contexts::MAIL_SERVER),
+            ProxyPass => Some(
+                Contexts::LOCATION | Contexts::IF_IN_LOCATION | Contexts::LIMIT_EXCEPT |
+                Contexts::STREAM_SERVER),
+            ProxyProtocol | ProxyTimeout => Some(
+                Contexts::MAIL | Contexts::MAIL_SERVER | Contexts::STREAM | Contexts::STREAM_SERVER),
+            Resolver | ResolverTimeout => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::UPSTREAM | Contexts::MAIL |
+                Contexts::MAIL_SERVER | Contexts::STREAM | Contexts::STREAM_SERVER),
+            RewriteLog | UninitializedVariableWarn => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::LOCATION | Contexts::IF_IN_SERVER |
+                Contexts::IF_IN_LOCATION),
+            Server => Some(
+                Contexts::HTTP | Contexts::UPSTREAM | Contexts::MAIL | Contexts::STREAM |
+                Contexts::STREAM_UPSTREAM),
+            ServerName => Some(
+                Contexts::SERVER | Contexts::MAIL | Contexts::MAIL_SERVER | Contexts::STREAM_SERVER),
+            Ssl => Some(Contexts::HTTP | Contexts::SERVER | Contexts::MAIL | Contexts::MAIL_SERVER),
+            SslCertificate | SslCertificateCache | SslCertificateKey | SslCiphers | SslClientCertificate |
+            SslConfCommand | SslCrl | SslDhparam | SslEcdhCurve | SslPasswordFile | SslPreferServerCiphers |
+            SslProtocols | SslSessionCache | SslSessionTicketKey | SslSessionTickets | SslSessionTimeout |
+            SslTrustedCertificate | SslVerifyClient | SslVerifyDepth => Some(
+                Contexts::HTTP | Contexts::SERVER | Contexts::MAIL | Contexts::MAIL_SERVER |
+                Contexts::STREAM | Contexts::STREAM_SERVER),
+            SslCertificateByLuaBlock | SslCertificateByLuaFile => Some(Contexts::SERVER),
+            StatusZone => Some(
+                Contexts::SERVER | Contexts::LOCATION | Contexts::IF_IN_LOCATION | Contexts::STREAM_SERVER),
+            _ => None,
+        }
+    }
+
+    /// Arguments the directive takes, `None` for directives whose syntax isn't known
+    /// or takes arguments of different kinds
+    pub fn signature(&self) -> Option<Signature> {
+        use DirectiveName::*;
+        match self {
+            AbsoluteRedirect | AcceptMutex | Autoindex | AutoindexExactSize | AutoindexLocaltime | Brotli |
+            ChunkedTransferEncoding | ClientBodyInSingleBuffer | CreateFullPutPath | Daemon | Etag |
+            FastcgiBuffering | FastcgiCacheBackgroundUpdate | FastcgiCacheLock | FastcgiCacheRevalidate |
+            FastcgiForceRanges | FastcgiIgnoreClientAbort | FastcgiInterceptErrors | FastcgiKeepConn |
+            FastcgiPassRequestBody | FastcgiPassRequestHeaders | FastcgiRequestBuffering |
+            FastcgiSocketKeepalive | GrpcInterceptErrors | GrpcSocketKeepalive | GrpcSslServerName |
+            GrpcSslSessionReuse | GrpcSslVerify | Gunzip | Gzip | GzipVary | Http2 | Http2PushPreload |
+            Http3 | Http3Hq | IgnoreInvalidHeaders | LimitConnDryRun | LimitReqDryRun | LogNotFound |
+            LogSubrequest | LuaCodeCache | LuaNeedRequestBody | LuaSocketLogErrors | MasterProcess |
+            MemcachedForceRanges | MemcachedSocketKeepalive | MergeSlashes | MirrorRequestBody | MsiePadding |
+            MsieRefresh | MultiAccept | OpenFileCacheErrors | PcreJit | PortInRedirect | ProxyBuffering |
+            ProxyCacheBackgroundUpdate | ProxyCacheConvertHead | ProxyCacheLock | ProxyCacheRevalidate |
+            ProxyForceRanges | ProxyHalfClose | ProxyIgnoreClientAbort | ProxyInterceptErrors |
+            ProxyPassRequestBody | ProxyPassRequestHeaders | ProxyProtocol | ProxyRequestBuffering |
+            ProxySocketKeepalive | ProxySsl | ProxySslServerName | ProxySslSessionReuse | ProxySslVerify |
+            QuicGso | QuicRetry | RandomIndex | RealIpRecursive | RecursiveErrorPages |
+            ResetTimedoutConnection | RewriteLog | ScgiBuffering | ScgiCacheBackgroundUpdate | ScgiCacheLock |
+            ScgiCacheRevalidate | ScgiForceRanges | ScgiIgnoreClientAbort | ScgiInterceptErrors |
+            ScgiPassRequestBody | ScgiPassRequestHeaders | ScgiRequestBuffering | ScgiSocketKeepalive |
+            Sendfile | ServerNameInRedirect | Ssi | SsiLastModified | SsiSilentErrors | Ssl | SslEarlyData |
+            SslPreferServerCiphers | SslPreread | SslRejectHandshake | SslSessionTickets | SslStapling |
+            SslStaplingVerify | SubFilterLastModified | SubFilterOnce | TcpNodelay | TcpNopush |
+            UnderscoresInHeaders | UninitializedVariableWarn | UwsgiBuffering | UwsgiCacheBackgroundUpdate |
+            UwsgiCacheLock | UwsgiCacheRevalidate | UwsgiForceRanges | UwsgiIgnoreClientAbort |
+            UwsgiInterceptErrors | UwsgiPassRequestBody | UwsgiPassRequestHeaders | UwsgiRequestBuffering |
+            UwsgiSocketKeepalive | UwsgiSslServerName | UwsgiSslSessionReuse | UwsgiSslVerify | Xclient => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::Flag })
+            },
+            AcceptMutexDelay | AuthDelay | AuthHttpTimeout | ClientBodyTimeout | ClientHeaderTimeout |
+            FastcgiCacheLockAge | FastcgiCacheLockTimeout | FastcgiConnectTimeout |
+            FastcgiNextUpstreamTimeout | FastcgiReadTimeout | FastcgiSendTimeout | GrpcConnectTimeout |
+            GrpcNextUpstreamTimeout | GrpcReadTimeout | GrpcSendTimeout | HealthCheckTimeout |
+            Http2IdleTimeout | Http2RecvTimeout | JsFetchTimeout | KeepaliveTime | LingeringTime |
+            LingeringTimeout | LuaSocketConnectTimeout | LuaSocketKeepaliveTimeout | LuaSocketReadTimeout |
+            LuaSocketSendTimeout | MemcachedConnectTimeout | MemcachedNextUpstreamTimeout |
+            MemcachedReadTimeout | MemcachedSendTimeout | OpenFileCacheValid | PrereadTimeout |
+            ProxyCacheLockAge | ProxyCacheLockTimeout | ProxyConnectTimeout | ProxyNextUpstreamTimeout |
+            ProxyProtocolTimeout | ProxyReadTimeout | ProxySendTimeout | ProxyTimeout | ResolverTimeout |
+            ScgiCacheLockAge | ScgiCacheLockTimeout | ScgiConnectTimeout | ScgiNextUpstreamTimeout |
+            ScgiReadTimeout | ScgiSendTimeout | SendTimeout | SmtpGreetingDelay | SslHandshakeTimeout |
+            SslSessionTimeout | Timeout | TimerResolution | UwsgiCacheLockAge | UwsgiCacheLockTimeout |
+            UwsgiConnectTimeout | UwsgiNextUpstreamTimeout | UwsgiReadTimeout | UwsgiSendTimeout |
+            WorkerShutdownTimeout => Some(Signature { min: 1, max: Some(1), kind: ArgKind::Time }),
+            AccessByLua | AccessByLuaBlock | AccessByLuaFile | Allow | AuthBasic | AuthHttp | AuthRequest |
+            BodyFilterByLua | BodyFilterByLuaBlock | BodyFilterByLuaFile | Charset | ContentByLua |
+            ContentByLuaBlock | ContentByLuaFile | DebugConnection | DefaultType | Deny | Directio | Env |
+            FastcgiCache | FastcgiCacheKey | FastcgiHideHeader | FastcgiIndex | FastcgiPass |
+            FastcgiPassHeader | GrpcHideHeader | GrpcPass | GrpcPassHeader | GrpcSslCiphers | GrpcSslName |
+            HeaderFilterByLua | HeaderFilterByLuaBlock | HeaderFilterByLuaFile | InitByLua | InitByLuaBlock |
+            InitByLuaFile | InitWorkerByLua | InitWorkerByLuaBlock | InitWorkerByLuaFile | JsContent |
+            JsPath | LogByLua | LogByLuaBlock | LogByLuaFile | LuaPackageCpath | LuaPackagePath |
+            MemcachedGzipFlag | MemcachedPass | Mirror | OverrideCharset | Pass | Perl | Protocol |
+            ProxyCache | ProxyCacheKey | ProxyHideHeader | ProxyMethod | ProxyPass | ProxyPassHeader |
+            ProxySslCiphers | ProxySslName | RealIpHeader | RewriteByLua | RewriteByLuaBlock |
+            RewriteByLuaFile | ScgiCache | ScgiCacheKey | ScgiHideHeader | ScgiPass | ScgiPassHeader |
+            ServerTokens | SetRealIpFrom | SourceCharset | SslCiphers | SslEcdhCurve | SslOcspCache |
+            SslStaplingResponder | Upstream | Use | UwsgiCache | UwsgiCacheKey | UwsgiHideHeader | UwsgiPass |
+            UwsgiPassHeader | UwsgiSslCiphers | UwsgiSslName => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::Any })
+            },
+            AccessLog | AdditionTypes | BrotliTypes | CharsetTypes | EarlyHints | FastcgiCacheBypass |
+            FastcgiCacheMethods | FastcgiCacheUseStale | FastcgiCacheValid | FastcgiIgnoreHeaders |
+            FastcgiNextUpstream | FastcgiNoCache | GrpcIgnoreHeaders | GrpcNextUpstream | GzipDisable |
+            GzipTypes | Index | KeepaliveDisable | LimitExcept | Listen | MemcachedNextUpstream |
+            ProxyCacheBypass | ProxyCacheMethods | ProxyCacheUseStale | ProxyCacheValid | ProxyIgnoreHeaders |
+            ProxyNextUpstream | ProxyNoCache | Resolver | ScgiCacheBypass | ScgiCacheMethods |
+            ScgiCacheUseStale | ScgiCacheValid | ScgiIgnoreHeaders | ScgiNextUpstream | ScgiNoCache |
+            ServerName | SsiTypes | Sticky | SubFilterTypes | UwsgiCacheBypass | UwsgiCacheMethods |
+            UwsgiCacheUseStale | UwsgiCacheValid | UwsgiIgnoreHeaders | UwsgiNextUpstream | UwsgiNoCache |
+            ValidReferers | WorkerCpuAffinity => Some(Signature { min: 1, max: None, kind: ArgKind::Any }),
+            AddHeader | AddTrailer | FastcgiParam | JsSet | Rewrite | ScgiParam | UwsgiParam => {
+                Some(Signature { min: 2, max: Some(3), kind: ArgKind::Any })
+            },
+            Alias | AuthBasicUserFile | GrpcSslCertificate | GrpcSslCertificateKey | GrpcSslCrl |
+            GrpcSslPasswordFile | GrpcSslTrustedCertificate | Include | JsFetchTrustedCertificate |
+            LoadModule | LockFile | LuaSslTrustedCertificate | Pid | ProxySslCertificate |
+            ProxySslCertificateKey | ProxySslCrl | ProxySslPasswordFile | ProxySslTrustedCertificate | Root |
+            SslCertificate | SslCertificateKey | SslClientCertificate | SslCrl | SslDhparam |
+            SslPasswordFile | SslSessionTicketKey | SslStaplingFile | SslTrustedCertificate |
+            UwsgiSslCertificate | UwsgiSslCertificateKey | UwsgiSslCrl | UwsgiSslPasswordFile |
+            UwsgiSslTrustedCertificate | WorkingDirectory => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::Path })
+            },
+            Api | StubStatus => Some(Signature { min: 0, max: Some(1), kind: ArgKind::Any }),
+            AuthRequestSet | BrotliBuffers | FastcgiBuffers | GrpcSetHeader | GzipBuffers | LimitConn |
+            LimitConnZone | LuaSharedDict | Map | OutputBuffers | PerlSet | ProxyBuffers | ProxySetHeader |
+            ScgiBuffers | Set | SetByLuaBlock | SplitClients | SubFilter | UwsgiBuffers => {
+                Some(Signature { min: 2, max: Some(2), kind: ArgKind::Any })
+            },
+            Break | EmptyGif | Events | F4f | Flv | Hls | Http | Internal | IpHash | LeastConn | Mail | Mp4 |
+            Ntlm | Stream | Types => Some(Signature { min: 0, max: Some(0), kind: ArgKind::Any }),
+            BrotliCompLevel | FastcgiCacheMinUses | FastcgiNextUpstreamTries | GrpcNextUpstreamTries |
+            GrpcSslVerifyDepth | GzipCompLevel | Http2MaxConcurrentStreams | Http2MaxRequests |
+            Http3MaxConcurrentStreams | ImageFilterJpegQuality | ImageFilterSharpen | ImageFilterWebpQuality |
+            JsFetchVerifyDepth | Keepalive | KeepaliveRequests | LimitConnStatus | LimitReqStatus |
+            LuaMaxPendingTimers | LuaMaxRunningTimers | LuaSocketPoolSize | LuaSslVerifyDepth |
+            MapHashBucketSize | MapHashMaxSize | MaxErrors | MaxRanges | MemcachedNextUpstreamTries |
+            MinDeleteDepth | OpenFileCacheMinUses | ProxyCacheMinUses | ProxyHeadersHashBucketSize |
+            ProxyHeadersHashMaxSize | ProxyNextUpstreamTries | ProxySslVerifyDepth |
+            QuicActiveConnectionIdLimit | RefererHashBucketSize | RefererHashMaxSize | ScgiCacheMinUses |
+            ScgiNextUpstreamTries | ServerNamesHashBucketSize | ServerNamesHashMaxSize | SsiValueLength |
+            SslVerifyDepth | TypesHashBucketSize | TypesHashMaxSize | UwsgiCacheMinUses |
+            UwsgiNextUpstreamTries | UwsgiSslVerifyDepth | VariablesHashBucketSize | VariablesHashMaxSize |
+            WorkerAioRequests | WorkerConnections | WorkerPriority | WorkerRlimitNofile => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::Number })
+            },
+            BrotliMinLength | BrotliWindow | ClientBodyBufferSize | ClientHeaderBufferSize |
+            ClientMaxBodySize | ConnectionPoolSize | DirectioAlignment | F4fBufferSize | FastcgiBufferSize |
+            FastcgiBusyBuffersSize | FastcgiLimitRate | FastcgiMaxTempFileSize | FastcgiTempFileWriteSize |
+            GrpcBufferSize | GzipMinLength | HlsMp4BufferSize | HlsMp4MaxBufferSize | Http2BodyPrereadSize |
+            Http2ChunkSize | Http2MaxFieldSize | Http2MaxHeaderSize | Http2RecvBufferSize |
+            Http3StreamBufferSize | ImageFilterBuffer | ImapClientBuffer | JsFetchBufferSize |
+            JsFetchMaxResponseBufferSize | LimitRate | LimitRateAfter | LuaSocketBufferSize |
+            MemcachedBufferSize | Mp4BufferSize | Mp4MaxBufferSize | PostponeOutput | PrereadBufferSize |
+            ProxyBuffer | ProxyBufferSize | ProxyBusyBuffersSize | ProxyDownloadRate | ProxyLimitRate |
+            ProxyMaxTempFileSize | ProxyTempFileWriteSize | ProxyUploadRate | RequestPoolSize |
+            ScgiBufferSize | ScgiBusyBuffersSize | ScgiLimitRate | ScgiMaxTempFileSize |
+            ScgiTempFileWriteSize | SendLowat | SendfileMaxChunk | Slice | SmtpClientBuffer |
+            SsiMinFileChunk | SslBufferSize | SubrequestOutputBufferSize | UwsgiBufferSize |
+            UwsgiBusyBuffersSize | UwsgiLimitRate | UwsgiMaxTempFileSize | UwsgiTempFileWriteSize |
+            WorkerRlimitCore => Some(Signature { min: 1, max: Some(1), kind: ArgKind::Size }),
+            BrotliStatic | GzipStatic | LingeringClose => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::OneOf(ON_OFF_ALWAYS) })
+            },
+            ClientBodyInFileOnly => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::OneOf(ON_CLEAN_OFF) })
+            },
+            ClientBodyTempPath | FastcgiTempPath | ProxyTempPath | ScgiTempPath | UwsgiTempPath => {
+                Some(Signature { min: 1, max: Some(4), kind: ArgKind::Any })
+            },
+            DisableSymlinks | ErrorLog | Expires | FastcgiBind | Geo | GeoipCity | GeoipCountry | GeoipOrg |
+            GrpcBind | Hash | JsVar | LeastTime | Location | MemcachedBind | OpenFileCache | ProxyBind |
+            ProxyCookieDomain | ProxyCookiePath | ProxyRedirect | Queue | Return | ScgiBind |
+            SslSessionCache | User | UwsgiBind | Zone => {
+                Some(Signature { min: 1, max: Some(2), kind: ArgKind::Any })
+            },
+            ErrorPage | FastcgiCachePath | LogFormat | ProxyCachePath | ScgiCachePath | TryFiles |
+            UwsgiCachePath => Some(Signature { min: 2, max: None, kind: ArgKind::Any }),
+            FastcgiStoreAccess | JsImport | LimitReq | ProxyStoreAccess | ScgiStoreAccess | UwsgiStoreAccess => {
+                Some(Signature { min: 1, max: Some(3), kind: ArgKind::Any })
+            },
+            GrpcSslProtocols | LuaSslProtocols | ProxySslProtocols | SslProtocols | UwsgiSslProtocols |
+            ZoneSyncSslProtocols => {
+                Some(Signature { min: 1, max: None, kind: ArgKind::OneOf(TLS_PROTOCOLS) })
+            },
+            GzipHttpVersion | ProxyHttpVersion => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::OneOf(HTTP_VERSIONS) })
+            },
+            GzipProxied => Some(Signature { min: 1, max: None, kind: ArgKind::OneOf(GZIP_PROXIED) }),
+            IfModifiedSince => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::OneOf(IF_MODIFIED_SINCE) })
+            },
+            KeepaliveTimeout => Some(Signature { min: 1, max: Some(2), kind: ArgKind::Time }),
+            LimitConnLogLevel | LimitReqLogLevel => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::OneOf(LOG_LEVELS) })
+            },
+            LimitReqZone => Some(Signature { min: 3, max: Some(4), kind: ArgKind::Any }),
+            Random => Some(Signature { min: 0, max: Some(2), kind: ArgKind::Any }),
+            Satisfy => Some(Signature { min: 1, max: Some(1), kind: ArgKind::OneOf(SATISFY) }),
+            SslOcsp => Some(Signature { min: 1, max: Some(1), kind: ArgKind::OneOf(SSL_OCSP) }),
+            SslVerifyClient => {
+                Some(Signature { min: 1, max: Some(1), kind: ArgKind::OneOf(SSL_VERIFY_CLIENT) })
+            },
+            WorkerProcesses => Some(Signature { min: 1, max: Some(1), kind: ArgKind::NumberOrAuto }),
+            _ => None,
+        }
+    }
+}
+
+/// Prefixes of directives from modules outside nginx builds as usually packaged
+const MODULE_PREFIXES: &[(&str, &str)] = &[
+    ("brotli", "ngx_brotli"),
+    ("geoip2", "ngx_http_geoip2_module"),
+    ("geoip_", "ngx_http_geoip_module"),
+    ("image_filter", "ngx_http_image_filter_module"),
+    ("js_", "njs"),
+    ("lua_", "lua-nginx-module"),
+    ("perl", "ngx_http_perl_module"),
+    ("xslt_", "ngx_http_xslt_module"),
+    ("auth_jwt", "NGINX Plus"),
+    ("health_check", "NGINX Plus"),
+    ("keyval", "NGINX Plus"),
+    ("session_log", "NGINX Plus"),
+    ("sticky", "NGINX Plus"),
+    ("zone_sync", "NGINX Plus"),
+];
+
+/// Prefixes of third-party directives the parser doesn't know
+const THIRD_PARTY_PREFIXES: &[(&str, &str)] = &[
+    ("more_", "headers-more-nginx-module"),
+    ("vhost_traffic_status", "nginx-module-vts"),
+    ("pagespeed", "ngx_pagespeed"),
+    ("modsecurity", "ModSecurity-nginx"),
+    ("passenger_", "Phusion Passenger"),
+    ("echo", "echo-nginx-module"),
+    ("upload_progress", "nginx-upload-progress-module"),
+    ("set_misc", "set-misc-nginx-module"),
+    ("srcache_", "srcache-nginx-module"),
+    ("redis2_", "redis2-nginx-module"),
+    ("nchan_", "nchan"),
+    ("rtmp", "nginx-rtmp-module"),
+    ("push_stream_", "nginx-push-stream-module"),
+    ("fancyindex", "ngx-fancyindex"),
+    ("ndk_", "ngx_devel_kit"),
+    ("dav_ext_", "nginx-dav-ext-module"),
+];
+
+impl DirectiveName {
+    /// Module providing the directive when nginx builds as usually packaged don't come with it:
+    /// third-party, dynamic and NGINX Plus modules
+    pub fn module(&self) -> Option<&'static str> {
+        use DirectiveName::*;
+        let name = self.as_str();
+        if name.contains("_by_lua") {
+            return Some("lua-nginx-module")
+        }
+        if let Some((_, module)) = MODULE_PREFIXES.iter().find(|(prefix, _)| name.starts_with(prefix)) {
+            return Some(module)
+        }
+        match self {
+            XmlEntities => Some("ngx_http_xslt_module"),
+            Api | F4f | F4fBufferSize | Hls | HlsBuffers | HlsForwardArgs | HlsFragment | HlsMp4BufferSize
+            | HlsMp4MaxBufferSize | LeastTime | LicenseToken | Mgmt | Ntlm | Queue | Status | StatusFormat
+            | StatusZone | UpstreamConf | UsageReport => Some("NGINX Plus"),
+            _ => None,
+        }
+    }
+}
+
+/// Third-party module an unknown directive comes from, judging by its name
+pub fn third_party_module(name: &str) -> Option<&'static str> {
+    THIRD_PARTY_PREFIXES.iter().find(|(prefix, _)| name.starts_with(prefix)).map(|(_, module)| *module)
+}
+
+impl DirectiveName {
+    /// First nginx release with the directive, `None` when it predates what configs in use target
+    /// or comes from a module versioned apart from nginx
+    pub fn since(&self) -> Option<Version> {
+        use DirectiveName::*;
+        let version = |major, minor, patch| Some(Version::new(major, minor, patch));
+        match self {
+            SslEcdhCurve => version(1, 1, 0),
+            ProxyHttpVersion => version(1, 1, 4),
+            SslStapling | SslStaplingFile | SslStaplingResponder | SslStaplingVerify => version(1, 3, 7),
+            AuthRequest | AuthRequestSet => version(1, 5, 4),
+            SslSessionTicketKey => version(1, 5, 7),
+            SslBufferSize => version(1, 5, 9),
+            ProxyRequestBuffering | FastcgiRequestBuffering | UwsgiRequestBuffering | ScgiRequestBuffering => {
+                version(1, 7, 11)
+            },
+            Stream | Zone => version(1, 9, 0),
+            Http2ChunkSize | Http2IdleTimeout | Http2MaxConcurrentStreams | Http2MaxFieldSize
+            | Http2MaxHeaderSize | Http2RecvBufferSize | Http2RecvTimeout => version(1, 9, 5),
+            ProxyCacheConvertHead => version(1, 9, 7),
+            Slice => version(1, 9, 8),
+            AioWrite => version(1, 9, 13),
+            Http2BodyPrereadSize => version(1, 11, 0),
+            SslPreread => version(1, 11, 5),
+            AbsoluteRedirect => version(1, 11, 8),
+            ProxyCacheBackgroundUpdate | FastcgiCacheBackgroundUpdate | UwsgiCacheBackgroundUpdate
+            | ScgiCacheBackgroundUpdate => version(1, 11, 10),
+            WorkerShutdownTimeout => version(1, 11, 11),
+            AddTrailer => version(1, 13, 2),
+            Mirror | MirrorRequestBody => version(1, 13, 4),
+            Http2Push | Http2PushPreload => version(1, 13, 9),
+            GrpcPass | SubrequestOutputBufferSize => version(1, 13, 10),
+            Random => version(1, 15, 1),
+            SslEarlyData => version(1, 15, 3),
+            ProxySocketKeepalive | FastcgiSocketKeepalive | GrpcSocketKeepalive | UwsgiSocketKeepalive
+            | ScgiSocketKeepalive | MemcachedSocketKeepalive => version(1, 15, 6),
+            LimitReqDryRun => version(1, 17, 1),
+            LimitConnDryRun => version(1, 17, 6),
+            SslOcsp | SslOcspCache | SslOcspResponder => version(1, 19, 0),
+            SslConfCommand | ProxySslConfCommand | GrpcSslConfCommand | UwsgiSslConfCommand | SslRejectHandshake => {
+                version(1, 19, 4)
+            },
+            KeepaliveTime => version(1, 19, 10),
+            Http3 | Http3Hq | Http3MaxConcurrentStreams | Http3StreamBufferSize | QuicActiveConnectionIdLimit
+            | QuicBpf | QuicGso | QuicHostKey | QuicRetry => version(1, 25, 0),
+            Http2 => version(1, 25, 1),
+            QuicMtu => version(1, 25, 2),
+            SslCertificateCache => version(1, 27, 4),
+            EarlyHints => version(1, 29, 0),
+            _ => None,
+        }
+    }
+
+    /// Release that made the directive obsolete, nginx warns about or rejects it from then on
+    pub fn obsolete(&self) -> Option<Obsolete> {
+        use DirectiveName::*;
+        let obsolete = |major, minor, patch, use_instead| Some(Obsolete { since: Version::new(major, minor, patch), use_instead });
+        match self {
+            OptimizeServerNames => obsolete(0, 7, 54, "`server_name_in_redirect`"),
+            LimitZone => obsolete(1, 1, 8, "`limit_conn_zone`"),
+            SpdyChunkSize | SpdyHeadersComp | SpdyKeepaliveTimeout | SpdyMaxConcurrentStreams | SpdyPoolSize
+            | SpdyRecvBufferSize | SpdyRecvTimeout | SpdyStreamsIndexSize => obsolete(1, 9, 5, "the `http2_*` directives"),
+            Ssl => obsolete(1, 15, 0, "the `ssl` parameter of `listen`"),
+            Http2IdleTimeout => obsolete(1, 19, 7, "`keepalive_timeout`"),
+            Http2MaxRequests => obsolete(1, 19, 7, "`keepalive_requests`"),
+            Http2MaxFieldSize | Http2MaxHeaderSize => obsolete(1, 19, 7, "`large_client_header_buffers`"),
+            Http2RecvTimeout => obsolete(1, 19, 7, "`client_header_timeout`"),
+            Http2Push | Http2PushPreload | Http3Push | Http3PushPreload | Http3MaxConcurrentPushes => {
+                obsolete(1, 25, 1, "`early_hints` or preload links, server push is gone")
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Variables nginx and the modules of `DirectiveName` define, in http and stream
+pub const BUILTIN_VARIABLES: &[&str] = &[
+    "ancient_browser", "args", "binary_remote_addr", "body_bytes_sent", "bytes_received", "bytes_sent",
+    "connection", "connection_requests", "connection_time", "connections_active", "connections_reading",
+    "connections_waiting", "connections_writing", "content_length", "content_type", "date_gmt", "date_local",
+    "document_root", "document_uri", "fastcgi_path_info", "fastcgi_script_name", "geoip_area_code", "geoip_city",
+    "geoip_city_continent_code", "geoip_city_country_code", "geoip_city_country_code3", "geoip_city_country_name",
+    "geoip_country_code", "geoip_country_code3", "geoip_country_name", "geoip_dma_code", "geoip_latitude",
+    "geoip_longitude", "geoip_org", "geoip_postal_code", "geoip_region", "geoip_region_name", "gzip_ratio", "host",
+    "hostname", "http2", "http3", "https", "invalid_referer", "is_args", "jwt_payload", "limit_conn_status",
+    "limit_rate", "limit_req_status", "memcached_key", "modern_browser", "msec", "msie", "nginx_version", "pid",
+    "pipe", "protocol", "proxy_add_x_forwarded_for", "proxy_host", "proxy_port", "proxy_protocol_addr",
+    "proxy_protocol_port", "proxy_protocol_server_addr", "proxy_protocol_server_port", "query_string",
+    "realip_remote_addr", "realip_remote_port", "realpath_root", "remote_addr", "remote_port", "remote_user",
+    "request", "request_body", "request_body_file", "request_completion", "request_filename", "request_id",
+    "request_length", "request_method", "request_time", "request_uri", "scheme", "secure_link",
+    "secure_link_expires", "server_addr", "server_name", "server_port", "server_protocol", "session_log_binary_id",
+    "session_log_id", "session_time", "slice_range", "ssl_alpn_protocol", "ssl_cipher", "ssl_ciphers",
+    "ssl_client_cert", "ssl_client_escaped_cert", "ssl_client_fingerprint", "ssl_client_i_dn",
+    "ssl_client_i_dn_legacy", "ssl_client_raw_cert", "ssl_client_s_dn", "ssl_client_s_dn_legacy",
+    "ssl_client_serial", "ssl_client_v_end", "ssl_client_v_remain", "ssl_client_v_start", "ssl_client_verify",
+    "ssl_curve", "ssl_curves", "ssl_early_data", "ssl_preread_alpn_protocols", "ssl_preread_protocol",
+    "ssl_preread_server_name", "ssl_protocol", "ssl_server_name", "ssl_session_id", "ssl_session_reused", "status",
+    "tcpinfo_rcv_space", "tcpinfo_rtt", "tcpinfo_rttvar", "tcpinfo_snd_cwnd", "time_iso8601", "time_local",
+    "uid_got", "uid_reset", "uid_set", "upstream_addr", "upstream_bytes_received", "upstream_bytes_sent",
+    "upstream_cache_status", "upstream_connect_time", "upstream_first_byte_time", "upstream_header_time",
+    "upstream_last_server_name", "upstream_queue_time", "upstream_response_length", "upstream_response_time",
+    "upstream_session_time", "upstream_status", "uri",
+];
+
+/// Prefixes of the variable families, such as `$http_user_agent` for a request header
+pub const BUILTIN_VARIABLE_PREFIXES: &[&str] = &[
+    "arg_", "cookie_", "http_", "jwt_claim_", "jwt_header_", "proxy_protocol_tlv_", "sent_http_", "sent_trailer_",
+    "upstream_cookie_", "upstream_http_", "upstream_trailer_",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_become_obsolete_after_they_appear() {
+        for name in DirectiveName::ALL {
+            if let (Some(since), Some(obsolete)) = (name.since(), name.obsolete()) {
+                assert!(since < obsolete.since, "`{name}` is obsolete before it appears");
+            }
+        }
+        assert_eq!(DirectiveName::Http2IdleTimeout.since(), Some(Version::new(1, 9, 5)));
+        assert_eq!(DirectiveName::Http2IdleTimeout.obsolete().map(|o| o.since), Some(Version::new(1, 19, 7)));
+        assert_eq!(DirectiveName::Root.since(), None);
+    }
+
+    #[test]
+    fn modules_of_directives() {
+        assert_eq!(DirectiveName::ContentByLuaBlock.module(), Some("lua-nginx-module"));
+        assert_eq!(DirectiveName::Api.module(), Some("NGINX Plus"));
+        assert_eq!(DirectiveName::ProxyPass.module(), None);
+        assert_eq!(third_party_module("fancyindex_exact_size"), Some("ngx-fancyindex"));
+        assert_eq!(third_party_module("proxy_pass"), None);
+    }
+}
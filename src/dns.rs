@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io,
+    net::{IpAddr, ToSocketAddrs},
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    names::DirectiveName,
+    validate::{pass_host, pass_upstream, walk, Context, Diagnostic, Problem},
+    Structure,
+};
+
+/// Lookups running at the same time
+const PARALLEL_LOOKUPS: usize = 16;
+/// Time the lookups of a `DnsCheck` get when not set
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What's wrong with a host the config sends requests to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsProblem {
+    /// No address, the name doesn't exist (NXDOMAIN) or the resolver failed, as `error` tells
+    Unresolvable { host: String, error: String },
+    /// Only AAAA records, unreachable from hosts without IPv6
+    OnlyIpv6 { host: String },
+    /// No answer before the check gave up
+    TimedOut { host: String },
+}
+
+impl Display for DnsProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unresolvable { host, error } => write!(f, "`{host}` doesn't resolve: {error}"),
+            Self::OnlyIpv6 { host } => write!(f, "`{host}` only resolves to IPv6 addresses"),
+            Self::TimedOut { host } => write!(f, "`{host}` didn't resolve in time"),
+        }
+    }
+}
+
+/// Looks up the addresses of a host name
+pub trait Resolver: Send + Sync {
+    /// Fails, or gives no address, when the name doesn't exist
+    fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>>;
+}
+
+/// The resolver of the system, as `getaddrinfo` does it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        Ok((host, 0).to_socket_addrs()?.map(|address| address.ip()).collect())
+    }
+}
+
+/// Resolves the hosts of `*_pass` targets and of upstream `server` entries,
+/// reporting the names that don't resolve or only have IPv6 addresses, such as decommissioned backends
+///
+/// Upstream names, IP addresses, unix sockets and targets with variables aren't looked up
+#[derive(Clone)]
+pub struct DnsCheck {
+    pub resolver: Arc<dyn Resolver>,
+    /// How long all the lookups of a config may take, hosts still unresolved by then are reported as timed out
+    pub timeout: Duration,
+}
+
+impl Default for DnsCheck {
+    fn default() -> Self {
+        Self { resolver: Arc::new(SystemResolver), timeout: DEFAULT_TIMEOUT }
+    }
+}
+
+impl DnsCheck {
+    pub fn new(resolver: impl Resolver + 'static) -> Self {
+        Self { resolver: Arc::new(resolver), ..Self::default() }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Starts looking up the hosts of the config in the background, each one once
+    /// Lookups run on their own threads, which a hanging resolver may keep past `PendingDns::wait`
+    pub fn start<'a>(&self, root: &'a Structure<'a>) -> PendingDns<'a> {
+        let references = host_references(root);
+        let mut hosts = references.iter().map(|(_, _, host)| host.to_ascii_lowercase()).collect::<Vec<_>>();
+        hosts.sort();
+        hosts.dedup();
+        let (sender, results) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(hosts.clone().into_iter()));
+        for _ in 0..hosts.len().min(PARALLEL_LOOKUPS) {
+            let (queue, sender, resolver) = (queue.clone(), sender.clone(), self.resolver.clone());
+            std::thread::spawn(move || {
+                while let Some(host) = queue.lock().ok().and_then(|mut queue| queue.next()) {
+                    let problem = lookup(resolver.as_ref(), &host);
+                    if sender.send((host, problem)).is_err() {
+                        return
+                    }
+                }
+            });
+        }
+        PendingDns { references, hosts, results, deadline: Instant::now() + self.timeout }
+    }
+}
+
+/// Lookups of a `DnsCheck` under way
+pub struct PendingDns<'a> {
+    references: Vec<(&'a Structure<'a>, &'a str, &'a str)>,
+    /// Lowercased, each once
+    hosts: Vec<String>,
+    results: mpsc::Receiver<(String, Option<DnsProblem>)>,
+    deadline: Instant,
+}
+
+impl<'a> PendingDns<'a> {
+    /// Waits for the lookups, until the timeout of the check at most, and reports every statement naming a problem host
+    pub fn wait(self) -> Vec<Diagnostic<'a>> {
+        let mut problems = HashMap::new();
+        let mut answered = 0;
+        while answered < self.hosts.len() {
+            let Ok((host, problem)) = self.results.recv_timeout(self.deadline.saturating_duration_since(Instant::now())) else {
+                break;
+            };
+            answered += 1;
+            problems.insert(host, problem);
+        }
+        self.references
+            .into_iter()
+            .filter_map(|(statement, name, host)| {
+                let problem = match problems.get(&host.to_ascii_lowercase()) {
+                    Some(problem) => problem.clone()?,
+                    None => DnsProblem::TimedOut { host: host.to_string() },
+                };
+                Some(Diagnostic::new(name, statement, Problem::Dns(problem)))
+            })
+            .collect()
+    }
+}
+
+/// Looks up every host with the system resolver, see `DnsCheck`
+pub fn check_dns<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    DnsCheck::default().start(root).wait()
+}
+
+fn lookup(resolver: &dyn Resolver, host: &str) -> Option<DnsProblem> {
+    match resolver.lookup(host) {
+        Err(error) => Some(DnsProblem::Unresolvable { host: host.to_string(), error: error.to_string() }),
+        Ok(addresses) if addresses.is_empty() => Some(DnsProblem::Unresolvable { host: host.to_string(), error: "no address".to_string() }),
+        Ok(addresses) if addresses.iter().all(IpAddr::is_ipv6) => Some(DnsProblem::OnlyIpv6 { host: host.to_string() }),
+        Ok(_) => None,
+    }
+}
+
+/// Statements naming a host to look up, with the directive as written and the host
+fn host_references<'a>(root: &'a Structure<'a>) -> Vec<(&'a Structure<'a>, &'a str, &'a str)> {
+    let mut upstreams = Vec::new();
+    walk(root, Context::Main, &mut |statement, _, directive, _| {
+        if let (Some(DirectiveName::Upstream), Structure::Block { args, .. }) = (directive, statement) {
+            upstreams.extend(args.get(1).map(|name| name.value().to_ascii_lowercase()));
+        }
+    });
+    let mut references = Vec::new();
+    walk(root, Context::Main, &mut |statement, name, directive, context| {
+        let Some(directive) = directive else {
+            return
+        };
+        let host = match (directive, context, statement) {
+            (DirectiveName::Server, Context::Upstream | Context::StreamUpstream, Structure::Statement { args }) => {
+                args.get(1).and_then(|address| server_host(address.value()))
+            },
+            (DirectiveName::Server, ..) => None,
+            _ => pass_host(statement, directive).filter(|host| {
+                pass_upstream(statement, directive).is_none() || !upstreams.contains(&host.to_ascii_lowercase())
+            }),
+        };
+        if let Some(host) = host.filter(|host| host.parse::<IpAddr>().is_err()) {
+            references.push((statement, name, host));
+        }
+    });
+    references
+}
+
+/// Host of an upstream `server` address, `None` for unix sockets and addresses with variables
+fn server_host(address: &str) -> Option<&str> {
+    if address.starts_with("unix:") || address.contains('$') {
+        return None
+    }
+    let host = match address.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next()?,
+        None => address.split(':').next()?,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers from a fixed table, `slow.internal` only after a second
+    struct Table;
+
+    impl Resolver for Table {
+        fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+            match host {
+                "app.internal" => Ok(vec![IpAddr::from([10, 0, 0, 2])]),
+                "dual.internal" => Ok(vec![IpAddr::from([0xfd00, 0, 0, 0, 0, 0, 0, 1]), IpAddr::from([10, 0, 0, 3])]),
+                "v6.internal" => Ok(vec![IpAddr::from([0xfd00, 0, 0, 0, 0, 0, 0, 2])]),
+                "empty.internal" => Ok(Vec::new()),
+                "slow.internal" => {
+                    std::thread::sleep(Duration::from_secs(1));
+                    Ok(vec![IpAddr::from([10, 0, 0, 4])])
+                },
+                _ => Err(io::Error::new(io::ErrorKind::NotFound, "NXDOMAIN")),
+            }
+        }
+    }
+
+    #[test]
+    fn hits_misses_and_timeouts() {
+        let source = "http {\n\
+            upstream backend { server app.internal:8080; server gone.internal; server 10.0.0.1; server unix:/run/app.sock; }\n\
+            server {\n\
+            location / { proxy_pass http://backend; }\n\
+            location /a { proxy_pass http://dual.internal; }\n\
+            location /b { proxy_pass http://v6.internal; }\n\
+            location /c { proxy_pass http://slow.internal:81; }\n\
+            location /d { proxy_pass http://GONE.internal; fastcgi_pass empty.internal:9000; }\n\
+            location /e { proxy_pass http://$upstream; }\n\
+            }\n\
+            }";
+        let root = Structure::parse(source).unwrap();
+        let diagnostics = DnsCheck::new(Table).with_timeout(Duration::from_millis(200)).start(&root).wait();
+        let problems = diagnostics
+            .iter()
+            .map(|d| (d.statement.position(source).unwrap().line, d.problem.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            [
+                (2, "`gone.internal` doesn't resolve: NXDOMAIN".to_string()),
+                (6, "`v6.internal` only resolves to IPv6 addresses".to_string()),
+                (7, "`slow.internal` didn't resolve in time".to_string()),
+                (8, "`gone.internal` doesn't resolve: NXDOMAIN".to_string()),
+                (8, "`empty.internal` doesn't resolve: no address".to_string()),
+            ]
+        );
+        assert!(diagnostics[0].problem.is_error() && !diagnostics[1].problem.is_error() && !diagnostics[2].problem.is_error());
+    }
+
+    #[test]
+    fn configs_without_hosts_return_at_once() {
+        let root = Structure::parse("http { server { location / { proxy_pass http://127.0.0.1:8080; } } }").unwrap();
+        assert!(DnsCheck::new(Table).with_timeout(Duration::from_secs(60)).start(&root).wait().is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+
+use crate::{names::DirectiveName, types::Directive, Structure};
+
+/// Directives of the contexts from `http` down to a location, as nginx merges them
+#[derive(Debug, Clone, Default)]
+pub struct EffectiveSettings<'a> {
+    /// Keyed by the first directive of each inheritance group, see `EffectiveSettings::setting`
+    pub settings: BTreeMap<DirectiveName, Setting<'a>>,
+}
+
+/// Statements of one directive (or group of directives sharing a setting) that apply
+#[derive(Debug, Clone)]
+pub struct Setting<'a> {
+    /// Index of the context they come from, in the slice given to `effective_config`
+    pub context: usize,
+    /// In config order, statements of inherited contexts are never mixed in
+    pub statements: Vec<&'a Structure<'a>>,
+}
+
+impl<'a> EffectiveSettings<'a> {
+    /// Setting a directive contributes to, `allow` and `deny` share their list, so do `root` and `alias`
+    pub fn setting(&self, name: DirectiveName) -> Option<&Setting<'a>> {
+        self.settings.get(&group(name))
+    }
+
+    /// Statements of this very directive that apply
+    pub fn statements(&self, name: DirectiveName) -> impl Iterator<Item = &'a Structure<'a>> + '_ {
+        let statements = self.setting(name).map(|s| s.statements.as_slice()).unwrap_or_default();
+        statements.iter().copied().filter(move |s| s.name() == Some(name))
+    }
+
+    /// Typed statements of this directive, those that don't parse are skipped
+    pub fn directives(&self, name: DirectiveName) -> impl Iterator<Item = Directive> + '_ {
+        self.statements(name).filter_map(|s| Directive::try_from(s).ok())
+    }
+
+    /// Arguments of the last statement of this directive, as written and without the name
+    pub fn args(&self, name: DirectiveName) -> Option<Vec<&'a str>> {
+        let statement = self.statements(name).last()?;
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+        Some(args.iter().skip(1).map(|a| a.value()).collect())
+    }
+}
+
+/// Merges the contexts on the way to a location, outermost (usually `http`) first
+///
+/// A directive defined in a context replaces everything inherited for it, lists included:
+/// a single `add_header` or `proxy_set_header` in a location drops all of the server's ones.
+/// Directives that only apply where written (`rewrite`, `return`, `try_files`, `proxy_pass`...)
+/// are taken from the innermost context alone
+pub fn effective_config<'a>(contexts: &[&'a Structure<'a>]) -> EffectiveSettings<'a> {
+    let mut effective = EffectiveSettings::default();
+    for (depth, context) in contexts.iter().enumerate() {
+        let Structure::Block { children, .. } = context else {
+            continue;
+        };
+        let innermost = depth + 1 == contexts.len();
+        let mut defined = BTreeMap::<DirectiveName, Setting<'a>>::new();
+        for child in children {
+            let Some(name) = child.name() else {
+                continue;
+            };
+            if is_context(name) || (!innermost && !is_inherited(name)) {
+                continue;
+            }
+            defined
+                .entry(group(name))
+                .or_insert_with(|| Setting { context: depth, statements: Vec::new() })
+                .statements
+                .push(child);
+        }
+        effective.settings.extend(defined);
+    }
+    effective
+}
+
+/// Directive whose setting this one is part of, nginx keeps them in a single slot
+pub(crate) fn group(name: DirectiveName) -> DirectiveName {
+    match name {
+        DirectiveName::Deny => DirectiveName::Allow,
+        DirectiveName::Alias => DirectiveName::Root,
+        name => name,
+    }
+}
+
+/// Blocks opening a nested context rather than setting anything
+fn is_context(name: DirectiveName) -> bool {
+    matches!(
+        name,
+        DirectiveName::Http | DirectiveName::Server | DirectiveName::Location | DirectiveName::If | DirectiveName::Upstream
+    )
+}
+
+/// Whether nested contexts get the directive when they don't set it themselves
+fn is_inherited(name: DirectiveName) -> bool {
+    !matches!(
+        name,
+        // rewrite module directives run in the context they're written in
+        DirectiveName::Rewrite
+            | DirectiveName::Return
+            | DirectiveName::Break
+            | DirectiveName::Set
+            // content handlers
+            | DirectiveName::TryFiles
+            | DirectiveName::ProxyPass
+            | DirectiveName::FastcgiPass
+            | DirectiveName::GrpcPass
+            | DirectiveName::UwsgiPass
+            | DirectiveName::ScgiPass
+            | DirectiveName::MemcachedPass
+            | DirectiveName::ContentByLua
+            | DirectiveName::ContentByLuaBlock
+            | DirectiveName::ContentByLuaFile
+            | DirectiveName::JsContent
+            | DirectiveName::Perl
+            | DirectiveName::StubStatus
+            | DirectiveName::Api
+            | DirectiveName::EmptyGif
+            | DirectiveName::Flv
+            | DirectiveName::Mp4
+            | DirectiveName::Hls
+            // location properties
+            | DirectiveName::Internal
+            | DirectiveName::LimitExcept
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+http {
+    root /srv/www;
+    add_header X-Frame-Options DENY;
+    add_header X-Content-Type-Options nosniff;
+    proxy_set_header Host $host;
+    allow 10.0.0.0/8;
+    deny all;
+    server {
+        return 301 https://$host$request_uri;
+        location / {
+            alias /srv/static/;
+            add_header Cache-Control no-store;
+            try_files $uri =404;
+        }
+    }
+}
+";
+
+    /// Arguments of each statement in effect for the directive
+    fn values(settings: &EffectiveSettings, name: DirectiveName) -> Vec<String> {
+        settings
+            .statements(name)
+            .map(|statement| {
+                let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+                args.iter().skip(1).map(|arg| arg.value()).collect::<Vec<_>>().join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn contexts_replace_what_they_inherit() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let Structure::Block { children, .. } = &root else { unreachable!() };
+        let http = &children[0];
+        let Structure::Block { children, .. } = http else { unreachable!() };
+        let server = &children[children.len() - 1];
+        let Structure::Block { children, .. } = server else { unreachable!() };
+        let location = &children[children.len() - 1];
+
+        let server_settings = effective_config(&[http, server]);
+        assert_eq!(values(&server_settings, DirectiveName::AddHeader), ["X-Frame-Options DENY", "X-Content-Type-Options nosniff"]);
+        assert_eq!(server_settings.setting(DirectiveName::AddHeader).unwrap().context, 0);
+        assert_eq!(values(&server_settings, DirectiveName::Return), ["301 https://$host$request_uri"]);
+        assert_eq!(server_settings.args(DirectiveName::Root), Some(vec!["/srv/www"]));
+
+        let settings = effective_config(&[http, server, location]);
+        // a single add_header drops all of the inherited ones
+        assert_eq!(values(&settings, DirectiveName::AddHeader), ["Cache-Control no-store"]);
+        assert_eq!(settings.setting(DirectiveName::AddHeader).unwrap().context, 2);
+        assert_eq!(values(&settings, DirectiveName::ProxySetHeader), ["Host $host"]);
+        // alias replaces root, allow and deny stay together
+        assert_eq!(settings.args(DirectiveName::Root), None);
+        assert_eq!(settings.args(DirectiveName::Alias), Some(vec!["/srv/static/"]));
+        assert_eq!(values(&settings, DirectiveName::Deny), ["all"]);
+        assert_eq!(settings.setting(DirectiveName::Deny).unwrap().statements.len(), 2);
+        // return only applies to the server it's written in
+        assert!(values(&settings, DirectiveName::Return).is_empty());
+        assert_eq!(values(&settings, DirectiveName::TryFiles), ["$uri =404"]);
+        assert!(settings.setting(DirectiveName::Location).is_none());
+        assert_eq!(settings.directives(DirectiveName::AddHeader).count(), 1);
+    }
+}
@@ -0,0 +1,270 @@
+use url::Url;
+
+use crate::{
+    effective::EffectiveSettings,
+    filesystem::FilesystemView,
+    locations::location_paths,
+    names::DirectiveName,
+    router::{Action, Request, Route, Router},
+    types::{Directive, ErrorPageStatus, Location, LocationBlock, ServerBlock, ServerNamePattern},
+};
+
+/// Internal redirects nginx follows before answering 500
+const MAX_REDIRECTS: usize = 10;
+
+/// Where an error ends up being handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorHandler {
+    /// nginx's built-in page, no `error_page` applies
+    Builtin(u16),
+    /// The upstream's own error page is passed through, `*_intercept_errors` is off
+    /// Errors nginx produces itself in the location, such as 413 or 403 from access rules, still go through `error_page`
+    Upstream { directive: DirectiveName },
+    /// File served by the location the error page redirects to, with the status sent
+    Static { path: String, status: u16 },
+    /// Proxied by the location the error page redirects to, `status` is `None` when the upstream's is sent
+    Pass { directive: DirectiveName, target: String, status: Option<u16> },
+    /// `return` of the location the error page redirects to
+    Return { code: u16, content: Option<String> },
+    /// Redirect to another URL, either by `error_page` itself or the location it redirects to
+    Redirect { code: u16, url: String },
+    /// Error pages redirect to one another more times than nginx allows, it answers 500
+    Cycle,
+}
+
+/// One internal redirect of an error page
+#[derive(Debug, Clone)]
+pub struct ErrorHop {
+    /// Error being handled
+    pub code: u16,
+    /// `error_page` target as written
+    pub target: String,
+    /// Location the target lands in, `None` when none matches
+    pub location: Option<Location>,
+}
+
+/// How an error happening in a location is handled
+#[derive(Debug, Clone)]
+pub struct ErrorFlow {
+    pub code: u16,
+    pub hops: Vec<ErrorHop>,
+    pub handler: ErrorHandler,
+}
+
+/// Error flows of one location
+#[derive(Debug, Clone)]
+pub struct LocationErrors<'r, 'a> {
+    pub server: &'r ServerBlock<'a>,
+    /// The location and the ones enclosing it, outermost first
+    pub locations: Vec<&'r LocationBlock<'a>>,
+    pub flows: Vec<ErrorFlow>,
+}
+
+/// Follows the `error_page` directives in effect when `code` happens while handling `route`,
+/// with `recursive_error_pages` and `*_intercept_errors` of each location on the way
+/// Targets served from disk that don't exist on `filesystem` fail with 404, without one they're taken to exist
+pub fn trace_error(
+    router: &Router,
+    route: &Route,
+    request: &Request,
+    code: u16,
+    filesystem: Option<&dyn FilesystemView>,
+) -> ErrorFlow {
+    let mut flow = ErrorFlow { code, hops: Vec::new(), handler: ErrorHandler::Builtin(code) };
+    let mut route = route.clone();
+    let mut code = code;
+    loop {
+        let settings = router.effective_config(&route);
+        if flow.hops.is_empty() {
+            if let Some(directive) = passed_through(&settings, code) {
+                flow.handler = ErrorHandler::Upstream { directive };
+                return flow
+            }
+        } else if !enabled(&settings, DirectiveName::RecursiveErrorPages) {
+            flow.handler = ErrorHandler::Builtin(code);
+            return flow
+        }
+        let error_page = settings.directives(DirectiveName::ErrorPage).find_map(|directive| match directive {
+            Directive::ErrorPage { codes, status, target } if codes.contains(&code) => Some((status, target)),
+            _ => None,
+        });
+        let Some((status, target)) = error_page else {
+            flow.handler = ErrorHandler::Builtin(code);
+            return flow
+        };
+        if flow.hops.len() >= MAX_REDIRECTS {
+            flow.handler = ErrorHandler::Cycle;
+            return flow
+        }
+        if Url::parse(&target).is_ok() {
+            let code = match status {
+                ErrorPageStatus::Code(code @ (301 | 302 | 303 | 307 | 308)) => code,
+                _ => 302,
+            };
+            flow.handler = ErrorHandler::Redirect { code, url: target };
+            return flow
+        }
+
+        let next = router.internal_redirect(route.server, request, &target, filesystem);
+        let location = next.locations.last().map(|l| l.location.clone());
+        flow.hops.push(ErrorHop { code, target, location });
+        let sent = |target_status: Option<u16>| match status {
+            ErrorPageStatus::Original => Some(code),
+            ErrorPageStatus::Code(code) => Some(code),
+            ErrorPageStatus::FromTarget => target_status,
+        };
+        match &next.action {
+            Action::Static { path } if filesystem.is_some_and(|filesystem| !filesystem.is_file(path)) => code = 404,
+            Action::Static { path } => {
+                flow.handler = ErrorHandler::Static { path: path.clone(), status: sent(Some(200)).unwrap_or(200) };
+                return flow
+            },
+            Action::Pass { directive, target } => {
+                flow.handler = ErrorHandler::Pass { directive: *directive, target: target.clone(), status: sent(None) };
+                return flow
+            },
+            Action::Return { code: returned @ 400.., .. } => code = *returned,
+            Action::Return { code: returned @ (301 | 302 | 303 | 307 | 308), content: Some(url) } => {
+                flow.handler = ErrorHandler::Redirect { code: *returned, url: url.clone() };
+                return flow
+            },
+            Action::Return { code, content } => {
+                flow.handler = ErrorHandler::Return { code: *code, content: content.clone() };
+                return flow
+            },
+        }
+        route = next;
+    }
+}
+
+/// Error flows of `codes` for every location of every server, nested ones included
+/// Each location is traced with a GET of its path, regex locations with `/`
+pub fn error_report<'r, 'a>(
+    router: &'r Router<'a>,
+    codes: &[u16],
+    filesystem: Option<&dyn FilesystemView>,
+) -> Vec<LocationErrors<'r, 'a>> {
+    let mut report = Vec::new();
+    for server in &router.servers.servers {
+        let host = server.names.iter().find_map(|name| match name {
+            ServerNamePattern::Exact(name) if !name.is_empty() => Some(name.as_str()),
+            _ => None,
+        });
+        for locations in location_paths(&server.locations) {
+            let uri = match &locations.last().map(|l| &l.location) {
+                Some(Location::Exact(path) | Location::Prefix(path) | Location::PriorityPrefix(path)) => path.clone(),
+                _ => "/".to_string(),
+            };
+            let Ok(mut request) = Request::get(&format!("http://{}/", host.unwrap_or("localhost"))) else {
+                continue;
+            };
+            request.uri = uri.clone();
+            let route = Route {
+                server,
+                locations: locations.clone(),
+                ifs: Vec::new(),
+                rewrites: Vec::new(),
+                trace: Vec::new(),
+                uri,
+                args: None,
+                action: Action::Return { code: 200, content: None },
+            };
+            let flows = codes.iter().map(|code| trace_error(router, &route, &request, *code, filesystem)).collect();
+            report.push(LocationErrors { server, locations, flows });
+        }
+    }
+    report
+}
+
+/// The `*_pass` directive sending the upstream's error response as is, `None` when nginx handles the error
+fn passed_through(settings: &EffectiveSettings, code: u16) -> Option<DirectiveName> {
+    let (directive, intercept) = [
+        (DirectiveName::ProxyPass, DirectiveName::ProxyInterceptErrors),
+        (DirectiveName::FastcgiPass, DirectiveName::FastcgiInterceptErrors),
+        (DirectiveName::GrpcPass, DirectiveName::GrpcInterceptErrors),
+        (DirectiveName::UwsgiPass, DirectiveName::UwsgiInterceptErrors),
+        (DirectiveName::ScgiPass, DirectiveName::ScgiInterceptErrors),
+    ]
+    .into_iter()
+    .find(|(directive, _)| settings.args(*directive).is_some())?;
+    // nginx produces these itself when the upstream can't be reached
+    let unreachable = matches!(code, 502 | 504);
+    (!unreachable && !enabled(settings, intercept)).then_some(directive)
+}
+
+fn enabled(settings: &EffectiveSettings, name: DirectiveName) -> bool {
+    settings.args(name).is_some_and(|args| args == ["on"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{filesystem::FilesystemSnapshot, Structure};
+
+    const CONFIG: &str = "\
+http {
+    server {
+        listen 80;
+        server_name example.com;
+        root /srv/www;
+        error_page 404 /404.html;
+        error_page 500 502 = @fallback;
+        location / { }
+        location /api/ {
+            proxy_pass http://127.0.0.1:8080;
+            location /api/strict/ { proxy_pass http://127.0.0.1:8080; proxy_intercept_errors on; }
+        }
+        location @fallback { proxy_pass http://127.0.0.1:8081; }
+        location /moved { error_page 404 =301 https://example.org/; }
+        location /loop { recursive_error_pages on; error_page 404 /loop/missing; }
+        location /pages { error_page 404 /missing.html; }
+    }
+}
+";
+
+    /// Each location followed by how its errors are handled
+    fn report(codes: &[u16], filesystem: Option<&dyn FilesystemView>) -> Vec<(String, Vec<ErrorHandler>)> {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        error_report(&router, codes, filesystem)
+            .into_iter()
+            .map(|errors| {
+                let path = errors
+                    .locations
+                    .iter()
+                    .map(|location| {
+                        let (Structure::Statement { args } | Structure::Block { args, .. }) = location.block;
+                        args.iter().skip(1).map(|arg| arg.value()).collect::<Vec<_>>().join(" ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" > ");
+                (path, errors.flows.into_iter().map(|flow| flow.handler).collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn errors_follow_error_pages() {
+        let static_page = |path: &str| ErrorHandler::Static { path: path.to_string(), status: 404 };
+        let fallback = ErrorHandler::Pass { directive: DirectiveName::ProxyPass, target: "http://127.0.0.1:8081".to_string(), status: None };
+        let upstream = ErrorHandler::Upstream { directive: DirectiveName::ProxyPass };
+        let moved = ErrorHandler::Redirect { code: 301, url: "https://example.org/".to_string() };
+        let expected = [
+            ("/", vec![static_page("/srv/www/404.html"), fallback.clone()]),
+            ("/api/", vec![upstream.clone(), upstream.clone()]),
+            ("/api/ > /api/strict/", vec![static_page("/srv/www/404.html"), fallback]),
+            ("@fallback", vec![upstream.clone(), upstream]),
+            // `error_page` of the location replaces the server's ones
+            ("/moved", vec![moved.clone(), ErrorHandler::Builtin(500)]),
+            ("/loop", vec![static_page("/srv/www/loop/missing"), ErrorHandler::Builtin(500)]),
+            ("/pages", vec![static_page("/srv/www/missing.html"), ErrorHandler::Builtin(500)]),
+        ];
+        let expected = expected.into_iter().map(|(path, handlers)| (path.to_string(), handlers)).collect::<Vec<_>>();
+        assert_eq!(report(&[404, 500], None), expected);
+
+        // missing pages fail with 404 again, handled by the next error_page if recursive_error_pages is on
+        let filesystem = FilesystemSnapshot::with_files(["/srv/www/404.html"]);
+        let handlers = report(&[404], Some(&filesystem)).into_iter().flat_map(|(_, handlers)| handlers).collect::<Vec<_>>();
+        assert_eq!(handlers[4..], [moved, ErrorHandler::Cycle, ErrorHandler::Builtin(404)]);
+    }
+}
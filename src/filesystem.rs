@@ -0,0 +1,135 @@
+use std::{collections::BTreeSet, fmt::Debug, path::PathBuf};
+
+/// Files as nginx would see them, for `if` file tests and `try_files`
+pub trait FilesystemView: Debug {
+    fn is_file(&self, path: &str) -> bool;
+    fn is_dir(&self, path: &str) -> bool;
+    fn is_executable(&self, path: &str) -> bool;
+
+    /// `-e`, a file, directory or symlink
+    fn exists(&self, path: &str) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+}
+
+/// In-memory set of files, the directories holding them exist too
+#[derive(Debug, Clone, Default)]
+pub struct FilesystemSnapshot {
+    pub files: BTreeSet<String>,
+    /// Directories in addition to the ones holding files
+    pub dirs: BTreeSet<String>,
+    pub executables: BTreeSet<String>,
+}
+
+impl FilesystemSnapshot {
+    /// Snapshot of these file paths, such as `/srv/www/index.html`
+    pub fn with_files<'f>(files: impl IntoIterator<Item = &'f str>) -> Self {
+        Self { files: files.into_iter().map(normalize).collect(), ..Self::default() }
+    }
+
+    pub fn add_file(&mut self, path: &str) {
+        self.files.insert(normalize(path));
+    }
+
+    pub fn add_dir(&mut self, path: &str) {
+        self.dirs.insert(normalize(path));
+    }
+}
+
+impl FilesystemView for FilesystemSnapshot {
+    fn is_file(&self, path: &str) -> bool {
+        self.files.contains(&normalize(path))
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        let path = normalize(path);
+        if self.dirs.contains(&path) {
+            return true
+        }
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        self.files.iter().chain(&self.dirs).any(|entry| entry.starts_with(&prefix))
+    }
+
+    fn is_executable(&self, path: &str) -> bool {
+        self.executables.contains(&normalize(path))
+    }
+}
+
+/// Path without repeated or trailing slashes
+fn normalize(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c != '/' || !normalized.ends_with('/') {
+            normalized.push(c);
+        }
+    }
+    match normalized.len() > 1 {
+        true => normalized.trim_end_matches('/').to_string(),
+        false => normalized,
+    }
+}
+
+/// The filesystem of this machine
+#[derive(Debug, Clone, Default)]
+pub struct RealFilesystem {
+    /// Relative paths, such as the default `html` root, are resolved against it, as nginx does with its prefix
+    pub prefix: PathBuf,
+}
+
+impl RealFilesystem {
+    fn path(&self, path: &str) -> PathBuf {
+        self.prefix.join(path)
+    }
+}
+
+impl FilesystemView for RealFilesystem {
+    fn is_file(&self, path: &str) -> bool {
+        self.path(path).is_file()
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        self.path(path).is_dir()
+    }
+
+    #[cfg(unix)]
+    fn is_executable(&self, path: &str) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        self.path(path).metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(not(unix))]
+    fn is_executable(&self, path: &str) -> bool {
+        self.is_file(path)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.path(path).symlink_metadata().is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_directories_hold_their_files() {
+        let mut snapshot = FilesystemSnapshot::with_files(["/srv/www//index.html", "/srv/www/assets/app.js"]);
+        snapshot.add_dir("/srv/empty/");
+        snapshot.executables.insert("/srv/cgi/run".to_string());
+        assert!(snapshot.is_file("/srv/www/index.html") && !snapshot.is_dir("/srv/www/index.html"));
+        assert!(snapshot.is_dir("/srv/www/") && snapshot.is_dir("/srv") && snapshot.is_dir("/srv/empty") && snapshot.is_dir("/"));
+        assert!(!snapshot.is_dir("/srv/ww") && !snapshot.exists("/srv/www/missing.html"));
+        assert!(snapshot.exists("/srv/www/assets") && snapshot.is_executable("/srv/cgi/run"));
+    }
+
+    #[test]
+    fn real_paths_are_taken_from_the_prefix() {
+        let prefix = std::env::temp_dir().join(format!("nginx-config-parser-{}-filesystem", std::process::id()));
+        let _ = std::fs::remove_dir_all(&prefix);
+        std::fs::create_dir_all(prefix.join("html")).unwrap();
+        std::fs::write(prefix.join("html/index.html"), "").unwrap();
+        let filesystem = RealFilesystem { prefix: prefix.clone() };
+        assert!(filesystem.is_dir("html") && filesystem.is_file("html/index.html") && !filesystem.is_executable("html/index.html"));
+        assert!(filesystem.exists(&prefix.join("html").to_string_lossy()) && !filesystem.exists("html/missing.html"));
+    }
+}
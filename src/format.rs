@@ -0,0 +1,327 @@
+use logos::Logos;
+
+use crate::{is_lua_block, lua_block_length, Structure, Token};
+
+/// How `format` lays a config out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Spaces per nesting level
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self { indent: 4 }
+    }
+}
+
+/// Reformats a config: one statement per line, blocks indented by nesting level,
+/// arguments separated by a single space and blank lines collapsed to one
+///
+/// Comments are kept, on their own line or trailing the statement they follow, after its `;` or `{`,
+/// statements written over several lines keep their line breaks with the continuation lines indented once more,
+/// and the bodies of `*_by_lua_block` are left as written
+/// Fails on text that doesn't lex, and on unbalanced braces
+pub fn format(source: &str, options: FormatOptions) -> Result<String, String> {
+    let mut printer = Printer::new(options);
+    // lines of the statement being read
+    let mut statement = vec![Line::default()];
+    let mut lex = Token::lexer(source).spanned();
+    while let Some((token, span)) = lex.next() {
+        let token = token.map_err(|()| format!("{span:?}"))?;
+        let pending = statement.iter().any(|line| !line.words.is_empty());
+        match token {
+            Token::Newline if pending => {
+                if statement.last().is_some_and(|line| !line.is_empty()) {
+                    statement.push(Line::default());
+                }
+            },
+            Token::Newline => printer.newlines += 1,
+            // trails the line of the statement it's written on
+            Token::Comment(_) if pending => {
+                if let Some(line) = statement.last_mut() {
+                    line.comment = Some(token.to_string());
+                }
+                statement.push(Line::default());
+            },
+            Token::Comment(_) => printer.comment(&token.to_string()),
+            Token::Semicolon => printer.statement(&std::mem::replace(&mut statement, vec![Line::default()]), ";"),
+            Token::BracketOpen => {
+                let lines = std::mem::replace(&mut statement, vec![Line::default()]);
+                let name = lines.first().and_then(|line| line.words.first()).map(String::as_str).unwrap_or_default();
+                if is_lua_block(&[Token::Word(name)]) {
+                    let length = lua_block_length(lex.remainder()).ok_or_else(|| format!("{:?}", lex.span()))?;
+                    let body = &lex.remainder()[..length];
+                    lex.bump(length + 1);
+                    printer.statement(&lines, &format!(" {{{body}}}"));
+                } else {
+                    printer.statement(&lines, " {");
+                    printer.depth += 1;
+                    printer.at_block_start = true;
+                }
+            },
+            Token::BracketClose => {
+                if pending {
+                    printer.statement(&std::mem::replace(&mut statement, vec![Line::default()]), "");
+                }
+                printer.depth = printer.depth.checked_sub(1).ok_or_else(|| format!("unbalanced `}}` at {span:?}"))?;
+                printer.newlines = 0;
+                printer.line("}");
+                printer.line_has_code = true;
+            },
+            Token::Word(_) | Token::QuotedString(_) | Token::BracedString(_) | Token::LuaBlock(_) => {
+                if let Some(line) = statement.last_mut() {
+                    line.words.push(token.to_string());
+                }
+            },
+        }
+    }
+    if statement.iter().any(|line| !line.words.is_empty()) {
+        printer.statement(&statement, "");
+    }
+    if printer.depth > 0 {
+        return Err(format!("{} unclosed block(s) at the end", printer.depth))
+    }
+    Ok(printer.out)
+}
+
+/// Writes a tree out the way `format` lays configs out, for trees changed in place as by `deprecated::modernize`
+///
+/// Trees don't hold comments or blank lines, those of the config the tree was parsed from are lost
+pub fn print(root: &Structure, options: FormatOptions) -> String {
+    let mut printer = Printer::new(options);
+    if let Structure::Block { children, .. } = root {
+        for child in children {
+            print_structure(&mut printer, child);
+        }
+    }
+    printer.out
+}
+
+fn print_structure(printer: &mut Printer, structure: &Structure) {
+    let (Structure::Statement { args } | Structure::Block { args, .. }) = structure;
+    let line = Line { words: args.iter().map(Token::to_string).collect(), comment: None };
+    match structure {
+        Structure::Statement { .. } if matches!(args.last(), Some(Token::LuaBlock(_))) => printer.statement(&[line], ""),
+        Structure::Statement { .. } => printer.statement(&[line], ";"),
+        Structure::Block { children, .. } => {
+            printer.statement(&[line], " {");
+            printer.depth += 1;
+            printer.at_block_start = true;
+            for child in children {
+                print_structure(printer, child);
+            }
+            printer.depth -= 1;
+            printer.line("}");
+        },
+    }
+}
+
+/// Whether `formatted` holds the same statements and comments as `source`, what `format` must keep
+/// Fails with the first difference, or when either doesn't parse
+pub fn check_round_trip(source: &str, formatted: &str) -> Result<(), String> {
+    let expected = Structure::parse(source)?;
+    let got = Structure::parse(formatted).map_err(|error| format!("formatted config doesn't parse: {error}"))?;
+    same_structure(&expected, &got)?;
+    let comments = |text: &str| {
+        Token::lexer(text).filter_map(|token| match token {
+            Ok(comment @ Token::Comment(_)) => Some(comment.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+    };
+    if comments(source) != comments(formatted) {
+        return Err("formatting would change comments".to_string())
+    }
+    Ok(())
+}
+
+fn same_structure(expected: &Structure, got: &Structure) -> Result<(), String> {
+    let (Structure::Statement { args: expected_args } | Structure::Block { args: expected_args, .. }) = expected;
+    let (Structure::Statement { args: got_args } | Structure::Block { args: got_args, .. }) = got;
+    let words = |args: &[Token]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    if words(expected_args) != words(got_args) {
+        return Err(format!("formatting would change `{}` into `{}`", words(expected_args).join(" "), words(got_args).join(" ")))
+    }
+    match (expected, got) {
+        (Structure::Statement { .. }, Structure::Statement { .. }) => Ok(()),
+        (Structure::Block { children: expected, .. }, Structure::Block { children: got, .. }) if expected.len() == got.len() => {
+            expected.iter().zip(got).try_for_each(|(expected, got)| same_structure(expected, got))
+        },
+        _ => Err(format!("formatting would change the block `{}`", words(expected_args).join(" "))),
+    }
+}
+
+/// A line of a statement written over several
+#[derive(Default)]
+struct Line {
+    /// Arguments as written
+    words: Vec<String>,
+    /// Comment ending the line
+    comment: Option<String>,
+}
+
+impl Line {
+    fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.comment.is_none()
+    }
+
+    fn text(&self) -> String {
+        self.words.join(" ")
+    }
+}
+
+struct Printer {
+    options: FormatOptions,
+    out: String,
+    depth: usize,
+    /// Line breaks since the last statement, brace or comment, a comment counting its own
+    newlines: usize,
+    /// The last line written ends with code a comment can trail
+    line_has_code: bool,
+    /// Nothing was written in the current block yet, blank lines are dropped
+    at_block_start: bool,
+}
+
+impl Printer {
+    fn new(options: FormatOptions) -> Self {
+        Self { options, out: String::new(), depth: 0, newlines: 0, line_has_code: false, at_block_start: true }
+    }
+
+    /// Starts a new line at the current depth, after a blank one if the source had any
+    fn line(&mut self, text: &str) {
+        if self.newlines >= 2 && !self.at_block_start && !text.starts_with('}') {
+            self.out.push('\n');
+        }
+        self.at_block_start = false;
+        self.push(self.depth, text);
+    }
+
+    fn push(&mut self, depth: usize, text: &str) {
+        self.out.push_str(&" ".repeat(depth * self.options.indent));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// The end (`;`, ` {`...) goes on the last line with code, before its comment,
+    /// lines holding only a comment after it are kept after it
+    fn statement(&mut self, lines: &[Line], end: &str) {
+        let last_code = lines.iter().rposition(|line| !line.words.is_empty());
+        // a comment can trail the last line written when it's code without a comment
+        let trailable = !end.is_empty()
+            && last_code.is_some_and(|index| lines[index].comment.is_none())
+            && last_code == lines.iter().rposition(|line| !line.is_empty());
+        let mut lines = lines.iter().enumerate().filter(|(_, line)| !line.is_empty()).map(|(index, line)| {
+            let mut text = line.text();
+            if Some(index) == last_code {
+                text.push_str(end);
+            }
+            if let Some(comment) = &line.comment {
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                text.push_str(comment);
+            }
+            (index, text)
+        });
+        if let Some((_, first)) = lines.next() {
+            self.line(&first);
+        }
+        for (index, line) in lines {
+            // comments after the end are on their own
+            let continued = last_code.is_some_and(|last| index <= last);
+            self.push(self.depth + usize::from(continued), &line);
+        }
+        self.newlines = 0;
+        self.line_has_code = trailable;
+    }
+
+    fn comment(&mut self, comment: &str) {
+        if self.newlines == 0 && self.line_has_code {
+            self.out.pop();
+            self.out.push(' ');
+            self.out.push_str(comment);
+            self.out.push('\n');
+        } else {
+            self.line(comment);
+        }
+        // the comment token holds its line break
+        self.newlines = 1;
+        self.line_has_code = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Formats `source`, checking the result reads the same and formats to itself
+    fn formatted(source: &str) -> String {
+        let formatted = format(source, FormatOptions::default()).unwrap();
+        check_round_trip(source, &formatted).unwrap();
+        assert_eq!(format(&formatted, FormatOptions::default()).unwrap(), formatted, "not idempotent");
+        formatted
+    }
+
+    #[test]
+    fn indents_blocks() {
+        assert_eq!(
+            formatted("http {\nserver  {  listen   80;\n\n\n\nroot /srv; }\n}\n"),
+            "http {\n    server {\n        listen 80;\n\n        root /srv;\n    }\n}\n",
+        );
+        assert_eq!(format("a {\n\tb;\n}\n", FormatOptions { indent: 2 }).unwrap(), "a {\n  b;\n}\n");
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source = "# top\nhttp { # http\n    # inside\n    root /srv; # trailing\n}\n# end\n";
+        assert_eq!(formatted(source), source);
+    }
+
+    #[test]
+    fn ends_statements_before_their_trailing_comment() {
+        assert_eq!(formatted("server # main\n{\n}\n"), "server { # main\n}\n");
+        assert_eq!(formatted("proxy_pass http://x # c\n;\n"), "proxy_pass http://x; # c\n");
+        assert_eq!(formatted("proxy_pass http://x\n# c\n;\n"), "proxy_pass http://x;\n# c\n");
+    }
+
+    #[test]
+    fn keeps_continuation_lines() {
+        assert_eq!(
+            formatted("log_format main '$a' # first\n'$b';\n"),
+            "log_format main '$a' # first\n    '$b';\n",
+        );
+    }
+
+    #[test]
+    fn keeps_if_regexes_together() {
+        let source = "if ($request_method !~ ^(GET|POST|HEAD)$) {\n    return 405;\n}\n";
+        assert_eq!(formatted(source), source);
+        let root = Structure::parse(source).unwrap();
+        assert_eq!(print(&root, FormatOptions::default()), source);
+        assert_eq!(formatted("if ($a ~* \"(x)\" ) { return 403; }"), "if ($a ~* \"(x)\" ) {\n    return 403;\n}\n");
+    }
+
+    #[test]
+    fn keeps_lua_blocks_verbatim() {
+        let source = "location / {\ncontent_by_lua_block {\n  local s = \"}\" -- }\n    ngx.say(s)\n}\n}\n";
+        assert_eq!(
+            formatted(source),
+            "location / {\n    content_by_lua_block {\n  local s = \"}\" -- }\n    ngx.say(s)\n}\n}\n",
+        );
+    }
+
+    #[test]
+    fn fails_on_unbalanced_braces() {
+        assert!(format("a {\n", FormatOptions::default()).is_err());
+        assert!(format("}\n", FormatOptions::default()).is_err());
+    }
+
+    #[test]
+    fn round_trip_check_finds_changes() {
+        assert!(check_round_trip("a b;", "a  b;\n").is_ok());
+        assert!(check_round_trip("a b$c;", "a b $c;").is_err());
+        assert!(check_round_trip("a { b; }", "a { } b;").is_err());
+        assert!(check_round_trip("a; # c\n", "a;\n").is_err());
+    }
+}
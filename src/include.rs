@@ -0,0 +1,233 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{names::DirectiveName, types::Directive, Span, Structure};
+
+/// Guards against files including each other
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// A path as nginx reads it, relative ones are taken from `prefix`
+pub fn full_path(path: impl AsRef<Path>, prefix: &Path) -> PathBuf {
+    match path.as_ref().is_absolute() {
+        true => path.as_ref().to_path_buf(),
+        false => prefix.join(path),
+    }
+}
+
+/// Files matching an `include` pattern, in the order nginx reads them
+/// Relative patterns are taken from `prefix`, the directory holding the main config file
+pub fn resolve(pattern: &str, prefix: &Path) -> Vec<PathBuf> {
+    let full = full_path(pattern, prefix);
+    if !is_glob(pattern) {
+        return match full.is_file() {
+            true => vec![full],
+            false => Vec::new(),
+        };
+    }
+    let mut paths = glob::glob(&full.to_string_lossy())
+        .map(|paths| paths.filter_map(Result::ok).filter(|p| p.is_file()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+/// Fills in `resolved` of an `include` directive, other directives are left alone
+pub fn resolve_directive(directive: &mut Directive, prefix: &Path) {
+    if let Directive::Include { pattern, resolved, .. } = directive {
+        *resolved = Some(resolve(pattern, prefix));
+    }
+}
+
+fn include_pattern(structure: &Structure) -> Option<String> {
+    if structure.name() != Some(DirectiveName::Include) {
+        return None
+    }
+    match Directive::try_from(structure) {
+        Ok(Directive::Include { pattern, .. }) => Some(pattern),
+        _ => None,
+    }
+}
+
+fn include_patterns(block: &Structure) -> Vec<String> {
+    let mut patterns = Vec::new();
+    if let Structure::Block { children, .. } = block {
+        for child in children {
+            patterns.extend(include_pattern(child));
+            patterns.extend(include_patterns(child));
+        }
+    }
+    patterns
+}
+
+/// The main config file and the sources of every file it includes, directly or not
+#[derive(Debug, Clone)]
+pub struct ConfigFiles {
+    pub prefix: PathBuf,
+    pub main: PathBuf,
+    pub files: Vec<(PathBuf, String)>,
+}
+
+impl ConfigFiles {
+    pub fn load(main: impl AsRef<Path>) -> io::Result<Self> {
+        let main = main.as_ref().to_path_buf();
+        let prefix = main.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut config = Self { prefix, main: main.clone(), files: Vec::new() };
+        let mut pending = vec![main];
+        while let Some(path) = pending.pop() {
+            if config.source(&path).is_some() {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path)?;
+            if let Ok(tree) = Structure::parse(&source) {
+                for pattern in include_patterns(&tree) {
+                    pending.extend(resolve(&pattern, &config.prefix));
+                }
+            }
+            config.files.push((path, source));
+        }
+        Ok(config)
+    }
+
+    pub fn source(&self, path: &Path) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|(file, _)| file == path)
+            .map(|(_, source)| source.as_str())
+    }
+
+    /// File a statement of `tree` comes from, and where it's written there
+    pub fn locate(&self, statement: &Structure) -> Option<(&Path, Span)> {
+        self.files.iter().find_map(|(path, source)| Some((path.as_path(), statement.span(source)?)))
+    }
+
+    /// Main config tree with every `include` replaced by the contents of the files it matches
+    pub fn tree(&self) -> Result<Structure<'_>, String> {
+        self.parse_spliced(&self.main, 0)
+    }
+
+    fn parse_spliced(&self, path: &Path, depth: usize) -> Result<Structure<'_>, String> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(format!("{}: includes nested too deeply", path.display()))
+        }
+        let source = self
+            .source(path)
+            .ok_or_else(|| format!("{}: not loaded", path.display()))?;
+        let mut tree = Structure::parse(source).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.splice(&mut tree, depth)?;
+        Ok(tree)
+    }
+
+    fn splice<'s>(&'s self, block: &mut Structure<'s>, depth: usize) -> Result<(), String> {
+        let Structure::Block { children, .. } = block else {
+            return Ok(())
+        };
+        let mut spliced = Vec::with_capacity(children.len());
+        for mut child in std::mem::take(children) {
+            match include_pattern(&child) {
+                Some(pattern) => {
+                    for path in resolve(&pattern, &self.prefix) {
+                        if let Structure::Block { children: included, .. } = self.parse_spliced(&path, depth + 1)? {
+                            spliced.extend(included);
+                        }
+                    }
+                },
+                None => {
+                    self.splice(&mut child, depth)?;
+                    spliced.push(child);
+                },
+            }
+        }
+        *children = spliced;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of the temporary directory for this test
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("nginx-config-parser-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn include(source: &str) -> Directive {
+        let root = Structure::parse(source).unwrap();
+        let Structure::Block { children, .. } = &root else { unreachable!() };
+        Directive::try_from(&children[0]).unwrap()
+    }
+
+    /// Every statement of the tree, as written
+    fn statements(block: &Structure) -> Vec<String> {
+        let Structure::Block { children, .. } = block else { unreachable!() };
+        children
+            .iter()
+            .flat_map(|child| {
+                let (Structure::Statement { args } | Structure::Block { args, .. }) = child;
+                let statement = args.iter().map(|arg| arg.value()).collect::<Vec<_>>().join(" ");
+                let nested = match child {
+                    Structure::Block { .. } => statements(child),
+                    Structure::Statement { .. } => Vec::new(),
+                };
+                [statement].into_iter().chain(nested)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn patterns_resolve_in_name_order() {
+        let prefix = directory("include-resolve");
+        std::fs::create_dir_all(prefix.join("conf.d/skipped.conf")).unwrap();
+        for file in ["conf.d/b.conf", "conf.d/a.conf", "conf.d/c.txt", "mime.types"] {
+            std::fs::write(prefix.join(file), "").unwrap();
+        }
+        assert_eq!(resolve("conf.d/*.conf", &prefix), [prefix.join("conf.d/a.conf"), prefix.join("conf.d/b.conf")]);
+        assert_eq!(resolve(&prefix.join("mime.types").to_string_lossy(), Path::new("/elsewhere")), [prefix.join("mime.types")]);
+        assert!(resolve("sites/*.conf", &prefix).is_empty());
+
+        let mut glob = include("include conf.d/*.conf;");
+        let mut missing = include("include missing.conf;");
+        assert!(matches!(&glob, Directive::Include { is_glob: true, resolved: None, .. }));
+        resolve_directive(&mut glob, &prefix);
+        resolve_directive(&mut missing, &prefix);
+        assert!(matches!(glob, Directive::Include { resolved: Some(paths), .. } if paths.len() == 2));
+        assert!(matches!(missing, Directive::Include { is_glob: false, resolved: Some(paths), .. } if paths.is_empty()));
+    }
+
+    #[test]
+    fn included_files_are_spliced() {
+        let prefix = directory("include-splice");
+        std::fs::create_dir_all(prefix.join("sites")).unwrap();
+        std::fs::write(prefix.join("nginx.conf"), "http {\n    include sites/*.conf;\n    include missing.conf;\n}\n").unwrap();
+        std::fs::write(prefix.join("sites/a.conf"), "server { listen 80; include snippets.conf; }\n").unwrap();
+        std::fs::write(prefix.join("sites/b.conf"), "server { listen 81; }\n").unwrap();
+        std::fs::write(prefix.join("snippets.conf"), "root /srv;\n").unwrap();
+        let files = ConfigFiles::load(prefix.join("nginx.conf")).unwrap();
+        assert_eq!(files.files.len(), 4);
+        let tree = files.tree().unwrap();
+        assert_eq!(statements(&tree), ["http", "server", "listen 80", "root /srv", "server", "listen 81"]);
+
+        let Structure::Block { children: http, .. } = &tree else { unreachable!() };
+        let Structure::Block { children: servers, .. } = &http[0] else { unreachable!() };
+        let Structure::Block { children, .. } = &servers[0] else { unreachable!() };
+        let (file, span) = files.locate(&children[1]).unwrap();
+        assert_eq!((file, span.start.line, span.start.column), (prefix.join("snippets.conf").as_path(), 1, 1));
+    }
+
+    #[test]
+    fn include_cycles_fail() {
+        let prefix = directory("include-cycle");
+        std::fs::write(prefix.join("nginx.conf"), "include nginx.conf;\n").unwrap();
+        let files = ConfigFiles::load(prefix.join("nginx.conf")).unwrap();
+        assert!(files.tree().unwrap_err().ends_with("includes nested too deeply"));
+    }
+}
@@ -1,7 +1,31 @@
 use std::fmt::Display;
 
 use logos::Logos;
-mod types;
+pub mod compression;
+pub mod cors;
+pub mod deprecated;
+pub mod directives;
+pub mod dns;
+pub mod effective;
+pub mod error_pages;
+pub mod file_checks;
+pub mod filesystem;
+pub mod format;
+pub mod include;
+pub mod lint;
+pub mod locations;
+pub mod mail;
+pub mod names;
+pub mod output;
+pub mod proxy;
+pub mod rate_limits;
+pub mod redirects;
+pub mod router;
+pub mod security;
+pub mod stream;
+pub mod tls;
+pub mod types;
+pub mod validate;
 
 #[derive(Debug, Logos, Copy, Clone)]
 #[logos(skip r"[ \t]+", error = ())]
@@ -21,35 +45,93 @@ pub enum Token<'a> {
     #[token(";", priority = 3)]
     Semicolon,
 
-    #[regex(r#"\([^\)]+\)"#)]
+    /// Up to the matching `)`, nested parentheses included, as in `if ($request_uri ~ ^/(a|b)$)`
+    /// Quoted strings and escaped characters inside may contain `)`, as in `if ($http_user_agent ~* "(iphone|android)")`
+    #[token("(", braced_string)]
     BracedString(&'a str),
 
-    #[regex(r#"\"[^\"]*\""#, priority = 4)]
+    #[regex(r#""([^"\\]|\\.)*"|'([^'\\]|\\.)*'"#, priority = 4)]
     QuotedString(&'a str),
 
-    #[regex(r#"(\w|\d|\[|\]|=|\^|\$|:|-|\+|!|\*|~|\'|\.|\/)+"#)]
+    #[regex(r#"[^\s;{}"'(#][^\s;{}"']*"#)]
     Word(&'a str),
+
+    /// Body of a `*_by_lua_block`, between (not including) the braces
+    /// Never produced by the lexer itself, the parser captures it verbatim
+    LuaBlock(&'a str),
+}
+
+impl<'a> Token<'a> {
+    /// Token content without the surrounding quotes or braces,
+    /// use `to_string()` to get the token as written in the config
+    pub fn value(&self) -> &'a str {
+        match self {
+            Self::Comment(c) => c.trim_end(),
+            Self::BracketOpen => "{",
+            Self::BracketClose => "}",
+            Self::Semicolon => ";",
+            Self::Newline => "\n",
+            Self::BracedString(s) | Self::QuotedString(s) => &s[1..s.len() - 1],
+            Self::Word(s) | Self::LuaBlock(s) => s,
+        }
+    }
 }
 
 impl<'l> Display for Token<'l> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Comment(c) => write!(f, "# {}", c.trim()),
+            Self::Comment(c) => write!(f, "{}", c.trim_end()),
             Self::BracketOpen => write!(f, "{{"),
             Self::BracketClose => write!(f, "}}"),
             Self::Semicolon => write!(f, ";"),
-            Self::BracedString(s) => write!(f, "({})", s),
-            Self::QuotedString(s) => write!(f, "\"{}\"", s),
+            Self::BracedString(s) => write!(f, "{}", s),
+            Self::QuotedString(s) => write!(f, "{}", s),
             Self::Word(s) => write!(f, "{}", s),
-            Self::Newline => write!(f, "\n"),
+            Self::LuaBlock(s) => write!(f, "{{{}}}", s),
+            Self::Newline => writeln!(f),
         }
     }
 }
 
+/// Line and column in a config, both starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Range of a config between two positions, the end is the column past the last character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Line and column of a byte offset in `source`, `None` past its end
+fn position_at(source: &str, offset: usize) -> Option<Position> {
+    let before = source.get(..offset)?;
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |at| at + 1) + 1;
+    Some(Position { line, column })
+}
+
 /// Statements are one-line, ';'-terminated directives
 /// Blocks are nulti-line, '{' and '}' enclosing directives
 /// Content of the directive preceding the ';'/'{' is stored in the `args` field as a `Vec<String>`
 /// The directive name is stored under args[0]
+/// `*_by_lua_block` directives are statements, their last argument is the raw Lua body
 #[derive(Debug, Clone)]
 pub enum Structure<'l> {
     Statement {
@@ -75,19 +157,73 @@ impl<'l> Structure<'l> {
         }
     }
 
+    /// Name of the directive when it's a known one, `None` for unknown directives and the root block
+    pub fn name(&self) -> Option<names::DirectiveName> {
+        let (Self::Statement { args } | Self::Block { args, .. }) = self;
+        args.first().and_then(|name| name.value().parse().ok())
+    }
+
+    /// Where the statement or block starts in `source`, the text it was parsed from,
+    /// `None` when it comes from another text (such as an included file)
+    pub fn position(&self, source: &str) -> Option<Position> {
+        self.span(source).map(|span| span.start)
+    }
+
+    /// Where the name and arguments are written in `source`, a block's children aren't included
+    /// `None` when it comes from another text (such as an included file)
+    pub fn span(&self, source: &str) -> Option<Span> {
+        let (Self::Statement { args } | Self::Block { args, .. }) = self;
+        let text = |token: &Token<'l>| match token {
+            Token::Word(s) | Token::QuotedString(s) | Token::BracedString(s) => Some(*s),
+            _ => None,
+        };
+        let first = text(args.first()?)?;
+        let last = args.iter().rev().find_map(text)?;
+        let offset = |s: &str| (s.as_ptr() as usize).checked_sub(source.as_ptr() as usize);
+        let start = position_at(source, offset(first)?)?;
+        let end = position_at(source, offset(last)? + last.len())?;
+        Some(Span { start, end })
+    }
+
+    /// Statements and blocks, at any depth, whose arguments reference `variable` (e.g. `$geoip_country_code`),
+    /// such as the `map` or `if` consuming a geo lookup
+    pub fn variable_uses(&self, variable: &str) -> Vec<&Structure<'l>> {
+        let uses_variable = |token: &Token| {
+            let text = token.value();
+            text.match_indices(variable).any(|(at, _)| {
+                !text[at + variable.len()..]
+                    .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            })
+        };
+        let mut uses = Vec::new();
+        if let Self::Block { children, .. } = self {
+            for child in children {
+                let args = match child {
+                    Self::Statement { args } | Self::Block { args, .. } => args,
+                };
+                if args.iter().skip(1).any(uses_variable) {
+                    uses.push(child);
+                }
+                uses.extend(child.variable_uses(variable));
+            }
+        }
+        uses
+    }
+
     /// @warning 
-    /// ```
+    /// ```text
     /// This is a dumb parser used to read existing nginx configs
     /// It doesn't validate the configuration
     /// ```
     /// @example
-    /// ```
+    /// ```no_run
+    ///    # use nginx_config_parser::Structure;
     ///    let cfg = std::fs::read_to_string("./example.conf").unwrap();
-    ///    let cfg = Structure::parse(cfg);
+    ///    let cfg = Structure::parse(&cfg);
     ///```
     ///  
     pub fn parse(cfg: &'l str) -> Result<Self, String> {
-        let mut lex = Token::lexer(&cfg).spanned();
+        let mut lex = Token::lexer(cfg).spanned();
         let mut stack = Vec::new();
         let mut current_block = Self::Block {
             args: Vec::new(),
@@ -104,37 +240,34 @@ impl<'l> Structure<'l> {
             };
 
             match token {
+                Token::BracketOpen if is_lua_block(current_statement.args()) => {
+                    let length = lua_block_length(lex.remainder())
+                        .ok_or_else(|| format!("{:?}", lex.span()))?;
+                    let body = &lex.remainder()[..length];
+                    lex.bump(length + 1);
+                    current_statement.args().push(Token::LuaBlock(body));
+                    current_block.children().push(current_statement);
+                    current_statement = Self::Statement { args: Vec::new() };
+                }
+
                 Token::BracketOpen => {
                     stack.push(current_block.clone());
                     current_block = Self::Block {
-                        args: current_statement
-                            .args()
-                            .iter()
-                            .map(|v| v.clone())
-                            .collect::<Vec<_>>(),
+                        args: current_statement.args().clone(),
                         children: Vec::new(),
                     };
                     current_statement = Self::Statement { args: Vec::new() };
                 }
 
                 Token::BracketClose => {
-                    if current_statement.args().len() > 0 {
+                    if !current_statement.args().is_empty() {
                         current_block.children().push(Self::Statement {
                             args: current_statement.args().clone(),
                         });
                     }
-                    if let Some(parent_block) = stack.pop() {
-                        if let Self::Block { args, mut children } = parent_block {
-                            children.push(current_block.clone());
-                            stack.push(Self::Block {
-                                args: args.clone(),
-                                children: children.clone(),
-                            });
-                            current_block = Self::Block {
-                                args: args,
-                                children: children.clone(),
-                            }
-                        }
+                    if let Some(mut parent_block) = stack.pop() {
+                        parent_block.children().push(current_block);
+                        current_block = parent_block;
                     }
                     current_statement = Self::Statement { args: Vec::new() };
                 }
@@ -153,6 +286,7 @@ impl<'l> Structure<'l> {
                 Token::QuotedString(_content) => { current_statement.args().push(token); },
                 Token::BracedString(_content) => { current_statement.args().push(token); },
                 Token::Word(_content) => { current_statement.args().push(token); }
+                Token::LuaBlock(_content) => unreachable!(),
             }
         }
         
@@ -164,4 +298,157 @@ impl<'l> Structure<'l> {
     }
 }
 
+/// Reads a braced string after its `(`, `None` when it isn't closed
+fn braced_string<'a>(lex: &mut logos::Lexer<'a, Token<'a>>) -> Option<&'a str> {
+    let bytes = lex.remainder().as_bytes();
+    let mut depth = 1usize;
+    let mut at = 0;
+    while at < bytes.len() {
+        match bytes[at] {
+            b'(' => depth += 1,
+            b')' if depth == 1 => {
+                lex.bump(at + 1);
+                return Some(lex.slice())
+            }
+            b')' => depth -= 1,
+            b'\\' => at += 1,
+            quote @ (b'"' | b'\'') => {
+                at += 1;
+                while at < bytes.len() && bytes[at] != quote {
+                    at += if bytes[at] == b'\\' { 2 } else { 1 };
+                }
+            }
+            _ => {}
+        }
+        at += 1;
+    }
+    None
+}
+
+pub(crate) fn is_lua_block(args: &[Token]) -> bool {
+    matches!(args.first(), Some(Token::Word(name)) if name.ends_with("_by_lua_block"))
+}
+
+/// Length of a Lua block body up to its closing brace, skipping braces inside strings and comments
+pub(crate) fn lua_block_length(source: &str) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut at = 0;
+    while at < bytes.len() {
+        match bytes[at] {
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Some(at),
+            b'}' => depth -= 1,
+            quote @ (b'"' | b'\'') => {
+                at += 1;
+                while at < bytes.len() && bytes[at] != quote {
+                    at += if bytes[at] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'[' if long_bracket_level(&source[at..]).is_some() => {
+                at += long_bracket_skip(&source[at..])?;
+                continue;
+            }
+            b'-' if source[at..].starts_with("--") => {
+                at += 2;
+                if long_bracket_level(&source[at..]).is_some() {
+                    at += long_bracket_skip(&source[at..])?;
+                    continue;
+                }
+                at += source[at..].find('\n').unwrap_or(source.len() - at);
+                continue;
+            }
+            _ => {}
+        }
+        at += 1;
+    }
+    None
+}
+
+/// Level of a Lua long bracket opening `[[` or `[==[`, the number of `=`
+fn long_bracket_level(source: &str) -> Option<usize> {
+    let rest = source.strip_prefix('[')?;
+    let level = rest.bytes().take_while(|b| *b == b'=').count();
+    rest[level..].starts_with('[').then_some(level)
+}
+
+/// Length of a long string or comment starting at its opening bracket, including the closing one
+fn long_bracket_skip(source: &str) -> Option<usize> {
+    let level = long_bracket_level(source)?;
+    let close = format!("]{}]", "=".repeat(level));
+    let open = level + 2;
+    source[open..].find(&close).map(|end| open + end + close.len())
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(source: &str) -> Vec<String> {
+        Token::lexer(source).map(|token| format!("{:?}", token.unwrap())).collect()
+    }
+
+    fn words(source: &str) -> Vec<String> {
+        Token::lexer(source).map(|token| token.unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn bare_words_take_commas_at_signs_and_question_marks() {
+        assert_eq!(
+            lex("log_format main escape=json,x;"),
+            [r#"Word("log_format")"#, r#"Word("main")"#, r#"Word("escape=json,x")"#, "Semicolon"],
+        );
+        assert_eq!(lex("try_files $uri @app;"), [r#"Word("try_files")"#, r#"Word("$uri")"#, r#"Word("@app")"#, "Semicolon"]);
+        assert_eq!(lex("return 301 /a?b=1&c;"), [r#"Word("return")"#, r#"Word("301")"#, r#"Word("/a?b=1&c")"#, "Semicolon"]);
+    }
+
+    #[test]
+    fn bare_words_take_inner_parentheses() {
+        assert_eq!(lex(r"location ~ ^/(a|b)/(.*)$ {"), [
+            r#"Word("location")"#,
+            r#"Word("~")"#,
+            r#"Word("^/(a|b)/(.*)$")"#,
+            "BracketOpen",
+        ]);
+    }
+
+    #[test]
+    fn braced_strings_balance_parentheses() {
+        assert_eq!(lex("if ($a ~ ^(x)$) {"), [r#"Word("if")"#, r#"BracedString("($a ~ ^(x)$)")"#, "BracketOpen"]);
+        assert_eq!(lex(r"if ($a ~ ^((a|b)(c))?$) {"), [r#"Word("if")"#, r#"BracedString("($a ~ ^((a|b)(c))?$)")"#, "BracketOpen"]);
+        assert_eq!(lex(r"if ($a ~ \)$) {"), [r#"Word("if")"#, r#"BracedString("($a ~ \\)$)")"#, "BracketOpen"]);
+        assert_eq!(lex(r#"if ($a ~ "(" ) {"#), [r#"Word("if")"#, r#"BracedString("($a ~ \"(\" )")"#, "BracketOpen"]);
+        assert!(Token::lexer("if ($a ~ (x) {").any(|token| token.is_err()));
+    }
+
+    #[test]
+    fn comments_and_quotes_end_bare_words() {
+        assert_eq!(lex("root /a#b;"), [r#"Word("root")"#, r#"Word("/a#b")"#, "Semicolon"]);
+        assert_eq!(lex("root /a; # b\n"), [r#"Word("root")"#, r#"Word("/a")"#, "Semicolon", "Comment(\"# b\\n\")"]);
+        assert_eq!(lex(r#"a"b""#), [r#"Word("a")"#, r#"QuotedString("\"b\"")"#]);
+    }
+
+    #[test]
+    fn tokens_display_as_written() {
+        assert_eq!(words(r#"add_header X "a b" 'c';"#), ["add_header", "X", r#""a b""#, "'c'", ";"]);
+        assert_eq!(words(r#"if ($a = "x)") {"#), ["if", r#"($a = "x)")"#, "{"]);
+        assert_eq!(words("#  comment  \n"), ["#  comment"]);
+    }
+
+    #[test]
+    fn token_values_are_unquoted() {
+        let values = Token::lexer(r#"add_header X "a b" 'c' ($d);"#).map(|token| token.unwrap().value()).collect::<Vec<_>>();
+        assert_eq!(values, ["add_header", "X", "a b", "c", "$d", ";"]);
+        assert_eq!(Token::QuotedString(r#""a \"b\"""#).value(), r#"a \"b\""#);
+    }
+
+    #[test]
+    fn blocks_nest() {
+        let root = Structure::parse("http { server { listen 80; } server { listen 81; } }\nevents {}").unwrap();
+        let Structure::Block { children, .. } = &root else { unreachable!() };
+        assert_eq!(children.len(), 2);
+        let Structure::Block { children: servers, .. } = &children[0] else { panic!("http isn't a block") };
+        assert_eq!(servers.len(), 2);
+        assert!(servers.iter().all(|server| matches!(server, Structure::Block { children, .. } if children.len() == 1)));
+    }
+}
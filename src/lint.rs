@@ -0,0 +1,712 @@
+use std::{collections::HashMap, fmt::Display, io, path::Path, str::FromStr};
+
+use crate::{
+    compression::BreachRule, cors, include::ConfigFiles, locations, names::DirectiveName, proxy, router::Router, security,
+    validate::Diagnostic, Position, Span, Structure,
+};
+
+/// How much a finding matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::High => write!(f, "high"),
+        }
+    }
+}
+
+/// Something a lint rule reports about a statement or block
+#[derive(Debug, Clone)]
+pub struct Finding<'a> {
+    /// Identifier of the rule, such as `alias-traversal`
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub statement: &'a Structure<'a>,
+    /// Where the statement is written, `None` when it comes from another text than the linted one
+    pub span: Option<Span>,
+}
+
+impl<'a> Finding<'a> {
+    pub fn new(rule: &'static str, severity: Severity, statement: &'a Structure<'a>, source: &str, message: String) -> Self {
+        Self { rule, severity, message, statement, span: statement.span(source) }
+    }
+}
+
+impl Display for Finding<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} [{}] {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// A config as lint rules see it, the tree and the text it was parsed from
+#[derive(Debug, Clone)]
+pub struct Config<'a> {
+    pub source: &'a str,
+    pub root: Structure<'a>,
+    /// Texts of the files spliced into `root` by `include`, their suppression comments count too
+    pub included: Vec<&'a str>,
+}
+
+impl<'a> Config<'a> {
+    pub fn parse(source: &'a str) -> Result<Self, String> {
+        Ok(Self { source, root: Structure::parse(source)?, included: Vec::new() })
+    }
+
+    /// The main config of `files`, with the files it includes spliced in
+    pub fn from_files(files: &'a ConfigFiles) -> Result<Self, String> {
+        let source = files.source(&files.main).unwrap_or_default();
+        let included = files.files.iter().filter(|(path, _)| *path != files.main).map(|(_, source)| source.as_str()).collect();
+        Ok(Self { source, root: files.tree()?, included })
+    }
+}
+
+/// A check run by `LintRunner`, the crate's rules and in-house ones alike
+pub trait LintRule {
+    /// Identifier findings are reported and configured under, such as `alias-traversal`
+    fn id(&self) -> &'static str;
+    /// Short description, such as "alias path traversal"
+    fn name(&self) -> &'static str;
+    /// `router` routes through the `http` block of the config, built once for all the rules of a run
+    fn check<'c>(&self, config: &'c Config<'c>, router: &Router<'c>) -> Vec<Finding<'c>>;
+}
+
+/// What the check function of a builtin rule reads
+#[derive(Debug, Clone, Copy)]
+pub enum Check {
+    /// The tree and the text it was parsed from
+    Tree(for<'a> fn(&'a Structure<'a>, &str) -> Vec<Finding<'a>>),
+    /// The servers and locations of `http`, as a request reaches them
+    Routed(for<'a> fn(&Router<'a>, &str) -> Vec<Finding<'a>>),
+}
+
+/// A rule of the crate, the findings of a check function reported under this rule's id
+/// Check functions reporting several rules are shared by several of these
+#[derive(Debug, Clone, Copy)]
+pub struct BuiltinRule {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub check: Check,
+}
+
+impl LintRule for BuiltinRule {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn check<'c>(&self, config: &'c Config<'c>, router: &Router<'c>) -> Vec<Finding<'c>> {
+        let mut findings = match self.check {
+            Check::Tree(check) => check(&config.root, config.source),
+            Check::Routed(check) => check(router, config.source),
+        };
+        findings.retain(|finding| finding.rule == self.id);
+        findings
+    }
+}
+
+/// Rules shipped with the crate, those taking options set up from `config`
+pub fn builtin_rules(config: &LintConfig) -> Vec<Box<dyn LintRule>> {
+    let rules = security::rules().into_iter().chain(proxy::rules()).chain(cors::rules()).chain(locations::rules());
+    let mut rules = rules.map(|rule| Box::new(rule) as Box<dyn LintRule>).collect::<Vec<_>>();
+    rules.push(Box::new(BreachRule::from_config(config)));
+    rules
+}
+
+/// What happens to the findings of a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Level {
+    /// Not reported
+    Allow,
+    #[default]
+    Warn,
+    /// Reported as an error, failing the run
+    Deny,
+}
+
+impl FromStr for Level {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "deny" => Ok(Self::Deny),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Warn => write!(f, "warn"),
+            Self::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// Levels of the rules, read from a TOML file such as
+///
+/// ```toml
+/// # level of the rules not listed
+/// default = "warn"
+///
+/// [rules]
+/// autoindex = "allow"
+/// alias-traversal = "deny"
+///
+/// # options of the rules taking some, a table per rule
+/// [breach]
+/// content_types = "text/html, application/json"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    pub default: Level,
+    /// By rule id, ids of rules the runner doesn't have are kept
+    pub rules: HashMap<String, Level>,
+    /// By table then key, the tables other than `[rules]`
+    pub options: HashMap<String, HashMap<String, String>>,
+}
+
+impl LintConfig {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        text.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn level(&self, rule: &str) -> Level {
+        self.rules.get(rule).copied().unwrap_or(self.default)
+    }
+
+    pub fn option(&self, table: &str, key: &str) -> Option<&str> {
+        self.options.get(table)?.get(key).map(String::as_str)
+    }
+}
+
+/// Parses the subset of TOML the config uses: a top-level `default`, a `[rules]` table and option tables
+/// of string values, bare or quoted keys and `#` comments
+impl FromStr for LintConfig {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Self::default();
+        let mut table = String::new();
+        for (number, line) in s.lines().enumerate() {
+            let error = |message: &str| format!("line {}: {message}", number + 1);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = name.trim().to_string();
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| error("expected `key = \"value\"`"))?;
+            let key = unquote(key.trim()).unwrap_or(key.trim());
+            let value = unquote(value.trim()).ok_or_else(|| error("expected a quoted string"))?;
+            let level = || value.parse().map_err(|_| error(&format!("`{value}` isn't one of `allow`, `warn`, `deny`")));
+            match (table.as_str(), key) {
+                ("", "default") => config.default = level()?,
+                ("", _) => return Err(error(&format!("unknown key `{key}`"))),
+                ("rules", rule) => {
+                    config.rules.insert(rule.to_string(), level()?);
+                },
+                (table, key) => {
+                    config.options.entry(table.to_string()).or_default().insert(key.to_string(), value.to_string());
+                },
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Line without its `#` comment
+fn strip_comment(line: &str) -> &str {
+    comment_start(line).map_or(line, |at| &line[..at])
+}
+
+/// Where the `#` comment of a line starts, ignoring `#` in quoted strings
+fn comment_start(line: &str) -> Option<usize> {
+    let mut quote = None;
+    for (at, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('#', None) => return Some(at),
+            _ => {},
+        }
+    }
+    None
+}
+
+fn unquote(text: &str) -> Option<&str> {
+    ['"', '\''].iter().find_map(|quote| text.strip_prefix(*quote)?.strip_suffix(*quote))
+}
+
+/// Comment marker turning rules off for a statement or block, `# nginx-lint: disable=autoindex,server-tokens`
+const SUPPRESSION: &str = "nginx-lint:";
+
+/// Rules a `# nginx-lint: disable=...` comment turns off, and the statement or block it applies to:
+/// the one starting on the comment's line when it trails code, else the next one of its block
+/// `all` turns every rule off
+#[derive(Debug, Clone)]
+pub struct Suppression<'a> {
+    pub rules: Vec<String>,
+    pub line: usize,
+    pub target: &'a Structure<'a>,
+}
+
+impl Suppression<'_> {
+    /// Whether the finding is about the target or a statement inside it, of a rule turned off
+    pub fn covers(&self, finding: &Finding) -> bool {
+        self.rules.iter().any(|rule| rule == "all" || rule == finding.rule) && encloses(self.target, finding.statement)
+    }
+}
+
+/// Suppression comments of a config and of the files it includes, those applying to nothing
+/// (at the end of a block or file) left out
+pub fn suppressions<'c>(config: &'c Config<'c>) -> Vec<Suppression<'c>> {
+    let mut suppressions = Vec::new();
+    for source in std::iter::once(config.source).chain(config.included.iter().copied()) {
+        file_suppressions(&config.root, source, &mut suppressions);
+    }
+    suppressions
+}
+
+/// Suppression comments of `source`, applying to the statements of `root` written in it
+fn file_suppressions<'c>(root: &'c Structure<'c>, source: &str, suppressions: &mut Vec<Suppression<'c>>) {
+    let mut starts = Vec::new();
+    statement_starts(root, source, &mut starts);
+    let lines = source.lines().collect::<Vec<_>>();
+    for (index, text) in lines.iter().enumerate() {
+        let Some(at) = comment_start(text) else {
+            continue;
+        };
+        let Some(directive) = text[at + 1..].trim().strip_prefix(SUPPRESSION) else {
+            continue;
+        };
+        let Some(("disable", rules)) = directive.split_once('=').map(|(key, rules)| (key.trim(), rules)) else {
+            continue;
+        };
+        let rules = rules.split(',').map(|rule| rule.trim().to_string()).filter(|rule| !rule.is_empty()).collect();
+        let line = index + 1;
+        let trailing = !text[..at].trim().is_empty();
+        // the first in tree order is the outermost of the statements starting on a line
+        let target = match trailing {
+            true => starts.iter().find(|(start, _)| start.line == line),
+            false => starts
+                .iter()
+                .filter(|(start, _)| start.line > line)
+                .min_by_key(|(start, _)| *start)
+                .filter(|(start, _)| !closes_block(&lines, line, *start)),
+        };
+        if let Some((_, target)) = target {
+            suppressions.push(Suppression { rules, line, target });
+        }
+    }
+}
+
+/// Where the statements and blocks of a tree start, in tree order
+fn statement_starts<'a>(block: &'a Structure<'a>, source: &str, starts: &mut Vec<(Position, &'a Structure<'a>)>) {
+    if let Structure::Block { children, .. } = block {
+        for child in children {
+            if let Some(position) = child.position(source) {
+                starts.push((position, child));
+            }
+            statement_starts(child, source, starts);
+        }
+    }
+}
+
+/// Whether a `}` is written between the end of the comment line `line` and `next`, the start of the
+/// next statement, so that the statement isn't in the block of the comment
+fn closes_block(lines: &[&str], line: usize, next: Position) -> bool {
+    let before = lines[line..next.line - 1].iter().copied();
+    let start = lines[next.line - 1].get(..next.column - 1).unwrap_or_default();
+    before.chain([start]).any(|text| strip_comment(text).contains('}'))
+}
+
+/// Whether `inner` is `outer` or one of the statements it holds, at any depth
+fn encloses(outer: &Structure, inner: &Structure) -> bool {
+    if std::ptr::eq(outer, inner) {
+        return true
+    }
+    match outer {
+        Structure::Block { children, .. } => children.iter().any(|child| encloses(child, inner)),
+        Structure::Statement { .. } => false,
+    }
+}
+
+/// Findings of a run, at the level of their rule
+#[derive(Debug, Clone, Default)]
+pub struct LintReport<'a> {
+    /// In the order of the rules, allowed rules' findings left out
+    pub findings: Vec<(Level, Finding<'a>)>,
+    /// Findings a suppression comment silenced, by rule
+    pub suppressed: HashMap<&'static str, usize>,
+    /// Findings left out for being in the baseline
+    pub baselined: usize,
+}
+
+impl<'a> LintReport<'a> {
+    pub fn errors(&self) -> impl Iterator<Item = &Finding<'a>> {
+        self.findings.iter().filter(|(level, _)| *level == Level::Deny).map(|(_, finding)| finding)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding<'a>> {
+        self.findings.iter().filter(|(level, _)| *level == Level::Warn).map(|(_, finding)| finding)
+    }
+
+    /// A denied rule has findings
+    pub fn failed(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn suppressed_total(&self) -> usize {
+        self.suppressed.values().sum()
+    }
+}
+
+/// Runs lint rules at the levels of a `LintConfig`
+pub struct LintRunner {
+    pub rules: Vec<Box<dyn LintRule>>,
+    pub config: LintConfig,
+}
+
+impl LintRunner {
+    /// The crate's rules, with their options from `config`
+    pub fn new(config: LintConfig) -> Self {
+        Self { rules: builtin_rules(&config), config }
+    }
+
+    /// Adds an in-house rule, run after the ones already there
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Findings of `run` that aren't in the baseline, those already there before
+    pub fn run_with_baseline<'c>(&self, config: &'c Config<'c>, baseline: &Baseline) -> LintReport<'c> {
+        let mut report = self.run(config);
+        report.baselined = baseline.retain_new(&mut report.findings, |(_, finding)| fingerprint(config, finding));
+        report
+    }
+
+    /// Findings of every rule not allowed, except those a suppression comment silences
+    pub fn run<'c>(&self, config: &'c Config<'c>) -> LintReport<'c> {
+        let suppressions = suppressions(config);
+        let router = Router::new(&config.root);
+        let mut report = LintReport::default();
+        for rule in &self.rules {
+            let level = self.config.level(rule.id());
+            if level == Level::Allow {
+                continue;
+            }
+            for finding in rule.check(config, &router) {
+                match suppressions.iter().any(|suppression| suppression.covers(&finding)) {
+                    true => *report.suppressed.entry(finding.rule).or_default() += 1,
+                    false => report.findings.push((level, finding)),
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Findings known to be there, that `LintRunner::run_with_baseline` doesn't report again,
+/// and validation diagnostics, that `retain_new_diagnostics` leaves out
+///
+/// Findings are told apart by rule, by the statement as written and by the blocks enclosing it,
+/// not by line, so that edits elsewhere in the file keep them matching
+/// Saved as a text file of one fingerprint per line, a finding repeated being listed as many times
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Baseline {
+    /// How many findings of each fingerprint are known
+    pub fingerprints: HashMap<String, usize>,
+}
+
+impl Baseline {
+    pub fn from_report(config: &Config, report: &LintReport) -> Self {
+        let mut baseline = Self::default();
+        for (_, finding) in &report.findings {
+            *baseline.fingerprints.entry(fingerprint(config, finding)).or_default() += 1;
+        }
+        baseline
+    }
+
+    /// Adds the diagnostics of `validate::validate`, told apart like findings with their problem's id as the rule
+    pub fn with_diagnostics(mut self, config: &Config, diagnostics: &[Diagnostic]) -> Self {
+        for diagnostic in diagnostics {
+            *self.fingerprints.entry(diagnostic_fingerprint(config, diagnostic)).or_default() += 1;
+        }
+        self
+    }
+
+    /// Leaves out of `diagnostics` those in the baseline, returns how many
+    pub fn retain_new_diagnostics(&self, config: &Config, diagnostics: &mut Vec<Diagnostic>) -> usize {
+        self.retain_new(diagnostics, |diagnostic| diagnostic_fingerprint(config, diagnostic))
+    }
+
+    /// Leaves out the items whose fingerprint is in the baseline, each listed one matching a single item,
+    /// returns how many
+    fn retain_new<T>(&self, items: &mut Vec<T>, fingerprint: impl Fn(&T) -> String) -> usize {
+        let mut remaining = self.fingerprints.clone();
+        let before = items.len();
+        items.retain(|item| match remaining.get_mut(&fingerprint(item)) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            },
+            _ => true,
+        });
+        before - items.len()
+    }
+
+    /// The findings of a previous version of the config
+    pub fn from_previous(runner: &LintRunner, previous: &Config) -> Self {
+        Self::from_report(previous, &runner.run(previous))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(text.parse().unwrap_or_default())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Number of findings
+    pub fn len(&self) -> usize {
+        self.fingerprints.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Sorted, so that saved baselines diff well
+impl Display for Baseline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut fingerprints = self.fingerprints.iter().collect::<Vec<_>>();
+        fingerprints.sort();
+        for (fingerprint, count) in fingerprints {
+            for _ in 0..*count {
+                writeln!(f, "{fingerprint}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Blank lines and lines starting with `#` are skipped
+impl FromStr for Baseline {
+    type Err = std::convert::Infallible;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut baseline = Self::default();
+        for line in s.lines().map(str::trim_end).filter(|line| !line.trim().is_empty() && !line.starts_with('#')) {
+            *baseline.fingerprints.entry(line.to_string()).or_default() += 1;
+        }
+        Ok(baseline)
+    }
+}
+
+/// Rule, enclosing blocks and statement of a finding, tab-separated:
+/// `proxy-host-header\thttp > server example.com > location /api\tproxy_pass http://app`
+pub fn fingerprint(config: &Config, finding: &Finding) -> String {
+    statement_fingerprint(config, finding.rule, finding.statement)
+}
+
+/// Fingerprint of a validation diagnostic, with the `Problem::id` as the rule
+pub fn diagnostic_fingerprint(config: &Config, diagnostic: &Diagnostic) -> String {
+    statement_fingerprint(config, diagnostic.problem.id(), diagnostic.statement)
+}
+
+fn statement_fingerprint(config: &Config, rule: &str, statement: &Structure) -> String {
+    let mut enclosing = Vec::new();
+    enclosing_blocks(&config.root, statement, &mut enclosing);
+    let blocks = enclosing.iter().map(|block| header(block)).collect::<Vec<_>>().join(" > ");
+    format!("{rule}\t{blocks}\t{}", header(statement))
+}
+
+/// Fills `path` with the blocks from the root down to the one holding `target`, whether it was found
+fn enclosing_blocks<'a>(block: &'a Structure<'a>, target: &Structure, path: &mut Vec<&'a Structure<'a>>) -> bool {
+    let Structure::Block { children, .. } = block else {
+        return false
+    };
+    for child in children {
+        if std::ptr::eq(child, target) {
+            return true
+        }
+        path.push(child);
+        if enclosing_blocks(child, target, path) {
+            return true
+        }
+        path.pop();
+    }
+    false
+}
+
+/// A statement, or the header of a block, on one line: `server_name example.com www.example.com`
+/// The `server` block also shows its names, servers would look all alike otherwise
+fn header(statement: &Structure) -> String {
+    let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+    let mut words = args.iter().map(|arg| arg.value().to_string()).collect::<Vec<_>>();
+    if let Structure::Block { children, .. } = statement {
+        if statement.name() == Some(DirectiveName::Server) {
+            let names = children.iter().find(|child| child.name() == Some(DirectiveName::ServerName));
+            if let Some(Structure::Statement { args }) = names {
+                words.extend(args.iter().skip(1).map(|arg| arg.value().to_string()));
+            }
+        }
+    }
+    words.join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate::validate, Token};
+
+    /// Flags every `root`
+    struct RootRule;
+
+    impl LintRule for RootRule {
+        fn id(&self) -> &'static str {
+            "root"
+        }
+
+        fn name(&self) -> &'static str {
+            "root set"
+        }
+
+        fn check<'c>(&self, config: &'c Config<'c>, _: &Router<'c>) -> Vec<Finding<'c>> {
+            fn walk<'a>(block: &'a Structure<'a>, source: &str, findings: &mut Vec<Finding<'a>>) {
+                if let Structure::Block { children, .. } = block {
+                    for child in children {
+                        if child.name() == Some(DirectiveName::Root) {
+                            findings.push(Finding::new("root", Severity::Low, child, source, "root set".to_string()));
+                        }
+                        walk(child, source, findings);
+                    }
+                }
+            }
+            let mut findings = Vec::new();
+            walk(&config.root, config.source, &mut findings);
+            findings
+        }
+    }
+
+    fn run(lint: &str, source: &str) -> (Vec<(Level, usize)>, usize) {
+        let config = Config::parse(source).unwrap();
+        let runner = LintRunner { rules: Vec::new(), config: lint.parse().unwrap() }.with_rule(RootRule);
+        let report = runner.run(&config);
+        let findings = report.findings.iter().map(|(level, finding)| (*level, finding.span.unwrap().start.line)).collect();
+        (findings, report.suppressed_total())
+    }
+
+    #[test]
+    fn lint_configs() {
+        let config: LintConfig = "# comment\ndefault = \"deny\"\n\n[rules]\nautoindex = 'allow' # off\n\"server-tokens\" = \"warn\"\n\n[breach]\ncontent_types = \"text/html, a#b\"\n"
+            .parse()
+            .unwrap();
+        assert_eq!(config.level("autoindex"), Level::Allow);
+        assert_eq!(config.level("server-tokens"), Level::Warn);
+        assert_eq!(config.level("alias-traversal"), Level::Deny);
+        assert_eq!(config.option("breach", "content_types"), Some("text/html, a#b"));
+        assert_eq!(config.option("breach", "other"), None);
+        assert_eq!("".parse::<LintConfig>(), Ok(LintConfig::default()));
+
+        assert_eq!("[rules]\nautoindex = \"off\"".parse::<LintConfig>().unwrap_err(), "line 2: `off` isn't one of `allow`, `warn`, `deny`");
+        assert_eq!("level = \"warn\"".parse::<LintConfig>().unwrap_err(), "line 1: unknown key `level`");
+        assert_eq!("default = warn".parse::<LintConfig>().unwrap_err(), "line 1: expected a quoted string");
+        assert_eq!("[rules]\nautoindex".parse::<LintConfig>().unwrap_err(), "line 2: expected `key = \"value\"`");
+    }
+
+    #[test]
+    fn rules_report_at_their_level() {
+        let source = "http {\n    root /a;\n}\n";
+        assert_eq!(run("", source), (vec![(Level::Warn, 2)], 0));
+        assert_eq!(run("[rules]\nroot = \"allow\"", source), (vec![], 0));
+        assert_eq!(run("default = \"allow\"\n[rules]\nroot = \"deny\"", source), (vec![(Level::Deny, 2)], 0));
+
+        let config = Config::parse(source).unwrap();
+        let warn = LintRunner { rules: Vec::new(), config: LintConfig::default() }.with_rule(RootRule);
+        assert!(!warn.run(&config).failed());
+        let deny = LintRunner { rules: Vec::new(), config: "default = \"deny\"".parse().unwrap() }.with_rule(RootRule);
+        assert!(deny.run(&config).failed());
+    }
+
+    #[test]
+    fn custom_rules_run_after_the_builtin_ones() {
+        let runner = LintRunner::new(LintConfig::default()).with_rule(RootRule);
+        assert_eq!(runner.rules.last().map(|rule| rule.id()), Some("root"));
+        let config = Config::parse("http {\n    root /a;\n    autoindex on;\n}\n").unwrap();
+        let rules = runner.run(&config).findings.iter().map(|(_, finding)| finding.rule).collect::<Vec<_>>();
+        assert!(rules.contains(&"autoindex"));
+        assert_eq!(rules.last(), Some(&"root"));
+    }
+
+    #[test]
+    fn suppression_comments() {
+        // trailing the statement
+        assert_eq!(run("", "http {\n    root /a; # nginx-lint: disable=root\n    root /b;\n}\n"), (vec![(Level::Warn, 3)], 1));
+        // on the line before
+        assert_eq!(run("", "http {\n    # nginx-lint: disable=autoindex, root\n    root /a;\n    root /b;\n}\n"), (vec![(Level::Warn, 4)], 1));
+        // of a block, for everything in it
+        assert_eq!(
+            run("", "# nginx-lint: disable=all\nhttp {\n    root /a;\n    server { root /b; }\n}\nevents { root /c; }\n"),
+            (vec![(Level::Warn, 6)], 2),
+        );
+        // at the end of a block, not for what follows it
+        assert_eq!(
+            run("", "http {\n    server {\n        root /a;\n        # nginx-lint: disable=all\n    }\n    server { root /b; }\n}\nevents { root /c; }\n"),
+            (vec![(Level::Warn, 3), (Level::Warn, 6), (Level::Warn, 8)], 0),
+        );
+        assert_eq!(run("", "http {\n    root /a;\n    # nginx-lint: disable=root\n    }\nroot /b;\n"), (vec![(Level::Warn, 2), (Level::Warn, 5)], 0));
+        // with other comments in between
+        assert_eq!(run("", "http {\n    # nginx-lint: disable=root\n    # why\n\n    root /a;\n}\n"), (vec![], 1));
+        // of other rules, or at the end of a block
+        assert_eq!(run("", "http {\n    root /a; # nginx-lint: disable=autoindex\n    # nginx-lint: disable=root\n}\n"), (vec![(Level::Warn, 2)], 0));
+    }
+
+    #[test]
+    fn baselines_leave_out_known_diagnostics() {
+        let old = Config::parse("http {\n    root /a;\n    root /b;\n}\n").unwrap();
+        let baseline = Baseline::default().with_diagnostics(&old, &validate(&old.root));
+        assert_eq!(baseline.to_string(), "duplicate\thttp\troot /b\n");
+
+        // moved down a line and joined by another duplicate
+        let new = Config::parse("\nhttp {\n    root /a;\n    root /b;\n    root /c;\n}\n").unwrap();
+        let mut diagnostics = validate(&new.root);
+        assert_eq!(baseline.retain_new_diagnostics(&new, &mut diagnostics), 1);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].statement.span(new.source).map(|span| span.start.line), Some(5));
+    }
+
+    #[test]
+    fn suppressions_of_included_files_count() {
+        let included = "server {\n    location / { autoindex on; } # nginx-lint: disable=autoindex\n}\n";
+        let Structure::Block { children, .. } = Structure::parse(included).unwrap() else { unreachable!() };
+        let root = Structure::Block { args: Vec::new(), children: vec![Structure::Block { args: vec![Token::Word("http")], children }] };
+        let mut config = Config { source: "http {\n    include conf.d/*.conf;\n}\n", root, included: Vec::new() };
+        let runner = LintRunner::new(LintConfig::default());
+        assert!(runner.run(&config).findings.iter().any(|(_, finding)| finding.rule == "autoindex"));
+        config.included.push(included);
+        let report = runner.run(&config);
+        assert!(report.findings.iter().all(|(_, finding)| finding.rule != "autoindex"));
+        assert_eq!(report.suppressed.get("autoindex"), Some(&1));
+    }
+}
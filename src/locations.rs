@@ -0,0 +1,234 @@
+use std::fmt::Display;
+
+use crate::{
+    lint::{BuiltinRule, Check, Finding, Severity},
+    names::DirectiveName,
+    types::{Location, LocationBlock, LocationSet, ServerSet},
+    Structure,
+};
+
+/// Location nginx never selects, dead config
+pub const SHADOWED_LOCATION: &str = "shadowed-location";
+
+/// Regexes matching every request path, all of which start with `/`
+const CATCH_ALL_REGEXES: &[&str] = &["", "^", "/", "^/", ".", ".*", "^.*", "^/.*", ".+", "^.+"];
+
+/// The rules of this module, for `lint::LintRunner`
+pub fn rules() -> Vec<BuiltinRule> {
+    vec![BuiltinRule { id: SHADOWED_LOCATION, name: "unreachable location", check: Check::Tree(check_shadowed_locations) }]
+}
+
+/// Why a location is never selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shadowing {
+    /// A regex location of the same pattern comes first, or the same pattern case-insensitively
+    SameRegex,
+    /// A regex location matching any path comes first
+    CatchAllRegex,
+    /// The regex only matches paths under a `^~` prefix, which turns regexes off
+    PriorityPrefix,
+    /// Exact, prefix or named location of the same path, nginx refuses to start
+    Duplicate,
+}
+
+impl Display for Shadowing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SameRegex => write!(f, "an earlier regex location has the same pattern"),
+            Self::CatchAllRegex => write!(f, "an earlier regex location matches every path"),
+            Self::PriorityPrefix => write!(f, "it only matches paths of a `^~` location, which skips regexes"),
+            Self::Duplicate => write!(f, "another location has the same path"),
+        }
+    }
+}
+
+/// A location of a set that's never selected, and the location taking its requests
+#[derive(Debug, Clone)]
+pub struct ShadowedLocation<'a> {
+    pub location: &'a Structure<'a>,
+    pub shadowing: Shadowing,
+    pub by: &'a Structure<'a>,
+}
+
+/// Every location of a set with the ones enclosing it, outermost first, nested ones right after the location holding them
+pub fn location_paths<'r, 'a>(set: &'r LocationSet<'a>) -> Vec<Vec<&'r LocationBlock<'a>>> {
+    fn visit<'r, 'a>(set: &'r LocationSet<'a>, enclosing: &mut Vec<&'r LocationBlock<'a>>, paths: &mut Vec<Vec<&'r LocationBlock<'a>>>) {
+        for location in &set.locations {
+            enclosing.push(location);
+            paths.push(enclosing.clone());
+            visit(&location.nested, enclosing, paths);
+            enclosing.pop();
+        }
+    }
+    let mut paths = Vec::new();
+    visit(set, &mut Vec::new(), &mut paths);
+    paths
+}
+
+/// Locations of a set (and of the sets nested in it) that can never be selected
+pub fn shadowed_locations<'a>(set: &LocationSet<'a>) -> Vec<ShadowedLocation<'a>> {
+    let mut shadowed = Vec::new();
+    for (index, location) in set.locations.iter().enumerate() {
+        let earlier = &set.locations[..index];
+        if let Some((shadowing, by)) = shadowing(location, earlier, set) {
+            shadowed.push(ShadowedLocation { location: location.block, shadowing, by: by.block });
+        }
+        shadowed.extend(shadowed_locations(&location.nested));
+    }
+    shadowed
+}
+
+fn shadowing<'s, 'a>(
+    location: &LocationBlock<'a>,
+    earlier: &'s [LocationBlock<'a>],
+    set: &'s LocationSet<'a>,
+) -> Option<(Shadowing, &'s LocationBlock<'a>)> {
+    let Some((pattern, insensitive)) = regex(&location.location) else {
+        let path = literal_path(&location.location)?;
+        let first = earlier.iter().find(|other| {
+            let same_kind = matches!(
+                (&location.location, &other.location),
+                (Location::Exact(_), Location::Exact(_))
+                    | (Location::Virtual(_), Location::Virtual(_))
+                    | (Location::Prefix(_) | Location::PriorityPrefix(_), Location::Prefix(_) | Location::PriorityPrefix(_))
+            );
+            same_kind && literal_path(&other.location) == Some(path)
+        })?;
+        return Some((Shadowing::Duplicate, first))
+    };
+    for other in earlier {
+        let Some((other_pattern, other_insensitive)) = regex(&other.location) else {
+            continue;
+        };
+        if other_pattern == pattern && (other_insensitive || !insensitive) {
+            return Some((Shadowing::SameRegex, other))
+        }
+        if CATCH_ALL_REGEXES.contains(&other_pattern) {
+            return Some((Shadowing::CatchAllRegex, other))
+        }
+    }
+    let prefix = anchored_prefix(pattern)?;
+    // `~*` also matches the path in other cases, which the `^~` prefix doesn't
+    if insensitive && prefix.chars().any(|c| c.is_ascii_alphabetic()) {
+        return None
+    }
+    let by = set
+        .locations
+        .iter()
+        .filter(|other| matches!(&other.location, Location::PriorityPrefix(priority) if prefix.starts_with(priority.as_str())))
+        .max_by_key(|other| literal_path(&other.location).map_or(0, str::len))?;
+    let priority = literal_path(&by.location)?;
+    // a longer plain prefix some of the paths fall under takes them back to the regexes
+    let reclaimed = set.locations.iter().any(|other| match &other.location {
+        Location::Prefix(other) => {
+            other.len() > priority.len() && (other.starts_with(prefix) || prefix.starts_with(other.as_str()))
+        },
+        _ => false,
+    });
+    (!reclaimed).then_some((Shadowing::PriorityPrefix, by))
+}
+
+/// Pattern as written and whether it's case-insensitive
+fn regex(location: &Location) -> Option<(&str, bool)> {
+    match location {
+        Location::Casitive(regex) => Some((regex.as_str(), false)),
+        Location::Incasitive(regex) => Some((regex.as_str().strip_prefix("(?i)").unwrap_or(regex.as_str()), true)),
+        _ => None,
+    }
+}
+
+fn literal_path(location: &Location) -> Option<&str> {
+    match location {
+        Location::Exact(path) | Location::Prefix(path) | Location::PriorityPrefix(path) | Location::Virtual(path) => Some(path),
+        _ => None,
+    }
+}
+
+/// The literal text every match of a `^`-anchored pattern starts with, `None` for unanchored patterns and alternations
+fn anchored_prefix(pattern: &str) -> Option<&str> {
+    let rest = pattern.strip_prefix('^')?;
+    if pattern.contains('|') {
+        return None
+    }
+    let mut end = 0;
+    for (at, c) in rest.char_indices() {
+        match c {
+            '\\' | '.' | '^' | '$' | '(' | ')' | '[' | ']' | '{' | '}' | '+' => break,
+            '?' | '*' => {
+                // the character before is optional
+                end = rest[..at].char_indices().last().map_or(0, |(last, _)| last);
+                break;
+            },
+            _ => end = at + c.len_utf8(),
+        }
+    }
+    Some(&rest[..end]).filter(|prefix| !prefix.is_empty())
+}
+
+/// Locations of every server that nginx never selects, reported on the dead location
+pub fn check_shadowed_locations<'a>(root: &'a Structure<'a>, source: &str) -> Vec<Finding<'a>> {
+    let children = match root {
+        Structure::Block { children, .. } => children.as_slice(),
+        Structure::Statement { .. } => &[],
+    };
+    let mut findings = Vec::new();
+    for http in children.iter().filter(|c| c.name() == Some(DirectiveName::Http)) {
+        for server in ServerSet::from_block(http).servers {
+            for shadowed in shadowed_locations(&server.locations) {
+                let by = match shadowed.by.position(source) {
+                    Some(position) => format!("`{}` on line {}", label(shadowed.by), position.line),
+                    None => format!("`{}`", label(shadowed.by)),
+                };
+                let message = format!("`{}` is never selected, {} ({by})", label(shadowed.location), shadowed.shadowing);
+                findings.push(Finding::new(SHADOWED_LOCATION, Severity::Low, shadowed.location, source, message));
+            }
+        }
+    }
+    findings
+}
+
+fn label(block: &Structure) -> String {
+    let (Structure::Statement { args } | Structure::Block { args, .. }) = block;
+    args.iter().map(|arg| arg.value()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+
+    static CONFIG: &str = "http { server {\n\
+        location ~ \\.php$ { }\n\
+        location ~ \\.php$ { }\n\
+        location ^~ /static/ { }\n\
+        location ~ ^/static/img { }\n\
+        location ~ ^/static/js { }\n\
+        location /static/js/ { }\n\
+        location /static/ { }\n\
+        location /api { location ~ .* { } location ~ \\.json$ { } }\n\
+        location ~* ^/static/fonts { }\n\
+        } }";
+
+    #[test]
+    fn locations_nginx_never_selects() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let line = |block: &Structure| block.position(CONFIG).unwrap().line;
+        let router = Router::new(&root);
+        let shadowed = shadowed_locations(&router.servers.servers[0].locations)
+            .iter()
+            .map(|shadowed| (line(shadowed.location), shadowed.shadowing, line(shadowed.by)))
+            .collect::<Vec<_>>();
+        assert_eq!(shadowed, [(3, Shadowing::SameRegex, 2), (5, Shadowing::PriorityPrefix, 4), (8, Shadowing::Duplicate, 4), (9, Shadowing::CatchAllRegex, 9)]);
+
+        let messages = check_shadowed_locations(&root, CONFIG).into_iter().map(|finding| finding.message).collect::<Vec<_>>();
+        assert_eq!(messages[0], "`location ~ \\.php$` is never selected, an earlier regex location has the same pattern (`location ~ \\.php$` on line 2)");
+    }
+
+    #[test]
+    fn location_paths_enclosing_first() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        let paths = location_paths(&router.servers.servers[0].locations).iter().map(|path| path.iter().map(|location| label(location.block)).collect::<Vec<_>>().join(" > ")).collect::<Vec<_>>();
+        assert_eq!(paths[7..], ["location /api", "location /api > location ~ .*", "location /api > location ~ \\.json$", "location ~* ^/static/fonts"]);
+    }
+}
@@ -0,0 +1,236 @@
+use std::{net::SocketAddr, str::FromStr};
+
+use crate::{
+    names::DirectiveName,
+    types::{parse_listen_addr, switch},
+    Structure, Token,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailProtocol {
+    Imap,
+    Pop3,
+    Smtp,
+}
+
+impl FromStr for MailProtocol {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "imap" => Ok(Self::Imap),
+            "pop3" => Ok(Self::Pop3),
+            "smtp" => Ok(Self::Smtp),
+            _ => Err(()),
+        }
+    }
+}
+
+impl MailProtocol {
+    /// Protocol nginx picks for a server without `protocol`, from the well-known port it listens on
+    pub fn from_port(port: u16) -> Option<Self> {
+        match port {
+            143 | 993 => Some(Self::Imap),
+            110 | 995 => Some(Self::Pop3),
+            25 | 465 | 587 => Some(Self::Smtp),
+            _ => None,
+        }
+    }
+}
+
+/// `starttls`, whether plain text connections may upgrade to TLS
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Starttls {
+    #[default]
+    Off,
+    On,
+    /// Plain text sessions must upgrade before authenticating
+    Only,
+}
+
+impl FromStr for Starttls {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "on" => Ok(Self::On),
+            "only" => Ok(Self::Only),
+            _ => Err(()),
+        }
+    }
+}
+
+/// `listen 993 ssl;`, `listen 127.0.0.1:25 proxy_protocol;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailListen {
+    pub addr: SocketAddr,
+    pub ssl: bool,
+    pub proxy_protocol: bool,
+}
+
+/// Parses the arguments following `listen`
+impl TryFrom<&[&str]> for MailListen {
+    type Error = ();
+    fn try_from(args: &[&str]) -> Result<Self, Self::Error> {
+        let (addr, params) = args.split_first().ok_or(())?;
+        Ok(Self {
+            addr: parse_listen_addr(addr)?,
+            ssl: params.contains(&"ssl"),
+            proxy_protocol: params.contains(&"proxy_protocol"),
+        })
+    }
+}
+
+/// `server` block of the `mail` context
+/// Settings not given in the block are inherited from the `mail` level
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MailServer {
+    pub listen: Vec<MailListen>,
+    /// As written, `None` means nginx detects it from the port, see `MailServer::protocol`
+    pub protocol: Option<MailProtocol>,
+    /// URL of the HTTP service authenticating clients and picking the backend
+    pub auth_http: Option<String>,
+    /// Forward the backend's error message to the client after authentication
+    pub proxy_pass_error_message: bool,
+    pub starttls: Starttls,
+}
+
+impl MailServer {
+    /// Applies a directive to the server, directives this model doesn't cover are ignored
+    fn apply(&mut self, args: &[Token]) -> Result<(), ()> {
+        match args.first().and_then(|a| DirectiveName::from_str(a.value()).ok()) {
+            Some(DirectiveName::Listen) => {
+                let args = args[1..].iter().map(|a| a.value()).collect::<Vec<_>>();
+                self.listen.push(MailListen::try_from(args.as_slice())?);
+            },
+            Some(DirectiveName::Protocol) => self.protocol = Some(MailProtocol::from_str(args.get(1).ok_or(())?.value())?),
+            Some(DirectiveName::AuthHttp) => self.auth_http = Some(args.get(1).ok_or(())?.value().to_string()),
+            Some(DirectiveName::ProxyPassErrorMessage) => self.proxy_pass_error_message = switch(args.get(1).ok_or(())?.value())?,
+            Some(DirectiveName::Starttls) => self.starttls = Starttls::from_str(args.get(1).ok_or(())?.value())?,
+            _ => {},
+        }
+        Ok(())
+    }
+
+    fn with_defaults(block: &Structure, defaults: &Self) -> Result<Self, ()> {
+        let Structure::Block { children, .. } = block else {
+            return Err(())
+        };
+        if block.name() != Some(DirectiveName::Server) {
+            return Err(())
+        }
+        let mut server = defaults.clone();
+        for child in children {
+            if let Structure::Statement { args } = child {
+                server.apply(args)?;
+            }
+        }
+        Ok(server)
+    }
+
+    /// The configured protocol, or the one implied by the first listen port
+    pub fn protocol(&self) -> Option<MailProtocol> {
+        self.protocol.or_else(|| self.listen.first().and_then(|l| MailProtocol::from_port(l.addr.port())))
+    }
+
+    /// Whether clients can send credentials before the session is encrypted
+    pub fn allows_plaintext_auth(&self) -> bool {
+        self.listen.iter().any(|l| !l.ssl) && self.starttls != Starttls::Only
+    }
+}
+
+impl TryFrom<&Structure<'_>> for MailServer {
+    type Error = ();
+    fn try_from(block: &Structure) -> Result<Self, Self::Error> {
+        Self::with_defaults(block, &Self::default())
+    }
+}
+
+/// Servers of a `mail {}` block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MailConfig {
+    pub servers: Vec<MailServer>,
+}
+
+impl TryFrom<&Structure<'_>> for MailConfig {
+    type Error = ();
+    fn try_from(block: &Structure) -> Result<Self, Self::Error> {
+        let Structure::Block { children, .. } = block else {
+            return Err(())
+        };
+        if block.name() != Some(DirectiveName::Mail) {
+            return Err(())
+        }
+        // listen isn't allowed at the mail level, only the rest is inherited
+        let mut defaults = MailServer::default();
+        for child in children {
+            if let Structure::Statement { args } = child {
+                if child.name() != Some(DirectiveName::Listen) {
+                    defaults.apply(args)?;
+                }
+            }
+        }
+        let servers = children
+            .iter()
+            .filter(|child| matches!(child, Structure::Block { .. }) && child.name() == Some(DirectiveName::Server))
+            .map(|child| MailServer::with_defaults(child, &defaults))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { servers })
+    }
+}
+
+impl MailConfig {
+    /// Every `mail` block of a config
+    pub fn from_main<'a>(main: &'a Structure) -> impl Iterator<Item = Result<Self, ()>> + 'a {
+        let children = match main {
+            Structure::Block { children, .. } => children.as_slice(),
+            Structure::Statement { .. } => &[],
+        };
+        children
+            .iter()
+            .filter(|child| matches!(child, Structure::Block { .. }) && child.name() == Some(DirectiveName::Mail))
+            .map(Self::try_from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+mail {
+    auth_http http://127.0.0.1:9000/auth;
+    proxy_pass_error_message on;
+    starttls only;
+    server { listen 143; }
+    server { listen 993 ssl; protocol imap; }
+    server { listen 587; listen 127.0.0.1:2525 proxy_protocol; starttls on; auth_http http://10.0.0.1/smtp; }
+    server { listen 8025; }
+}
+";
+
+    fn configs(source: &str) -> Vec<Result<MailConfig, ()>> {
+        let root = Structure::parse(source).unwrap();
+        MailConfig::from_main(&root).collect()
+    }
+
+    #[test]
+    fn servers_inherit_the_mail_level() {
+        let configs = configs(CONFIG);
+        let [Ok(config)] = configs.as_slice() else { panic!("not one mail block") };
+        let [imap, imaps, smtp, unknown] = config.servers.as_slice() else { panic!("not four servers") };
+        assert_eq!((imap.protocol, imap.protocol()), (None, Some(MailProtocol::Imap)));
+        assert_eq!((imap.auth_http.as_deref(), imap.proxy_pass_error_message), (Some("http://127.0.0.1:9000/auth"), true));
+        assert!(!imap.allows_plaintext_auth());
+        assert_eq!((imaps.protocol(), imaps.listen[0].ssl), (Some(MailProtocol::Imap), true));
+        assert_eq!((smtp.protocol(), smtp.starttls, smtp.auth_http.as_deref()), (Some(MailProtocol::Smtp), Starttls::On, Some("http://10.0.0.1/smtp")));
+        assert!(smtp.listen[1].proxy_protocol && smtp.allows_plaintext_auth());
+        assert_eq!(unknown.protocol(), None);
+    }
+
+    #[test]
+    fn invalid_mail_blocks() {
+        assert_eq!(configs("mail { server { protocol gopher; } }"), [Err(())]);
+        assert_eq!(configs("mail { starttls maybe; }"), [Err(())]);
+        assert!(configs("stream { }").is_empty());
+    }
+}
@@ -1,27 +1,264 @@
-#![allow(unused)]
-use nginx_config_parser::{Token, Structure};
-
-// TBD
-// fn print_directive(cfg: &Structure) {
-//     match cfg {
-//         Structure::Statement { args, .. } => {
-//             let args = args.iter().map(|s| format!("{}", s.to_string())).collect::<String>();
-//             println!("{args}");
-//         },
-//         Structure::Block { args, children, .. } => {
-//             let args = args.iter().map(|s| format!("{}", s.to_string())).collect::<String>();
-//             for each in children.iter() {
-//                 print_directive(&each);
-//             }
-//         }
-//     }
-// }
-
-fn main() {
-
-    let cfg = std::env::args().nth(1).unwrap();   
-    let cfg = std::fs::read_to_string(&cfg).unwrap();
-    let cfg = Structure::parse(&cfg).unwrap();
-
-    dbg!(cfg);
+use std::{process::ExitCode, time::Duration};
+
+use nginx_config_parser::{
+    dns::{DnsCheck, DEFAULT_TIMEOUT},
+    format::{check_round_trip, format, FormatOptions},
+    include::ConfigFiles,
+    lint::{Baseline, Config, Level, LintConfig, LintRunner},
+    output::{to_json, to_sarif, Record, Related},
+    validate::validate,
+};
+
+const USAGE: &str = "\
+usage: nginx-config <command> [options]
+
+commands:
+    fmt [--check] [--indent N] <files...>    reformat configs in place, or with --check
+                                             list the ones that would change and fail
+    lint [options] <nginx.conf>              validate and lint a config and the files it includes,
+                                             failing on errors and denied lint rules
+
+lint options:
+    --format human|json|sarif    output format, human by default
+    --config <lint.toml>         rule levels and options
+    --baseline <file>            leave out the findings listed in the baseline
+    --write-baseline <file>      write the lint findings to a baseline file
+    --dns                        look up the hosts of `*_pass` targets and upstream servers
+    --dns-timeout <seconds>      how long the --dns lookups may take, 5 by default";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("fmt") => fmt(&args[1..]),
+        Some("lint") => lint(&args[1..]),
+        Some("-h" | "--help" | "help") => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        },
+        Some(command) => usage(&format!("unknown command `{command}`")),
+        None => usage("missing command"),
+    }
+}
+
+/// Exit code of wrong invocations, and of files that can't be read or parsed
+fn usage(error: &str) -> ExitCode {
+    eprintln!("error: {error}\n\n{USAGE}");
+    ExitCode::from(2)
+}
+
+/// Exits with 1 when `--check` finds files that aren't formatted, 2 on errors
+fn fmt(args: &[String]) -> ExitCode {
+    let mut check = false;
+    let mut options = FormatOptions::default();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--indent" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(indent) => options.indent = indent,
+                None => return usage("`--indent` takes a number of spaces"),
+            },
+            flag if flag.starts_with("--") => return usage(&format!("unknown option `{flag}`")),
+            file => files.push(file),
+        }
+    }
+    if files.is_empty() {
+        return usage("no file to format");
+    }
+    let mut failed = false;
+    let mut unformatted = false;
+    for file in files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{file}: {error}");
+                failed = true;
+                continue;
+            },
+        };
+        let formatted = match format(&source, options) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                eprintln!("{file}: can't parse: {error}");
+                failed = true;
+                continue;
+            },
+        };
+        if formatted == source {
+            continue;
+        }
+        // never write a config that would read differently
+        if let Err(error) = check_round_trip(&source, &formatted) {
+            eprintln!("{file}: not formatted, {error}");
+            failed = true;
+            continue;
+        }
+        if check {
+            println!("{file} would be reformatted");
+            unformatted = true;
+        } else if let Err(error) = std::fs::write(file, formatted) {
+            eprintln!("{file}: {error}");
+            failed = true;
+        } else {
+            println!("{file} reformatted");
+        }
+    }
+    match (failed, unformatted) {
+        (true, _) => ExitCode::from(2),
+        (false, true) => ExitCode::FAILURE,
+        (false, false) => ExitCode::SUCCESS,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Human,
+    Json,
+    Sarif,
+}
+
+/// Exits with 1 when the config has errors or findings of denied rules, 2 when it can't be read or parsed
+fn lint(args: &[String]) -> ExitCode {
+    let mut output = OutputFormat::Human;
+    let mut lint_config = None;
+    let mut baseline = None;
+    let mut write_baseline = None;
+    let mut dns = false;
+    let mut dns_timeout = DEFAULT_TIMEOUT;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let option = arg.as_str();
+        let value = match option {
+            "--dns" => {
+                dns = true;
+                continue;
+            },
+            "--format" | "--config" | "--baseline" | "--write-baseline" | "--dns-timeout" => match args.next() {
+                Some(value) => value,
+                None => return usage(&format!("`{option}` takes a value")),
+            },
+            flag if flag.starts_with("--") => return usage(&format!("unknown option `{flag}`")),
+            _ if path.is_some() => return usage("lint takes a single config"),
+            file => {
+                path = Some(file);
+                continue;
+            },
+        };
+        match option {
+            "--format" => {
+                output = match value.as_str() {
+                    "human" => OutputFormat::Human,
+                    "json" => OutputFormat::Json,
+                    "sarif" => OutputFormat::Sarif,
+                    other => return usage(&format!("unknown format `{other}`, expected `human`, `json` or `sarif`")),
+                }
+            },
+            "--config" => lint_config = Some(value),
+            "--baseline" => baseline = Some(value),
+            "--dns-timeout" => match value.parse() {
+                Ok(seconds) => dns_timeout = Duration::from_secs(seconds),
+                Err(_) => return usage("`--dns-timeout` takes a number of seconds"),
+            },
+            _ => write_baseline = Some(value),
+        }
+    }
+    let Some(path) = path else {
+        return usage("no config to lint");
+    };
+
+    let lint_config = match lint_config.map(LintConfig::load).transpose() {
+        Ok(config) => config.unwrap_or_default(),
+        Err(error) => return failure(&format!("can't read the lint config: {error}")),
+    };
+    let baseline = match baseline.map(Baseline::load).transpose() {
+        Ok(baseline) => baseline,
+        Err(error) => return failure(&format!("can't read the baseline: {error}")),
+    };
+    let files = match ConfigFiles::load(path) {
+        Ok(files) => files,
+        Err(error) => return failure(&format!("{path}: {error}")),
+    };
+    let config = match Config::from_files(&files) {
+        Ok(config) => config,
+        Err(error) => return failure(&format!("can't parse: {error}")),
+    };
+    let source = config.source;
+    // lookups run while the rules do
+    let dns = dns.then(|| DnsCheck::default().with_timeout(dns_timeout).start(&config.root));
+
+    let runner = LintRunner::new(lint_config);
+    let report = match &baseline {
+        Some(baseline) => runner.run_with_baseline(&config, baseline),
+        None => runner.run(&config),
+    };
+    let mut diagnostics = validate(&config.root);
+    diagnostics.extend(dns.map(|dns| dns.wait()).unwrap_or_default());
+    if let Some(file) = write_baseline {
+        if let Err(error) = Baseline::from_report(&config, &report).with_diagnostics(&config, &diagnostics).save(file) {
+            return failure(&format!("can't write the baseline: {error}"));
+        }
+    }
+    let baselined = report.baselined
+        + baseline.as_ref().map_or(0, |baseline| baseline.retain_new_diagnostics(&config, &mut diagnostics));
+    let mut records = Vec::new();
+    for diagnostic in &diagnostics {
+        records.push((Record::from_diagnostic(diagnostic, source, None), diagnostic.statement, diagnostic.related));
+    }
+    for (level, finding) in &report.findings {
+        records.push((Record::from_finding(*level, finding, None), finding.statement, None));
+    }
+    // statements of included files are located in their own file
+    let locate = |statement| match files.locate(statement) {
+        Some((file, span)) => (file.display().to_string(), Some(span)),
+        None => (path.to_string(), None),
+    };
+    let records = records
+        .into_iter()
+        .map(|(record, statement, related)| {
+            let (file, span) = locate(statement);
+            let related = record.related.zip(related).map(|(record, statement)| {
+                let (file, span) = locate(statement);
+                Related { file: Some(file), span: span.or(record.span), ..record }
+            });
+            Record { file: Some(file), span: span.or(record.span), related, ..record }
+        })
+        .collect::<Vec<_>>();
+
+    match output {
+        OutputFormat::Json => print!("{}", to_json(&records)),
+        OutputFormat::Sarif => print!("{}", to_sarif(&records)),
+        OutputFormat::Human => {
+            for record in &records {
+                let file = record.file.as_deref().unwrap_or(path);
+                let at = record.span.map(|span| format!("{}:", span.start)).unwrap_or_default();
+                let level = if record.level == Level::Deny { "error" } else { "warning" };
+                let related = match &record.related {
+                    Some(Related { message, file: related_file, span: Some(span) }) => match related_file.as_deref() {
+                        Some(related_file) if related_file != file => format!(" ({message} at {related_file}:{})", span.start),
+                        _ => format!(" ({message} at {})", span.start),
+                    },
+                    _ => String::new(),
+                };
+                println!("{file}:{at} {level} [{}] {}{related}", record.rule, record.message);
+            }
+        },
+    }
+    let errors = records.iter().filter(|record| record.level == Level::Deny).count();
+    let warnings = records.len() - errors;
+    eprintln!(
+        "{errors} error(s), {warnings} warning(s), {} suppressed, {baselined} baselined",
+        report.suppressed_total(),
+    );
+    match errors {
+        0 => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
+    }
+}
+
+/// Exit code of configs that can't be read or parsed
+fn failure(error: &str) -> ExitCode {
+    eprintln!("error: {error}");
+    ExitCode::from(2)
 }
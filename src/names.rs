@@ -0,0 +1,861 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::OnceLock};
+
+macro_rules! directive_names {
+    ($($variant:ident => $name:literal,)*) => {
+        /// Name of every directive known to the parser: nginx and NGINX Plus modules,
+        /// including removed ones still found in old configs, plus common third-party modules
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum DirectiveName {
+            $($variant,)*
+        }
+
+        impl DirectiveName {
+            pub const ALL: &'static [Self] = &[$(Self::$variant,)*];
+
+            /// The name as written in configs
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+directive_names! {
+    AbsoluteRedirect => "absolute_redirect",
+    AcceptMutex => "accept_mutex",
+    AcceptMutexDelay => "accept_mutex_delay",
+    AcceptexRead => "acceptex_read",
+    AccessByLua => "access_by_lua",
+    AccessByLuaBlock => "access_by_lua_block",
+    AccessByLuaFile => "access_by_lua_file",
+    AccessLog => "access_log",
+    AddAfterBody => "add_after_body",
+    AddBeforeBody => "add_before_body",
+    AddHeader => "add_header",
+    AddTrailer => "add_trailer",
+    AdditionTypes => "addition_types",
+    Aio => "aio",
+    AioWrite => "aio_write",
+    Alias => "alias",
+    Allow => "allow",
+    AncientBrowser => "ancient_browser",
+    AncientBrowserValue => "ancient_browser_value",
+    Api => "api",
+    AuthBasic => "auth_basic",
+    AuthBasicUserFile => "auth_basic_user_file",
+    AuthDelay => "auth_delay",
+    AuthHttp => "auth_http",
+    AuthHttpHeader => "auth_http_header",
+    AuthHttpPassClientCert => "auth_http_pass_client_cert",
+    AuthHttpTimeout => "auth_http_timeout",
+    AuthJwt => "auth_jwt",
+    AuthJwtClaimSet => "auth_jwt_claim_set",
+    AuthJwtHeaderSet => "auth_jwt_header_set",
+    AuthJwtKeyCache => "auth_jwt_key_cache",
+    AuthJwtKeyFile => "auth_jwt_key_file",
+    AuthJwtKeyRequest => "auth_jwt_key_request",
+    AuthJwtLeeway => "auth_jwt_leeway",
+    AuthJwtRequire => "auth_jwt_require",
+    AuthJwtType => "auth_jwt_type",
+    AuthRequest => "auth_request",
+    AuthRequestSet => "auth_request_set",
+    Autoindex => "autoindex",
+    AutoindexExactSize => "autoindex_exact_size",
+    AutoindexFormat => "autoindex_format",
+    AutoindexLocaltime => "autoindex_localtime",
+    BalancerByLuaBlock => "balancer_by_lua_block",
+    BalancerByLuaFile => "balancer_by_lua_file",
+    BodyFilterByLua => "body_filter_by_lua",
+    BodyFilterByLuaBlock => "body_filter_by_lua_block",
+    BodyFilterByLuaFile => "body_filter_by_lua_file",
+    Break => "break",
+    Brotli => "brotli",
+    BrotliBuffers => "brotli_buffers",
+    BrotliCompLevel => "brotli_comp_level",
+    BrotliMinLength => "brotli_min_length",
+    BrotliStatic => "brotli_static",
+    BrotliTypes => "brotli_types",
+    BrotliWindow => "brotli_window",
+    Charset => "charset",
+    CharsetMap => "charset_map",
+    CharsetTypes => "charset_types",
+    ChunkedTransferEncoding => "chunked_transfer_encoding",
+    ClientBodyBufferSize => "client_body_buffer_size",
+    ClientBodyInFileOnly => "client_body_in_file_only",
+    ClientBodyInSingleBuffer => "client_body_in_single_buffer",
+    ClientBodyTempPath => "client_body_temp_path",
+    ClientBodyTimeout => "client_body_timeout",
+    ClientHeaderBufferSize => "client_header_buffer_size",
+    ClientHeaderTimeout => "client_header_timeout",
+    ClientMaxBodySize => "client_max_body_size",
+    ConnectionPoolSize => "connection_pool_size",
+    Connections => "connections",
+    ContentByLua => "content_by_lua",
+    ContentByLuaBlock => "content_by_lua_block",
+    ContentByLuaFile => "content_by_lua_file",
+    CreateFullPutPath => "create_full_put_path",
+    Daemon => "daemon",
+    DavAccess => "dav_access",
+    DavMethods => "dav_methods",
+    DebugConnection => "debug_connection",
+    DebugPoints => "debug_points",
+    DefaultType => "default_type",
+    Degradation => "degradation",
+    Degrade => "degrade",
+    Deny => "deny",
+    DevpollChanges => "devpoll_changes",
+    DevpollEvents => "devpoll_events",
+    Directio => "directio",
+    DirectioAlignment => "directio_alignment",
+    DisableSymlinks => "disable_symlinks",
+    EarlyHints => "early_hints",
+    EmptyGif => "empty_gif",
+    Env => "env",
+    EpollEvents => "epoll_events",
+    ErrorLog => "error_log",
+    ErrorPage => "error_page",
+    Etag => "etag",
+    EventportEvents => "eventport_events",
+    Events => "events",
+    ExitWorkerByLuaBlock => "exit_worker_by_lua_block",
+    ExitWorkerByLuaFile => "exit_worker_by_lua_file",
+    Expires => "expires",
+    F4f => "f4f",
+    F4fBufferSize => "f4f_buffer_size",
+    FastcgiBind => "fastcgi_bind",
+    FastcgiBufferSize => "fastcgi_buffer_size",
+    FastcgiBuffering => "fastcgi_buffering",
+    FastcgiBuffers => "fastcgi_buffers",
+    FastcgiBusyBuffersSize => "fastcgi_busy_buffers_size",
+    FastcgiCache => "fastcgi_cache",
+    FastcgiCacheBackgroundUpdate => "fastcgi_cache_background_update",
+    FastcgiCacheBypass => "fastcgi_cache_bypass",
+    FastcgiCacheKey => "fastcgi_cache_key",
+    FastcgiCacheLock => "fastcgi_cache_lock",
+    FastcgiCacheLockAge => "fastcgi_cache_lock_age",
+    FastcgiCacheLockTimeout => "fastcgi_cache_lock_timeout",
+    FastcgiCacheMaxRangeOffset => "fastcgi_cache_max_range_offset",
+    FastcgiCacheMethods => "fastcgi_cache_methods",
+    FastcgiCacheMinUses => "fastcgi_cache_min_uses",
+    FastcgiCachePath => "fastcgi_cache_path",
+    FastcgiCachePurge => "fastcgi_cache_purge",
+    FastcgiCacheRevalidate => "fastcgi_cache_revalidate",
+    FastcgiCacheUseStale => "fastcgi_cache_use_stale",
+    FastcgiCacheValid => "fastcgi_cache_valid",
+    FastcgiCatchStderr => "fastcgi_catch_stderr",
+    FastcgiConnectTimeout => "fastcgi_connect_timeout",
+    FastcgiForceRanges => "fastcgi_force_ranges",
+    FastcgiHideHeader => "fastcgi_hide_header",
+    FastcgiIgnoreClientAbort => "fastcgi_ignore_client_abort",
+    FastcgiIgnoreHeaders => "fastcgi_ignore_headers",
+    FastcgiIndex => "fastcgi_index",
+    FastcgiInterceptErrors => "fastcgi_intercept_errors",
+    FastcgiKeepConn => "fastcgi_keep_conn",
+    FastcgiLimitRate => "fastcgi_limit_rate",
+    FastcgiMaxTempFileSize => "fastcgi_max_temp_file_size",
+    FastcgiNextUpstream => "fastcgi_next_upstream",
+    FastcgiNextUpstreamTimeout => "fastcgi_next_upstream_timeout",
+    FastcgiNextUpstreamTries => "fastcgi_next_upstream_tries",
+    FastcgiNoCache => "fastcgi_no_cache",
+    FastcgiParam => "fastcgi_param",
+    FastcgiPass => "fastcgi_pass",
+    FastcgiPassHeader => "fastcgi_pass_header",
+    FastcgiPassRequestBody => "fastcgi_pass_request_body",
+    FastcgiPassRequestHeaders => "fastcgi_pass_request_headers",
+    FastcgiReadTimeout => "fastcgi_read_timeout",
+    FastcgiRequestBuffering => "fastcgi_request_buffering",
+    FastcgiSendLowat => "fastcgi_send_lowat",
+    FastcgiSendTimeout => "fastcgi_send_timeout",
+    FastcgiSocketKeepalive => "fastcgi_socket_keepalive",
+    FastcgiSplitPathInfo => "fastcgi_split_path_info",
+    FastcgiStore => "fastcgi_store",
+    FastcgiStoreAccess => "fastcgi_store_access",
+    FastcgiTempFileWriteSize => "fastcgi_temp_file_write_size",
+    FastcgiTempPath => "fastcgi_temp_path",
+    Flv => "flv",
+    Geo => "geo",
+    Geoip2 => "geoip2",
+    Geoip2Proxy => "geoip2_proxy",
+    Geoip2ProxyRecursive => "geoip2_proxy_recursive",
+    GeoipCity => "geoip_city",
+    GeoipCountry => "geoip_country",
+    GeoipOrg => "geoip_org",
+    GeoipProxy => "geoip_proxy",
+    GeoipProxyRecursive => "geoip_proxy_recursive",
+    GooglePerftoolsProfiles => "google_perftools_profiles",
+    GrpcBind => "grpc_bind",
+    GrpcBufferSize => "grpc_buffer_size",
+    GrpcConnectTimeout => "grpc_connect_timeout",
+    GrpcHideHeader => "grpc_hide_header",
+    GrpcIgnoreHeaders => "grpc_ignore_headers",
+    GrpcInterceptErrors => "grpc_intercept_errors",
+    GrpcNextUpstream => "grpc_next_upstream",
+    GrpcNextUpstreamTimeout => "grpc_next_upstream_timeout",
+    GrpcNextUpstreamTries => "grpc_next_upstream_tries",
+    GrpcPass => "grpc_pass",
+    GrpcPassHeader => "grpc_pass_header",
+    GrpcReadTimeout => "grpc_read_timeout",
+    GrpcSendTimeout => "grpc_send_timeout",
+    GrpcSetHeader => "grpc_set_header",
+    GrpcSocketKeepalive => "grpc_socket_keepalive",
+    GrpcSslCertificate => "grpc_ssl_certificate",
+    GrpcSslCertificateKey => "grpc_ssl_certificate_key",
+    GrpcSslCiphers => "grpc_ssl_ciphers",
+    GrpcSslConfCommand => "grpc_ssl_conf_command",
+    GrpcSslCrl => "grpc_ssl_crl",
+    GrpcSslName => "grpc_ssl_name",
+    GrpcSslPasswordFile => "grpc_ssl_password_file",
+    GrpcSslProtocols => "grpc_ssl_protocols",
+    GrpcSslServerName => "grpc_ssl_server_name",
+    GrpcSslSessionReuse => "grpc_ssl_session_reuse",
+    GrpcSslTrustedCertificate => "grpc_ssl_trusted_certificate",
+    GrpcSslVerify => "grpc_ssl_verify",
+    GrpcSslVerifyDepth => "grpc_ssl_verify_depth",
+    Gunzip => "gunzip",
+    GunzipBuffers => "gunzip_buffers",
+    Gzip => "gzip",
+    GzipBuffers => "gzip_buffers",
+    GzipCompLevel => "gzip_comp_level",
+    GzipDisable => "gzip_disable",
+    GzipHash => "gzip_hash",
+    GzipHttpVersion => "gzip_http_version",
+    GzipMinLength => "gzip_min_length",
+    GzipNoBuffer => "gzip_no_buffer",
+    GzipProxied => "gzip_proxied",
+    GzipStatic => "gzip_static",
+    GzipTypes => "gzip_types",
+    GzipVary => "gzip_vary",
+    GzipWindow => "gzip_window",
+    Hash => "hash",
+    HeaderFilterByLua => "header_filter_by_lua",
+    HeaderFilterByLuaBlock => "header_filter_by_lua_block",
+    HeaderFilterByLuaFile => "header_filter_by_lua_file",
+    HealthCheck => "health_check",
+    HealthCheckTimeout => "health_check_timeout",
+    Hls => "hls",
+    HlsBuffers => "hls_buffers",
+    HlsForwardArgs => "hls_forward_args",
+    HlsFragment => "hls_fragment",
+    HlsMp4BufferSize => "hls_mp4_buffer_size",
+    HlsMp4MaxBufferSize => "hls_mp4_max_buffer_size",
+    Http => "http",
+    Http2 => "http2",
+    Http2BodyPrereadSize => "http2_body_preread_size",
+    Http2ChunkSize => "http2_chunk_size",
+    Http2IdleTimeout => "http2_idle_timeout",
+    Http2MaxConcurrentStreams => "http2_max_concurrent_streams",
+    Http2MaxFieldSize => "http2_max_field_size",
+    Http2MaxHeaderSize => "http2_max_header_size",
+    Http2MaxRequests => "http2_max_requests",
+    Http2Push => "http2_push",
+    Http2PushPreload => "http2_push_preload",
+    Http2RecvBufferSize => "http2_recv_buffer_size",
+    Http2RecvTimeout => "http2_recv_timeout",
+    Http3 => "http3",
+    Http3Hq => "http3_hq",
+    Http3MaxConcurrentPushes => "http3_max_concurrent_pushes",
+    Http3MaxConcurrentStreams => "http3_max_concurrent_streams",
+    Http3Push => "http3_push",
+    Http3PushPreload => "http3_push_preload",
+    Http3StreamBufferSize => "http3_stream_buffer_size",
+    If => "if",
+    IfModifiedSince => "if_modified_since",
+    IgnoreInvalidHeaders => "ignore_invalid_headers",
+    ImageFilter => "image_filter",
+    ImageFilterBuffer => "image_filter_buffer",
+    ImageFilterInterlace => "image_filter_interlace",
+    ImageFilterJpegQuality => "image_filter_jpeg_quality",
+    ImageFilterSharpen => "image_filter_sharpen",
+    ImageFilterTransparency => "image_filter_transparency",
+    ImageFilterWebpQuality => "image_filter_webp_quality",
+    Imap => "imap",
+    ImapAuth => "imap_auth",
+    ImapCapabilities => "imap_capabilities",
+    ImapClientBuffer => "imap_client_buffer",
+    Include => "include",
+    Index => "index",
+    InitByLua => "init_by_lua",
+    InitByLuaBlock => "init_by_lua_block",
+    InitByLuaFile => "init_by_lua_file",
+    InitWorkerByLua => "init_worker_by_lua",
+    InitWorkerByLuaBlock => "init_worker_by_lua_block",
+    InitWorkerByLuaFile => "init_worker_by_lua_file",
+    Internal => "internal",
+    IocpThreads => "iocp_threads",
+    IpHash => "ip_hash",
+    JsAccess => "js_access",
+    JsBodyFilter => "js_body_filter",
+    JsContent => "js_content",
+    JsEngine => "js_engine",
+    JsFetchBufferSize => "js_fetch_buffer_size",
+    JsFetchCiphers => "js_fetch_ciphers",
+    JsFetchMaxResponseBufferSize => "js_fetch_max_response_buffer_size",
+    JsFetchProtocols => "js_fetch_protocols",
+    JsFetchTimeout => "js_fetch_timeout",
+    JsFetchTrustedCertificate => "js_fetch_trusted_certificate",
+    JsFetchVerify => "js_fetch_verify",
+    JsFetchVerifyDepth => "js_fetch_verify_depth",
+    JsFilter => "js_filter",
+    JsHeaderFilter => "js_header_filter",
+    JsImport => "js_import",
+    JsInclude => "js_include",
+    JsPath => "js_path",
+    JsPeriodic => "js_periodic",
+    JsPreloadObject => "js_preload_object",
+    JsPreread => "js_preread",
+    JsSet => "js_set",
+    JsSharedDictZone => "js_shared_dict_zone",
+    JsVar => "js_var",
+    Keepalive => "keepalive",
+    KeepaliveDisable => "keepalive_disable",
+    KeepaliveRequests => "keepalive_requests",
+    KeepaliveTime => "keepalive_time",
+    KeepaliveTimeout => "keepalive_timeout",
+    Keyval => "keyval",
+    KeyvalZone => "keyval_zone",
+    KqueueChanges => "kqueue_changes",
+    KqueueEvents => "kqueue_events",
+    LargeClientHeaderBuffers => "large_client_header_buffers",
+    LeastConn => "least_conn",
+    LeastTime => "least_time",
+    LicenseToken => "license_token",
+    LimitConn => "limit_conn",
+    LimitConnDryRun => "limit_conn_dry_run",
+    LimitConnLogLevel => "limit_conn_log_level",
+    LimitConnStatus => "limit_conn_status",
+    LimitConnZone => "limit_conn_zone",
+    LimitExcept => "limit_except",
+    LimitRate => "limit_rate",
+    LimitRateAfter => "limit_rate_after",
+    LimitReq => "limit_req",
+    LimitReqDryRun => "limit_req_dry_run",
+    LimitReqLogLevel => "limit_req_log_level",
+    LimitReqStatus => "limit_req_status",
+    LimitReqZone => "limit_req_zone",
+    LimitZone => "limit_zone",
+    LingeringClose => "lingering_close",
+    LingeringTime => "lingering_time",
+    LingeringTimeout => "lingering_timeout",
+    Listen => "listen",
+    LoadModule => "load_module",
+    Location => "location",
+    LockFile => "lock_file",
+    LogByLua => "log_by_lua",
+    LogByLuaBlock => "log_by_lua_block",
+    LogByLuaFile => "log_by_lua_file",
+    LogFormat => "log_format",
+    LogNotFound => "log_not_found",
+    LogSubrequest => "log_subrequest",
+    LuaCodeCache => "lua_code_cache",
+    LuaMaxPendingTimers => "lua_max_pending_timers",
+    LuaMaxRunningTimers => "lua_max_running_timers",
+    LuaNeedRequestBody => "lua_need_request_body",
+    LuaPackageCpath => "lua_package_cpath",
+    LuaPackagePath => "lua_package_path",
+    LuaSharedDict => "lua_shared_dict",
+    LuaSocketBufferSize => "lua_socket_buffer_size",
+    LuaSocketConnectTimeout => "lua_socket_connect_timeout",
+    LuaSocketKeepaliveTimeout => "lua_socket_keepalive_timeout",
+    LuaSocketLogErrors => "lua_socket_log_errors",
+    LuaSocketPoolSize => "lua_socket_pool_size",
+    LuaSocketReadTimeout => "lua_socket_read_timeout",
+    LuaSocketSendTimeout => "lua_socket_send_timeout",
+    LuaSslCiphers => "lua_ssl_ciphers",
+    LuaSslProtocols => "lua_ssl_protocols",
+    LuaSslTrustedCertificate => "lua_ssl_trusted_certificate",
+    LuaSslVerifyDepth => "lua_ssl_verify_depth",
+    Mail => "mail",
+    Map => "map",
+    MapHashBucketSize => "map_hash_bucket_size",
+    MapHashMaxSize => "map_hash_max_size",
+    MasterProcess => "master_process",
+    Match => "match",
+    MaxErrors => "max_errors",
+    MaxRanges => "max_ranges",
+    MemcachedBind => "memcached_bind",
+    MemcachedBufferSize => "memcached_buffer_size",
+    MemcachedConnectTimeout => "memcached_connect_timeout",
+    MemcachedForceRanges => "memcached_force_ranges",
+    MemcachedGzipFlag => "memcached_gzip_flag",
+    MemcachedNextUpstream => "memcached_next_upstream",
+    MemcachedNextUpstreamTimeout => "memcached_next_upstream_timeout",
+    MemcachedNextUpstreamTries => "memcached_next_upstream_tries",
+    MemcachedPass => "memcached_pass",
+    MemcachedReadTimeout => "memcached_read_timeout",
+    MemcachedSendTimeout => "memcached_send_timeout",
+    MemcachedSocketKeepalive => "memcached_socket_keepalive",
+    MergeSlashes => "merge_slashes",
+    Mgmt => "mgmt",
+    MinDeleteDepth => "min_delete_depth",
+    Mirror => "mirror",
+    MirrorRequestBody => "mirror_request_body",
+    ModernBrowser => "modern_browser",
+    ModernBrowserValue => "modern_browser_value",
+    Mp4 => "mp4",
+    Mp4BufferSize => "mp4_buffer_size",
+    Mp4LimitRate => "mp4_limit_rate",
+    Mp4LimitRateAfter => "mp4_limit_rate_after",
+    Mp4MaxBufferSize => "mp4_max_buffer_size",
+    MsiePadding => "msie_padding",
+    MsieRefresh => "msie_refresh",
+    MultiAccept => "multi_accept",
+    MysqlTest => "mysql_test",
+    Ntlm => "ntlm",
+    OpenFileCache => "open_file_cache",
+    OpenFileCacheErrors => "open_file_cache_errors",
+    OpenFileCacheEvents => "open_file_cache_events",
+    OpenFileCacheMinUses => "open_file_cache_min_uses",
+    OpenFileCacheRetest => "open_file_cache_retest",
+    OpenFileCacheValid => "open_file_cache_valid",
+    OpenLogFileCache => "open_log_file_cache",
+    OptimizeServerNames => "optimize_server_names",
+    OutputBuffers => "output_buffers",
+    OverrideCharset => "override_charset",
+    Pass => "pass",
+    PcreJit => "pcre_jit",
+    Perl => "perl",
+    PerlModules => "perl_modules",
+    PerlRequire => "perl_require",
+    PerlSet => "perl_set",
+    Pid => "pid",
+    Pop3Auth => "pop3_auth",
+    Pop3Capabilities => "pop3_capabilities",
+    PortInRedirect => "port_in_redirect",
+    PostAcceptex => "post_acceptex",
+    PostAction => "post_action",
+    PostponeGzipping => "postpone_gzipping",
+    PostponeOutput => "postpone_output",
+    PrereadBufferSize => "preread_buffer_size",
+    PrereadTimeout => "preread_timeout",
+    Protocol => "protocol",
+    Proxy => "proxy",
+    ProxyBind => "proxy_bind",
+    ProxyBuffer => "proxy_buffer",
+    ProxyBufferSize => "proxy_buffer_size",
+    ProxyBuffering => "proxy_buffering",
+    ProxyBuffers => "proxy_buffers",
+    ProxyBusyBuffersSize => "proxy_busy_buffers_size",
+    ProxyCache => "proxy_cache",
+    ProxyCacheBackgroundUpdate => "proxy_cache_background_update",
+    ProxyCacheBypass => "proxy_cache_bypass",
+    ProxyCacheConvertHead => "proxy_cache_convert_head",
+    ProxyCacheKey => "proxy_cache_key",
+    ProxyCacheLock => "proxy_cache_lock",
+    ProxyCacheLockAge => "proxy_cache_lock_age",
+    ProxyCacheLockTimeout => "proxy_cache_lock_timeout",
+    ProxyCacheMaxRangeOffset => "proxy_cache_max_range_offset",
+    ProxyCacheMethods => "proxy_cache_methods",
+    ProxyCacheMinUses => "proxy_cache_min_uses",
+    ProxyCachePath => "proxy_cache_path",
+    ProxyCachePurge => "proxy_cache_purge",
+    ProxyCacheRevalidate => "proxy_cache_revalidate",
+    ProxyCacheUseStale => "proxy_cache_use_stale",
+    ProxyCacheValid => "proxy_cache_valid",
+    ProxyConnectTimeout => "proxy_connect_timeout",
+    ProxyCookieDomain => "proxy_cookie_domain",
+    ProxyCookieFlags => "proxy_cookie_flags",
+    ProxyCookiePath => "proxy_cookie_path",
+    ProxyDownloadRate => "proxy_download_rate",
+    ProxyForceRanges => "proxy_force_ranges",
+    ProxyHalfClose => "proxy_half_close",
+    ProxyHeadersHashBucketSize => "proxy_headers_hash_bucket_size",
+    ProxyHeadersHashMaxSize => "proxy_headers_hash_max_size",
+    ProxyHideHeader => "proxy_hide_header",
+    ProxyHttpVersion => "proxy_http_version",
+    ProxyIgnoreClientAbort => "proxy_ignore_client_abort",
+    ProxyIgnoreHeaders => "proxy_ignore_headers",
+    ProxyInterceptErrors => "proxy_intercept_errors",
+    ProxyLimitRate => "proxy_limit_rate",
+    ProxyMaxTempFileSize => "proxy_max_temp_file_size",
+    ProxyMethod => "proxy_method",
+    ProxyNextUpstream => "proxy_next_upstream",
+    ProxyNextUpstreamTimeout => "proxy_next_upstream_timeout",
+    ProxyNextUpstreamTries => "proxy_next_upstream_tries",
+    ProxyNoCache => "proxy_no_cache",
+    ProxyPass => "proxy_pass",
+    ProxyPassErrorMessage => "proxy_pass_error_message",
+    ProxyPassHeader => "proxy_pass_header",
+    ProxyPassRequestBody => "proxy_pass_request_body",
+    ProxyPassRequestHeaders => "proxy_pass_request_headers",
+    ProxyProtocol => "proxy_protocol",
+    ProxyProtocolTimeout => "proxy_protocol_timeout",
+    ProxyReadTimeout => "proxy_read_timeout",
+    ProxyRedirect => "proxy_redirect",
+    ProxyRequestBuffering => "proxy_request_buffering",
+    ProxyRequests => "proxy_requests",
+    ProxyResponses => "proxy_responses",
+    ProxySendLowat => "proxy_send_lowat",
+    ProxySendTimeout => "proxy_send_timeout",
+    ProxySessionDrop => "proxy_session_drop",
+    ProxySetBody => "proxy_set_body",
+    ProxySetHeader => "proxy_set_header",
+    ProxySmtpAuth => "proxy_smtp_auth",
+    ProxySocketKeepalive => "proxy_socket_keepalive",
+    ProxySsl => "proxy_ssl",
+    ProxySslCertificate => "proxy_ssl_certificate",
+    ProxySslCertificateKey => "proxy_ssl_certificate_key",
+    ProxySslCiphers => "proxy_ssl_ciphers",
+    ProxySslConfCommand => "proxy_ssl_conf_command",
+    ProxySslCrl => "proxy_ssl_crl",
+    ProxySslName => "proxy_ssl_name",
+    ProxySslPasswordFile => "proxy_ssl_password_file",
+    ProxySslProtocols => "proxy_ssl_protocols",
+    ProxySslServerName => "proxy_ssl_server_name",
+    ProxySslSessionReuse => "proxy_ssl_session_reuse",
+    ProxySslTrustedCertificate => "proxy_ssl_trusted_certificate",
+    ProxySslVerify => "proxy_ssl_verify",
+    ProxySslVerifyDepth => "proxy_ssl_verify_depth",
+    ProxyStore => "proxy_store",
+    ProxyStoreAccess => "proxy_store_access",
+    ProxyTempFileWriteSize => "proxy_temp_file_write_size",
+    ProxyTempPath => "proxy_temp_path",
+    ProxyTimeout => "proxy_timeout",
+    ProxyUploadRate => "proxy_upload_rate",
+    Queue => "queue",
+    QuicActiveConnectionIdLimit => "quic_active_connection_id_limit",
+    QuicBpf => "quic_bpf",
+    QuicGso => "quic_gso",
+    QuicHostKey => "quic_host_key",
+    QuicMtu => "quic_mtu",
+    QuicRetry => "quic_retry",
+    Random => "random",
+    RandomIndex => "random_index",
+    ReadAhead => "read_ahead",
+    RealIpHeader => "real_ip_header",
+    RealIpRecursive => "real_ip_recursive",
+    RecursiveErrorPages => "recursive_error_pages",
+    RefererHashBucketSize => "referer_hash_bucket_size",
+    RefererHashMaxSize => "referer_hash_max_size",
+    RequestPoolSize => "request_pool_size",
+    ResetTimedoutConnection => "reset_timedout_connection",
+    Resolver => "resolver",
+    ResolverTimeout => "resolver_timeout",
+    Return => "return",
+    Rewrite => "rewrite",
+    RewriteByLua => "rewrite_by_lua",
+    RewriteByLuaBlock => "rewrite_by_lua_block",
+    RewriteByLuaFile => "rewrite_by_lua_file",
+    RewriteLog => "rewrite_log",
+    Root => "root",
+    RtsigOverflowEvents => "rtsig_overflow_events",
+    RtsigOverflowTest => "rtsig_overflow_test",
+    RtsigOverflowThreshold => "rtsig_overflow_threshold",
+    RtsigSigno => "rtsig_signo",
+    Satisfy => "satisfy",
+    SatisfyAny => "satisfy_any",
+    ScgiBind => "scgi_bind",
+    ScgiBufferSize => "scgi_buffer_size",
+    ScgiBuffering => "scgi_buffering",
+    ScgiBuffers => "scgi_buffers",
+    ScgiBusyBuffersSize => "scgi_busy_buffers_size",
+    ScgiCache => "scgi_cache",
+    ScgiCacheBackgroundUpdate => "scgi_cache_background_update",
+    ScgiCacheBypass => "scgi_cache_bypass",
+    ScgiCacheKey => "scgi_cache_key",
+    ScgiCacheLock => "scgi_cache_lock",
+    ScgiCacheLockAge => "scgi_cache_lock_age",
+    ScgiCacheLockTimeout => "scgi_cache_lock_timeout",
+    ScgiCacheMaxRangeOffset => "scgi_cache_max_range_offset",
+    ScgiCacheMethods => "scgi_cache_methods",
+    ScgiCacheMinUses => "scgi_cache_min_uses",
+    ScgiCachePath => "scgi_cache_path",
+    ScgiCachePurge => "scgi_cache_purge",
+    ScgiCacheRevalidate => "scgi_cache_revalidate",
+    ScgiCacheUseStale => "scgi_cache_use_stale",
+    ScgiCacheValid => "scgi_cache_valid",
+    ScgiConnectTimeout => "scgi_connect_timeout",
+    ScgiForceRanges => "scgi_force_ranges",
+    ScgiHideHeader => "scgi_hide_header",
+    ScgiIgnoreClientAbort => "scgi_ignore_client_abort",
+    ScgiIgnoreHeaders => "scgi_ignore_headers",
+    ScgiInterceptErrors => "scgi_intercept_errors",
+    ScgiLimitRate => "scgi_limit_rate",
+    ScgiMaxTempFileSize => "scgi_max_temp_file_size",
+    ScgiNextUpstream => "scgi_next_upstream",
+    ScgiNextUpstreamTimeout => "scgi_next_upstream_timeout",
+    ScgiNextUpstreamTries => "scgi_next_upstream_tries",
+    ScgiNoCache => "scgi_no_cache",
+    ScgiParam => "scgi_param",
+    ScgiPass => "scgi_pass",
+    ScgiPassHeader => "scgi_pass_header",
+    ScgiPassRequestBody => "scgi_pass_request_body",
+    ScgiPassRequestHeaders => "scgi_pass_request_headers",
+    ScgiReadTimeout => "scgi_read_timeout",
+    ScgiRequestBuffering => "scgi_request_buffering",
+    ScgiSendTimeout => "scgi_send_timeout",
+    ScgiSocketKeepalive => "scgi_socket_keepalive",
+    ScgiStore => "scgi_store",
+    ScgiStoreAccess => "scgi_store_access",
+    ScgiTempFileWriteSize => "scgi_temp_file_write_size",
+    ScgiTempPath => "scgi_temp_path",
+    SecureLink => "secure_link",
+    SecureLinkMd5 => "secure_link_md5",
+    SecureLinkSecret => "secure_link_secret",
+    SendLowat => "send_lowat",
+    SendTimeout => "send_timeout",
+    Sendfile => "sendfile",
+    SendfileMaxChunk => "sendfile_max_chunk",
+    Server => "server",
+    ServerName => "server_name",
+    ServerNameInRedirect => "server_name_in_redirect",
+    ServerNamesHashBucketSize => "server_names_hash_bucket_size",
+    ServerNamesHashMaxSize => "server_names_hash_max_size",
+    ServerRewriteByLuaBlock => "server_rewrite_by_lua_block",
+    ServerRewriteByLuaFile => "server_rewrite_by_lua_file",
+    ServerTokens => "server_tokens",
+    SessionLog => "session_log",
+    SessionLogFormat => "session_log_format",
+    SessionLogZone => "session_log_zone",
+    Set => "set",
+    SetByLua => "set_by_lua",
+    SetByLuaBlock => "set_by_lua_block",
+    SetByLuaFile => "set_by_lua_file",
+    SetRealIpFrom => "set_real_ip_from",
+    Slice => "slice",
+    SmtpAuth => "smtp_auth",
+    SmtpCapabilities => "smtp_capabilities",
+    SmtpClientBuffer => "smtp_client_buffer",
+    SmtpGreetingDelay => "smtp_greeting_delay",
+    SoKeepalive => "so_keepalive",
+    SourceCharset => "source_charset",
+    SpdyChunkSize => "spdy_chunk_size",
+    SpdyHeadersComp => "spdy_headers_comp",
+    SpdyKeepaliveTimeout => "spdy_keepalive_timeout",
+    SpdyMaxConcurrentStreams => "spdy_max_concurrent_streams",
+    SpdyPoolSize => "spdy_pool_size",
+    SpdyRecvBufferSize => "spdy_recv_buffer_size",
+    SpdyRecvTimeout => "spdy_recv_timeout",
+    SpdyStreamsIndexSize => "spdy_streams_index_size",
+    SplitClients => "split_clients",
+    Ssi => "ssi",
+    SsiIgnoreRecycledBuffers => "ssi_ignore_recycled_buffers",
+    SsiLastModified => "ssi_last_modified",
+    SsiMinFileChunk => "ssi_min_file_chunk",
+    SsiSilentErrors => "ssi_silent_errors",
+    SsiTypes => "ssi_types",
+    SsiValueLength => "ssi_value_length",
+    Ssl => "ssl",
+    SslAlpn => "ssl_alpn",
+    SslBufferSize => "ssl_buffer_size",
+    SslCertificate => "ssl_certificate",
+    SslCertificateByLuaBlock => "ssl_certificate_by_lua_block",
+    SslCertificateByLuaFile => "ssl_certificate_by_lua_file",
+    SslCertificateCache => "ssl_certificate_cache",
+    SslCertificateKey => "ssl_certificate_key",
+    SslCiphers => "ssl_ciphers",
+    SslClientCertificate => "ssl_client_certificate",
+    SslClientHelloByLuaBlock => "ssl_client_hello_by_lua_block",
+    SslClientHelloByLuaFile => "ssl_client_hello_by_lua_file",
+    SslConfCommand => "ssl_conf_command",
+    SslCrl => "ssl_crl",
+    SslDhparam => "ssl_dhparam",
+    SslEarlyData => "ssl_early_data",
+    SslEcdhCurve => "ssl_ecdh_curve",
+    SslEngine => "ssl_engine",
+    SslHandshakeTimeout => "ssl_handshake_timeout",
+    SslOcsp => "ssl_ocsp",
+    SslOcspCache => "ssl_ocsp_cache",
+    SslOcspResponder => "ssl_ocsp_responder",
+    SslPasswordFile => "ssl_password_file",
+    SslPreferServerCiphers => "ssl_prefer_server_ciphers",
+    SslPreread => "ssl_preread",
+    SslProtocols => "ssl_protocols",
+    SslRejectHandshake => "ssl_reject_handshake",
+    SslSessionCache => "ssl_session_cache",
+    SslSessionFetchByLuaBlock => "ssl_session_fetch_by_lua_block",
+    SslSessionFetchByLuaFile => "ssl_session_fetch_by_lua_file",
+    SslSessionStoreByLuaBlock => "ssl_session_store_by_lua_block",
+    SslSessionStoreByLuaFile => "ssl_session_store_by_lua_file",
+    SslSessionTicketKey => "ssl_session_ticket_key",
+    SslSessionTickets => "ssl_session_tickets",
+    SslSessionTimeout => "ssl_session_timeout",
+    SslStapling => "ssl_stapling",
+    SslStaplingFile => "ssl_stapling_file",
+    SslStaplingResponder => "ssl_stapling_responder",
+    SslStaplingVerify => "ssl_stapling_verify",
+    SslTrustedCertificate => "ssl_trusted_certificate",
+    SslVerifyClient => "ssl_verify_client",
+    SslVerifyDepth => "ssl_verify_depth",
+    Starttls => "starttls",
+    State => "state",
+    Status => "status",
+    StatusFormat => "status_format",
+    StatusZone => "status_zone",
+    Sticky => "sticky",
+    StickyCookieInsert => "sticky_cookie_insert",
+    Stream => "stream",
+    StubStatus => "stub_status",
+    SubFilter => "sub_filter",
+    SubFilterLastModified => "sub_filter_last_modified",
+    SubFilterOnce => "sub_filter_once",
+    SubFilterTypes => "sub_filter_types",
+    SubrequestOutputBufferSize => "subrequest_output_buffer_size",
+    TcpNodelay => "tcp_nodelay",
+    TcpNopush => "tcp_nopush",
+    ThreadPool => "thread_pool",
+    ThreadStackSize => "thread_stack_size",
+    Timeout => "timeout",
+    TimerResolution => "timer_resolution",
+    TryFiles => "try_files",
+    Types => "types",
+    TypesHashBucketSize => "types_hash_bucket_size",
+    TypesHashMaxSize => "types_hash_max_size",
+    UnderscoresInHeaders => "underscores_in_headers",
+    UninitializedVariableWarn => "uninitialized_variable_warn",
+    Upstream => "upstream",
+    UpstreamConf => "upstream_conf",
+    UsageReport => "usage_report",
+    Use => "use",
+    User => "user",
+    Userid => "userid",
+    UseridDomain => "userid_domain",
+    UseridExpires => "userid_expires",
+    UseridMark => "userid_mark",
+    UseridName => "userid_name",
+    UseridP3p => "userid_p3p",
+    UseridPath => "userid_path",
+    UseridService => "userid_service",
+    UwsgiBind => "uwsgi_bind",
+    UwsgiBufferSize => "uwsgi_buffer_size",
+    UwsgiBuffering => "uwsgi_buffering",
+    UwsgiBuffers => "uwsgi_buffers",
+    UwsgiBusyBuffersSize => "uwsgi_busy_buffers_size",
+    UwsgiCache => "uwsgi_cache",
+    UwsgiCacheBackgroundUpdate => "uwsgi_cache_background_update",
+    UwsgiCacheBypass => "uwsgi_cache_bypass",
+    UwsgiCacheKey => "uwsgi_cache_key",
+    UwsgiCacheLock => "uwsgi_cache_lock",
+    UwsgiCacheLockAge => "uwsgi_cache_lock_age",
+    UwsgiCacheLockTimeout => "uwsgi_cache_lock_timeout",
+    UwsgiCacheMaxRangeOffset => "uwsgi_cache_max_range_offset",
+    UwsgiCacheMethods => "uwsgi_cache_methods",
+    UwsgiCacheMinUses => "uwsgi_cache_min_uses",
+    UwsgiCachePath => "uwsgi_cache_path",
+    UwsgiCachePurge => "uwsgi_cache_purge",
+    UwsgiCacheRevalidate => "uwsgi_cache_revalidate",
+    UwsgiCacheUseStale => "uwsgi_cache_use_stale",
+    UwsgiCacheValid => "uwsgi_cache_valid",
+    UwsgiConnectTimeout => "uwsgi_connect_timeout",
+    UwsgiForceRanges => "uwsgi_force_ranges",
+    UwsgiHideHeader => "uwsgi_hide_header",
+    UwsgiIgnoreClientAbort => "uwsgi_ignore_client_abort",
+    UwsgiIgnoreHeaders => "uwsgi_ignore_headers",
+    UwsgiInterceptErrors => "uwsgi_intercept_errors",
+    UwsgiLimitRate => "uwsgi_limit_rate",
+    UwsgiMaxTempFileSize => "uwsgi_max_temp_file_size",
+    UwsgiModifier1 => "uwsgi_modifier1",
+    UwsgiModifier2 => "uwsgi_modifier2",
+    UwsgiNextUpstream => "uwsgi_next_upstream",
+    UwsgiNextUpstreamTimeout => "uwsgi_next_upstream_timeout",
+    UwsgiNextUpstreamTries => "uwsgi_next_upstream_tries",
+    UwsgiNoCache => "uwsgi_no_cache",
+    UwsgiParam => "uwsgi_param",
+    UwsgiPass => "uwsgi_pass",
+    UwsgiPassHeader => "uwsgi_pass_header",
+    UwsgiPassRequestBody => "uwsgi_pass_request_body",
+    UwsgiPassRequestHeaders => "uwsgi_pass_request_headers",
+    UwsgiReadTimeout => "uwsgi_read_timeout",
+    UwsgiRequestBuffering => "uwsgi_request_buffering",
+    UwsgiSendTimeout => "uwsgi_send_timeout",
+    UwsgiSocketKeepalive => "uwsgi_socket_keepalive",
+    UwsgiSslCertificate => "uwsgi_ssl_certificate",
+    UwsgiSslCertificateKey => "uwsgi_ssl_certificate_key",
+    UwsgiSslCiphers => "uwsgi_ssl_ciphers",
+    UwsgiSslConfCommand => "uwsgi_ssl_conf_command",
+    UwsgiSslCrl => "uwsgi_ssl_crl",
+    UwsgiSslName => "uwsgi_ssl_name",
+    UwsgiSslPasswordFile => "uwsgi_ssl_password_file",
+    UwsgiSslProtocols => "uwsgi_ssl_protocols",
+    UwsgiSslServerName => "uwsgi_ssl_server_name",
+    UwsgiSslSessionReuse => "uwsgi_ssl_session_reuse",
+    UwsgiSslTrustedCertificate => "uwsgi_ssl_trusted_certificate",
+    UwsgiSslVerify => "uwsgi_ssl_verify",
+    UwsgiSslVerifyDepth => "uwsgi_ssl_verify_depth",
+    UwsgiStore => "uwsgi_store",
+    UwsgiStoreAccess => "uwsgi_store_access",
+    UwsgiString => "uwsgi_string",
+    UwsgiTempFileWriteSize => "uwsgi_temp_file_write_size",
+    UwsgiTempPath => "uwsgi_temp_path",
+    ValidReferers => "valid_referers",
+    VariablesHashBucketSize => "variables_hash_bucket_size",
+    VariablesHashMaxSize => "variables_hash_max_size",
+    WorkerAioRequests => "worker_aio_requests",
+    WorkerConnections => "worker_connections",
+    WorkerCpuAffinity => "worker_cpu_affinity",
+    WorkerPriority => "worker_priority",
+    WorkerProcesses => "worker_processes",
+    WorkerRlimitCore => "worker_rlimit_core",
+    WorkerRlimitNofile => "worker_rlimit_nofile",
+    WorkerRlimitSigpending => "worker_rlimit_sigpending",
+    WorkerShutdownTimeout => "worker_shutdown_timeout",
+    WorkerThreads => "worker_threads",
+    WorkingDirectory => "working_directory",
+    Xclient => "xclient",
+    XmlEntities => "xml_entities",
+    XsltLastModified => "xslt_last_modified",
+    XsltParam => "xslt_param",
+    XsltStringParam => "xslt_string_param",
+    XsltStylesheet => "xslt_stylesheet",
+    XsltTypes => "xslt_types",
+    Zone => "zone",
+    ZoneSync => "zone_sync",
+    ZoneSyncBuffers => "zone_sync_buffers",
+    ZoneSyncConnectRetryInterval => "zone_sync_connect_retry_interval",
+    ZoneSyncConnectTimeout => "zone_sync_connect_timeout",
+    ZoneSyncInterval => "zone_sync_interval",
+    ZoneSyncRecvBufferSize => "zone_sync_recv_buffer_size",
+    ZoneSyncServer => "zone_sync_server",
+    ZoneSyncSsl => "zone_sync_ssl",
+    ZoneSyncSslCertificate => "zone_sync_ssl_certificate",
+    ZoneSyncSslCertificateKey => "zone_sync_ssl_certificate_key",
+    ZoneSyncSslCiphers => "zone_sync_ssl_ciphers",
+    ZoneSyncSslConfCommand => "zone_sync_ssl_conf_command",
+    ZoneSyncSslCrl => "zone_sync_ssl_crl",
+    ZoneSyncSslName => "zone_sync_ssl_name",
+    ZoneSyncSslPasswordFile => "zone_sync_ssl_password_file",
+    ZoneSyncSslProtocols => "zone_sync_ssl_protocols",
+    ZoneSyncSslServerName => "zone_sync_ssl_server_name",
+    ZoneSyncSslTrustedCertificate => "zone_sync_ssl_trusted_certificate",
+    ZoneSyncSslVerify => "zone_sync_ssl_verify",
+    ZoneSyncSslVerifyDepth => "zone_sync_ssl_verify_depth",
+    ZoneSyncTimeout => "zone_sync_timeout",
+}
+
+impl FromStr for DirectiveName {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        static NAMES: OnceLock<HashMap<&'static str, DirectiveName>> = OnceLock::new();
+        let names = NAMES.get_or_init(|| Self::ALL.iter().map(|name| (name.as_str(), *name)).collect());
+        names.get(s).copied().ok_or(())
+    }
+}
+
+impl Display for DirectiveName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for name in DirectiveName::ALL {
+            assert_eq!(name.as_str().parse(), Ok(*name));
+            assert_eq!(name.to_string(), name.as_str());
+        }
+        assert!(DirectiveName::ALL.windows(2).all(|pair| pair[0] < pair[1] && pair[0].as_str() < pair[1].as_str()));
+    }
+
+    #[test]
+    fn unknown_names_do_not_parse() {
+        assert_eq!("proxy_pass".parse(), Ok(DirectiveName::ProxyPass));
+        assert_eq!("Proxy_Pass".parse::<DirectiveName>(), Err(()));
+        assert_eq!("proxy_pas".parse::<DirectiveName>(), Err(()));
+        assert_eq!("".parse::<DirectiveName>(), Err(()));
+    }
+}
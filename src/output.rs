@@ -0,0 +1,279 @@
+use std::fmt::Write;
+
+use crate::{
+    lint::{Finding, Level, LintReport, Severity},
+    validate::Diagnostic,
+    Span, Structure,
+};
+
+/// SARIF version written by `to_sarif`
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A lint finding or a validation diagnostic, as written out by `to_json` and `to_sarif`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Rule of a finding, or `Problem::id` of a diagnostic
+    pub rule: &'static str,
+    pub message: String,
+    /// `Deny` for errors, `Warn` for warnings
+    pub level: Level,
+    /// Of lint findings only
+    pub severity: Option<Severity>,
+    /// Path of the config, as given
+    pub file: Option<String>,
+    pub span: Option<Span>,
+    /// Of diagnostics about two statements, such as duplicates
+    pub related: Option<Related>,
+}
+
+/// The other statement a record is about, such as the first of duplicates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Related {
+    /// What it is, "first `root`"
+    pub message: String,
+    pub file: Option<String>,
+    pub span: Option<Span>,
+}
+
+impl Record {
+    pub fn from_finding(level: Level, finding: &Finding, file: Option<&str>) -> Self {
+        Self {
+            rule: finding.rule,
+            message: finding.message.clone(),
+            level,
+            severity: Some(finding.severity),
+            file: file.map(String::from),
+            span: finding.span,
+            related: None,
+        }
+    }
+
+    /// `source` is the text the diagnostic's statement was parsed from
+    pub fn from_diagnostic(diagnostic: &Diagnostic, source: &str, file: Option<&str>) -> Self {
+        Self {
+            rule: diagnostic.problem.id(),
+            message: diagnostic.to_string(),
+            level: if diagnostic.problem.is_error() { Level::Deny } else { Level::Warn },
+            severity: None,
+            file: file.map(String::from),
+            span: diagnostic.statement.span(source),
+            related: diagnostic.related.map(|related| Related {
+                message: format!("first `{}`", related_directive(related)),
+                file: file.map(String::from),
+                span: related.span(source),
+            }),
+        }
+    }
+
+    pub fn from_report(report: &LintReport, file: Option<&str>) -> Vec<Self> {
+        report.findings.iter().map(|(level, finding)| Self::from_finding(*level, finding, file)).collect()
+    }
+}
+
+/// An array of objects with `rule`, `message`, `level` (`error` or `warning`), `severity` (`low`, `medium`, `high`),
+/// `file`, the `line`, `column`, `end_line` and `end_column` of the span, and `related` with the `message`, `file`
+/// and span of the other statement, `null` when unknown
+pub fn to_json(records: &[Record]) -> String {
+    let mut json = String::from("[");
+    for (index, record) in records.iter().enumerate() {
+        json.push_str(if index == 0 { "\n" } else { ",\n" });
+        let related = match &record.related {
+            Some(related) => format!(
+                r#"{{"message": {}, "file": {}, {}}}"#,
+                string(&related.message),
+                related.file.as_deref().map_or("null".to_string(), string),
+                json_span(related.span),
+            ),
+            None => "null".to_string(),
+        };
+        let _ = write!(
+            json,
+            r#"  {{"rule": {}, "message": {}, "level": {}, "severity": {}, "file": {}, {}, "related": {related}}}"#,
+            string(record.rule),
+            string(&record.message),
+            string(level(record.level)),
+            record.severity.map_or("null".to_string(), |severity| string(&severity.to_string())),
+            record.file.as_deref().map_or("null".to_string(), string),
+            json_span(record.span),
+        );
+    }
+    json.push_str(if records.is_empty() { "]\n" } else { "\n]\n" });
+    json
+}
+
+/// A SARIF log of a single run, for GitHub code scanning and other SARIF consumers
+/// Rules are listed from the records, lint severities go in the results' `properties`
+pub fn to_sarif(records: &[Record]) -> String {
+    let mut rules = Vec::new();
+    for record in records {
+        if !rules.contains(&record.rule) {
+            rules.push(record.rule);
+        }
+    }
+    let mut sarif = String::new();
+    let _ = writeln!(sarif, r#"{{"$schema": {}, "version": {}, "runs": [{{"#, string(SARIF_SCHEMA), string(SARIF_VERSION));
+    let _ = writeln!(
+        sarif,
+        r#"  "tool": {{"driver": {{"name": {}, "version": {}, "informationUri": {}, "rules": ["#,
+        string(env!("CARGO_PKG_NAME")),
+        string(env!("CARGO_PKG_VERSION")),
+        string(env!("CARGO_PKG_HOMEPAGE")),
+    );
+    for (index, rule) in rules.iter().enumerate() {
+        let separator = if index + 1 == rules.len() { "" } else { "," };
+        let _ = writeln!(sarif, r#"    {{"id": {}}}{separator}"#, string(rule));
+    }
+    let _ = writeln!(sarif, "  ]}}}},");
+    let _ = writeln!(sarif, r#"  "results": ["#);
+    for (index, record) in records.iter().enumerate() {
+        let rule_index = rules.iter().position(|rule| *rule == record.rule).unwrap_or_default();
+        let mut result = format!(
+            r#"{{"ruleId": {}, "ruleIndex": {rule_index}, "level": {}, "message": {{"text": {}}}"#,
+            string(record.rule),
+            string(level(record.level)),
+            string(&record.message),
+        );
+        if let Some(file) = &record.file {
+            let _ = write!(result, r#", "locations": [{{"physicalLocation": {}}}]"#, physical_location(file, record.span));
+        }
+        if let Some(Related { message, file: Some(file), span }) = &record.related {
+            let _ = write!(
+                result,
+                r#", "relatedLocations": [{{"id": 0, "physicalLocation": {}, "message": {{"text": {}}}}}]"#,
+                physical_location(file, *span),
+                string(message),
+            );
+        }
+        if let Some(severity) = record.severity {
+            let _ = write!(result, r#", "properties": {{"severity": {}}}"#, string(&severity.to_string()));
+        }
+        result.push('}');
+        let separator = if index + 1 == records.len() { "" } else { "," };
+        let _ = writeln!(sarif, "    {result}{separator}");
+    }
+    sarif.push_str("  ]\n}]}\n");
+    sarif
+}
+
+/// `line`, `column`, `end_line` and `end_column` members
+fn json_span(span: Option<Span>) -> String {
+    let (start, end) = span.map(|span| (span.start, span.end)).unzip();
+    format!(
+        r#""line": {}, "column": {}, "end_line": {}, "end_column": {}"#,
+        number(start.map(|p| p.line)),
+        number(start.map(|p| p.column)),
+        number(end.map(|p| p.line)),
+        number(end.map(|p| p.column)),
+    )
+}
+
+/// SARIF `physicalLocation` object
+fn physical_location(file: &str, span: Option<Span>) -> String {
+    let mut location = format!(r#"{{"artifactLocation": {{"uri": {}}}"#, string(&uri(file)));
+    if let Some(span) = span {
+        let _ = write!(
+            location,
+            r#", "region": {{"startLine": {}, "startColumn": {}, "endLine": {}, "endColumn": {}}}"#,
+            span.start.line, span.start.column, span.end.line, span.end.column,
+        );
+    }
+    location.push('}');
+    location
+}
+
+/// Name of a directive as written
+fn related_directive<'a>(statement: &'a Structure<'a>) -> &'a str {
+    let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+    args.first().map_or("", |name| name.value())
+}
+
+fn level(level: Level) -> &'static str {
+    match level {
+        Level::Deny => "error",
+        Level::Warn | Level::Allow => "warning",
+    }
+}
+
+fn number(value: Option<usize>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
+
+/// JSON string literal
+fn string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            },
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// URI of a path, with `/` separators and the characters URIs can't hold escaped,
+/// a `file://` one for absolute paths and a relative reference otherwise
+fn uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut uri = String::with_capacity(path.len() + 7);
+    if path.starts_with('/') {
+        uri.push_str("file://");
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => uri.push(byte as char),
+            _ => {
+                let _ = write!(uri, "%{byte:02X}");
+            },
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::check_duplicates;
+
+    const SOURCE: &str = "http {\n    root /a;\n    root /b;\n}\n";
+
+    fn duplicate(root: &Structure<'_>) -> Record {
+        let diagnostics = check_duplicates(root);
+        Record::from_diagnostic(&diagnostics[0], SOURCE, Some("nginx.conf"))
+    }
+
+    #[test]
+    fn diagnostics_keep_their_related_statement() {
+        let root = Structure::parse(SOURCE).unwrap();
+        let record = duplicate(&root);
+        assert_eq!(record.span.map(|span| span.start.to_string()), Some("3:5".to_string()));
+        let related = record.related.unwrap();
+        assert_eq!(related.message, "first `root`");
+        assert_eq!(related.span.map(|span| span.start.to_string()), Some("2:5".to_string()));
+    }
+
+    #[test]
+    fn json_and_sarif_hold_the_related_location() {
+        let root = Structure::parse(SOURCE).unwrap();
+        let records = [duplicate(&root)];
+        let json = to_json(&records);
+        assert!(json.contains(r#""related": {"message": "first `root`", "file": "nginx.conf", "line": 2, "column": 5, "end_line": 2, "end_column": 12}"#));
+        let sarif = to_sarif(&records);
+        assert!(sarif.contains(r#""relatedLocations": [{"id": 0, "physicalLocation": {"artifactLocation": {"uri": "nginx.conf"}, "region": {"startLine": 2, "startColumn": 5, "endLine": 2, "endColumn": 12}}, "message": {"text": "first `root`"}}]"#));
+    }
+
+    #[test]
+    fn json_escapes_strings() {
+        assert_eq!(string("a \"b\"\\\n\u{1}"), r#""a \"b\"\\\n\u0001""#);
+        assert_eq!(uri("/etc/nginx/my site.conf"), "file:///etc/nginx/my%20site.conf");
+    }
+}
@@ -0,0 +1,316 @@
+use std::{net::SocketAddr, time::Duration};
+
+use crate::{
+    effective::{effective_config, EffectiveSettings},
+    lint::{BuiltinRule, Check, Finding, Severity},
+    names::DirectiveName,
+    router::Router,
+    tls::is_tls,
+    types::{grpcs_without_trusted_certificate, Directive, ServerBlock, UpstreamTimeouts},
+    validate::pass_upstream,
+    Structure, Token,
+};
+
+/// `proxy_pass` without `proxy_set_header Host`, the upstream gets the name of the `proxy_pass` target
+pub const PROXY_HOST_HEADER: &str = "proxy-host-header";
+/// `proxy_pass` without an `X-Forwarded-For` carrying the client address
+pub const PROXY_X_FORWARDED_FOR: &str = "proxy-x-forwarded-for";
+/// `proxy_pass` without an `X-Forwarded-Proto` matching the scheme
+pub const PROXY_X_FORWARDED_PROTO: &str = "proxy-x-forwarded-proto";
+/// `proxy_pass` to `$host` or `$http_host` in a server answering any host
+pub const OPEN_PROXY: &str = "open-proxy";
+/// Location passing to an app server with `client_max_body_size` left at 1m or lowered
+pub const CLIENT_MAX_BODY_SIZE: &str = "client-max-body-size";
+/// `proxy_read_timeout` shorter than the `keepalive_timeout` of the upstream
+pub const PROXY_READ_TIMEOUT: &str = "proxy-read-timeout";
+/// `grpc_pass grpcs://` without a `grpc_ssl_trusted_certificate` to verify the upstream against
+pub const GRPCS_TRUSTED_CERTIFICATE: &str = "grpcs-trusted-certificate";
+
+/// Directives passing requests to an app server
+pub(crate) const APP_PASSES: &[DirectiveName] = &[
+    DirectiveName::ProxyPass,
+    DirectiveName::FastcgiPass,
+    DirectiveName::UwsgiPass,
+    DirectiveName::ScgiPass,
+    DirectiveName::GrpcPass,
+];
+/// `keepalive_timeout` of upstreams when not set
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+/// `client_max_body_size` when not set, 1m
+const DEFAULT_BODY_SIZE: u64 = 1 << 20;
+
+/// Host no config names, to find the servers answering requests for any host
+const UNKNOWN_HOST: &str = "unknown-host.invalid";
+
+/// The rules of this module, for `lint::LintRunner`
+pub fn rules() -> Vec<BuiltinRule> {
+    vec![
+        BuiltinRule { id: PROXY_HOST_HEADER, name: "proxied Host header", check: Check::Routed(check_proxy_headers) },
+        BuiltinRule { id: PROXY_X_FORWARDED_FOR, name: "proxied X-Forwarded-For", check: Check::Routed(check_proxy_headers) },
+        BuiltinRule { id: PROXY_X_FORWARDED_PROTO, name: "proxied X-Forwarded-Proto", check: Check::Routed(check_proxy_headers) },
+        BuiltinRule { id: OPEN_PROXY, name: "open proxy", check: Check::Routed(check_open_proxy) },
+        BuiltinRule { id: CLIENT_MAX_BODY_SIZE, name: "default upload size", check: Check::Routed(check_body_size) },
+        BuiltinRule { id: PROXY_READ_TIMEOUT, name: "read timeout below upstream keepalive", check: Check::Routed(check_read_timeout) },
+        BuiltinRule { id: GRPCS_TRUSTED_CERTIFICATE, name: "unverified gRPC upstream", check: Check::Routed(check_grpcs_certificate) },
+    ]
+}
+
+/// A `*_pass` of a location of an `http` server
+struct Proxied<'r, 'a> {
+    server: &'r ServerBlock<'a>,
+    /// `http`, the server and the locations down to the one with the `*_pass`
+    contexts: Vec<&'a Structure<'a>>,
+    directive: DirectiveName,
+    pass: &'a Structure<'a>,
+}
+
+/// Locations with one of these `*_pass` directives
+fn proxied<'r, 'a>(router: &'r Router<'a>, passes: &[DirectiveName]) -> Vec<Proxied<'r, 'a>> {
+    let mut proxied = Vec::new();
+    for scope in router.scopes().into_iter().filter(|scope| !scope.locations.is_empty()) {
+        let Structure::Block { children, .. } = scope.block() else {
+            continue;
+        };
+        let Some((pass, directive)) = children.iter().find_map(|c| Some((c, c.name().filter(|n| passes.contains(n))?))) else {
+            continue;
+        };
+        let contexts = router.contexts(scope.server, &scope.locations);
+        proxied.push(Proxied { server: scope.server, contexts, directive, pass });
+    }
+    proxied
+}
+
+/// Value of the `proxy_set_header` in effect for a header, `None` when it's not set
+fn proxy_header<'a>(settings: &EffectiveSettings<'a>, header: &str) -> Option<&'a str> {
+    settings.statements(DirectiveName::ProxySetHeader).find_map(|statement| {
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+        match args.as_slice() {
+            [_, name, value] if name.value().eq_ignore_ascii_case(header) => Some(value.value()),
+            _ => None,
+        }
+    })
+}
+
+/// `Host`, `X-Forwarded-For` and `X-Forwarded-Proto` in effect for every `proxy_pass`,
+/// `proxy_set_header` of a context replacing all the inherited ones
+pub fn check_proxy_headers<'a>(router: &Router<'a>, source: &str) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    for proxied in proxied(router, &[DirectiveName::ProxyPass]) {
+        let settings = effective_config(&proxied.contexts);
+        let mut report = |rule, severity, message: String| {
+            findings.push(Finding::new(rule, severity, proxied.pass, source, message));
+        };
+        if proxy_header(&settings, "Host").is_none() {
+            let message = "no `proxy_set_header Host`, the upstream gets the host of the `proxy_pass` target".to_string();
+            report(PROXY_HOST_HEADER, Severity::Medium, message);
+        }
+        match proxy_header(&settings, "X-Forwarded-For") {
+            None => {
+                let message = "no `proxy_set_header X-Forwarded-For`, the upstream only sees nginx's address".to_string();
+                report(PROXY_X_FORWARDED_FOR, Severity::Low, message);
+            },
+            Some(value) if !value.contains("$proxy_add_x_forwarded_for") && !value.contains("$remote_addr") => {
+                let message = format!(
+                    "`X-Forwarded-For {value}` doesn't carry the client address, use `$proxy_add_x_forwarded_for`"
+                );
+                report(PROXY_X_FORWARDED_FOR, Severity::Medium, message);
+            },
+            Some(_) => {},
+        }
+        let tls = is_tls(proxied.server.block);
+        match proxy_header(&settings, "X-Forwarded-Proto") {
+            None => {
+                let message = "no `proxy_set_header X-Forwarded-Proto`, the upstream can't tell HTTPS requests".to_string();
+                report(PROXY_X_FORWARDED_PROTO, Severity::Low, message);
+            },
+            Some("http") if tls => {
+                let message = "`X-Forwarded-Proto http` on an HTTPS server, use `$scheme`".to_string();
+                report(PROXY_X_FORWARDED_PROTO, Severity::Medium, message);
+            },
+            Some(_) => {},
+        }
+    }
+    findings
+}
+
+/// `proxy_pass` to a target built from the client's `Host` (`$host`, `$http_host`) in a server
+/// that also answers hosts it isn't named after, making nginx forward requests anywhere
+pub fn check_open_proxy<'a>(router: &Router<'a>, source: &str) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    for proxied in proxied(router, &[DirectiveName::ProxyPass]) {
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = proxied.pass;
+        let Some(target) = args.get(1).map(Token::value) else {
+            continue;
+        };
+        let Some(variable) = ["$http_host", "$host"].into_iter().find(|v| uses_variable(target, v)) else {
+            continue;
+        };
+        let server = proxied.server;
+        let catch_all = server.listen.iter().any(|(addr, _)| answers_any_host(router, server, *addr));
+        if catch_all {
+            let message = format!(
+                "`proxy_pass {target}` follows the client's `{variable}` in a server answering any host, nginx proxies to wherever it's told"
+            );
+            findings.push(Finding::new(OPEN_PROXY, Severity::High, proxied.pass, source, message));
+        }
+    }
+    findings
+}
+
+/// The server gets requests for hosts it's not named after on `addr`, as the default server or by a catch-all name
+fn answers_any_host(router: &Router, server: &ServerBlock, addr: SocketAddr) -> bool {
+    router.servers.select(addr, UNKNOWN_HOST).is_some_and(|selected| std::ptr::eq(selected.block, server.block))
+}
+
+fn uses_variable(text: &str, variable: &str) -> bool {
+    text.match_indices(variable)
+        .any(|(at, _)| !text[at + variable.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// Locations passing to an app server with a `client_max_body_size` of 1m or less, in them or inherited,
+/// the default when none is set, uploads over it fail with 413
+pub fn check_body_size<'a>(router: &Router<'a>, source: &str) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    for proxied in proxied(router, APP_PASSES) {
+        let settings = effective_config(&proxied.contexts);
+        let size = settings.directives(DirectiveName::ClientMaxBodySize).last().and_then(|directive| match directive {
+            Directive::ClientMaxBodySize { size } => Some(size),
+            _ => None,
+        });
+        let message = match size {
+            None => format!("`{}` without `client_max_body_size`, request bodies over the 1m default fail with 413", proxied.directive),
+            // 0 disables the check
+            Some(size) if size.bytes() == 0 || size.bytes() > DEFAULT_BODY_SIZE => continue,
+            Some(size) => format!("`{}` with a `client_max_body_size` of {} bytes, request bodies over it fail with 413", proxied.directive, size.bytes()),
+        };
+        findings.push(Finding::new(CLIENT_MAX_BODY_SIZE, Severity::Low, proxied.pass, source, message));
+    }
+    findings
+}
+
+/// Locations passing to a `grpcs://` upstream with no `grpc_ssl_trusted_certificate` set in them or inherited
+pub fn check_grpcs_certificate<'a>(router: &Router<'a>, source: &str) -> Vec<Finding<'a>> {
+    proxied(router, &[DirectiveName::GrpcPass])
+        .into_iter()
+        .filter(|proxied| grpcs_without_trusted_certificate(&proxied.contexts))
+        .map(|proxied| {
+            let message = "`grpc_pass` to `grpcs://` without `grpc_ssl_trusted_certificate`, the upstream's certificate can't be verified";
+            Finding::new(GRPCS_TRUSTED_CERTIFICATE, Severity::Medium, proxied.pass, source, message.to_string())
+        })
+        .collect()
+}
+
+/// `proxy_pass` to an upstream keeping idle connections (`keepalive_timeout` of the upstream block) longer
+/// than the location waits for a response, slow responses fail with 504 on connections nginx still deems fresh
+pub fn check_read_timeout<'a>(router: &Router<'a>, source: &str) -> Vec<Finding<'a>> {
+    let upstreams = router
+        .http
+        .into_iter()
+        .flat_map(|http| match http {
+            Structure::Block { children, .. } => children.as_slice(),
+            Structure::Statement { .. } => &[],
+        })
+        .filter(|child| child.name() == Some(DirectiveName::Upstream))
+        .collect::<Vec<_>>();
+    let mut findings = Vec::new();
+    for proxied in proxied(router, &[DirectiveName::ProxyPass]) {
+        let Some(host) = pass_upstream(proxied.pass, proxied.directive) else {
+            continue;
+        };
+        let upstream = upstreams.iter().find(|upstream| {
+            let (Structure::Statement { args } | Structure::Block { args, .. }) = upstream;
+            args.get(1).is_some_and(|name| name.value() == host)
+        });
+        let Some(Structure::Block { children, .. }) = upstream else {
+            continue;
+        };
+        // without `keepalive` there are no idle connections
+        if !children.iter().any(|child| child.name() == Some(DirectiveName::Keepalive)) {
+            continue;
+        }
+        let keepalive = children.iter().find_map(|child| match Directive::try_from(child) {
+            Ok(Directive::KeepaliveTimeout { timeout, .. }) => Some(timeout),
+            _ => None,
+        });
+        let keepalive = keepalive.unwrap_or(DEFAULT_TIMEOUT);
+        let read = UpstreamTimeouts::from_settings(&effective_config(&proxied.contexts)).read;
+        if read < keepalive {
+            let message = format!(
+                "`proxy_read_timeout` of {}s is shorter than the {}s `keepalive_timeout` of upstream `{host}`, slow responses end in 504",
+                read.as_secs(),
+                keepalive.as_secs(),
+            );
+            findings.push(Finding::new(PROXY_READ_TIMEOUT, Severity::Low, proxied.pass, source, message));
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines of the findings of `rule`
+    fn lines(check: for<'a> fn(&Router<'a>, &str) -> Vec<Finding<'a>>, rule: &str, source: &str) -> Vec<usize> {
+        let root = Structure::parse(source).unwrap();
+        let router = Router::new(&root);
+        check(&router, source).iter().filter(|finding| finding.rule == rule).map(|finding| finding.span.unwrap().start.line).collect()
+    }
+
+    #[test]
+    fn proxy_headers_in_effect() {
+        let source = "http {\n\
+            proxy_set_header Host $host; proxy_set_header X-Forwarded-For $proxy_add_x_forwarded_for; proxy_set_header X-Forwarded-Proto $scheme;\n\
+            server { listen 443 ssl;\n\
+            location /a { proxy_pass http://app; }\n\
+            location /b { proxy_set_header X-Request-Id $request_id; proxy_pass http://app; }\n\
+            location /c { proxy_set_header Host $host; proxy_set_header X-Forwarded-For $http_x_real_ip; proxy_set_header X-Forwarded-Proto http; proxy_pass http://app; }\n\
+            location /d { fastcgi_pass unix:/php.sock; }\n\
+            }\n\
+            }";
+        assert_eq!(lines(check_proxy_headers, PROXY_HOST_HEADER, source), [5]);
+        assert_eq!(lines(check_proxy_headers, PROXY_X_FORWARDED_FOR, source), [5, 6]);
+        assert_eq!(lines(check_proxy_headers, PROXY_X_FORWARDED_PROTO, source), [5, 6]);
+    }
+
+    #[test]
+    fn proxying_to_the_client_host_in_catch_all_servers() {
+        let source = "http {\n\
+            server { listen 80 default_server; location / { proxy_pass http://$host; } }\n\
+            server { listen 80; server_name api.example.com; location / { proxy_pass http://$host$request_uri; } }\n\
+            server { listen 8080; server_name _; location / { proxy_pass https://$http_host/; } }\n\
+            server { listen 8081; location / { proxy_pass http://$hostname; } }\n\
+            }";
+        assert_eq!(lines(check_open_proxy, OPEN_PROXY, source), [2, 4]);
+    }
+
+    #[test]
+    fn body_size_at_most_the_default() {
+        let source = "http {\n\
+            server { location /a { proxy_pass http://app; } }\n\
+            server { client_max_body_size 1m; location /b { proxy_pass http://app; } }\n\
+            server { client_max_body_size 64k; location /c { fastcgi_pass unix:/php.sock; } }\n\
+            server { client_max_body_size 10m; location /d { proxy_pass http://app; } }\n\
+            server { client_max_body_size 512k; location /e { client_max_body_size 0; proxy_pass http://app; } }\n\
+            server { location /f { root /srv; } }\n\
+            }";
+        assert_eq!(lines(check_body_size, CLIENT_MAX_BODY_SIZE, source), [2, 3, 4]);
+    }
+
+    #[test]
+    fn read_timeout_below_upstream_keepalive() {
+        let source = "http {\n\
+            upstream idle { server 10.0.0.1; keepalive 8; keepalive_timeout 120s; }\n\
+            upstream fresh { server 10.0.0.2; keepalive 8; }\n\
+            upstream cold { server 10.0.0.3; keepalive_timeout 120s; }\n\
+            server { proxy_read_timeout 30s;\n\
+            location /a { proxy_pass http://idle; }\n\
+            location /b { proxy_read_timeout 5m; proxy_pass http://idle; }\n\
+            location /c { proxy_pass http://fresh; }\n\
+            location /d { proxy_pass http://cold; }\n\
+            }\n\
+            server { location /e { proxy_pass http://idle; } location /f { proxy_pass http://fresh; } }\n\
+            }";
+        assert_eq!(lines(check_read_timeout, PROXY_READ_TIMEOUT, source), [6, 8, 11]);
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt::Display;
+
+use crate::{
+    effective::effective_config,
+    names::DirectiveName,
+    proxy::APP_PASSES,
+    router::{Router, Scope},
+    types::{Directive, LimitConnZone, LimitReq, LimitReqZone},
+    Structure,
+};
+
+/// `limit_req` and `limit_conn` zones of an `http` block
+#[derive(Debug, Clone, Default)]
+pub struct Zones {
+    pub requests: Vec<LimitReqZone>,
+    pub connections: Vec<LimitConnZone>,
+}
+
+impl Zones {
+    pub fn from_block(http: &Structure) -> Self {
+        let mut zones = Self::default();
+        if let Structure::Block { children, .. } = http {
+            for child in children {
+                match Directive::try_from(child) {
+                    Ok(Directive::LimitReqZone(zone)) => zones.requests.push(zone),
+                    Ok(Directive::LimitConnZone(zone)) => zones.connections.push(zone),
+                    _ => {},
+                }
+            }
+        }
+        zones
+    }
+}
+
+/// A `limit_conn` in effect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitConn {
+    pub zone: String,
+    pub connections: u32,
+}
+
+/// Limits in effect in an `http` server, or in one of its locations
+#[derive(Debug, Clone)]
+pub struct LimitCoverage<'r, 'a> {
+    pub scope: Scope<'r, 'a>,
+    /// `limit_req` in effect and the zone it refers to, `None` when no `limit_req_zone` defines it
+    pub requests: Vec<(LimitReq, Option<LimitReqZone>)>,
+    /// `limit_conn` in effect and the zone it refers to, `None` when no `limit_conn_zone` defines it
+    pub connections: Vec<(LimitConn, Option<LimitConnZone>)>,
+    /// The location passes requests to an app server (`proxy_pass`, `fastcgi_pass`...)
+    pub proxies: bool,
+}
+
+impl LimitCoverage<'_, '_> {
+    pub fn is_limited(&self) -> bool {
+        !self.requests.is_empty() || !self.connections.is_empty()
+    }
+
+    /// Passes requests to an app server without any limit
+    pub fn is_unprotected(&self) -> bool {
+        self.proxies && !self.is_limited()
+    }
+}
+
+/// One line per context, `server api.example, location /login: limit_req login (5r/m, burst 10), limit_conn addr 20`
+impl Display for LimitCoverage<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = |block: &Structure| {
+            let (Structure::Statement { args } | Structure::Block { args, .. }) = block;
+            args.iter().map(|arg| arg.value()).collect::<Vec<_>>().join(" ")
+        };
+        let statement = |name| match &self.scope.server.block {
+            Structure::Block { children, .. } => children.iter().find(|c| c.name() == Some(name)),
+            Structure::Statement { .. } => None,
+        };
+        // servers without a name go by their first `listen`
+        let server = match (statement(DirectiveName::ServerName), statement(DirectiveName::Listen)) {
+            (Some(names), _) => label(names).replacen("server_name", "server", 1),
+            (None, Some(listen)) => format!("server ({})", label(listen)),
+            (None, None) => "server".to_string(),
+        };
+        write!(f, "{server}")?;
+        for location in &self.scope.locations {
+            write!(f, ", {}", label(location.block))?;
+        }
+        let mut limits = Vec::new();
+        for (limit, zone) in &self.requests {
+            let mut text = format!("limit_req {}", limit.zone);
+            match zone {
+                Some(zone) => text.push_str(&format!(" ({}, burst {})", zone.rate, limit.burst)),
+                None => text.push_str(" (undefined zone)"),
+            }
+            limits.push(text);
+        }
+        for (limit, zone) in &self.connections {
+            let mut text = format!("limit_conn {} {}", limit.zone, limit.connections);
+            if zone.is_none() {
+                text.push_str(" (undefined zone)");
+            }
+            limits.push(text);
+        }
+        match (limits.is_empty(), self.proxies) {
+            (true, true) => write!(f, ": no limits, proxied"),
+            (true, false) => write!(f, ": no limits"),
+            (false, _) => write!(f, ": {}", limits.join(", ")),
+        }
+    }
+}
+
+/// Limits in effect in every server of the `http` block and in each of its locations, nested ones included
+/// `limit_req` and `limit_conn` of a context replace all of the inherited ones
+pub fn rate_limits<'r, 'a>(router: &'r Router<'a>) -> Vec<LimitCoverage<'r, 'a>> {
+    let zones = router.http.map(Zones::from_block).unwrap_or_default();
+    let mut coverage = Vec::new();
+    for scope in router.scopes() {
+        let settings = effective_config(&router.contexts(scope.server, &scope.locations));
+        let requests = settings
+            .directives(DirectiveName::LimitReq)
+            .filter_map(|directive| match directive {
+                Directive::LimitReq(limit) => {
+                    let zone = zones.requests.iter().find(|zone| zone.zone == limit.zone).cloned();
+                    Some((limit, zone))
+                },
+                _ => None,
+            })
+            .collect();
+        let connections = settings
+            .directives(DirectiveName::LimitConn)
+            .filter_map(|directive| match directive {
+                Directive::LimitConn { zone, connections } => {
+                    let defined = zones.connections.iter().find(|z| z.zone == zone).cloned();
+                    Some((LimitConn { zone, connections }, defined))
+                },
+                _ => None,
+            })
+            .collect();
+        let proxies = match scope.locations.last().map(|l| l.block) {
+            Some(Structure::Block { children, .. }) => {
+                children.iter().any(|c| c.name().is_some_and(|name| APP_PASSES.contains(&name)))
+            },
+            _ => false,
+        };
+        coverage.push(LimitCoverage { scope, requests, connections, proxies });
+    }
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "http {\n\
+        limit_req_zone $binary_remote_addr zone=login:10m rate=5r/m;\n\
+        limit_conn_zone $binary_remote_addr zone=addr:10m;\n\
+        server { server_name api.example; limit_conn addr 20;\n\
+        location /login { limit_req zone=login burst=10; proxy_pass http://app; }\n\
+        location /search { limit_req zone=search; proxy_pass http://app; }\n\
+        location /static { root /srv; }\n\
+        location /upload { limit_conn addr 2; proxy_pass http://app; }\n\
+        }\n\
+        server { listen 8080; location / { proxy_pass http://app; } }\n\
+        }";
+
+    #[test]
+    fn limits_in_effect() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        let coverage = rate_limits(&router);
+        let lines = coverage.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "server api.example: limit_conn addr 20",
+                "server api.example, location /login: limit_req login (5r/m, burst 10), limit_conn addr 20",
+                "server api.example, location /search: limit_req search (undefined zone), limit_conn addr 20",
+                "server api.example, location /static: limit_conn addr 20",
+                "server api.example, location /upload: limit_conn addr 2",
+                "server (listen 8080): no limits",
+                "server (listen 8080), location /: no limits, proxied",
+            ]
+        );
+        let unprotected = coverage.iter().map(|c| c.is_unprotected()).collect::<Vec<_>>();
+        assert_eq!(unprotected, [false, false, false, false, false, false, true]);
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+
+use url::Url;
+
+use crate::{
+    names::DirectiveName,
+    router::{Action, Request, Router},
+    types::{normalize_host, Directive, ServerBlock, ServerNamePattern},
+    Structure,
+};
+
+/// Redirects followed before a chain is reported as too long
+const MAX_HOPS: usize = 20;
+
+/// A redirect answered by the config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    pub from: String,
+    pub code: u16,
+    pub to: String,
+}
+
+/// How a redirect chain ends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainEnd {
+    /// A response that isn't a redirect
+    Served(Action),
+    /// Redirect to a host no server of the config is named after
+    External(String),
+    /// Redirect back to a URL already in the chain
+    Loop(String),
+    /// No server listens on the redirect's port
+    Unrouted(String),
+    /// More redirects than browsers follow
+    TooLong,
+}
+
+/// Redirects a URL served by the config goes through
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectChain {
+    pub start: String,
+    pub hops: Vec<RedirectHop>,
+    pub end: ChainEnd,
+}
+
+impl RedirectChain {
+    /// More than one redirect before the final answer, each costing a round trip
+    pub fn is_multi_hop(&self) -> bool {
+        self.hops.len() > 1
+    }
+
+    pub fn is_loop(&self) -> bool {
+        matches!(self.end, ChainEnd::Loop(_))
+    }
+}
+
+/// Follows `return` and `rewrite` redirects of `url` through the servers of the config
+pub fn redirect_chain(router: &Router, url: &str) -> RedirectChain {
+    let mut chain = RedirectChain { start: url.to_string(), hops: Vec::new(), end: ChainEnd::TooLong };
+    let mut seen = HashSet::from([url.to_string()]);
+    let mut url = url.to_string();
+    while chain.hops.len() < MAX_HOPS {
+        let Ok(request) = Request::get(&url) else {
+            chain.end = ChainEnd::External(url);
+            return chain
+        };
+        if !serves_host(router, &request.host) {
+            chain.end = ChainEnd::External(url);
+            return chain
+        }
+        let Some(route) = router.route(&request) else {
+            chain.end = ChainEnd::Unrouted(url);
+            return chain
+        };
+        let (code, to) = match route.action {
+            Action::Return { code: code @ (301 | 302 | 303 | 307 | 308), content: Some(to) } => (code, to),
+            action => {
+                chain.end = ChainEnd::Served(action);
+                return chain
+            },
+        };
+        // relative redirects are made absolute by nginx with the request's scheme, host and port
+        let to = Url::parse(&url).and_then(|base| base.join(&to)).map_or(to, |to| to.to_string());
+        chain.hops.push(RedirectHop { from: url, code, to: to.clone() });
+        if !seen.insert(to.clone()) {
+            chain.end = ChainEnd::Loop(to);
+            return chain
+        }
+        url = to;
+    }
+    chain
+}
+
+/// Chains starting at the root of every server name (exact ones, and `.example.com` as `example.com`),
+/// on every port and scheme the server listens on
+pub fn redirect_chains(router: &Router) -> Vec<RedirectChain> {
+    let mut starts = Vec::new();
+    for server in &router.servers.servers {
+        let hosts = server.names.iter().filter_map(|name| match name {
+            ServerNamePattern::Exact(name) if !name.is_empty() => Some(name.clone()),
+            ServerNamePattern::LeadingWildcard { suffix, include_bare: true } => Some(suffix[1..].to_string()),
+            _ => None,
+        });
+        let hosts = hosts.collect::<Vec<_>>();
+        for (port, ssl) in listen_ports(server) {
+            let scheme = if ssl { "https" } else { "http" };
+            for host in &hosts {
+                let url = match (port, ssl) {
+                    (80, false) | (443, true) => format!("{scheme}://{host}/"),
+                    _ => format!("{scheme}://{host}:{port}/"),
+                };
+                if !starts.contains(&url) {
+                    starts.push(url);
+                }
+            }
+        }
+    }
+    starts.iter().map(|url| redirect_chain(router, url)).collect()
+}
+
+/// Whether a server of the config is named after the host, rather than it only reaching a default server
+fn serves_host(router: &Router, host: &str) -> bool {
+    let host = normalize_host(host);
+    router.servers.servers.iter().any(|server| server.names.iter().any(|name| name.matches(&host)))
+}
+
+/// Ports of the `listen` directives, and whether they take TLS
+fn listen_ports(server: &ServerBlock) -> Vec<(u16, bool)> {
+    let Structure::Block { children, .. } = server.block else {
+        return Vec::new()
+    };
+    let mut ports = children
+        .iter()
+        .filter(|child| child.name() == Some(DirectiveName::Listen))
+        .filter_map(|child| {
+            let Structure::Statement { args } = child else {
+                return None
+            };
+            let ssl = args.iter().any(|a| a.value() == "ssl");
+            match Directive::try_from(child) {
+                Ok(Directive::Listen { sock_addr, is_http3, .. }) => Some((sock_addr.port(), ssl || is_http3)),
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>();
+    if ports.is_empty() {
+        ports.push((80, false));
+    }
+    ports.dedup();
+    ports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+http {
+    server { listen 80; server_name example.com www.example.com; return 301 https://$host$request_uri; }
+    server { listen 443 ssl; server_name www.example.com; return 301 https://example.com$request_uri; }
+    server { listen 443 ssl; server_name example.com; root /srv/www; location /old { return 302 /new; } location /new { } }
+    server { listen 443 ssl; server_name ping.example.com; return 302 https://pong.example.com/; }
+    server { listen 443 ssl; server_name pong.example.com; return 302 https://ping.example.com/; }
+    server { listen 443 ssl; server_name away.example.com; return 308 https://example.org/; }
+}
+";
+
+    fn chain(url: &str) -> RedirectChain {
+        let root = Structure::parse(CONFIG).unwrap();
+        redirect_chain(&Router::new(&root), url)
+    }
+
+    #[test]
+    fn chains_follow_servers() {
+        let chain = chain("http://www.example.com/old");
+        let hops = chain.hops.iter().map(|hop| (hop.code, hop.to.as_str())).collect::<Vec<_>>();
+        assert_eq!(hops, [(301, "https://www.example.com/old"), (301, "https://example.com/old"), (302, "https://example.com/new")]);
+        assert_eq!(chain.end, ChainEnd::Served(Action::Static { path: "/srv/www/new".to_string() }));
+        assert!(chain.is_multi_hop() && !chain.is_loop());
+    }
+
+    #[test]
+    fn chains_end_in_loops_and_other_hosts() {
+        let ping = chain("https://ping.example.com/");
+        assert_eq!((ping.hops.len(), ping.end), (2, ChainEnd::Loop("https://ping.example.com/".to_string())));
+        assert_eq!(chain("https://away.example.com/").end, ChainEnd::External("https://example.org/".to_string()));
+        assert_eq!(chain("http://example.com:8080/").end, ChainEnd::Unrouted("http://example.com:8080/".to_string()));
+    }
+
+    #[test]
+    fn chains_start_at_every_server_name() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let starts = redirect_chains(&Router::new(&root)).into_iter().map(|chain| chain.start).collect::<Vec<_>>();
+        assert_eq!(starts[..4], ["http://example.com/", "http://www.example.com/", "https://www.example.com/", "https://example.com/"]);
+        assert_eq!(starts.len(), 7);
+    }
+}
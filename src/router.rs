@@ -0,0 +1,833 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
+
+use regex::Regex;
+use url::Url;
+
+use crate::{
+    effective::{effective_config, EffectiveSettings},
+    filesystem::FilesystemView,
+    locations::location_paths,
+    names::DirectiveName,
+    types::{
+        normalize_uri, parse_template, Condition, Directive, Location, LocationBlock, MapSet, RewriteFlag, ServerBlock, ServerSet,
+        TemplateSegment, TryFilesFallback,
+    },
+    Structure,
+};
+
+/// Internal redirects and `last` rewrites nginx follows before giving up with 500
+const MAX_URI_CHANGES: usize = 10;
+
+/// Simulated client request
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub scheme: String,
+    /// Host header, empty when the client sent none
+    pub host: String,
+    pub port: u16,
+    /// Address the connection was accepted on, unspecified to only match wildcard `listen`s
+    pub local_ip: IpAddr,
+    pub remote_addr: IpAddr,
+    pub method: String,
+    /// Path with the query string, as sent
+    pub uri: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// GET request for a URL such as `https://example.com/path?query`, from 127.0.0.1
+    pub fn get(url: &str) -> Result<Self, url::ParseError> {
+        let url = Url::parse(url)?;
+        let host = url.host_str().unwrap_or_default().to_string();
+        let uri = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        Ok(Self {
+            scheme: url.scheme().to_string(),
+            port: url.port_or_known_default().unwrap_or(80),
+            headers: vec![("Host".to_string(), host.clone())],
+            host,
+            local_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            remote_addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            method: "GET".to_string(),
+            uri,
+        })
+    }
+
+    /// First header with this name, names compare case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+}
+
+/// How the location in effect maps URIs to files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentRoot {
+    /// `root`, the URI is appended to it
+    Root(String),
+    /// `alias` replaces the `prefix` of the URI a prefix location matched,
+    /// regex locations have no prefix and alias the whole URI
+    Alias { path: String, prefix: Option<String> },
+}
+
+impl Default for DocumentRoot {
+    fn default() -> Self {
+        Self::Root("html".to_string())
+    }
+}
+
+/// Which `try_files` candidate is used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TryFilesOutcome {
+    /// First candidate that exists, a directory for ones ending in `/`, `uri` becomes the request's `$uri`
+    Found { uri: String, path: String },
+    /// None exist, the fallback with its variables expanded
+    Fallback(TryFilesFallback),
+}
+
+impl DocumentRoot {
+    /// File a URI maps to, variables of the root expanded
+    pub fn path(&self, uri: &str, context: &RequestContext) -> String {
+        match self {
+            Self::Root(root) => format!("{}{}", context.expand(root).trim_end_matches('/'), uri),
+            Self::Alias { path, prefix: Some(prefix) } => {
+                format!("{}{}", context.expand(path), uri.strip_prefix(prefix.as_str()).unwrap_or(uri))
+            },
+            Self::Alias { path, prefix: None } => context.expand(path),
+        }
+    }
+
+    /// Checks `try_files` candidates in order against `filesystem`, with the variables of the context
+    pub fn try_files(
+        &self,
+        files: &[String],
+        fallback: &TryFilesFallback,
+        context: &RequestContext,
+        filesystem: &dyn FilesystemView,
+    ) -> TryFilesOutcome {
+        for file in files {
+            let uri = context.expand(file);
+            let path = self.path(&uri, context);
+            let exists = match uri.ends_with('/') {
+                true => filesystem.is_dir(&path),
+                false => filesystem.is_file(&path),
+            };
+            if exists {
+                return TryFilesOutcome::Found { uri, path }
+            }
+        }
+        TryFilesOutcome::Fallback(match fallback {
+            TryFilesFallback::Uri(uri) => TryFilesFallback::Uri(context.expand(uri)),
+            fallback => fallback.clone(),
+        })
+    }
+}
+
+/// Variables of a request while it's being processed
+#[derive(Debug, Clone)]
+pub struct RequestContext<'r> {
+    pub request: &'r Request,
+    /// `$uri`, normalized and changed by rewrites
+    pub uri: String,
+    pub args: Option<String>,
+    /// `$1`...`$9` of the last successful regex match, `$0` is the whole match
+    pub captures: Vec<Option<String>>,
+    /// Set with `set` and the like, names without the `$`
+    pub variables: HashMap<String, String>,
+    /// Maps of the `http` block, evaluated when their variable is used
+    pub maps: Option<&'r MapSet>,
+    /// `root` or `alias` of the location in effect, for `$request_filename`
+    pub document_root: DocumentRoot,
+    /// Files seen by `if` file tests, they never hold without one
+    pub filesystem: Option<&'r dyn FilesystemView>,
+}
+
+impl<'r> RequestContext<'r> {
+    pub fn new(request: &'r Request, uri: String) -> Self {
+        let args = request.uri.split_once('?').map(|(_, args)| args.to_string());
+        Self {
+            request,
+            uri,
+            args,
+            captures: Vec::new(),
+            variables: HashMap::new(),
+            maps: None,
+            document_root: DocumentRoot::default(),
+            filesystem: None,
+        }
+    }
+
+    /// Value of a variable named without the `$`, `None` for ones this simulation doesn't know
+    pub fn variable(&self, name: &str) -> Option<String> {
+        self.builtin(name).or_else(|| self.maps?.evaluate(name, &|name| self.builtin(name)))
+    }
+
+    /// Variables other than the ones maps set
+    fn builtin(&self, name: &str) -> Option<String> {
+        if let Some(value) = self.variables.get(name) {
+            return Some(value.clone())
+        }
+        if let Ok(index) = name.parse::<usize>() {
+            return Some(self.captures.get(index).cloned().flatten().unwrap_or_default())
+        }
+        if let Some(header) = name.strip_prefix("http_") {
+            let header = header.replace('_', "-");
+            return Some(self.request.header(&header).unwrap_or_default().to_string())
+        }
+        if let Some(arg) = name.strip_prefix("arg_") {
+            let value = self.args.as_deref().unwrap_or_default().split('&').find_map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                key.eq_ignore_ascii_case(arg).then(|| value.to_string())
+            });
+            return Some(value.unwrap_or_default())
+        }
+        if let Some(cookie) = name.strip_prefix("cookie_") {
+            let value = self.request.header("Cookie").unwrap_or_default().split(';').find_map(|pair| {
+                let (key, value) = pair.trim().split_once('=')?;
+                (key == cookie).then(|| value.to_string())
+            });
+            return Some(value.unwrap_or_default())
+        }
+        let value = match name {
+            "uri" | "document_uri" => self.uri.clone(),
+            "request_uri" => self.request.uri.clone(),
+            "args" | "query_string" => self.args.clone().unwrap_or_default(),
+            "is_args" => if self.args.is_some() { "?".to_string() } else { String::new() },
+            "scheme" => self.request.scheme.clone(),
+            "https" => if self.request.scheme == "https" { "on".to_string() } else { String::new() },
+            "host" => self.request.host.to_ascii_lowercase(),
+            "server_port" => self.request.port.to_string(),
+            "request_method" => self.request.method.clone(),
+            "remote_addr" => self.request.remote_addr.to_string(),
+            "document_root" | "realpath_root" => match &self.document_root {
+                DocumentRoot::Root(path) | DocumentRoot::Alias { path, .. } => self.expand(path),
+            },
+            "request_filename" => self.document_root.path(&self.uri, self),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Substitutes the variables of a directive argument, unknown ones expand to nothing like unset ones
+    pub fn expand(&self, template: &str) -> String {
+        let Ok(segments) = parse_template(&[template]) else {
+            return template.to_string()
+        };
+        segments
+            .iter()
+            .map(|segment| match segment {
+                TemplateSegment::Literal(text) => text.clone(),
+                TemplateSegment::Variable(name) => self.variable(name).unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// `$uri` with `$is_args$args`
+    pub fn full_uri(&self) -> String {
+        match &self.args {
+            Some(args) => format!("{}?{}", self.uri, args),
+            None => self.uri.clone(),
+        }
+    }
+}
+
+/// A `rewrite` that matched while routing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedRewrite {
+    pub pattern: String,
+    pub from: String,
+    pub to: String,
+    pub flag: Option<RewriteFlag>,
+}
+
+/// What nginx ends up doing with the request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// `return`, a redirecting rewrite or an error, `content` is the Location of redirects
+    Return { code: u16, content: Option<String> },
+    /// Handed to `proxy_pass`, `fastcgi_pass` and the like, `target` with variables expanded
+    Pass { directive: DirectiveName, target: String },
+    /// File served from disk, variables in `root` or `alias` expanded
+    Static { path: String },
+}
+
+/// Request processing phases the rewrite module takes part in, in the order nginx runs them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Rewrite directives of the `server` block, run once
+    ServerRewrite,
+    /// Location selection
+    FindConfig,
+    /// Rewrite directives of the selected location
+    Rewrite,
+    /// Back to `FindConfig` when the URI changed
+    PostRewrite,
+    /// `try_files`, checked when routing on a filesystem
+    TryFiles,
+}
+
+/// Something the rewrite module did while processing a request
+#[derive(Debug, Clone)]
+pub enum RewriteEvent {
+    Rewrite(AppliedRewrite),
+    /// Value with its variables expanded
+    Set { variable: String, value: String },
+    If { condition: Condition, taken: bool },
+    Break,
+    Return { code: u16, content: Option<String> },
+    /// Selected location, `None` when none matched
+    Location(Option<Location>),
+    /// The URI changed, locations are searched again, the number of searches so far
+    Rematch(usize),
+    TryFiles(TryFilesOutcome),
+    /// Too many URI changes, nginx logs "rewrite or internal redirection cycle" and answers 500
+    Cycle,
+}
+
+/// `RewriteEvent` with the phase it happened in
+#[derive(Debug, Clone)]
+pub struct RewriteStep {
+    pub phase: Phase,
+    pub event: RewriteEvent,
+}
+
+/// Outcome of routing a request
+#[derive(Debug, Clone)]
+pub struct Route<'r, 'a> {
+    pub server: &'r ServerBlock<'a>,
+    /// Selected location and the ones enclosing it, outermost first, empty when none matched
+    pub locations: Vec<&'r LocationBlock<'a>>,
+    /// `if` blocks of the selected location whose condition held, their settings override the location's
+    pub ifs: Vec<&'a Structure<'a>>,
+    pub rewrites: Vec<AppliedRewrite>,
+    /// Everything the rewrite module did, in order
+    pub trace: Vec<RewriteStep>,
+    /// `$uri` once rewrites are done
+    pub uri: String,
+    pub args: Option<String>,
+    pub action: Action,
+}
+
+/// Where processing of a request starts
+#[derive(Clone, Copy)]
+enum Start<'t> {
+    /// Client request, normalized first
+    Request,
+    /// Internal redirect to a URI, with its arguments
+    Uri(&'t str),
+    /// Internal redirect to `@name`
+    Named(&'t str),
+}
+
+/// Where the rewrite directives of one context left the request
+enum Rewritten {
+    /// Went through all the directives
+    Continue { uri_changed: bool },
+    /// `break`, or a rewrite with the `break` flag
+    Break,
+    /// `last`, locations are searched again
+    Rematch,
+    Return(Action),
+}
+
+/// State of processing the rewrite directives of a request
+struct Rewriting<'s, 'r, 'a> {
+    state: &'s mut RequestContext<'r>,
+    trace: Vec<RewriteStep>,
+    ifs: Vec<&'a Structure<'a>>,
+}
+
+impl<'s, 'r, 'a> Rewriting<'s, 'r, 'a> {
+    fn step(&mut self, phase: Phase, event: RewriteEvent) {
+        self.trace.push(RewriteStep { phase, event });
+    }
+
+    /// Applies the rewrite module directives of one context in order
+    fn run(&mut self, phase: Phase, block: &'a Structure<'a>) -> Rewritten {
+        let Structure::Block { children, .. } = block else {
+            return Rewritten::Continue { uri_changed: false }
+        };
+        let mut uri_changed = false;
+        for child in children {
+            match Directive::try_from(child) {
+                Ok(Directive::Return { code, content }) => {
+                    let (code, content) = (code.unwrap_or(302), content.map(|c| self.state.expand(&c)));
+                    self.step(phase, RewriteEvent::Return { code, content: content.clone() });
+                    return Rewritten::Return(Action::Return { code, content })
+                },
+                Ok(Directive::Break) => {
+                    self.step(phase, RewriteEvent::Break);
+                    return Rewritten::Break
+                },
+                Ok(Directive::Set { variable, value }) => {
+                    let value = self.state.expand(&value);
+                    self.state.variables.insert(variable.clone(), value.clone());
+                    self.step(phase, RewriteEvent::Set { variable, value });
+                },
+                Ok(Directive::If { condition }) => {
+                    let taken = condition.evaluate(self.state);
+                    self.step(phase, RewriteEvent::If { condition, taken });
+                    if !taken {
+                        continue;
+                    }
+                    if phase == Phase::Rewrite {
+                        self.ifs.push(child);
+                    }
+                    match self.run(phase, child) {
+                        Rewritten::Continue { uri_changed: changed } => uri_changed |= changed,
+                        stop => return stop,
+                    }
+                },
+                Ok(Directive::Rewrite { regex, replacement, flag }) => {
+                    let Some(rewritten) = self.rewrite(phase, &regex, &replacement, flag) else {
+                        continue;
+                    };
+                    match rewritten {
+                        Rewritten::Continue { .. } => uri_changed = true,
+                        stop => return stop,
+                    }
+                },
+                _ => {}
+            }
+        }
+        Rewritten::Continue { uri_changed }
+    }
+
+    /// `None` when the regex doesn't match the URI
+    fn rewrite(&mut self, phase: Phase, regex: &Regex, replacement: &str, flag: Option<RewriteFlag>) -> Option<Rewritten> {
+        let state = &mut *self.state;
+        let captures = regex.captures(&state.uri)?;
+        state.captures = captures.iter().map(|c| c.map(|c| c.as_str().to_string())).collect();
+        let to = state.expand(replacement);
+        let applied = AppliedRewrite { pattern: regex.as_str().to_string(), from: state.full_uri(), to: to.clone(), flag };
+
+        // only a '?' written in the replacement starts new arguments, not one coming from a variable
+        let keep_args = !replacement.ends_with('?');
+        let to = if keep_args { to } else { to[..to.len() - 1].to_string() };
+        let (uri, args) = match replacement.contains('?').then(|| to.split_once('?')).flatten() {
+            Some((uri, args)) => (uri.to_string(), Some(args.to_string()).filter(|a| !a.is_empty())),
+            None => (to.clone(), None),
+        };
+        state.args = match (args, state.args.take().filter(|_| keep_args)) {
+            (Some(new), Some(old)) => Some(format!("{new}&{old}")),
+            (new, old) => new.or(old),
+        };
+        let external = ["http://", "https://"].iter().any(|scheme| to.starts_with(scheme));
+        let rewritten = if external || matches!(flag, Some(RewriteFlag::Redirect | RewriteFlag::Permanent)) {
+            let code = if flag == Some(RewriteFlag::Permanent) { 301 } else { 302 };
+            let location = match &state.args {
+                Some(args) => format!("{uri}?{args}"),
+                None => uri,
+            };
+            Rewritten::Return(Action::Return { code, content: Some(location) })
+        } else {
+            state.uri = uri;
+            match flag {
+                Some(RewriteFlag::Last) => Rewritten::Rematch,
+                Some(RewriteFlag::Break) => Rewritten::Break,
+                _ => Rewritten::Continue { uri_changed: true },
+            }
+        };
+        self.step(phase, RewriteEvent::Rewrite(applied));
+        Some(rewritten)
+    }
+}
+
+/// A server of the router, or one of its locations
+#[derive(Debug, Clone)]
+pub struct Scope<'r, 'a> {
+    pub server: &'r ServerBlock<'a>,
+    /// The location and the ones enclosing it, outermost first, empty for the server itself
+    pub locations: Vec<&'r LocationBlock<'a>>,
+}
+
+impl<'a> Scope<'_, 'a> {
+    /// The innermost block, the location or the server itself
+    pub fn block(&self) -> &'a Structure<'a> {
+        self.locations.last().map_or(self.server.block, |location| location.block)
+    }
+}
+
+/// Answers which server and location handle a request, and what they do with it
+#[derive(Debug, Clone)]
+pub struct Router<'a> {
+    pub http: Option<&'a Structure<'a>>,
+    pub servers: ServerSet<'a>,
+    pub maps: MapSet,
+}
+
+impl<'a> Router<'a> {
+    /// Routes through the first `http` block of a parsed config
+    pub fn new(main: &'a Structure<'a>) -> Self {
+        let http = match main {
+            Structure::Block { children, .. } => children.iter().find(|c| c.name() == Some(DirectiveName::Http)),
+            Structure::Statement { .. } => None,
+        };
+        Self {
+            http,
+            servers: http.map(ServerSet::from_block).unwrap_or_default(),
+            maps: http.map(MapSet::from_block).unwrap_or_default(),
+        }
+    }
+
+    /// Simulates the request through the rewrite module and location selection,
+    /// `None` when no server listens on the request's port
+    ///
+    /// File tests of `if` conditions can't be checked here and never hold, and `try_files` is skipped,
+    /// see `Router::route_on`
+    pub fn route<'r>(&'r self, request: &Request) -> Option<Route<'r, 'a>> {
+        self.simulate(request, None)
+    }
+
+    /// Routes with `if` file tests and `try_files` checked against `filesystem`
+    pub fn route_on<'r>(&'r self, request: &Request, filesystem: &dyn FilesystemView) -> Option<Route<'r, 'a>> {
+        self.simulate(request, Some(filesystem))
+    }
+
+    fn simulate<'r>(&'r self, request: &Request, filesystem: Option<&dyn FilesystemView>) -> Option<Route<'r, 'a>> {
+        let server = self.servers.select(SocketAddr::new(request.local_ip, request.port), &request.host)?;
+        Some(self.process(server, request, filesystem, Start::Request))
+    }
+
+    /// Route of an internal redirect in `server`, such as an `error_page` target: a URI or `@name`
+    /// The request goes on as a GET, named locations skip the server's rewrites
+    pub fn internal_redirect<'r>(
+        &'r self,
+        server: &'r ServerBlock<'a>,
+        request: &Request,
+        target: &str,
+        filesystem: Option<&dyn FilesystemView>,
+    ) -> Route<'r, 'a> {
+        let mut request = request.clone();
+        if request.method != "HEAD" {
+            request.method = "GET".to_string();
+        }
+        let start = match target.starts_with('@') {
+            true => Start::Named(target),
+            false => Start::Uri(target),
+        };
+        self.process(server, &request, filesystem, start)
+    }
+
+    fn process<'r>(
+        &'r self,
+        server: &'r ServerBlock<'a>,
+        request: &Request,
+        filesystem: Option<&dyn FilesystemView>,
+        start: Start,
+    ) -> Route<'r, 'a> {
+        let target = match start {
+            Start::Uri(uri) => uri,
+            Start::Request | Start::Named(_) => request.uri.as_str(),
+        };
+        let (path, args) = target.split_once('?').map_or((target, None), |(path, args)| (path, Some(args)));
+        let mut route = Route {
+            server,
+            locations: Vec::new(),
+            ifs: Vec::new(),
+            rewrites: Vec::new(),
+            trace: Vec::new(),
+            uri: path.to_string(),
+            args: None,
+            action: Action::Return { code: 400, content: None },
+        };
+        // internal redirects aren't normalized again
+        let uri = match start {
+            Start::Request => normalize_uri(path, server.locations.merge_slashes),
+            Start::Uri(_) | Start::Named(_) => Some(path.to_string()),
+        };
+        let Some(uri) = uri else {
+            return route
+        };
+        let mut state = RequestContext::new(request, uri);
+        state.args = args.map(str::to_string);
+        state.maps = Some(&self.maps);
+        state.filesystem = filesystem;
+        let mut rewriting = Rewriting { state: &mut state, trace: Vec::new(), ifs: Vec::new() };
+
+        // named location a `try_files` fallback or an internal redirect goes to
+        let mut named = match start {
+            Start::Named(name) => Some(name.to_string()),
+            Start::Request | Start::Uri(_) => None,
+        };
+        let mut action = match named {
+            Some(_) => None,
+            None => match rewriting.run(Phase::ServerRewrite, server.block) {
+                Rewritten::Return(action) => Some(action),
+                _ => None,
+            },
+        };
+        let mut searches = 0;
+        while action.is_none() {
+            route.locations = match (searches, named.take()) {
+                (_, Some(name)) => server.locations.redirect(&name),
+                (0, None) => server.locations.select_nested(&rewriting.state.uri),
+                (_, None) => server.locations.redirect(&rewriting.state.uri),
+            };
+            searches += 1;
+            let location = route.locations.last().map(|l| l.location.clone());
+            rewriting.step(Phase::FindConfig, RewriteEvent::Location(location));
+            let Some(location) = route.locations.last() else {
+                break;
+            };
+            rewriting.ifs.clear();
+            rewriting.state.document_root = self.document_root(&route, &self.effective_config(&route));
+            if let Some(captures) = location_captures(&location.location, &rewriting.state.uri) {
+                rewriting.state.captures = captures;
+            }
+            let rematch = match rewriting.run(Phase::Rewrite, location.block) {
+                Rewritten::Return(returned) => {
+                    action = Some(returned);
+                    continue;
+                },
+                Rewritten::Continue { uri_changed: false } | Rewritten::Break => false,
+                Rewritten::Continue { uri_changed: true } | Rewritten::Rematch => true,
+            };
+            if !rematch {
+                let outcome = filesystem.and_then(|filesystem| self.try_files(&route, rewriting.state, filesystem));
+                let Some(outcome) = outcome else {
+                    break;
+                };
+                rewriting.step(Phase::TryFiles, RewriteEvent::TryFiles(outcome.clone()));
+                match outcome {
+                    TryFilesOutcome::Found { uri, .. } => {
+                        rewriting.state.uri = uri;
+                        break;
+                    },
+                    TryFilesOutcome::Fallback(TryFilesFallback::Code(code)) => {
+                        action = Some(Action::Return { code, content: None });
+                        continue;
+                    },
+                    TryFilesOutcome::Fallback(TryFilesFallback::Named(name)) => named = Some(name),
+                    // an internal redirect starts over from the server's rewrites
+                    TryFilesOutcome::Fallback(TryFilesFallback::Uri(uri)) => {
+                        let (uri, args) = uri.split_once('?').map_or((uri.as_str(), None), |(uri, args)| (uri, Some(args)));
+                        rewriting.state.uri = uri.to_string();
+                        rewriting.state.args = args.map(str::to_string).or(rewriting.state.args.take());
+                        if let Rewritten::Return(returned) = rewriting.run(Phase::ServerRewrite, server.block) {
+                            action = Some(returned);
+                            continue;
+                        }
+                    },
+                }
+            }
+            if searches > MAX_URI_CHANGES {
+                rewriting.step(Phase::PostRewrite, RewriteEvent::Cycle);
+                action = Some(Action::Return { code: 500, content: None });
+            } else if rematch {
+                rewriting.step(Phase::PostRewrite, RewriteEvent::Rematch(searches));
+            }
+        }
+        route.ifs = std::mem::take(&mut rewriting.ifs);
+        route.trace = std::mem::take(&mut rewriting.trace);
+        route.rewrites = route
+            .trace
+            .iter()
+            .filter_map(|step| match &step.event {
+                RewriteEvent::Rewrite(applied) => Some(applied.clone()),
+                _ => None,
+            })
+            .collect();
+        route.action = match action {
+            Some(action) => action,
+            None => self.content(&route, &mut state),
+        };
+        route.uri = state.uri.clone();
+        route.args = state.args.clone();
+        route
+    }
+
+    /// Settings that apply to the selected location and its `if` blocks that held, see `effective_config`
+    pub fn effective_config<'r>(&self, route: &Route<'r, 'a>) -> EffectiveSettings<'a> {
+        let mut contexts = self.contexts(route.server, &route.locations);
+        contexts.extend(route.ifs.iter().copied());
+        effective_config(&contexts)
+    }
+
+    /// Every server, each followed by its locations, nested ones included
+    pub fn scopes(&self) -> Vec<Scope<'_, 'a>> {
+        let mut scopes = Vec::new();
+        for server in &self.servers.servers {
+            scopes.push(Scope { server, locations: Vec::new() });
+            scopes.extend(location_paths(&server.locations).into_iter().map(|locations| Scope { server, locations }));
+        }
+        scopes
+    }
+
+    /// `http`, the server, then the locations, the contexts `effective_config` merges on the way to the last one
+    pub fn contexts(&self, server: &ServerBlock<'a>, locations: &[&LocationBlock<'a>]) -> Vec<&'a Structure<'a>> {
+        self.http.into_iter().chain([server.block]).chain(locations.iter().map(|l| l.block)).collect()
+    }
+
+    /// `try_files` of the selected location, `None` when it has none
+    fn try_files(&self, route: &Route<'_, 'a>, context: &RequestContext, filesystem: &dyn FilesystemView) -> Option<TryFilesOutcome> {
+        let settings = self.effective_config(route);
+        let Some(Directive::TryFiles { files, fallback }) = settings.directives(DirectiveName::TryFiles).last() else {
+            return None
+        };
+        Some(context.document_root.try_files(&files, &fallback, context, filesystem))
+    }
+
+    /// Content handler of the selected location, a `*_pass` directive or the static file
+    fn content(&self, route: &Route<'_, 'a>, state: &mut RequestContext) -> Action {
+        let settings = self.effective_config(route);
+        let passes = [
+            DirectiveName::ProxyPass,
+            DirectiveName::FastcgiPass,
+            DirectiveName::GrpcPass,
+            DirectiveName::UwsgiPass,
+            DirectiveName::ScgiPass,
+            DirectiveName::MemcachedPass,
+        ];
+        for directive in passes {
+            if let Some(target) = settings.args(directive).and_then(|args| args.first().copied()) {
+                return Action::Pass { directive, target: state.expand(target) }
+            }
+        }
+        state.document_root = self.document_root(route, &settings);
+        let mut path = state.variable("request_filename").unwrap_or_default();
+        if path.ends_with('/') {
+            let index = settings.directives(DirectiveName::Index).last().and_then(|directive| match directive {
+                Directive::Index { files } => files.first().map(|file| state.expand(file)),
+                _ => None,
+            });
+            path.push_str(&index.unwrap_or_else(|| "index.html".to_string()));
+        }
+        Action::Static { path }
+    }
+
+    /// The `root` or `alias` in effect, `alias` strips the prefix of the location it's written in
+    fn document_root(&self, route: &Route<'_, 'a>, settings: &EffectiveSettings) -> DocumentRoot {
+        // contexts before the locations in the effective settings
+        let outer = usize::from(self.http.is_some()) + 1;
+        let root = settings.setting(DirectiveName::Root).and_then(|setting| {
+            let directive = Directive::try_from(*setting.statements.last()?).ok()?;
+            match directive {
+                Directive::Root { path } => Some(DocumentRoot::Root(path)),
+                Directive::Alias { path } => {
+                    let location = route.locations.get(setting.context.checked_sub(outer)?)?;
+                    let prefix = match &location.location {
+                        Location::Prefix(prefix) | Location::PriorityPrefix(prefix) | Location::Exact(prefix) => Some(prefix.clone()),
+                        _ => None,
+                    };
+                    Some(DocumentRoot::Alias { path, prefix })
+                },
+                _ => None,
+            }
+        });
+        root.unwrap_or_default()
+    }
+}
+
+/// Captures of a regex location for the `$1`... used in its directives
+fn location_captures(location: &Location, uri: &str) -> Option<Vec<Option<String>>> {
+    let (Location::Casitive(regex) | Location::Incasitive(regex)) = location else {
+        return None
+    };
+    let captures = regex.captures(uri)?;
+    Some(captures.iter().map(|c| c.map(|c| c.as_str().to_string())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+http {
+    server {
+        listen 80 default_server;
+        server_name _;
+        return 444;
+    }
+    server {
+        listen 80;
+        server_name example.com www.example.com;
+        root /srv/www;
+
+        location / {}
+        location = /exact {}
+        location /static/ {}
+        location ^~ /static/priority/ {}
+        location ~ \.php$ { fastcgi_pass 127.0.0.1:9000; }
+        location ~* \.(png|jpg)$ {}
+        location /api/ {
+            proxy_pass http://127.0.0.1:8080;
+            location ~ /api/v[0-9]+/ { proxy_pass http://127.0.0.1:8081; }
+        }
+        location /old/ { rewrite ^/old/(.*)$ /new/$1 permanent; }
+        location /moved { rewrite ^ /static/moved.html last; }
+    }
+}
+"#;
+
+    fn route(url: &str) -> (Vec<String>, Action) {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        let route = router.route(&Request::get(url).unwrap()).unwrap();
+        let locations = route
+            .locations
+            .iter()
+            .map(|location| {
+                let (Structure::Statement { args } | Structure::Block { args, .. }) = location.block;
+                args.iter().skip(1).map(|arg| arg.value()).collect::<Vec<_>>().join(" ")
+            })
+            .collect();
+        (locations, route.action)
+    }
+
+    fn location(url: &str) -> String {
+        route(url).0.join(" > ")
+    }
+
+    #[test]
+    fn exact_locations_come_first() {
+        assert_eq!(location("http://example.com/exact"), "= /exact");
+        assert_eq!(location("http://example.com/exact/more"), "/");
+    }
+
+    #[test]
+    fn regexes_come_before_the_longest_prefix() {
+        assert_eq!(location("http://example.com/static/a.css"), "/static/");
+        assert_eq!(location("http://example.com/static/a.php"), r"~ \.php$");
+        assert_eq!(location("http://example.com/static/A.PNG"), r"~* \.(png|jpg)$");
+        assert_eq!(location("http://example.com/a.PHP"), "/");
+    }
+
+    #[test]
+    fn priority_prefixes_skip_regexes() {
+        assert_eq!(location("http://example.com/static/priority/a.php"), "^~ /static/priority/");
+    }
+
+    #[test]
+    fn regexes_are_checked_in_order() {
+        // both regexes match, the first written wins
+        assert_eq!(location("http://example.com/a.php.png"), r"~* \.(png|jpg)$");
+        assert_eq!(location("http://example.com/a.png.php"), r"~ \.php$");
+    }
+
+    #[test]
+    fn nested_locations() {
+        assert_eq!(location("http://example.com/api/users"), "/api/");
+        assert_eq!(location("http://example.com/api/v2/users"), "/api/ > ~ /api/v[0-9]+/");
+        let (_, action) = route("http://example.com/api/v2/users");
+        assert_eq!(action, Action::Pass { directive: DirectiveName::ProxyPass, target: "http://127.0.0.1:8081".to_string() });
+    }
+
+    #[test]
+    fn servers_are_chosen_by_host() {
+        assert_eq!(route("http://unknown.test/").1, Action::Return { code: 444, content: None });
+        assert_eq!(route("http://www.example.com/").1, Action::Static { path: "/srv/www/index.html".to_string() });
+    }
+
+    #[test]
+    fn rewrites_lead_to_the_final_action() {
+        assert_eq!(route("http://example.com/old/page").1, Action::Return { code: 301, content: Some("/new/page".to_string()) });
+        let (locations, action) = route("http://example.com/moved");
+        assert_eq!(locations, ["/static/"]);
+        assert_eq!(action, Action::Static { path: "/srv/www/static/moved.html".to_string() });
+    }
+}
@@ -0,0 +1,493 @@
+use std::fmt::Display;
+
+use crate::{
+    effective::effective_config,
+    lint::{BuiltinRule, Check, Finding, Severity},
+    names::DirectiveName,
+    router::{Router, Scope},
+    tls::is_tls,
+    types::{Directive, Location, LocationSet, ServerSet},
+    Structure,
+};
+
+/// `location /files { alias /data/; }` serves `/files../` from the parent of the alias
+pub const ALIAS_TRAVERSAL: &str = "alias-traversal";
+/// `location /files/ { alias /data; }` maps `/files/a` to `/dataa`
+pub const ALIAS_SLASH_MISMATCH: &str = "alias-slash-mismatch";
+/// `if` in a location doing more than `return` or `rewrite ... last`
+pub const IF_IN_LOCATION: &str = "if-in-location";
+/// `add_header` of a context dropping the security headers of the enclosing one
+pub const ADD_HEADER_INHERITANCE: &str = "add-header-inheritance";
+/// HTTPS server or location whose effective `add_header`s lack a security header
+pub const MISSING_SECURITY_HEADERS: &str = "missing-security-headers";
+/// `autoindex on`
+pub const AUTOINDEX: &str = "autoindex";
+/// `server_tokens` left on
+pub const SERVER_TOKENS: &str = "server-tokens";
+
+/// Response headers hardening browsers, as nginx compares header names: case-insensitively
+pub const SECURITY_HEADERS: &[&str] = &[
+    "Strict-Transport-Security",
+    "Content-Security-Policy",
+    "X-Frame-Options",
+    "X-Content-Type-Options",
+    "Referrer-Policy",
+    "Permissions-Policy",
+    "Cross-Origin-Opener-Policy",
+    "Cross-Origin-Resource-Policy",
+    "Cross-Origin-Embedder-Policy",
+];
+
+/// Runs every security rule over a config parsed from `source`
+pub fn security_findings<'a>(root: &'a Structure<'a>, source: &str) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    findings.extend(check_alias(root, source));
+    findings.extend(check_if_in_location(root, source));
+    findings.extend(check_add_header_inheritance(root, source));
+    findings.extend(check_security_headers(&Router::new(root), source));
+    findings.extend(check_autoindex(root, source));
+    findings.extend(check_server_tokens(root, source));
+    findings
+}
+
+/// The rules of this module, for `lint::LintRunner`
+pub fn rules() -> Vec<BuiltinRule> {
+    vec![
+        BuiltinRule { id: ALIAS_TRAVERSAL, name: "alias path traversal", check: Check::Tree(check_alias) },
+        BuiltinRule { id: ALIAS_SLASH_MISMATCH, name: "alias trailing slash mismatch", check: Check::Tree(check_alias) },
+        BuiltinRule { id: IF_IN_LOCATION, name: "if is evil", check: Check::Tree(check_if_in_location) },
+        BuiltinRule { id: ADD_HEADER_INHERITANCE, name: "add_header inheritance", check: Check::Tree(check_add_header_inheritance) },
+        BuiltinRule { id: MISSING_SECURITY_HEADERS, name: "missing security headers", check: Check::Routed(check_security_headers) },
+        BuiltinRule { id: AUTOINDEX, name: "directory listing", check: Check::Tree(check_autoindex) },
+        BuiltinRule { id: SERVER_TOKENS, name: "nginx version disclosure", check: Check::Tree(check_server_tokens) },
+    ]
+}
+
+/// `alias` whose location and path disagree on trailing slashes, in prefix locations,
+/// and in regex locations around the capture the alias appends
+pub fn check_alias<'a>(root: &'a Structure<'a>, source: &str) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    for locations in location_sets(root) {
+        for_each_location(&locations, &mut |location, block| {
+            let Some(statement) = direct_children(block).find(|c| c.name() == Some(DirectiveName::Alias)) else {
+                return
+            };
+            let Ok(Directive::Alias { path }) = Directive::try_from(statement) else {
+                return
+            };
+            if let Some((rule, severity, message)) = alias_problem(location, &path) {
+                findings.push(Finding::new(rule, severity, statement, source, message));
+            }
+        });
+    }
+    findings
+}
+
+fn alias_problem(location: &Location, path: &str) -> Option<(&'static str, Severity, String)> {
+    match location {
+        Location::Prefix(prefix) | Location::PriorityPrefix(prefix) if !path.contains('$') => {
+            match (prefix.ends_with('/'), path.ends_with('/')) {
+                (false, true) => Some((
+                    ALIAS_TRAVERSAL,
+                    Severity::High,
+                    format!("`location {prefix}` doesn't end with `/` but `alias {path}` does, `{prefix}../` reaches the parent of the alias"),
+                )),
+                (true, false) => Some((
+                    ALIAS_SLASH_MISMATCH,
+                    Severity::Medium,
+                    format!("`location {prefix}` ends with `/` but `alias {path}` doesn't, `{prefix}file` maps to `{path}file`"),
+                )),
+                _ => None,
+            }
+        },
+        Location::Casitive(regex) | Location::Incasitive(regex) => {
+            let pattern = regex.as_str().strip_prefix("(?i)").unwrap_or(regex.as_str());
+            let (before, reference, dot_first) = first_capture(pattern)?;
+            let at = [format!("${{{reference}}}"), format!("${reference}")].iter().find_map(|r| path.find(r.as_str()))?;
+            let slash_before_capture = before.trim_start_matches('^').ends_with('/');
+            let slash_before_reference = path[..at].ends_with('/');
+            match (slash_before_capture, slash_before_reference) {
+                (false, true) if dot_first => Some((
+                    ALIAS_TRAVERSAL,
+                    Severity::High,
+                    format!("the location regex allows `..` right before the capture `alias {path}` puts after a `/`"),
+                )),
+                (true, false) if at > 0 => Some((
+                    ALIAS_SLASH_MISMATCH,
+                    Severity::Medium,
+                    format!("the location regex captures past a `/` that `alias {path}` leaves out"),
+                )),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Text of a regex before its first capture, the name `$1` or `$name` refers to it by,
+/// and whether the capture may start with a dot
+fn first_capture(pattern: &str) -> Option<(&str, String, bool)> {
+    let bytes = pattern.as_bytes();
+    let mut in_class = false;
+    let mut at = 0;
+    while at < bytes.len() {
+        match bytes[at] {
+            b'\\' => at += 1,
+            b'[' => in_class = true,
+            b']' => in_class = false,
+            b'(' if !in_class => {
+                let rest = &pattern[at + 1..];
+                let (name, body) = match rest.strip_prefix("?P<").or_else(|| rest.strip_prefix("?<")) {
+                    Some(named) if !named.starts_with(['=', '!']) => {
+                        let (name, body) = named.split_once('>')?;
+                        (name.to_string(), body)
+                    },
+                    _ if rest.starts_with('?') => {
+                        at += 1;
+                        continue;
+                    },
+                    _ => ("1".to_string(), rest),
+                };
+                let dot_first = body.starts_with('.') || body.starts_with("[^");
+                return Some((&pattern[..at], name, dot_first))
+            },
+            _ => {},
+        }
+        at += 1;
+    }
+    None
+}
+
+/// `if` blocks of locations holding anything but `return` and `rewrite ... last`,
+/// the only directives behaving as expected there
+pub fn check_if_in_location<'a>(root: &'a Structure<'a>, source: &str) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    for locations in location_sets(root) {
+        for_each_location(&locations, &mut |_, block| {
+            for condition in direct_children(block).filter(|c| c.name() == Some(DirectiveName::If)) {
+                let unsafe_directives = direct_children(condition)
+                    .filter(|child| !is_safe_in_if(child))
+                    .filter_map(|child| child.name())
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>();
+                if !unsafe_directives.is_empty() {
+                    let message = format!(
+                        "`if` in a location only reliably handles `return` and `rewrite ... last`, not {}",
+                        unsafe_directives.join(", "),
+                    );
+                    findings.push(Finding::new(IF_IN_LOCATION, Severity::Medium, condition, source, message));
+                }
+            }
+        });
+    }
+    findings
+}
+
+fn is_safe_in_if(statement: &Structure) -> bool {
+    let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+    match statement.name() {
+        Some(DirectiveName::Return) => true,
+        Some(DirectiveName::Rewrite) => args.last().is_some_and(|flag| flag.value() == "last"),
+        _ => false,
+    }
+}
+
+/// Contexts whose `add_header` replace all of the enclosing context's ones, dropping its security headers
+pub fn check_add_header_inheritance<'a>(root: &'a Structure<'a>, source: &str) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    header_inheritance(root, &[], source, &mut findings);
+    findings
+}
+
+fn header_inheritance<'a>(block: &'a Structure<'a>, inherited: &[String], source: &str, findings: &mut Vec<Finding<'a>>) {
+    for child in direct_children(block) {
+        if !matches!(child, Structure::Block { .. })
+            || !matches!(
+                child.name(),
+                Some(DirectiveName::Http | DirectiveName::Server | DirectiveName::Location | DirectiveName::If)
+            )
+        {
+            continue;
+        }
+        let mut first = None;
+        let mut own = Vec::new();
+        for statement in direct_children(child) {
+            if let Ok(Directive::AddHeader { name, .. }) = Directive::try_from(statement) {
+                first.get_or_insert(statement);
+                own.push(name);
+            }
+        }
+        let Some(first) = first else {
+            header_inheritance(child, inherited, source, findings);
+            continue;
+        };
+        let dropped = inherited
+            .iter()
+            .filter(|header| SECURITY_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(header)))
+            .filter(|header| !own.iter().any(|h| h.eq_ignore_ascii_case(header)))
+            .map(|header| format!("`{header}`"))
+            .collect::<Vec<_>>();
+        if !dropped.is_empty() {
+            let (Structure::Statement { args } | Structure::Block { args, .. }) = child;
+            let message = format!(
+                "`add_header` in this `{}` drops {} added by the enclosing context",
+                args[0].value(),
+                dropped.join(", "),
+            );
+            findings.push(Finding::new(ADD_HEADER_INHERITANCE, Severity::Medium, first, source, message));
+        }
+        header_inheritance(child, &own, source, findings);
+    }
+}
+
+/// Security header HTTPS responses should carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecurityHeader {
+    /// `Strict-Transport-Security`
+    Hsts,
+    /// `X-Content-Type-Options`
+    ContentTypeOptions,
+    /// `X-Frame-Options`, or a `Content-Security-Policy` with `frame-ancestors`
+    FrameOptions,
+}
+
+impl Display for SecurityHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hsts => write!(f, "`Strict-Transport-Security`"),
+            Self::ContentTypeOptions => write!(f, "`X-Content-Type-Options`"),
+            Self::FrameOptions => write!(f, "`X-Frame-Options` (or CSP `frame-ancestors`)"),
+        }
+    }
+}
+
+/// Headers in effect in an HTTPS server, or in one of its locations
+#[derive(Debug, Clone)]
+pub struct HeaderCoverage<'r, 'a> {
+    pub scope: Scope<'r, 'a>,
+    /// Names and values of the `add_header`s that apply, those of the innermost context defining any
+    pub headers: Vec<(String, String)>,
+    /// Whether the headers are defined by this very context rather than inherited
+    pub defines_own: bool,
+    pub missing: Vec<SecurityHeader>,
+}
+
+/// Effective `add_header`s of every HTTPS server and of each of its locations, nested ones included
+/// A context defining any `add_header` replaces all of the inherited ones
+pub fn security_headers<'r, 'a>(router: &'r Router<'a>) -> Vec<HeaderCoverage<'r, 'a>> {
+    let mut coverage = Vec::new();
+    for scope in router.scopes().into_iter().filter(|scope| is_tls(scope.server.block)) {
+        let contexts = router.contexts(scope.server, &scope.locations);
+        let settings = effective_config(&contexts);
+        let defines_own = settings.setting(DirectiveName::AddHeader).is_some_and(|s| s.context + 1 == contexts.len());
+        let headers = settings
+            .directives(DirectiveName::AddHeader)
+            .filter_map(|directive| match directive {
+                Directive::AddHeader { name, value, .. } => Some((name, value)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let missing = missing_headers(&headers);
+        coverage.push(HeaderCoverage { scope, headers, defines_own, missing });
+    }
+    coverage
+}
+
+fn missing_headers(headers: &[(String, String)]) -> Vec<SecurityHeader> {
+    let has = |header: &str| headers.iter().any(|(name, _)| name.eq_ignore_ascii_case(header));
+    let frame_ancestors = headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("Content-Security-Policy") && value.to_ascii_lowercase().contains("frame-ancestors")
+    });
+    let mut missing = Vec::new();
+    if !has("Strict-Transport-Security") {
+        missing.push(SecurityHeader::Hsts);
+    }
+    if !has("X-Content-Type-Options") {
+        missing.push(SecurityHeader::ContentTypeOptions);
+    }
+    if !has("X-Frame-Options") && !frame_ancestors {
+        missing.push(SecurityHeader::FrameOptions);
+    }
+    missing
+}
+
+/// HTTPS servers missing security headers, and locations whose own `add_header`s leave some out,
+/// locations inheriting the headers of their server aren't reported again
+pub fn check_security_headers<'a>(router: &Router<'a>, source: &str) -> Vec<Finding<'a>> {
+    security_headers(router)
+        .into_iter()
+        .filter(|coverage| !coverage.missing.is_empty() && (coverage.scope.locations.is_empty() || coverage.defines_own))
+        .map(|coverage| {
+            let context = if coverage.scope.locations.is_empty() { "server" } else { "location" };
+            let block = coverage.scope.block();
+            let missing = coverage.missing.iter().map(|header| header.to_string()).collect::<Vec<_>>();
+            let message = format!("responses of this HTTPS {context} lack {}", missing.join(", "));
+            Finding::new(MISSING_SECURITY_HEADERS, Severity::Medium, block, source, message)
+        })
+        .collect()
+}
+
+/// `autoindex on`, listing the content of directories without an index file
+pub fn check_autoindex<'a>(root: &'a Structure<'a>, source: &str) -> Vec<Finding<'a>> {
+    descendants(root)
+        .into_iter()
+        .filter(|s| matches!(Directive::try_from(*s), Ok(Directive::Autoindex { enabled: true })))
+        .map(|s| {
+            let message = "`autoindex on` lists the files of directories without an index file".to_string();
+            Finding::new(AUTOINDEX, Severity::Medium, s, source, message)
+        })
+        .collect()
+}
+
+/// `http` blocks not turning `server_tokens` off, and statements turning it back on,
+/// the nginx version then shows in the `Server` header and error pages
+pub fn check_server_tokens<'a>(root: &'a Structure<'a>, source: &str) -> Vec<Finding<'a>> {
+    let mut findings = Vec::new();
+    for http in direct_children(root).filter(|c| c.name() == Some(DirectiveName::Http)) {
+        if !direct_children(http).any(|c| c.name() == Some(DirectiveName::ServerTokens)) {
+            let message = "`server_tokens` is on by default, responses show the nginx version".to_string();
+            findings.push(Finding::new(SERVER_TOKENS, Severity::Low, http, source, message));
+        }
+    }
+    for statement in descendants(root) {
+        if let Ok(Directive::ServerTokens { enabled: true }) = Directive::try_from(statement) {
+            let message = "`server_tokens` isn't off, responses show the nginx version".to_string();
+            findings.push(Finding::new(SERVER_TOKENS, Severity::Low, statement, source, message));
+        }
+    }
+    findings
+}
+
+/// Locations of every server of every `http` block
+fn location_sets<'a>(root: &'a Structure<'a>) -> Vec<LocationSet<'a>> {
+    direct_children(root)
+        .filter(|c| c.name() == Some(DirectiveName::Http))
+        .flat_map(|http| ServerSet::from_block(http).servers)
+        .map(|server| server.locations)
+        .collect()
+}
+
+/// Visits the locations of a set and the ones nested in them, outermost first
+fn for_each_location<'a>(set: &LocationSet<'a>, visit: &mut impl FnMut(&Location, &'a Structure<'a>)) {
+    for location in &set.locations {
+        visit(&location.location, location.block);
+        for_each_location(&location.nested, visit);
+    }
+}
+
+fn direct_children<'a>(block: &'a Structure<'a>) -> impl Iterator<Item = &'a Structure<'a>> {
+    let children = match block {
+        Structure::Block { children, .. } => children.as_slice(),
+        Structure::Statement { .. } => &[],
+    };
+    children.iter()
+}
+
+/// Statements and blocks at any depth, in config order
+fn descendants<'a>(block: &'a Structure<'a>) -> Vec<&'a Structure<'a>> {
+    let mut all = Vec::new();
+    for child in direct_children(block) {
+        all.push(child);
+        all.extend(descendants(child));
+    }
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines of the findings of `rule` among those of every security rule
+    fn lines(rule: &str, source: &str) -> Vec<usize> {
+        let root = Structure::parse(source).unwrap();
+        security_findings(&root, source).iter().filter(|finding| finding.rule == rule).map(|finding| finding.span.unwrap().start.line).collect()
+    }
+
+    #[test]
+    fn aliases_disagreeing_with_their_location() {
+        let source = "http { server_tokens off; server {\n\
+            location /files { alias /data/; }\n\
+            location /img/ { alias /srv/img; }\n\
+            location /ok/ { alias /srv/ok/; }\n\
+            location /var { alias /srv/$host/; }\n\
+            location ~ ^/dl(.*)$ { alias /srv/dl/$1; }\n\
+            location ~ ^/pub/(.+)$ { alias /srv/pub$1; }\n\
+            location ~ ^/doc/(?<file>[a-z]+)$ { alias /srv/doc/$file; }\n\
+            } }";
+        assert_eq!(lines(ALIAS_TRAVERSAL, source), [2, 6]);
+        assert_eq!(lines(ALIAS_SLASH_MISMATCH, source), [3, 7]);
+    }
+
+    #[test]
+    fn ifs_doing_more_than_return_or_rewrite() {
+        let source = "http { server_tokens off; server {\n\
+            location / {\n\
+            if ($bad) { return 403; }\n\
+            if ($old) { rewrite ^ /new last; }\n\
+            if ($moved) { rewrite ^ /new permanent; }\n\
+            if ($api) { proxy_pass http://api; add_header X-Api 1; }\n\
+            }\n\
+            if ($host = old) { set $legacy 1; }\n\
+            } }";
+        assert_eq!(lines(IF_IN_LOCATION, source), [5, 6]);
+        let root = Structure::parse(source).unwrap();
+        let messages = check_if_in_location(&root, source).into_iter().map(|finding| finding.message).collect::<Vec<_>>();
+        assert!(messages[1].ends_with("not `proxy_pass`, `add_header`"), "{messages:?}");
+    }
+
+    #[test]
+    fn add_header_dropping_inherited_security_headers() {
+        let source = "http { server_tokens off;\n\
+            add_header X-Frame-Options DENY;\n\
+            server { listen 80;\n\
+            location /a { add_header Cache-Control no-store; }\n\
+            location /b { add_header x-frame-options SAMEORIGIN; add_header Cache-Control no-store; }\n\
+            location /c { root /srv; }\n\
+            }\n\
+            server { listen 81; add_header X-Debug 1; location /d { if ($slow) { add_header X-Slow 1; } } }\n\
+            }";
+        assert_eq!(lines(ADD_HEADER_INHERITANCE, source), [4, 8]);
+    }
+
+    #[test]
+    fn https_contexts_missing_security_headers() {
+        let source = "http { server_tokens off;\n\
+            server { listen 80; }\n\
+            server { listen 443 ssl;\n\
+            add_header Strict-Transport-Security max-age=63072000;\n\
+            add_header X-Content-Type-Options nosniff;\n\
+            add_header Content-Security-Policy \"frame-ancestors 'none'\";\n\
+            location / { root /srv; }\n\
+            location /api { add_header Cache-Control no-store; }\n\
+            }\n\
+            server { listen 8443 ssl; add_header X-Frame-Options DENY; location / { root /srv; } }\n\
+            }";
+        assert_eq!(lines(MISSING_SECURITY_HEADERS, source), [8, 10]);
+
+        let root = Structure::parse(source).unwrap();
+        let router = Router::new(&root);
+        let coverage = security_headers(&router);
+        let missing = coverage.iter().map(|c| (c.scope.locations.len(), c.defines_own, c.missing.clone())).collect::<Vec<_>>();
+        use SecurityHeader::*;
+        assert_eq!(
+            missing,
+            [
+                (0, true, vec![]),
+                (1, false, vec![]),
+                (1, true, vec![Hsts, ContentTypeOptions, FrameOptions]),
+                (0, true, vec![Hsts, ContentTypeOptions]),
+                (1, false, vec![Hsts, ContentTypeOptions]),
+            ]
+        );
+    }
+
+    #[test]
+    fn directory_listings_and_version_disclosure() {
+        let source = "http {\n\
+            server { autoindex on; location /pub { autoindex off; } location /dl { autoindex on; } }\n\
+            }\n\
+            http { server_tokens off; server { server_tokens on; } }";
+        assert_eq!(lines(AUTOINDEX, source), [2, 2]);
+        assert_eq!(lines(SERVER_TOKENS, source), [1, 4]);
+    }
+}
@@ -0,0 +1,236 @@
+use std::{net::SocketAddr, str::FromStr, time::Duration};
+
+use crate::{
+    names::DirectiveName,
+    types::{parse_listen_addr, parse_time, switch, PassTarget, Upstream},
+    Structure, Token,
+};
+
+/// Transport of a stream `listen` socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// `listen 5432;`, `listen [::1]:53 udp;`, `listen 443 ssl proxy_protocol;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamListen {
+    pub addr: SocketAddr,
+    pub transport: Transport,
+    /// TLS is terminated by nginx
+    pub ssl: bool,
+    /// Clients are expected to send a PROXY protocol header
+    pub proxy_protocol: bool,
+}
+
+/// Parses the arguments following `listen`
+impl TryFrom<&[&str]> for StreamListen {
+    type Error = ();
+    fn try_from(args: &[&str]) -> Result<Self, Self::Error> {
+        let (addr, params) = args.split_first().ok_or(())?;
+        let mut listen = Self {
+            addr: parse_listen_addr(addr)?,
+            transport: Transport::Tcp,
+            ssl: false,
+            proxy_protocol: false,
+        };
+        for param in params {
+            match *param {
+                "udp" => listen.transport = Transport::Udp,
+                "ssl" => listen.ssl = true,
+                "proxy_protocol" => listen.proxy_protocol = true,
+                _ => {},
+            }
+        }
+        Ok(listen)
+    }
+}
+
+/// `server` block of the `stream` context
+/// Settings not given in the block are inherited from the `stream` level, then the nginx defaults
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamServer {
+    pub listen: Vec<StreamListen>,
+    pub proxy_pass: Option<PassTarget>,
+    /// Idle time between two reads or writes after which the connection is closed
+    pub proxy_timeout: Duration,
+    pub proxy_connect_timeout: Duration,
+    /// Send a PROXY protocol header to the upstream
+    pub proxy_protocol: bool,
+    /// Read the TLS ClientHello without terminating, for `$ssl_preread_server_name` routing
+    pub ssl_preread: bool,
+}
+
+impl Default for StreamServer {
+    fn default() -> Self {
+        Self {
+            listen: Vec::new(),
+            proxy_pass: None,
+            proxy_timeout: Duration::from_secs(10 * 60),
+            proxy_connect_timeout: Duration::from_secs(60),
+            proxy_protocol: false,
+            ssl_preread: false,
+        }
+    }
+}
+
+impl StreamServer {
+    /// Applies a directive to the server, directives this model doesn't cover are ignored
+    fn apply(&mut self, args: &[Token]) -> Result<(), ()> {
+        match args.first().and_then(|a| DirectiveName::from_str(a.value()).ok()) {
+            Some(DirectiveName::Listen) => {
+                let args = args[1..].iter().map(|a| a.value()).collect::<Vec<_>>();
+                self.listen.push(StreamListen::try_from(args.as_slice())?);
+            },
+            Some(DirectiveName::ProxyPass) => self.proxy_pass = Some(PassTarget::from_str(args.get(1).ok_or(())?.value())?),
+            Some(DirectiveName::ProxyTimeout) => self.proxy_timeout = parse_time(args.get(1))?,
+            Some(DirectiveName::ProxyConnectTimeout) => self.proxy_connect_timeout = parse_time(args.get(1))?,
+            Some(DirectiveName::ProxyProtocol) => self.proxy_protocol = switch(args.get(1).ok_or(())?.value())?,
+            Some(DirectiveName::SslPreread) => self.ssl_preread = switch(args.get(1).ok_or(())?.value())?,
+            _ => {},
+        }
+        Ok(())
+    }
+
+    fn with_defaults(block: &Structure, defaults: &Self) -> Result<Self, ()> {
+        let Structure::Block { children, .. } = block else {
+            return Err(())
+        };
+        if block.name() != Some(DirectiveName::Server) {
+            return Err(())
+        }
+        let mut server = defaults.clone();
+        for child in children {
+            if let Structure::Statement { args } = child {
+                server.apply(args)?;
+            }
+        }
+        Ok(server)
+    }
+}
+
+impl TryFrom<&Structure<'_>> for StreamServer {
+    type Error = ();
+    fn try_from(block: &Structure) -> Result<Self, Self::Error> {
+        Self::with_defaults(block, &Self::default())
+    }
+}
+
+/// Servers and upstreams of a `stream {}` block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamConfig {
+    pub servers: Vec<StreamServer>,
+    pub upstreams: Vec<Upstream>,
+}
+
+impl TryFrom<&Structure<'_>> for StreamConfig {
+    type Error = ();
+    fn try_from(block: &Structure) -> Result<Self, Self::Error> {
+        let Structure::Block { children, .. } = block else {
+            return Err(())
+        };
+        if block.name() != Some(DirectiveName::Stream) {
+            return Err(())
+        }
+        // listen and proxy_pass aren't allowed at the stream level, only the rest is inherited
+        let mut defaults = StreamServer::default();
+        for child in children {
+            if let Structure::Statement { args } = child {
+                if !matches!(child.name(), Some(DirectiveName::Listen | DirectiveName::ProxyPass)) {
+                    defaults.apply(args)?;
+                }
+            }
+        }
+        let mut config = Self::default();
+        for child in children {
+            if !matches!(child, Structure::Block { .. }) {
+                continue;
+            }
+            match child.name() {
+                Some(DirectiveName::Server) => config.servers.push(StreamServer::with_defaults(child, &defaults)?),
+                Some(DirectiveName::Upstream) => config.upstreams.push(Upstream::try_from(child)?),
+                _ => {},
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl StreamConfig {
+    /// Every `stream` block of a config
+    pub fn from_main<'a>(main: &'a Structure) -> impl Iterator<Item = Result<Self, ()>> + 'a {
+        let children = match main {
+            Structure::Block { children, .. } => children.as_slice(),
+            Structure::Statement { .. } => &[],
+        };
+        children
+            .iter()
+            .filter(|child| matches!(child, Structure::Block { .. }) && child.name() == Some(DirectiveName::Stream))
+            .map(Self::try_from)
+    }
+
+    /// The upstream group a `proxy_pass` target names, `None` for direct addresses
+    pub fn upstream(&self, target: &PassTarget) -> Option<&Upstream> {
+        let PassTarget::Address(name) = target else {
+            return None
+        };
+        self.upstreams.iter().find(|u| &u.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+stream {
+    proxy_timeout 30s;
+    proxy_protocol on;
+    upstream postgres { server 10.0.0.1:5432; server 10.0.0.2:5432 backup; }
+    server {
+        listen 5432;
+        listen [::1]:5432 ssl proxy_protocol;
+        proxy_pass postgres;
+        proxy_connect_timeout 2s;
+    }
+    server {
+        listen 53 udp;
+        proxy_pass unix:/run/dns.sock;
+        proxy_protocol off;
+        ssl_preread on;
+    }
+}
+http { server { listen 80; } }
+";
+
+    fn configs(source: &str) -> Vec<Result<StreamConfig, ()>> {
+        let root = Structure::parse(source).unwrap();
+        StreamConfig::from_main(&root).collect()
+    }
+
+    #[test]
+    fn servers_inherit_the_stream_level() {
+        let configs = configs(CONFIG);
+        let [Ok(config)] = configs.as_slice() else { panic!("not one stream block") };
+        let [database, dns] = config.servers.as_slice() else { panic!("not two servers") };
+        assert_eq!(database.listen.len(), 2);
+        assert_eq!((database.listen[0].addr.port(), database.listen[0].transport, database.listen[0].ssl), (5432, Transport::Tcp, false));
+        assert!(database.listen[1].addr.is_ipv6() && database.listen[1].ssl && database.listen[1].proxy_protocol);
+        assert_eq!((database.proxy_timeout, database.proxy_connect_timeout, database.proxy_protocol), (Duration::from_secs(30), Duration::from_secs(2), true));
+        assert_eq!((dns.listen[0].transport, dns.proxy_protocol, dns.ssl_preread), (Transport::Udp, false, true));
+        assert_eq!(dns.proxy_pass, Some(PassTarget::Unix("/run/dns.sock".into())));
+        assert_eq!(dns.proxy_connect_timeout, Duration::from_secs(60));
+
+        let upstream = config.upstream(database.proxy_pass.as_ref().unwrap()).unwrap();
+        assert_eq!((upstream.name.as_str(), upstream.servers.len()), ("postgres", 2));
+        assert!(config.upstream(dns.proxy_pass.as_ref().unwrap()).is_none());
+    }
+
+    #[test]
+    fn invalid_stream_blocks() {
+        assert_eq!(configs("stream { server { listen nowhere; } }"), [Err(())]);
+        assert_eq!(configs("stream { proxy_timeout soon; }"), [Err(())]);
+        assert!(configs("http { }").is_empty());
+    }
+}
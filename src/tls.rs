@@ -0,0 +1,327 @@
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use crate::{
+    effective::{effective_config, EffectiveSettings},
+    names::DirectiveName,
+    router::Router,
+    types::{CipherList, Directive, ServerBlock, SessionCache, TlsProtocol},
+    Structure,
+};
+
+/// `ssl_ciphers` nginx uses when the directive isn't set
+const DEFAULT_CIPHERS: &str = "HIGH:!aNULL:!MD5";
+/// `ssl_session_timeout` nginx uses when the directive isn't set
+const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// TLS 1.2 suites of the intermediate profile, TLS 1.3 ones aren't set by `ssl_ciphers`
+const INTERMEDIATE_CIPHERS: &[&str] = &[
+    "ECDHE-ECDSA-AES128-GCM-SHA256",
+    "ECDHE-RSA-AES128-GCM-SHA256",
+    "ECDHE-ECDSA-AES256-GCM-SHA384",
+    "ECDHE-RSA-AES256-GCM-SHA384",
+    "ECDHE-ECDSA-CHACHA20-POLY1305",
+    "ECDHE-RSA-CHACHA20-POLY1305",
+    "DHE-RSA-AES128-GCM-SHA256",
+    "DHE-RSA-AES256-GCM-SHA384",
+    "DHE-RSA-CHACHA20-POLY1305",
+];
+
+/// Suites the old profile adds to the intermediate ones, for clients without TLS 1.2 AEAD support
+const OLD_EXTRA_CIPHERS: &[&str] = &[
+    "ECDHE-ECDSA-AES128-SHA256",
+    "ECDHE-RSA-AES128-SHA256",
+    "ECDHE-ECDSA-AES128-SHA",
+    "ECDHE-RSA-AES128-SHA",
+    "ECDHE-ECDSA-AES256-SHA384",
+    "ECDHE-RSA-AES256-SHA384",
+    "ECDHE-ECDSA-AES256-SHA",
+    "ECDHE-RSA-AES256-SHA",
+    "DHE-RSA-AES128-SHA256",
+    "DHE-RSA-AES256-SHA256",
+    "AES128-GCM-SHA256",
+    "AES256-GCM-SHA384",
+    "AES128-SHA256",
+    "AES256-SHA256",
+    "AES128-SHA",
+    "AES256-SHA",
+    "DES-CBC3-SHA",
+];
+
+/// Curves of every profile
+const CURVES: &[&str] = &["X25519", "prime256v1", "secp384r1"];
+
+/// HSTS `max-age` of every profile, two years
+const HSTS_MAX_AGE: u64 = 63072000;
+
+/// Server side TLS recommendations of Mozilla, from the strictest to the most compatible
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MozillaProfile {
+    /// TLS 1.3 only
+    Modern,
+    /// TLS 1.2 and 1.3 with forward secret AEAD ciphers, the general purpose recommendation
+    Intermediate,
+    /// Down to TLS 1.0, for ancient clients
+    Old,
+}
+
+impl MozillaProfile {
+    pub const ALL: [Self; 3] = [Self::Modern, Self::Intermediate, Self::Old];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Modern => "modern",
+            Self::Intermediate => "intermediate",
+            Self::Old => "old",
+        }
+    }
+
+    pub fn protocols(&self) -> &'static [TlsProtocol] {
+        match self {
+            Self::Modern => &[TlsProtocol::TlsV1_3],
+            Self::Intermediate => &[TlsProtocol::TlsV1_2, TlsProtocol::TlsV1_3],
+            Self::Old => &[TlsProtocol::TlsV1, TlsProtocol::TlsV1_1, TlsProtocol::TlsV1_2, TlsProtocol::TlsV1_3],
+        }
+    }
+
+    /// Whether a cipher suite may be offered, always true for modern where `ssl_ciphers` has no effect
+    pub fn allows_cipher(&self, cipher: &str) -> bool {
+        match self {
+            Self::Modern => true,
+            Self::Intermediate => INTERMEDIATE_CIPHERS.contains(&cipher),
+            Self::Old => INTERMEDIATE_CIPHERS.contains(&cipher) || OLD_EXTRA_CIPHERS.contains(&cipher),
+        }
+    }
+
+    /// `ssl_prefer_server_ciphers`, only old clients need to be steered away from their weak choices
+    pub fn prefer_server_ciphers(&self) -> bool {
+        *self == Self::Old
+    }
+
+    /// `ssl_session_timeout`
+    pub fn session_timeout(&self) -> Duration {
+        Duration::from_secs(24 * 60 * 60)
+    }
+}
+
+impl FromStr for MozillaProfile {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|profile| profile.as_str() == s).ok_or(())
+    }
+}
+
+impl Display for MozillaProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A setting of a server that differs from the profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TlsGap {
+    /// Enabled by `ssl_protocols` (or its default) but not part of the profile
+    ExtraProtocol(TlsProtocol),
+    /// Part of the profile but not enabled
+    MissingProtocol(TlsProtocol),
+    /// Included by `ssl_ciphers` (or its default) but not a suite of the profile, aliases such as `HIGH` included
+    ExtraCipher(String),
+    /// DHE suites are offered without `ssl_dhparam`, nginx then doesn't negotiate them
+    MissingDhparam,
+    /// Curve of `ssl_ecdh_curve` not part of the profile
+    ExtraCurve(String),
+    PreferServerCiphers { expected: bool },
+    /// Sessions aren't kept in a cache shared by the workers
+    NoSharedSessionCache,
+    SessionTimeout { got: Duration, expected: Duration },
+    /// Tickets are on, their keys aren't rotated by nginx and undo forward secrecy
+    SessionTickets,
+    /// No `Strict-Transport-Security` header added
+    MissingHsts,
+    HstsMaxAge { got: u64, expected: u64 },
+}
+
+impl Display for TlsGap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExtraProtocol(protocol) => write!(f, "{} is enabled", protocol.as_str()),
+            Self::MissingProtocol(protocol) => write!(f, "{} isn't enabled", protocol.as_str()),
+            Self::ExtraCipher(cipher) => write!(f, "cipher `{cipher}` is offered"),
+            Self::MissingDhparam => write!(f, "DHE ciphers are offered without `ssl_dhparam`"),
+            Self::ExtraCurve(curve) => write!(f, "curve `{curve}` is offered"),
+            Self::PreferServerCiphers { expected } => {
+                write!(f, "`ssl_prefer_server_ciphers` should be {}", if *expected { "on" } else { "off" })
+            },
+            Self::NoSharedSessionCache => write!(f, "no shared `ssl_session_cache`"),
+            Self::SessionTimeout { got, expected } => {
+                write!(f, "`ssl_session_timeout` is {}s instead of {}s", got.as_secs(), expected.as_secs())
+            },
+            Self::SessionTickets => write!(f, "`ssl_session_tickets` is on"),
+            Self::MissingHsts => write!(f, "no `Strict-Transport-Security` header"),
+            Self::HstsMaxAge { got, expected } => write!(f, "HSTS `max-age` is {got} instead of {expected}"),
+        }
+    }
+}
+
+/// How far a TLS server is from a profile
+#[derive(Debug, Clone)]
+pub struct TlsAudit<'r, 'a> {
+    pub server: &'r ServerBlock<'a>,
+    pub profile: MozillaProfile,
+    pub gaps: Vec<TlsGap>,
+}
+
+impl TlsAudit<'_, '_> {
+    pub fn complies(&self) -> bool {
+        self.gaps.is_empty()
+    }
+}
+
+/// Compares the effective TLS settings of every server listening with `ssl` or `quic` to a profile
+/// Unset directives take their nginx default, `ssl_protocols` the 1.23.4 one (`TLSv1.2 TLSv1.3`)
+pub fn tls_audit<'r, 'a>(router: &'r Router<'a>, profile: MozillaProfile) -> Vec<TlsAudit<'r, 'a>> {
+    router
+        .servers
+        .servers
+        .iter()
+        .filter(|server| is_tls(server.block))
+        .map(|server| {
+            let gaps = tls_gaps(&effective_config(&router.contexts(server, &[])), profile);
+            TlsAudit { server, profile, gaps }
+        })
+        .collect()
+}
+
+/// The strictest profile the server has no gap with, `None` when it doesn't comply with any
+pub fn strictest_profile(router: &Router, server: &ServerBlock) -> Option<MozillaProfile> {
+    let settings = effective_config(&router.contexts(server, &[]));
+    MozillaProfile::ALL.into_iter().find(|profile| tls_gaps(&settings, *profile).is_empty())
+}
+
+/// Whether a server listens with `ssl` or `quic` anywhere
+pub(crate) fn is_tls(server: &Structure) -> bool {
+    let Structure::Block { children, .. } = server else {
+        return false
+    };
+    children.iter().filter(|child| child.name() == Some(DirectiveName::Listen)).any(|listen| {
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = listen;
+        args.iter().skip(2).any(|arg| matches!(arg.value(), "ssl" | "quic"))
+    })
+}
+
+fn tls_gaps(settings: &EffectiveSettings, profile: MozillaProfile) -> Vec<TlsGap> {
+    let mut gaps = Vec::new();
+
+    let protocols = match settings.directives(DirectiveName::SslProtocols).last() {
+        Some(Directive::SslProtocols(protocols)) => protocols.protocols,
+        _ => vec![TlsProtocol::TlsV1_2, TlsProtocol::TlsV1_3],
+    };
+    let mut extra = protocols.iter().filter(|p| !profile.protocols().contains(p)).copied().collect::<Vec<_>>();
+    extra.sort();
+    gaps.extend(extra.into_iter().map(TlsGap::ExtraProtocol));
+    let missing = profile.protocols().iter().filter(|p| !protocols.contains(p));
+    gaps.extend(missing.copied().map(TlsGap::MissingProtocol));
+
+    // ciphers only apply up to TLS 1.2
+    if protocols.iter().any(|protocol| *protocol < TlsProtocol::TlsV1_3) {
+        let ciphers = match settings.directives(DirectiveName::SslCiphers).last() {
+            Some(Directive::SslCiphers(ciphers)) => ciphers,
+            _ => CipherList::from_str(DEFAULT_CIPHERS).unwrap_or_default(),
+        };
+        let included = ciphers.included().collect::<Vec<_>>();
+        gaps.extend(included.iter().filter(|c| !profile.allows_cipher(c)).map(|c| TlsGap::ExtraCipher(c.to_string())));
+        let dhe = included.iter().any(|c| c.starts_with("DHE-") || c.starts_with("EDH"));
+        if dhe && settings.args(DirectiveName::SslDhparam).is_none() {
+            gaps.push(TlsGap::MissingDhparam);
+        }
+        let prefer = settings.directives(DirectiveName::SslPreferServerCiphers).last();
+        let prefer = matches!(prefer, Some(Directive::SslPreferServerCiphers { enabled: true }));
+        if prefer != profile.prefer_server_ciphers() {
+            gaps.push(TlsGap::PreferServerCiphers { expected: profile.prefer_server_ciphers() });
+        }
+    }
+
+    if let Some(Directive::SslEcdhCurve { curves: Some(curves) }) = settings.directives(DirectiveName::SslEcdhCurve).last() {
+        gaps.extend(curves.into_iter().filter(|c| !CURVES.contains(&c.as_str())).map(TlsGap::ExtraCurve));
+    }
+
+    let caches = match settings.directives(DirectiveName::SslSessionCache).last() {
+        Some(Directive::SslSessionCache { caches }) => caches,
+        _ => Vec::new(),
+    };
+    if !caches.iter().any(|cache| matches!(cache, SessionCache::Shared { .. })) {
+        gaps.push(TlsGap::NoSharedSessionCache);
+    }
+    let timeout = match settings.directives(DirectiveName::SslSessionTimeout).last() {
+        Some(Directive::SslSessionTimeout { timeout }) => timeout,
+        _ => DEFAULT_SESSION_TIMEOUT,
+    };
+    if timeout != profile.session_timeout() {
+        gaps.push(TlsGap::SessionTimeout { got: timeout, expected: profile.session_timeout() });
+    }
+    let tickets = settings.directives(DirectiveName::SslSessionTickets).last();
+    if !matches!(tickets, Some(Directive::SslSessionTickets { enabled: false })) {
+        gaps.push(TlsGap::SessionTickets);
+    }
+
+    let hsts = settings.directives(DirectiveName::AddHeader).find_map(|directive| match directive {
+        Directive::AddHeader { name, value, .. } if name.eq_ignore_ascii_case("Strict-Transport-Security") => Some(value),
+        _ => None,
+    });
+    match hsts.map(|value| max_age(&value)) {
+        None => gaps.push(TlsGap::MissingHsts),
+        Some(got) if got < HSTS_MAX_AGE => gaps.push(TlsGap::HstsMaxAge { got, expected: HSTS_MAX_AGE }),
+        Some(_) => {},
+    }
+    gaps
+}
+
+/// `max-age` of a `Strict-Transport-Security` value, 0 when it's missing or broken
+fn max_age(value: &str) -> u64 {
+    value
+        .split(';')
+        .filter_map(|directive| directive.trim().split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("max-age"))
+        .and_then(|(_, age)| age.trim().trim_matches('"').parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "http {\n\
+        ssl_session_cache shared:SSL:10m; ssl_session_timeout 1d; ssl_session_tickets off;\n\
+        add_header Strict-Transport-Security \"max-age=63072000\" always;\n\
+        server { listen 80; }\n\
+        server { listen 443 ssl; ssl_protocols TLSv1.3; }\n\
+        server { listen 8443 ssl; ssl_ciphers ECDHE-ECDSA-AES128-GCM-SHA256:ECDHE-RSA-AES128-GCM-SHA256; }\n\
+        server { listen 9443 quic; ssl_protocols TLSv1 TLSv1.2 TLSv1.3; ssl_session_tickets on; add_header Strict-Transport-Security max-age=3600; }\n\
+        }";
+
+    #[test]
+    fn audits_tls_servers_against_a_profile() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        let audits = tls_audit(&router, MozillaProfile::Intermediate);
+        let gaps = audits.iter().map(|audit| audit.gaps.iter().map(|gap| gap.to_string()).collect::<Vec<_>>()).collect::<Vec<_>>();
+        assert_eq!(
+            gaps,
+            [
+                vec!["TLSv1.2 isn't enabled"],
+                vec![],
+                vec!["TLSv1 is enabled", "cipher `HIGH` is offered", "`ssl_session_tickets` is on", "HSTS `max-age` is 3600 instead of 63072000"],
+            ]
+        );
+        assert!(audits[1].complies());
+    }
+
+    #[test]
+    fn strictest_profile_without_gaps() {
+        let root = Structure::parse(CONFIG).unwrap();
+        let router = Router::new(&root);
+        let profiles = router.servers.servers.iter().map(|server| strictest_profile(&router, server)).collect::<Vec<_>>();
+        assert_eq!(profiles, [None, Some(MozillaProfile::Modern), Some(MozillaProfile::Intermediate), None]);
+        assert_eq!("old".parse(), Ok(MozillaProfile::Old));
+        assert_eq!("strict".parse::<MozillaProfile>(), Err(()));
+    }
+}
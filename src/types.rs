@@ -1,12 +1,16 @@
-use std::{net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
+use std::{collections::HashMap, fmt::Display, net::{IpAddr, Ipv4Addr, SocketAddr}, path::{Path, PathBuf}, str::FromStr, time::Duration};
+use ipnet::IpNet;
 use regex::Regex;
 use url::Url;
 
-use crate::Structure;
+use crate::{effective::EffectiveSettings, names::DirectiveName, router::RequestContext, Structure, Token};
 
+#[derive(Debug, Clone)]
 pub enum Location {
     Exact(String),
     Prefix(String),
+    /// `^~` prefix, regex locations aren't checked when it's the longest matching prefix
+    PriorityPrefix(String),
     Incasitive(Regex),
     Casitive(Regex),
     Virtual(String),
@@ -16,9 +20,9 @@ impl Location {
     
     pub fn matches(&self, path: &str) -> bool {
         match self {
-            Self::Exact(exact) => path.eq_ignore_ascii_case(exact),
-            Self::Prefix(prefix) => path.starts_with(prefix),
-            Self::Incasitive(pattern) => pattern.is_match(path),    // TODO
+            Self::Exact(exact) => path == exact,
+            Self::Prefix(prefix) | Self::PriorityPrefix(prefix) => path.starts_with(prefix),
+            Self::Incasitive(pattern) => pattern.is_match(path),
             Self::Casitive(pattern) => pattern.is_match(path),            
             _ => false
         }
@@ -32,154 +36,4787 @@ impl Location {
     }    
 }
 
+/// Parses `location` arguments, with the modifier either separate (`= /`) or attached (`=/`)
+impl TryFrom<&Structure<'_>> for Location {
+    type Error = ();
+    fn try_from(block: &Structure) -> Result<Self, Self::Error> {
+        let Structure::Block { args, .. } = block else {
+            return Err(())
+        };
+        if block.name() != Some(DirectiveName::Location) {
+            return Err(())
+        }
+        let (modifier, path) = match args.get(1..).ok_or(())? {
+            [modifier, path] => (modifier.value(), path.value()),
+            [path] => {
+                let path = path.value();
+                ["=", "^~", "~*", "~", "@"]
+                    .iter()
+                    .find_map(|m| path.strip_prefix(m).map(|rest| (*m, rest)))
+                    .unwrap_or(("", path))
+            }
+            _ => return Err(()),
+        };
+        if path.is_empty() {
+            return Err(())
+        }
+        let regex = |pattern: &str| Regex::new(pattern).map_err(|_| ());
+        match modifier {
+            "" => Ok(Self::Prefix(path.to_string())),
+            "=" => Ok(Self::Exact(path.to_string())),
+            "^~" => Ok(Self::PriorityPrefix(path.to_string())),
+            "~" => regex(path).map(Self::Casitive),
+            "~*" => regex(&format!("(?i){path}")).map(Self::Incasitive),
+            "@" => Ok(Self::Virtual(format!("@{path}"))),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Last argument of `try_files`, used when none of the files exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TryFilesFallback {
+    /// Internal redirect to a URI
+    Uri(String),
+    /// Internal redirect to a named location, with the `@`
+    Named(String),
+    /// `=404`
+    Code(u16),
+}
+
+impl FromStr for TryFilesFallback {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(code) = s.strip_prefix('=') {
+            return code.parse().map(Self::Code).map_err(|_| ())
+        }
+        match s {
+            "" | "@" => Err(()),
+            _ if s.starts_with('@') => Ok(Self::Named(s.to_string())),
+            _ => Ok(Self::Uri(s.to_string())),
+        }
+    }
+}
+
+/// Status sent for a response produced by `error_page`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPageStatus {
+    /// The original error status
+    #[default]
+    Original,
+    /// `=`, whatever status the target produces
+    FromTarget,
+    /// `=200`
+    Code(u16),
+}
+
+/// Last argument of `rewrite`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RewriteFlag {
+    /// Stop this set of rewrites and search a location for the new URI
+    Last,
+    /// Stop this set of rewrites and stay in the location
+    Break,
+    /// 302
+    Redirect,
+    /// 301
+    Permanent,
+}
+
+impl FromStr for RewriteFlag {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "last" => Ok(Self::Last),
+            "break" => Ok(Self::Break),
+            "redirect" => Ok(Self::Redirect),
+            "permanent" => Ok(Self::Permanent),
+            _ => Err(()),
+        }
+    }
+}
+
+/// File check of an `if` condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileTest {
+    /// `-f`
+    File,
+    /// `-d`
+    Directory,
+    /// `-e`, file, directory or symlink
+    Exists,
+    /// `-x`
+    Executable,
+}
+
+impl FromStr for FileTest {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "-f" => Ok(Self::File),
+            "-d" => Ok(Self::Directory),
+            "-e" => Ok(Self::Exists),
+            "-x" => Ok(Self::Executable),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Condition of an `if` block, variables named without the `$`
+#[derive(Debug, Clone)]
+pub enum Condition {
+    /// `($var)`, false when empty or "0"
+    Variable(String),
+    /// `($var = value)`, `($var != value)`, the value may contain variables
+    Compare { variable: String, value: String, negated: bool },
+    /// `($var ~ regex)`, `~*` compiles it case-insensitively, captures are available as `$1`...
+    Match { variable: String, regex: Regex, negated: bool },
+    /// `(-f $request_filename)`, `(!-d ...)`
+    File { test: FileTest, path: String, negated: bool },
+}
+
+impl Condition {
+    /// Whether the condition holds for the request, a successful regex match sets the captures
+    /// File tests go to the filesystem of the context and never hold without one
+    pub fn evaluate(&self, context: &mut RequestContext) -> bool {
+        match self {
+            Self::Variable(name) => !matches!(context.variable(name).as_deref(), None | Some("" | "0")),
+            Self::Compare { variable, value, negated } => {
+                (context.variable(variable).unwrap_or_default() == context.expand(value)) != *negated
+            },
+            Self::Match { variable, regex, negated } => {
+                let subject = context.variable(variable).unwrap_or_default();
+                match regex.captures(&subject) {
+                    Some(captures) => {
+                        context.captures = captures.iter().map(|c| c.map(|c| c.as_str().to_string())).collect();
+                        !negated
+                    },
+                    None => *negated,
+                }
+            },
+            Self::File { test, path, negated } => {
+                let path = context.expand(path);
+                let holds = context.filesystem.is_some_and(|filesystem| match test {
+                    FileTest::File => filesystem.is_file(&path),
+                    FileTest::Directory => filesystem.is_dir(&path),
+                    FileTest::Exists => filesystem.exists(&path),
+                    FileTest::Executable => filesystem.is_executable(&path),
+                });
+                holds != *negated
+            },
+        }
+    }
+}
+
+/// Parses the text between the parentheses
+impl FromStr for Condition {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = condition_words(s)?;
+        let variable = |word: &str| word.strip_prefix('$').filter(|v| !v.is_empty()).map(|v| v.trim_matches(['{', '}']).to_string());
+        match words.as_slice() {
+            [operand] => Ok(Self::Variable(variable(operand).ok_or(())?)),
+            [test, path] => {
+                let (negated, test) = test.strip_prefix('!').map_or((false, test.as_str()), |t| (true, t));
+                Ok(Self::File { test: FileTest::from_str(test)?, path: path.clone(), negated })
+            },
+            [operand, operator, value] => {
+                let variable = variable(operand).ok_or(())?;
+                let (negated, operator) = operator.strip_prefix('!').map_or((false, operator.as_str()), |o| (true, o));
+                match operator {
+                    "=" => Ok(Self::Compare { variable, value: value.clone(), negated }),
+                    "~" => Ok(Self::Match { variable, regex: Regex::new(value).map_err(|_| ())?, negated }),
+                    "~*" => {
+                        let regex = Regex::new(&format!("(?i){}", value)).map_err(|_| ())?;
+                        Ok(Self::Match { variable, regex, negated })
+                    },
+                    _ => Err(()),
+                }
+            },
+            _ => Err(()),
+        }
+    }
+}
+
+/// Parses the arguments following `if`, parentheses included
+impl TryFrom<&[Token<'_>]> for Condition {
+    type Error = ();
+    fn try_from(args: &[Token]) -> Result<Self, Self::Error> {
+        match args {
+            [condition @ Token::BracedString(_)] => Self::from_str(condition.value()),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Splits a condition on whitespace, quoted words lose their quotes
+pub(crate) fn condition_words(s: &str) -> Result<Vec<String>, ()> {
+    let mut words = Vec::new();
+    let mut chars = s.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' || c == '\'' {
+            chars.next();
+            loop {
+                match chars.next().ok_or(())? {
+                    '\\' => word.push(chars.next().ok_or(())?),
+                    q if q == c => break,
+                    ch => word.push(ch),
+                }
+            }
+        } else {
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() {
+                    break;
+                }
+                word.push(ch);
+                chars.next();
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// A `location` block with the locations nested in it
+#[derive(Debug, Clone)]
+pub struct LocationBlock<'a> {
+    pub location: Location,
+    pub block: &'a Structure<'a>,
+    pub nested: LocationSet<'a>,
+}
+
+/// How far selection got in one level of locations, mirrors the nginx lookup results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lookup {
+    /// Nothing matched
+    Declined,
+    /// A prefix matched, regexes of the outer levels may still win
+    Prefix,
+    /// An exact, `^~` or regex match, selection stops
+    Done,
+}
+
+/// Request path as nginx matches it against locations: the query string cut off,
+/// percent-escapes decoded, `.` and `..` segments resolved and, with `merge_slashes`, repeated slashes merged
+/// `None` where nginx rejects the request with 400, like a `..` above the root or a broken escape
+pub fn normalize_uri(uri: &str, merge_slashes: bool) -> Option<String> {
+    let raw = uri.split_once('?').map_or(uri, |(path, _)| path).as_bytes();
+    let mut decoded = Vec::with_capacity(raw.len());
+    let mut at = 0;
+    while at < raw.len() {
+        if raw[at] == b'%' {
+            let hex = raw.get(at + 1..at + 3).filter(|h| h.iter().all(u8::is_ascii_hexdigit))?;
+            decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            at += 3;
+        } else {
+            decoded.push(raw[at]);
+            at += 1;
+        }
+    }
+    let decoded = String::from_utf8_lossy(&decoded);
+    let rest = decoded.strip_prefix('/')?;
+
+    let mut segments = Vec::new();
+    let mut trailing_slash = false;
+    let mut parts = rest.split('/').peekable();
+    while let Some(part) = parts.next() {
+        let last = parts.peek().is_none();
+        match part {
+            "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            "" if merge_slashes || last => {}
+            part => segments.push(part),
+        }
+        trailing_slash = last && matches!(part, "" | "." | "..");
+    }
+    let mut path = format!("/{}", segments.join("/"));
+    if trailing_slash && !segments.is_empty() {
+        path.push('/');
+    }
+    Some(path)
+}
+
+/// The `location` blocks of one context, in file order
+#[derive(Debug, Clone)]
+pub struct LocationSet<'a> {
+    pub locations: Vec<LocationBlock<'a>>,
+    /// Whether `select` merges repeated slashes, from `merge_slashes` in the block, on by default
+    pub merge_slashes: bool,
+}
+
+impl Default for LocationSet<'_> {
+    fn default() -> Self {
+        Self { locations: Vec::new(), merge_slashes: true }
+    }
+}
+
+impl<'a> LocationSet<'a> {
+    /// Locations that fail to parse, such as regexes the `regex` crate can't compile, are left out
+    pub fn from_block(block: &'a Structure<'a>) -> Self {
+        let mut set = Self::default();
+        if let Structure::Block { children, .. } = block {
+            for child in children {
+                if let Ok(location) = Location::try_from(child) {
+                    set.locations.push(LocationBlock { location, block: child, nested: Self::from_block(child) });
+                } else if let Ok(Directive::MergeSlashes { enabled }) = Directive::try_from(child) {
+                    set.merge_slashes = enabled;
+                }
+            }
+        }
+        set
+    }
+
+    /// The location nginx picks for a request path, the innermost one when locations are nested
+    pub fn select(&self, path: &str) -> Option<&LocationBlock<'a>> {
+        self.select_nested(path).pop()
+    }
+
+    /// The selected location and the locations enclosing it, outermost first
+    /// The path is normalized with `normalize_uri` first, nothing is selected when that fails
+    /// At each level: an exact match, else the longest prefix (searching its nested locations),
+    /// unless that's `^~` the first matching regex in file order, else the longest prefix
+    pub fn select_nested(&self, path: &str) -> Vec<&LocationBlock<'a>> {
+        let mut selected = Vec::new();
+        if let Some(path) = normalize_uri(path, self.merge_slashes) {
+            self.lookup(&path, &mut selected);
+        }
+        selected
+    }
+
+    /// The named location `@name` refers to, named locations live at the server level only
+    pub fn named(&self, name: &str) -> Option<&LocationBlock<'a>> {
+        self.locations.iter().find(|l| l.location.matches_internal(name))
+    }
+
+    /// Where an internal redirect such as a `try_files` fallback or an `error_page` target lands,
+    /// as `select_nested` does, `@name` targets resolve to the named location
+    /// Empty for unknown named locations and for external redirects to a URL
+    pub fn redirect(&self, target: &str) -> Vec<&LocationBlock<'a>> {
+        if target.starts_with('@') {
+            return self.named(target).into_iter().collect()
+        }
+        if !target.starts_with('/') {
+            return Vec::new()
+        }
+        // internal redirects aren't normalized again
+        let path = target.split_once('?').map_or(target, |(path, _)| path);
+        let mut selected = Vec::new();
+        self.lookup(path, &mut selected);
+        selected
+    }
+
+    fn lookup<'s>(&'s self, path: &str, selected: &mut Vec<&'s LocationBlock<'a>>) -> Lookup {
+        let level = selected.len();
+        if let Some(exact) = self.locations.iter().find(|l| matches!(l.location, Location::Exact(_)) && l.location.matches(path)) {
+            selected.push(exact);
+            return Lookup::Done
+        }
+        let prefix = self
+            .locations
+            .iter()
+            .filter_map(|l| match &l.location {
+                Location::Prefix(prefix) | Location::PriorityPrefix(prefix) if path.starts_with(prefix.as_str()) => Some((prefix.len(), l)),
+                _ => None,
+            })
+            // the first of equally long prefixes, nginx rejects such duplicates anyway
+            .fold(None, |longest: Option<(usize, &LocationBlock)>, (length, l)| match longest {
+                Some((best, _)) if best >= length => longest,
+                _ => Some((length, l)),
+            })
+            .map(|(_, l)| l);
+        let mut result = Lookup::Declined;
+        let mut noregex = false;
+        if let Some(prefix) = prefix {
+            selected.push(prefix);
+            if prefix.nested.lookup(path, selected) == Lookup::Done {
+                return Lookup::Done
+            }
+            // `^~` only skips the regexes of its own level, outer levels still check theirs
+            noregex = matches!(prefix.location, Location::PriorityPrefix(_));
+            result = Lookup::Prefix;
+        }
+        if noregex {
+            return result
+        }
+        let regex = self
+            .locations
+            .iter()
+            .find(|l| matches!(l.location, Location::Casitive(_) | Location::Incasitive(_)) && l.location.matches(path));
+        if let Some(regex) = regex {
+            selected.truncate(level);
+            selected.push(regex);
+            regex.nested.lookup(path, selected);
+            return Lookup::Done
+        }
+        result
+    }
+}
+
+/// One `server_name` entry
+#[derive(Debug, Clone)]
+pub enum ServerNamePattern {
+    /// Lowercased, `""` matches requests without a Host header
+    Exact(String),
+    /// `*.example.com` keeps `.example.com`, `.example.com` also matches `example.com` itself
+    LeadingWildcard { suffix: String, include_bare: bool },
+    /// `www.example.*` keeps `www.example.`
+    TrailingWildcard(String),
+    Regex(Regex),
+}
+
+impl FromStr for ServerNamePattern {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(pattern) = s.strip_prefix('~') {
+            return Regex::new(pattern).map(Self::Regex).map_err(|_| ())
+        }
+        let name = s.to_ascii_lowercase();
+        if let Some(suffix) = name.strip_prefix('*') {
+            return match suffix.starts_with('.') && suffix.len() > 1 {
+                true => Ok(Self::LeadingWildcard { suffix: suffix.to_string(), include_bare: false }),
+                false => Err(()),
+            }
+        }
+        if name.starts_with('.') && name.len() > 1 {
+            return Ok(Self::LeadingWildcard { suffix: name, include_bare: true })
+        }
+        if let Some(prefix) = name.strip_suffix('*') {
+            return match prefix.ends_with('.') && prefix.len() > 1 {
+                true => Ok(Self::TrailingWildcard(prefix.to_string())),
+                false => Err(()),
+            }
+        }
+        if name.contains('*') {
+            return Err(())
+        }
+        Ok(Self::Exact(name))
+    }
+}
+
+impl ServerNamePattern {
+    /// Whether a normalized host (lowercase, no port or trailing dot) matches
+    pub fn matches(&self, host: &str) -> bool {
+        match self {
+            Self::Exact(name) => name == host,
+            Self::LeadingWildcard { suffix, include_bare } => {
+                (host.len() > suffix.len() && host.ends_with(suffix.as_str())) || (*include_bare && host == &suffix[1..])
+            }
+            Self::TrailingWildcard(prefix) => host.len() > prefix.len() && host.starts_with(prefix.as_str()),
+            Self::Regex(regex) => regex.is_match(host),
+        }
+    }
+
+    /// Length of the fixed part, the more specific wildcard wins
+    pub(crate) fn specificity(&self) -> usize {
+        match self {
+            Self::Exact(name) | Self::TrailingWildcard(name) => name.len(),
+            Self::LeadingWildcard { suffix, .. } => suffix.len(),
+            Self::Regex(_) => 0,
+        }
+    }
+}
+
+/// Host header as nginx compares it with server names: lowercased, without the port and trailing dot
+pub fn normalize_host(host: &str) -> String {
+    let host = host.trim();
+    let host = match host.strip_prefix('[') {
+        Some(literal) => literal.split_once(']').map_or(host, |(address, _)| &host[..address.len() + 2]),
+        None => host.split_once(':').map_or(host, |(name, _)| name),
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// A `server` block of the http context
+#[derive(Debug, Clone)]
+pub struct ServerBlock<'a> {
+    pub block: &'a Structure<'a>,
+    pub names: Vec<ServerNamePattern>,
+    /// Addresses and whether the server is the `default_server` there,
+    /// servers without `listen` listen on `*:80`
+    pub listen: Vec<(SocketAddr, bool)>,
+    pub locations: LocationSet<'a>,
+}
+
+impl<'a> TryFrom<&'a Structure<'a>> for ServerBlock<'a> {
+    type Error = ();
+    fn try_from(block: &'a Structure<'a>) -> Result<Self, Self::Error> {
+        let Structure::Block { children, .. } = block else {
+            return Err(())
+        };
+        if block.name() != Some(DirectiveName::Server) {
+            return Err(())
+        }
+        let mut server = Self { block, names: Vec::new(), listen: Vec::new(), locations: LocationSet::from_block(block) };
+        for child in children {
+            match Directive::try_from(child) {
+                Ok(Directive::ServerName { names }) => {
+                    for name in names {
+                        server.names.push(ServerNamePattern::from_str(&name)?);
+                    }
+                }
+                Ok(Directive::Listen { sock_addr, is_default, .. }) => server.listen.push((sock_addr, is_default)),
+                _ => {}
+            }
+        }
+        if server.listen.is_empty() {
+            server.listen.push((SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 80), false));
+        }
+        Ok(server)
+    }
+}
+
+/// The `server` blocks of an `http` block, in file order
+#[derive(Debug, Clone, Default)]
+pub struct ServerSet<'a> {
+    pub servers: Vec<ServerBlock<'a>>,
+}
+
+impl<'a> ServerSet<'a> {
+    /// Servers that fail to parse, such as ones with a regex name the `regex` crate can't compile, are left out
+    pub fn from_block(http: &'a Structure<'a>) -> Self {
+        let mut set = Self::default();
+        if let Structure::Block { children, .. } = http {
+            set.servers.extend(children.iter().filter_map(|child| ServerBlock::try_from(child).ok()));
+        }
+        set
+    }
+
+    /// The server nginx picks for a connection to `listen_addr` with this Host header:
+    /// among servers listening there, an exact name, else the longest leading wildcard,
+    /// else the longest trailing wildcard, else the first matching regex, else the default server
+    /// Servers listening on the exact address take precedence over ones listening on a wildcard address
+    pub fn select(&self, listen_addr: SocketAddr, host: &str) -> Option<&ServerBlock<'a>> {
+        let host = normalize_host(host);
+        let exact = self.listening(|addr| addr == listen_addr);
+        let candidates = match exact.is_empty() {
+            false => exact,
+            true => self.listening(|addr| {
+                addr.port() == listen_addr.port() && addr.ip().is_unspecified() && addr.is_ipv4() == listen_addr.is_ipv4()
+            }),
+        };
+
+        let names = || candidates.iter().flat_map(|(server, _)| server.names.iter().map(move |name| (*server, name)));
+        if let Some((server, _)) = names().find(|(_, name)| matches!(name, ServerNamePattern::Exact(_)) && name.matches(&host)) {
+            return Some(server)
+        }
+        let most_specific = |wildcard: fn(&ServerNamePattern) -> bool| {
+            names()
+                .filter(|(_, name)| wildcard(name) && name.matches(&host))
+                .fold(None, |best: Option<(&ServerBlock<'a>, usize)>, (server, name)| match best {
+                    Some((_, length)) if length >= name.specificity() => best,
+                    _ => Some((server, name.specificity())),
+                })
+                .map(|(server, _)| server)
+        };
+        most_specific(|name| matches!(name, ServerNamePattern::LeadingWildcard { .. }))
+            .or_else(|| most_specific(|name| matches!(name, ServerNamePattern::TrailingWildcard(_))))
+            .or_else(|| names().find(|(_, name)| matches!(name, ServerNamePattern::Regex(_)) && name.matches(&host)).map(|(server, _)| server))
+            .or_else(|| candidates.iter().find(|(_, is_default)| *is_default).or(candidates.first()).map(|(server, _)| *server))
+    }
+
+    /// Servers with a `listen` matching `addr`, and whether it's marked `default_server`
+    fn listening(&self, addr: impl Fn(SocketAddr) -> bool) -> Vec<(&ServerBlock<'a>, bool)> {
+        self.servers
+            .iter()
+            .filter_map(|server| {
+                let listens = server.listen.iter().filter(|(a, _)| addr(*a)).collect::<Vec<_>>();
+                (!listens.is_empty()).then(|| (server, listens.iter().any(|(_, is_default)| *is_default)))
+            })
+            .collect()
+    }
+}
+
+/// Source value a `map` entry matches
+#[derive(Debug, Clone)]
+pub enum MapKey {
+    /// Lowercased, values are compared ignoring case
+    Exact(String),
+    /// `*.example.com`, `.example.com` or `www.example.*` in a map with `hostnames`
+    Hostname(ServerNamePattern),
+    /// `~regex`, `~*regex` compiles it case-insensitively
+    Regex(Regex),
+}
+
+/// `map $source $variable {}`, variables named without the `$`
+#[derive(Debug, Clone)]
+pub struct Map {
+    /// Usually a single variable, such as `$http_host`
+    pub source: String,
+    pub variable: String,
+    /// Value when no entry matches, empty when not given
+    pub default: String,
+    /// Keys are host names that may have wildcards
+    pub hostnames: bool,
+    pub volatile: bool,
+    /// Keys with their values as written, the values may contain variables and regex captures
+    pub entries: Vec<(MapKey, String)>,
+}
+
+impl TryFrom<&Structure<'_>> for Map {
+    type Error = ();
+    fn try_from(block: &Structure) -> Result<Self, Self::Error> {
+        let Structure::Block { args, children } = block else {
+            return Err(())
+        };
+        if block.name() != Some(DirectiveName::Map) || args.len() != 3 {
+            return Err(())
+        }
+        let variable = args[2].value().strip_prefix('$').ok_or(())?.to_string();
+        let mut map = Self {
+            source: args[1].value().to_string(),
+            variable,
+            default: String::new(),
+            hostnames: false,
+            volatile: false,
+            entries: Vec::new(),
+        };
+        let mut keys = Vec::new();
+        for child in children {
+            let Structure::Statement { args } = child else {
+                return Err(())
+            };
+            match args.iter().map(|a| a.value()).collect::<Vec<_>>().as_slice() {
+                ["hostnames"] => map.hostnames = true,
+                ["volatile"] => map.volatile = true,
+                // left to the include module
+                ["include", _] => {}
+                ["default", value] => map.default = value.to_string(),
+                [key, value] => keys.push((key.to_string(), value.to_string())),
+                _ => return Err(()),
+            }
+        }
+        // `hostnames` may come after the entries it applies to
+        for (key, value) in keys {
+            let key = if let Some(pattern) = key.strip_prefix("~*") {
+                MapKey::Regex(Regex::new(&format!("(?i){}", pattern)).map_err(|_| ())?)
+            } else if let Some(pattern) = key.strip_prefix('~') {
+                MapKey::Regex(Regex::new(pattern).map_err(|_| ())?)
+            } else if map.hostnames && (key.starts_with('.') || key.starts_with('*') || key.ends_with('*')) {
+                MapKey::Hostname(ServerNamePattern::from_str(&key)?)
+            } else {
+                // `\default` and the like stand for the literal words
+                MapKey::Exact(key.strip_prefix('\\').unwrap_or(&key).to_ascii_lowercase())
+            };
+            map.entries.push((key, value));
+        }
+        Ok(map)
+    }
+}
+
+impl Map {
+    /// Value template of the entry matching a source value, with the captures of a matching regex
+    /// (numbered and named), `None` when the default applies
+    /// Exact keys are tried first, then the longest leading and trailing wildcards, then regexes in order
+    pub fn lookup(&self, value: &str) -> Option<(&str, HashMap<String, String>)> {
+        let value = match self.hostnames {
+            true => value.strip_suffix('.').unwrap_or(value),
+            false => value,
+        };
+        let lowercase = value.to_ascii_lowercase();
+        let exact = self.entries.iter().find(|(key, _)| matches!(key, MapKey::Exact(exact) if *exact == lowercase));
+        let most_specific = |wildcard: fn(&ServerNamePattern) -> bool| {
+            self.entries
+                .iter()
+                .filter(|(key, _)| matches!(key, MapKey::Hostname(name) if wildcard(name) && name.matches(&lowercase)))
+                .max_by_key(|(key, _)| match key {
+                    MapKey::Hostname(name) => name.specificity(),
+                    _ => 0,
+                })
+        };
+        let hostname = || {
+            most_specific(|name| matches!(name, ServerNamePattern::LeadingWildcard { .. }))
+                .or_else(|| most_specific(|name| matches!(name, ServerNamePattern::TrailingWildcard(_))))
+        };
+        if let Some((_, template)) = exact.or_else(hostname) {
+            return Some((template, HashMap::new()))
+        }
+        self.entries.iter().find_map(|(key, template)| {
+            let MapKey::Regex(regex) = key else {
+                return None
+            };
+            let captures = regex.captures(value)?;
+            let mut named = captures
+                .iter()
+                .enumerate()
+                .filter_map(|(index, capture)| Some((index.to_string(), capture?.as_str().to_string())))
+                .collect::<HashMap<_, _>>();
+            for name in regex.capture_names().flatten() {
+                if let Some(capture) = captures.name(name) {
+                    named.insert(name.to_string(), capture.as_str().to_string());
+                }
+            }
+            Some((template.as_str(), named))
+        })
+    }
+
+    /// Value the variable takes when the variables of the source and the values have these values,
+    /// `variable` gives `None` for unknown variables, they expand to nothing
+    pub fn evaluate(&self, variable: impl Fn(&str) -> Option<String>) -> String {
+        let source = expand_template(&self.source, &variable);
+        match self.lookup(&source) {
+            Some((template, captures)) => {
+                expand_template(template, |name| captures.get(name).cloned().or_else(|| variable(name)))
+            },
+            None => expand_template(&self.default, variable),
+        }
+    }
+}
+
+/// Variables nginx gives up on evaluating, as maps may depend on one another in a cycle
+const MAX_MAP_DEPTH: usize = 16;
+
+/// The `map` blocks of an `http` block, maps may use variables other maps set
+#[derive(Debug, Clone, Default)]
+pub struct MapSet {
+    pub maps: Vec<Map>,
+}
+
+impl MapSet {
+    /// Maps that fail to parse, such as ones with a regex the `regex` crate can't compile, are left out
+    pub fn from_block(http: &Structure) -> Self {
+        let mut set = Self::default();
+        if let Structure::Block { children, .. } = http {
+            set.maps.extend(children.iter().filter_map(|child| Map::try_from(child).ok()));
+        }
+        set
+    }
+
+    /// The map setting this variable
+    pub fn get(&self, variable: &str) -> Option<&Map> {
+        self.maps.iter().find(|map| map.variable == variable)
+    }
+
+    /// Value of a map variable given the values of the other variables, `None` when no map sets it
+    /// Variables set by maps in a cycle expand to nothing
+    pub fn evaluate(&self, variable: &str, values: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+        self.evaluate_at(variable, values, 0)
+    }
+
+    fn evaluate_at(&self, variable: &str, values: &dyn Fn(&str) -> Option<String>, depth: usize) -> Option<String> {
+        let map = self.get(variable)?;
+        if depth > MAX_MAP_DEPTH {
+            return Some(String::new())
+        }
+        Some(map.evaluate(|name| self.evaluate_at(name, values, depth + 1).or_else(|| values(name))))
+    }
+}
+
+/// Substitutes the variables of a template, ones `variable` doesn't know expand to nothing
+fn expand_template(template: &str, variable: impl Fn(&str) -> Option<String>) -> String {
+    let Ok(segments) = parse_template(&[template]) else {
+        return template.to_string()
+    };
+    segments
+        .iter()
+        .map(|segment| match segment {
+            TemplateSegment::Literal(text) => text.clone(),
+            TemplateSegment::Variable(name) => variable(name).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Byte size as written in configs: `512`, `8k`, `16m`, `1g`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Size(pub u64);
+
+impl Size {
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for Size {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (digits, multiplier) = match s.as_bytes().last().ok_or(())? {
+            b'k' | b'K' => (&s[..s.len() - 1], 1 << 10),
+            b'm' | b'M' => (&s[..s.len() - 1], 1 << 20),
+            b'g' | b'G' => (&s[..s.len() - 1], 1 << 30),
+            _ => (s, 1),
+        };
+        let value = digits.parse::<u64>().map_err(|_| ())?;
+        Ok(Self(value.checked_mul(multiplier).ok_or(())?))
+    }
+}
+
+/// Time interval as written in configs: `30`, `500ms`, `1h30m`, `7d`
+/// Bare numbers are seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NginxTime(pub Duration);
+
+impl NginxTime {
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl FromStr for NginxTime {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut total = Duration::ZERO;
+        let mut chars = s.trim().chars().filter(|c| !c.is_whitespace()).peekable();
+        if chars.peek().is_none() {
+            return Err(());
+        }
+        while chars.peek().is_some() {
+            let mut value = 0u64;
+            let mut has_digits = false;
+            while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                value = value.checked_mul(10).and_then(|v| v.checked_add(digit as u64)).ok_or(())?;
+                has_digits = true;
+                chars.next();
+            }
+            if !has_digits {
+                return Err(());
+            }
+            let unit = match chars.next() {
+                None => 1000,
+                Some('m') if chars.peek() == Some(&'s') => {
+                    chars.next();
+                    1
+                }
+                Some('s') => 1000,
+                Some('m') => 60 * 1000,
+                Some('h') => 60 * 60 * 1000,
+                Some('d') => 24 * 60 * 60 * 1000,
+                Some('w') => 7 * 24 * 60 * 60 * 1000,
+                Some('M') => 30 * 24 * 60 * 60 * 1000,
+                Some('y') => 365 * 24 * 60 * 60 * 1000,
+                Some(_) => return Err(()),
+            };
+            total = total.checked_add(Duration::from_millis(value.checked_mul(unit).ok_or(())?)).ok_or(())?;
+        }
+        Ok(Self(total))
+    }
+}
+
+/// `listen` address in any of the `addr:port`, `port`, `addr`, `*:port` and `[::]:port` forms,
+/// a missing address means all interfaces and a missing port means 80
+pub(crate) fn parse_listen_addr(s: &str) -> Result<SocketAddr, ()> {
+    if let Ok(addr) = SocketAddr::from_str(s) {
+        return Ok(addr)
+    }
+    let any = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+    if let Ok(port) = s.strip_prefix("*:").unwrap_or(s).parse::<u16>() {
+        return Ok(SocketAddr::new(any, port))
+    }
+    let ip = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')).unwrap_or(s);
+    let ip = IpAddr::from_str(ip).map_err(|_| ())?;
+    Ok(SocketAddr::new(ip, 80))
+}
+
+/// Whether HTTP/2 is on for a server block, either through the modern `http2 on;`
+/// or the deprecated `listen ... http2` parameter
+pub fn http2_enabled(server: &Structure) -> bool {
+    let Structure::Block { children, .. } = server else {
+        return false;
+    };
+    let mut enabled = false;
+    for child in children {
+        match Directive::try_from(child) {
+            Ok(Directive::Http2 { enabled: on }) => enabled = on,
+            Ok(Directive::Listen { is_http2: true, .. }) => return true,
+            _ => {}
+        }
+    }
+    enabled
+}
+
+/// HTTP/3 rollout state of a server block
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Http3Status {
+    /// Has a `listen ... quic` socket
+    pub quic_listen: bool,
+    /// `http3` isn't switched off (it defaults to on)
+    pub enabled: bool,
+    /// Sends the conventional `Alt-Svc: h3=...` header so clients discover HTTP/3
+    pub advertised: bool,
+}
+
+impl Http3Status {
+    pub fn from_server(server: &Structure) -> Self {
+        let mut status = Self { enabled: true, ..Self::default() };
+        let Structure::Block { children, .. } = server else {
+            return Self::default();
+        };
+        for child in children {
+            match Directive::try_from(child) {
+                Ok(Directive::Listen { is_http3: true, .. }) => status.quic_listen = true,
+                Ok(Directive::Http3 { enabled }) => status.enabled = enabled,
+                Ok(Directive::AddHeader { name, value, .. }) if name.eq_ignore_ascii_case("Alt-Svc") => {
+                    status.advertised |= value.contains("h3");
+                }
+                _ => {}
+            }
+        }
+        status.enabled &= status.quic_listen;
+        status
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.quic_listen && self.enabled && self.advertised
+    }
+}
+
+/// Argument that accepts variables, either a literal or an expression evaluated per request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dynamic<T> {
+    Literal(T),
+    Expression(String),
+}
+
+impl<T: FromStr> FromStr for Dynamic<T> {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('$') {
+            return Ok(Self::Expression(s.to_string()))
+        }
+        T::from_str(s).map(Self::Literal).map_err(|_| ())
+    }
+}
+
+/// Strict `on`/`off` switch value
+pub(crate) fn switch(s: &str) -> Result<bool, ()> {
+    match s {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(()),
+    }
+}
+
+fn parse_switch(token: Option<&Token>) -> Result<bool, ()> {
+    switch(token.ok_or(())?.value())
+}
+
+fn parse_size(token: Option<&Token>) -> Result<Size, ()> {
+    Size::from_str(token.ok_or(())?.value())
+}
+
+pub(crate) fn parse_time(token: Option<&Token>) -> Result<Duration, ()> {
+    NginxTime::from_str(token.ok_or(())?.value()).map(|t| t.duration())
+}
+
+fn parse_number<T: FromStr>(token: Option<&Token>) -> Result<T, ()> {
+    token.ok_or(())?.value().parse::<T>().map_err(|_| ())
+}
+
+/// Conditions listed in `proxy_next_upstream`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NextUpstreamCondition {
+    Error,
+    Timeout,
+    InvalidHeader,
+    Http500,
+    Http502,
+    Http503,
+    Http504,
+    Http403,
+    Http404,
+    Http429,
+    NonIdempotent,
+}
+
+impl FromStr for NextUpstreamCondition {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Self::Error),
+            "timeout" => Ok(Self::Timeout),
+            "invalid_header" => Ok(Self::InvalidHeader),
+            "http_500" => Ok(Self::Http500),
+            "http_502" => Ok(Self::Http502),
+            "http_503" => Ok(Self::Http503),
+            "http_504" => Ok(Self::Http504),
+            "http_403" => Ok(Self::Http403),
+            "http_404" => Ok(Self::Http404),
+            "http_429" => Ok(Self::Http429),
+            "non_idempotent" => Ok(Self::NonIdempotent),
+            _ => Err(()),
+        }
+    }
+}
+
+/// `proxy_next_upstream`, an empty condition list stands for `off`
+#[derive(Debug, Clone, Default)]
+pub struct NextUpstream {
+    pub conditions: Vec<NextUpstreamCondition>,
+}
+
+impl NextUpstream {
+    pub fn is_off(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    pub fn retries_on(&self, condition: NextUpstreamCondition) -> bool {
+        self.conditions.contains(&condition)
+    }
+
+    /// POST/LOCK/PATCH requests are passed to the next server as well,
+    /// which may replay side effects on the upstream
+    pub fn retries_non_idempotent(&self) -> bool {
+        self.retries_on(NextUpstreamCondition::NonIdempotent)
+    }
+}
+
+/// Target of the `*_pass` directives that take an address instead of a URL:
+/// `127.0.0.1:9000`, `backend`, `unix:/run/app.sock`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassTarget {
+    Unix(PathBuf),
+    Address(String),
+}
+
+impl FromStr for PassTarget {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("unix:") {
+            Some("") => Err(()),
+            Some(path) => Ok(Self::Unix(PathBuf::from(path))),
+            None if s.is_empty() => Err(()),
+            None => Ok(Self::Address(s.to_string())),
+        }
+    }
+}
+
+/// `gzip_proxied` conditions for compressing responses to proxied requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GzipProxied {
+    Expired,
+    NoCache,
+    NoStore,
+    Private,
+    NoLastModified,
+    NoEtag,
+    Auth,
+    Any,
+}
+
+impl FromStr for GzipProxied {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "expired" => Ok(Self::Expired),
+            "no-cache" => Ok(Self::NoCache),
+            "no-store" => Ok(Self::NoStore),
+            "private" => Ok(Self::Private),
+            "no_last_modified" => Ok(Self::NoLastModified),
+            "no_etag" => Ok(Self::NoEtag),
+            "auth" => Ok(Self::Auth),
+            "any" => Ok(Self::Any),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Serving of precompressed files (`gzip_static`, `brotli_static`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaticCompression {
+    Off,
+    On,
+    Always,
+}
+
+impl FromStr for StaticCompression {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "on" => Ok(Self::On),
+            "always" => Ok(Self::Always),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Protocol versions accepted by `ssl_protocols` and `proxy_ssl_protocols`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TlsProtocol {
+    SslV2,
+    SslV3,
+    TlsV1,
+    TlsV1_1,
+    TlsV1_2,
+    TlsV1_3,
+}
+
+impl TlsProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SslV2 => "SSLv2",
+            Self::SslV3 => "SSLv3",
+            Self::TlsV1 => "TLSv1",
+            Self::TlsV1_1 => "TLSv1.1",
+            Self::TlsV1_2 => "TLSv1.2",
+            Self::TlsV1_3 => "TLSv1.3",
+        }
+    }
+
+    /// Anything older than TLSv1.2
+    pub fn is_legacy(&self) -> bool {
+        *self < Self::TlsV1_2
+    }
+}
+
+impl FromStr for TlsProtocol {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SSLv2" => Ok(Self::SslV2),
+            "SSLv3" => Ok(Self::SslV3),
+            "TLSv1" => Ok(Self::TlsV1),
+            "TLSv1.1" => Ok(Self::TlsV1_1),
+            "TLSv1.2" => Ok(Self::TlsV1_2),
+            "TLSv1.3" => Ok(Self::TlsV1_3),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsProtocols {
+    pub protocols: Vec<TlsProtocol>,
+}
+
+impl TlsProtocols {
+    pub fn allows(&self, protocol: TlsProtocol) -> bool {
+        self.protocols.contains(&protocol)
+    }
+
+    pub fn allows_legacy(&self) -> bool {
+        self.protocols.iter().any(|p| p.is_legacy())
+    }
+}
+
+fn parse_protocols(args: &[Token]) -> Result<TlsProtocols, ()> {
+    let mut protocols = TlsProtocols::default();
+    for arg in args {
+        let protocol = TlsProtocol::from_str(arg.value())?;
+        if !protocols.allows(protocol) {
+            protocols.protocols.push(protocol);
+        }
+    }
+    if protocols.protocols.is_empty() {
+        return Err(())
+    }
+    Ok(protocols)
+}
+
+/// One element of an OpenSSL cipher list as used by `ssl_ciphers`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CipherItem {
+    /// Cipher suite or alias, e.g. `ECDHE-RSA-AES128-GCM-SHA256`, `HIGH`, `EECDH+AESGCM`
+    Include(String),
+    /// `!aNULL`, permanently excluded
+    Exclude(String),
+    /// `-MD5`, removed but may be added again later in the list
+    Remove(String),
+    /// `+RC4`, moved to the end of the list
+    MoveToEnd(String),
+    /// `@STRENGTH`, `@SECLEVEL=2`
+    Command(String),
+}
+
+impl FromStr for CipherItem {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (constructor, name): (fn(String) -> Self, &str) = match s.split_at_checked(1).ok_or(())? {
+            ("!", name) => (Self::Exclude, name),
+            ("-", name) => (Self::Remove, name),
+            ("+", name) => (Self::MoveToEnd, name),
+            ("@", name) => (Self::Command, name),
+            _ => (Self::Include, s),
+        };
+        if name.is_empty() {
+            return Err(())
+        }
+        Ok(constructor(name.to_string()))
+    }
+}
+
+/// Ordered OpenSSL cipher list, split on `:` (and the also accepted `,` and spaces)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CipherList {
+    pub items: Vec<CipherItem>,
+}
+
+impl FromStr for CipherList {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let items = s
+            .split([':', ',', ' '])
+            .filter(|item| !item.is_empty())
+            .map(CipherItem::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if items.is_empty() {
+            return Err(())
+        }
+        Ok(Self { items })
+    }
+}
+
+impl CipherList {
+    /// Names explicitly excluded with `!`
+    pub fn excluded(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().filter_map(|item| match item {
+            CipherItem::Exclude(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Names included as written, in preference order
+    pub fn included(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().filter_map(|item| match item {
+            CipherItem::Include(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// One `ssl_session_cache` entry, `builtin` and `shared` caches may be combined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionCache {
+    Off,
+    None,
+    /// OpenSSL builtin cache, size is in sessions rather than bytes
+    Builtin(Option<u32>),
+    Shared {
+        name: String,
+        size: Size,
+    },
+}
+
+impl FromStr for SessionCache {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(':').collect::<Vec<_>>().as_slice() {
+            ["off"] => Ok(Self::Off),
+            ["none"] => Ok(Self::None),
+            ["builtin"] => Ok(Self::Builtin(None)),
+            ["builtin", sessions] => Ok(Self::Builtin(Some(sessions.parse().map_err(|_| ())?))),
+            ["shared", name, size] if !name.is_empty() => Ok(Self::Shared {
+                name: name.to_string(),
+                size: Size::from_str(size)?,
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateUnit {
+    PerSecond,
+    PerMinute,
+}
+
+/// Request rate as written in `limit_req_zone`: `10r/s`, `30r/m`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub requests: u32,
+    pub unit: RateUnit,
+}
+
+impl Rate {
+    pub fn per_second(&self) -> f64 {
+        match self.unit {
+            RateUnit::PerSecond => self.requests as f64,
+            RateUnit::PerMinute => self.requests as f64 / 60.0,
+        }
+    }
+}
+
+impl Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.unit {
+            RateUnit::PerSecond => write!(f, "{}r/s", self.requests),
+            RateUnit::PerMinute => write!(f, "{}r/m", self.requests),
+        }
+    }
+}
+
+impl FromStr for Rate {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (requests, unit) = if let Some(requests) = s.strip_suffix("r/s") {
+            (requests, RateUnit::PerSecond)
+        } else if let Some(requests) = s.strip_suffix("r/m") {
+            (requests, RateUnit::PerMinute)
+        } else {
+            return Err(())
+        };
+        let requests = requests.parse::<u32>().map_err(|_| ())?;
+        Ok(Self { requests, unit })
+    }
+}
+
+/// `zone=name:size` parameter of the `*_zone` directives
+fn parse_zone(s: &str) -> Result<(String, Size), ()> {
+    let (name, size) = s.strip_prefix("zone=").ok_or(())?.split_once(':').ok_or(())?;
+    if name.is_empty() {
+        return Err(())
+    }
+    Ok((name.to_string(), Size::from_str(size)?))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitConnZone {
+    pub key: String,
+    pub zone: String,
+    pub size: Size,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitReqZone {
+    /// Expression requests are keyed on, e.g. `$binary_remote_addr`
+    pub key: String,
+    pub zone: String,
+    pub size: Size,
+    pub rate: Rate,
+    pub sync: bool,
+}
+
+/// How excess requests within the burst are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReqDelay {
+    /// Default, excess requests are delayed to match the rate
+    Delayed,
+    /// `nodelay`, excess requests within the burst are served immediately
+    NoDelay,
+    /// `delay=N`, the first N excess requests are served immediately
+    After(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitReq {
+    pub zone: String,
+    pub burst: u32,
+    pub delay: ReqDelay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Allow,
+    Deny,
+}
+
+/// Address matched by `allow`/`deny`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessTarget {
+    All,
+    /// `unix:`, clients connected over a UNIX-domain socket
+    Unix,
+    Net(IpNet),
+}
+
+impl AccessTarget {
+    pub fn matches(&self, ip: IpAddr) -> bool {
+        match self {
+            Self::All => true,
+            Self::Unix => false,
+            Self::Net(net) => net.contains(&ip),
+        }
+    }
+}
+
+impl FromStr for AccessTarget {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "unix:" => Ok(Self::Unix),
+            _ => IpNet::from_str(s)
+                .or_else(|_| IpAddr::from_str(s).map(IpNet::from))
+                .map(Self::Net)
+                .map_err(|_| ()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessRule {
+    pub access: Access,
+    pub target: AccessTarget,
+}
+
+/// The `allow`/`deny` rules of one context, in file order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessList {
+    pub rules: Vec<AccessRule>,
+}
+
+impl AccessList {
+    pub fn from_block(block: &Structure) -> Self {
+        let mut list = Self::default();
+        if let Structure::Block { children, .. } = block {
+            for child in children {
+                match Directive::try_from(child) {
+                    Ok(Directive::Allow { target }) => list.rules.push(AccessRule { access: Access::Allow, target }),
+                    Ok(Directive::Deny { target }) => list.rules.push(AccessRule { access: Access::Deny, target }),
+                    _ => {}
+                }
+            }
+        }
+        list
+    }
+
+    /// First matching rule wins, clients matching no rule are allowed
+    pub fn evaluate(&self, ip: IpAddr) -> Access {
+        self.rules
+            .iter()
+            .find(|rule| rule.target.matches(ip))
+            .map(|rule| rule.access)
+            .unwrap_or(Access::Allow)
+    }
+}
+
+/// `satisfy`, whether access and auth modules must all pass or any one suffices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SatisfyMode {
+    All,
+    Any,
+}
+
+/// Variable defined inside a `geoip2` block:
+/// `$geoip2_country_code default=US source=$remote_addr country iso_code;`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoIp2Variable {
+    pub variable: String,
+    pub default: Option<String>,
+    /// Variable holding the address to look up, `$remote_addr` when not set
+    pub source: Option<String>,
+    /// Path to the value inside the database record
+    pub path: Vec<String>,
+}
+
+/// Destination of `access_log` and `error_log`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogTarget {
+    File(PathBuf),
+    Stderr,
+    /// `syslog:server=address[,parameter=value...]`
+    Syslog {
+        server: String,
+        /// `facility`, `severity`, `tag` and `nohostname` (with an empty value)
+        parameters: Vec<(String, String)>,
+    },
+    /// `memory:size`, cyclic in-memory buffer for debugging
+    Memory(Size),
+}
+
+impl FromStr for LogTarget {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "stderr" {
+            return Ok(Self::Stderr)
+        }
+        if let Some(size) = s.strip_prefix("memory:") {
+            return Ok(Self::Memory(Size::from_str(size)?))
+        }
+        if let Some(parameters) = s.strip_prefix("syslog:") {
+            let mut server = None;
+            let mut rest = Vec::new();
+            for parameter in parameters.split(',') {
+                let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+                match name {
+                    "server" => server = Some(value.to_string()),
+                    _ => rest.push((name.to_string(), value.to_string())),
+                }
+            }
+            let server = server.filter(|s| !s.is_empty()).ok_or(())?;
+            return Ok(Self::Syslog { server, parameters: rest })
+        }
+        if s.is_empty() {
+            return Err(())
+        }
+        Ok(Self::File(PathBuf::from(s)))
+    }
+}
+
+/// `error_log` severity, ordered from most to least verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warn,
+    Error,
+    Crit,
+    Alert,
+    Emerg,
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "notice" => Ok(Self::Notice),
+            "warn" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            "crit" => Ok(Self::Crit),
+            "alert" => Ok(Self::Alert),
+            "emerg" => Ok(Self::Emerg),
+            _ => Err(()),
+        }
+    }
+}
+
+/// All `error_log` directives of one context, nginx writes to each of them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorLogs {
+    /// Targets with their level, `error` when not given
+    pub logs: Vec<(LogTarget, LogLevel)>,
+}
+
+impl ErrorLogs {
+    pub fn from_block(block: &Structure) -> Self {
+        let mut logs = Self::default();
+        if let Structure::Block { children, .. } = block {
+            for child in children {
+                if let Ok(Directive::ErrorLog { target, level }) = Directive::try_from(child) {
+                    logs.logs.push((target, level.unwrap_or(LogLevel::Error)));
+                }
+            }
+        }
+        logs
+    }
+
+    /// Targets that record a message of the given severity
+    pub fn targets_for(&self, level: LogLevel) -> impl Iterator<Item = &LogTarget> {
+        self.logs
+            .iter()
+            .filter(move |(_, threshold)| level >= *threshold)
+            .map(|(target, _)| target)
+    }
+}
+
+/// `open_file_cache max=N [inactive=time]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenFileCache {
+    pub max: u32,
+    /// 60s when not given
+    pub inactive: Duration,
+}
+
+/// `open_log_file_cache` settings, missing parameters take the nginx defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenLogFileCache {
+    pub max: u32,
+    pub inactive: Duration,
+    pub min_uses: u32,
+    pub valid: Duration,
+}
+
+impl OpenLogFileCache {
+    fn parse(args: &[Token]) -> Result<Self, ()> {
+        let mut max = None;
+        let mut cache = Self { max: 0, inactive: Duration::from_secs(10), min_uses: 1, valid: Duration::from_secs(60) };
+        for arg in args {
+            let (name, value) = arg.value().split_once('=').ok_or(())?;
+            match name {
+                "max" => max = Some(value.parse().map_err(|_| ())?),
+                "inactive" => cache.inactive = NginxTime::from_str(value)?.duration(),
+                "min_uses" => cache.min_uses = value.parse().map_err(|_| ())?,
+                "valid" => cache.valid = NginxTime::from_str(value)?.duration(),
+                _ => return Err(()),
+            }
+        }
+        cache.max = max.ok_or(())?;
+        Ok(cache)
+    }
+}
+
+/// `escape=` parameter of `log_format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogEscape {
+    Default,
+    Json,
+    None,
+}
+
+/// Piece of a string with embedded variables, such as a `log_format` or `secure_link_md5` expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSegment {
+    Literal(String),
+    /// Variable name without the `$` (or `${...}`)
+    Variable(String),
+}
+
+/// Splits the parts of a string with variables into literals and variables, parts are joined
+pub(crate) fn parse_template(parts: &[&str]) -> Result<Vec<TemplateSegment>, ()> {
+    let mut template = Vec::new();
+    let mut literal = String::new();
+    for part in parts {
+        let mut chars = part.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => literal.extend(chars.next()),
+                '$' => {
+                    let name = if chars.peek() == Some(&'{') {
+                        chars.next();
+                        chars.by_ref().take_while(|c| *c != '}').collect::<String>()
+                    } else {
+                        let mut name = String::new();
+                        while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                            name.push(c);
+                        }
+                        name
+                    };
+                    if name.is_empty() {
+                        return Err(())
+                    }
+                    if !literal.is_empty() {
+                        template.push(TemplateSegment::Literal(std::mem::take(&mut literal)));
+                    }
+                    template.push(TemplateSegment::Variable(name));
+                }
+                c => literal.push(c),
+            }
+        }
+    }
+    if !literal.is_empty() {
+        template.push(TemplateSegment::Literal(literal));
+    }
+    Ok(template)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFormat {
+    pub name: String,
+    pub escape: LogEscape,
+    /// All quoted parts of the format joined, in order
+    pub template: Vec<TemplateSegment>,
+}
+
+impl LogFormat {
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.template.iter().filter_map(|segment| match segment {
+            TemplateSegment::Variable(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// `variable` may be given with or without the leading `$`
+    pub fn contains_variable(&self, variable: &str) -> bool {
+        let variable = variable.strip_prefix('$').unwrap_or(variable);
+        self.variables().any(|v| v == variable)
+    }
+
+    /// Regex matching one log line written with this format, with a named group per variable
+    /// (repeated variables get `_2`, `_3`, ... suffixes)
+    pub fn to_regex(&self) -> Regex {
+        let mut pattern = String::from("^");
+        let mut seen = Vec::<&str>::new();
+        for segment in &self.template {
+            match segment {
+                TemplateSegment::Literal(text) => pattern.push_str(&regex::escape(text)),
+                TemplateSegment::Variable(name) => {
+                    seen.push(name);
+                    let count = seen.iter().filter(|n| *n == name).count();
+                    match count {
+                        1 => pattern.push_str(&format!("(?P<{}>.*?)", name)),
+                        _ => pattern.push_str(&format!("(?P<{}_{}>.*?)", name, count)),
+                    }
+                }
+            }
+        }
+        pattern.push('$');
+        Regex::new(&pattern).expect("variable names are valid group names")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddHeader {
+    pub name: String,
+    pub value: String,
+    pub always: bool,
+}
+
+/// The `add_header` directives of one context
+///
+/// A context inherits its parent's headers only if it has no `add_header` of its own,
+/// a single `add_header` in a location drops every header set at the server level
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddHeaders {
+    pub headers: Vec<AddHeader>,
+}
+
+impl AddHeaders {
+    pub fn from_block(block: &Structure) -> Self {
+        let mut headers = Self::default();
+        if let Structure::Block { children, .. } = block {
+            for child in children {
+                if let Ok(Directive::AddHeader { name, value, always }) = Directive::try_from(child) {
+                    headers.headers.push(AddHeader { name, value, always });
+                }
+            }
+        }
+        headers
+    }
+
+    pub fn inherit(self, parent: &AddHeaders) -> Self {
+        match self.headers.is_empty() {
+            true => parent.clone(),
+            false => self,
+        }
+    }
+
+    /// Headers in effect in the innermost of nested contexts, outermost first
+    /// (e.g. `[http, server, location]`)
+    pub fn effective(contexts: &[&Structure]) -> Self {
+        contexts
+            .iter()
+            .fold(Self::default(), |parent, context| Self::from_block(context).inherit(&parent))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AddHeader> {
+        self.headers.iter().find(|h| h.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// `expires` value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expires {
+    Off,
+    /// `Expires: Thu, 01 Jan 1970 00:00:01 GMT` and `Cache-Control: no-cache`
+    Epoch,
+    /// Ten years ahead
+    Max,
+    /// Relative to the request time, or to the file modification time with `modified`
+    After {
+        offset: Duration,
+        negative: bool,
+        modified: bool,
+    },
+    /// `@15h30m`, the next occurrence of that time of day
+    TimeOfDay(Duration),
+    Expression(String),
+}
+
+impl Expires {
+    fn parse(args: &[Token]) -> Result<Self, ()> {
+        let (modified, value) = match args {
+            [value] => (false, value.value()),
+            [modifier, value] if modifier.value() == "modified" => (true, value.value()),
+            _ => return Err(()),
+        };
+        let expires = match value {
+            "off" if !modified => Self::Off,
+            "epoch" if !modified => Self::Epoch,
+            "max" if !modified => Self::Max,
+            value if value.contains('$') => Self::Expression(value.to_string()),
+            value => match value.strip_prefix('@') {
+                Some(time) if !modified => Self::TimeOfDay(NginxTime::from_str(time)?.duration()),
+                Some(_) => return Err(()),
+                None => {
+                    let (negative, time) = match value.strip_prefix('-') {
+                        Some(time) => (true, time),
+                        None => (false, value.strip_prefix('+').unwrap_or(value)),
+                    };
+                    let offset = NginxTime::from_str(time)?.duration();
+                    Self::After { offset, negative, modified }
+                }
+            },
+        };
+        Ok(expires)
+    }
+
+    /// `Cache-Control` header nginx sends, `None` when it leaves the header alone
+    /// (`off`, variables, and times of day which depend on the clock)
+    pub fn cache_control(&self) -> Option<String> {
+        match self {
+            Self::Epoch => Some("no-cache".to_string()),
+            Self::Max => Some("max-age=315360000".to_string()),
+            Self::After { negative: true, .. } => Some("no-cache".to_string()),
+            Self::After { offset, modified: false, .. } => Some(format!("max-age={}", offset.as_secs())),
+            _ => None,
+        }
+    }
+}
+
+/// `if_modified_since`, how `If-Modified-Since` is compared with the file modification time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfModifiedSince {
+    /// The header is ignored
+    Off,
+    Exact,
+    Before,
+}
+
+/// Extension to MIME type map from a `types { ... }` block, extensions are lowercased
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MimeTypes {
+    pub types: HashMap<String, String>,
+}
+
+impl MimeTypes {
+    pub fn lookup(&self, extension: &str) -> Option<&str> {
+        self.types.get(&extension.to_ascii_lowercase()).map(|t| t.as_str())
+    }
+}
+
+/// Content-Type nginx would send for `file` in the innermost of nested contexts, outermost first
+/// Both `types` and `default_type` are inherited until a context sets its own
+pub fn content_type(contexts: &[&Structure], file: &str) -> String {
+    let mut types = None;
+    let mut default_type = "text/plain".to_string();
+    for context in contexts {
+        let Structure::Block { children, .. } = context else {
+            continue;
+        };
+        for child in children {
+            match Directive::try_from(child) {
+                Ok(Directive::Types(map)) => types = Some(map),
+                Ok(Directive::DefaultType { mime_type }) => default_type = mime_type,
+                _ => {}
+            }
+        }
+    }
+    let name = file.rsplit('/').next().unwrap_or(file);
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext);
+    extension
+        .and_then(|ext| types.as_ref()?.lookup(ext).map(|t| t.to_string()))
+        .unwrap_or(default_type)
+}
+
+/// `aio` mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aio {
+    Off,
+    On,
+    /// `threads[=pool]`, the `default` pool when not named
+    Threads(Option<String>),
+}
+
+/// Name server of the `resolver` directive, port 53 unless given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolverAddr {
+    Ip(SocketAddr),
+    /// Hostname, itself resolved once at startup
+    Host { name: String, port: u16 },
+}
+
+impl FromStr for ResolverAddr {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(addr) = SocketAddr::from_str(s) {
+            return Ok(Self::Ip(addr))
+        }
+        let ip = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')).unwrap_or(s);
+        if let Ok(ip) = IpAddr::from_str(ip) {
+            return Ok(Self::Ip(SocketAddr::new(ip, 53)))
+        }
+        let (name, port) = match s.rsplit_once(':') {
+            Some((name, port)) => (name, port.parse::<u16>().map_err(|_| ())?),
+            None => (s, 53),
+        };
+        if name.is_empty() || name.contains(['[', ']', ':']) {
+            return Err(())
+        }
+        Ok(Self::Host { name: name.to_string(), port })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerProcesses {
+    /// One worker per available CPU core
+    Auto,
+    Count(u32),
+}
+
+/// Connection processing method of `use`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventMethod {
+    Select,
+    Poll,
+    Kqueue,
+    Epoll,
+    DevPoll,
+    Eventport,
+}
+
+impl FromStr for EventMethod {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "select" => Ok(Self::Select),
+            "poll" => Ok(Self::Poll),
+            "kqueue" => Ok(Self::Kqueue),
+            "epoll" => Ok(Self::Epoll),
+            "/dev/poll" => Ok(Self::DevPoll),
+            "eventport" => Ok(Self::Eventport),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Worker and `events { ... }` settings of the main context, defaults where unset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventsConfig {
+    pub worker_processes: WorkerProcesses,
+    pub worker_connections: u32,
+    pub worker_rlimit_nofile: Option<u32>,
+    pub multi_accept: bool,
+    pub method: Option<EventMethod>,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            worker_processes: WorkerProcesses::Count(1),
+            worker_connections: 512,
+            worker_rlimit_nofile: None,
+            multi_accept: false,
+            method: None,
+        }
+    }
+}
+
+impl EventsConfig {
+    /// Reads the main context, i.e. the root returned by `Structure::parse`
+    pub fn from_main(main: &Structure) -> Self {
+        let mut config = Self::default();
+        let Structure::Block { children, .. } = main else {
+            return config;
+        };
+        for child in children {
+            match (Directive::try_from(child), child) {
+                (Ok(Directive::WorkerProcesses { processes }), _) => config.worker_processes = processes,
+                (Ok(Directive::WorkerRlimitNofile { limit }), _) => config.worker_rlimit_nofile = Some(limit),
+                (_, Structure::Block { args, children }) if args.first().is_some_and(|s| s.value() == "events") => {
+                    for child in children {
+                        match Directive::try_from(child) {
+                            Ok(Directive::WorkerConnections { connections }) => config.worker_connections = connections,
+                            Ok(Directive::MultiAccept { enabled }) => config.multi_accept = enabled,
+                            Ok(Directive::Use { method }) => config.method = Some(method),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        config
+    }
+
+    pub fn workers(&self, available_cpus: u32) -> u32 {
+        match self.worker_processes {
+            WorkerProcesses::Auto => available_cpus,
+            WorkerProcesses::Count(count) => count,
+        }
+    }
+
+    /// Upper bound of simultaneous connections (client and upstream ones alike),
+    /// each worker is further capped by its open file limit
+    pub fn max_connections(&self, available_cpus: u32) -> u64 {
+        let per_worker = match self.worker_rlimit_nofile {
+            Some(limit) => self.worker_connections.min(limit),
+            None => self.worker_connections,
+        };
+        self.workers(available_cpus) as u64 * per_worker as u64
+    }
+}
+
+/// True when the main context makes workers run as root (`user root;` or uid 0),
+/// without a `user` directive workers run as `nobody`
+pub fn runs_as_root(main: &Structure) -> bool {
+    let Structure::Block { children, .. } = main else {
+        return false;
+    };
+    children.iter().any(|child| {
+        matches!(
+            Directive::try_from(child),
+            Ok(Directive::User { name, .. }) if name == "root" || name == "0"
+        )
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoindexFormat {
+    Html,
+    Xml,
+    Json,
+    Jsonp,
+}
+
+/// One entry of `valid_referers`
+#[derive(Debug, Clone)]
+pub enum RefererPattern {
+    /// The `Referer` header is missing
+    None,
+    /// The header is present but its value was stripped of the scheme by a proxy or firewall
+    Blocked,
+    /// Host matches one of the server names
+    ServerNames,
+    /// `example.com`, `*.example.com` or `www.example.*`, optionally followed by a URI prefix
+    Host { host: String, uri: Option<String> },
+    /// `~regex`, matched case insensitively against the text after `http://` or `https://`
+    Regex(Regex),
+}
+
+impl FromStr for RefererPattern {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "blocked" => Ok(Self::Blocked),
+            "server_names" => Ok(Self::ServerNames),
+            _ => match s.strip_prefix('~') {
+                Some(pattern) => regex::RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map(Self::Regex)
+                    .map_err(|_| ()),
+                None => {
+                    let (host, uri) = match s.find('/') {
+                        Some(at) => (&s[..at], Some(s[at..].to_string())),
+                        None => (s, None),
+                    };
+                    if host.is_empty() {
+                        return Err(())
+                    }
+                    Ok(Self::Host { host: host.to_ascii_lowercase(), uri })
+                }
+            },
+        }
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    if let Some(suffix) = pattern.strip_prefix('*') {
+        return host.ends_with(suffix);
+    }
+    if let Some(prefix) = pattern.strip_suffix('*') {
+        return host.starts_with(prefix);
+    }
+    pattern == host
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidReferers {
+    pub patterns: Vec<RefererPattern>,
+}
+
+impl ValidReferers {
+    /// Whether a request with this `Referer` header (if any) is considered valid
+    /// `server_names` are the names of the server handling the request
+    pub fn is_valid(&self, referer: Option<&str>, server_names: &[&str]) -> bool {
+        let Some(referer) = referer else {
+            return self.patterns.iter().any(|p| matches!(p, RefererPattern::None));
+        };
+        let lowercase = referer.to_ascii_lowercase();
+        let scheme_length = match lowercase {
+            _ if lowercase.starts_with("http://") => 7,
+            _ if lowercase.starts_with("https://") => 8,
+            _ => return self.patterns.iter().any(|p| matches!(p, RefererPattern::Blocked)),
+        };
+        let stripped = &referer[scheme_length..];
+        let (host, uri) = match stripped.find('/') {
+            Some(at) => (&stripped[..at], &stripped[at..]),
+            None => (stripped, ""),
+        };
+        let host = host.to_ascii_lowercase();
+        self.patterns.iter().any(|pattern| match pattern {
+            RefererPattern::None | RefererPattern::Blocked => false,
+            RefererPattern::ServerNames => server_names.iter().any(|name| host_matches(&name.to_ascii_lowercase(), &host)),
+            RefererPattern::Host { host: pattern, uri: prefix } => {
+                host_matches(pattern, &host) && prefix.as_ref().is_none_or(|prefix| uri.starts_with(prefix.as_str()))
+            }
+            RefererPattern::Regex(regex) => regex.is_match(stripped),
+        })
+    }
+
+    /// Value of `$invalid_referer`, empty for valid referers and "1" otherwise
+    pub fn invalid_referer(&self, referer: Option<&str>, server_names: &[&str]) -> &'static str {
+        match self.is_valid(referer, server_names) {
+            true => "",
+            false => "1",
+        }
+    }
+}
+
+/// `server` entry of an `upstream` block, shared by the http and stream modules:
+/// `server 10.0.0.1:5432 weight=2 max_fails=3 fail_timeout=30s backup;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamServer {
+    pub target: PassTarget,
+    pub weight: u32,
+    pub max_fails: u32,
+    pub fail_timeout: Duration,
+    pub max_conns: Option<u32>,
+    pub backup: bool,
+    pub down: bool,
+}
+
+/// Parses the arguments following `server`
+impl TryFrom<&[&str]> for UpstreamServer {
+    type Error = ();
+    fn try_from(args: &[&str]) -> Result<Self, Self::Error> {
+        let (target, params) = args.split_first().ok_or(())?;
+        let mut server = Self {
+            target: PassTarget::from_str(target)?,
+            weight: 1,
+            max_fails: 1,
+            fail_timeout: Duration::from_secs(10),
+            max_conns: None,
+            backup: false,
+            down: false,
+        };
+        for param in params {
+            match param.split_once('=') {
+                Some(("weight", value)) => server.weight = value.parse().map_err(|_| ())?,
+                Some(("max_fails", value)) => server.max_fails = value.parse().map_err(|_| ())?,
+                Some(("fail_timeout", value)) => server.fail_timeout = NginxTime::from_str(value)?.duration(),
+                Some(("max_conns", value)) => server.max_conns = Some(value.parse().map_err(|_| ())?).filter(|n| *n != 0),
+                None if *param == "backup" => server.backup = true,
+                None if *param == "down" => server.down = true,
+                // Plus-only parameters such as `resolve`, `service=`, `slow_start=`
+                _ => {}
+            }
+        }
+        Ok(server)
+    }
+}
+
+/// `upstream name { ... }` group of servers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upstream {
+    pub name: String,
+    pub servers: Vec<UpstreamServer>,
+    /// Balancing method other than the default round-robin, such as `least_conn` or `hash $remote_addr`
+    pub balancing: Option<String>,
+    /// Shared memory zone keeping the group state across workers, the size is optional
+    pub zone: Option<(String, Option<Size>)>,
+    /// NGINX Plus file persisting servers changed through the API
+    pub state: Option<PathBuf>,
+}
+
+impl TryFrom<&Structure<'_>> for Upstream {
+    type Error = ();
+    fn try_from(block: &Structure) -> Result<Self, Self::Error> {
+        let Structure::Block { args, children } = block else {
+            return Err(())
+        };
+        if args.first().map(|a| a.value()) != Some("upstream") {
+            return Err(())
+        }
+        let name = args.get(1).ok_or(())?.value().to_string();
+        let mut upstream = Self { name, servers: Vec::new(), balancing: None, zone: None, state: None };
+        for child in children {
+            let Structure::Statement { args } = child else {
+                continue;
+            };
+            let args = args.iter().map(|a| a.value()).collect::<Vec<_>>();
+            match args.first() {
+                Some(&"server") => upstream.servers.push(UpstreamServer::try_from(&args[1..])?),
+                Some(&method @ ("least_conn" | "least_time" | "ip_hash" | "hash" | "random")) => {
+                    upstream.balancing = Some(std::iter::once(method).chain(args[1..].iter().copied()).collect::<Vec<_>>().join(" "));
+                }
+                Some(&"zone") => {
+                    let name = args.get(1).ok_or(())?.to_string();
+                    let size = args.get(2).map(|s| Size::from_str(s)).transpose()?;
+                    upstream.zone = Some((name, size));
+                }
+                Some(&"state") => upstream.state = Some(PathBuf::from(args.get(1).ok_or(())?)),
+                _ => {}
+            }
+        }
+        Ok(upstream)
+    }
+}
+
+impl Upstream {
+    /// Servers that receive traffic while every one of them is healthy
+    pub fn primary(&self) -> impl Iterator<Item = &UpstreamServer> {
+        self.servers.iter().filter(|s| !s.backup && !s.down)
+    }
+}
+
+/// NGINX Plus active `health_check` of the upstream a location passes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    pub interval: Duration,
+    /// Random delay added to each check
+    pub jitter: Duration,
+    /// Consecutive failures marking a server unhealthy
+    pub fails: u32,
+    /// Consecutive passes marking a server healthy again
+    pub passes: u32,
+    pub uri: String,
+    /// Name of the `match` block responses are tested against
+    pub matcher: Option<String>,
+    /// Checks go to this port instead of the server's
+    pub port: Option<u16>,
+    pub grpc: bool,
+    /// New servers stay unhealthy until the first check passes
+    pub mandatory: bool,
+    /// Keep the server's health state across reloads, only with `mandatory`
+    pub persistent: bool,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            jitter: Duration::ZERO,
+            fails: 1,
+            passes: 1,
+            uri: "/".to_string(),
+            matcher: None,
+            port: None,
+            grpc: false,
+            mandatory: false,
+            persistent: false,
+        }
+    }
+}
+
+impl HealthCheck {
+    fn parse(args: &[&str]) -> Result<Self, ()> {
+        let mut check = Self::default();
+        for arg in args {
+            match arg.split_once('=') {
+                Some(("interval", value)) => check.interval = NginxTime::from_str(value)?.duration(),
+                Some(("jitter", value)) => check.jitter = NginxTime::from_str(value)?.duration(),
+                Some(("fails", value)) => check.fails = value.parse().map_err(|_| ())?,
+                Some(("passes", value)) => check.passes = value.parse().map_err(|_| ())?,
+                Some(("uri", value)) => check.uri = value.to_string(),
+                Some(("match", value)) => check.matcher = Some(value.to_string()),
+                Some(("port", value)) => check.port = Some(value.parse().map_err(|_| ())?),
+                Some(("type", "grpc")) => check.grpc = true,
+                Some(("type", _)) => return Err(()),
+                None if *arg == "mandatory" => check.mandatory = true,
+                None if *arg == "persistent" => check.persistent = true,
+                // `keepalive_time=`, `grpc_service=`, `grpc_status=`
+                _ => {}
+            }
+        }
+        if check.persistent && !check.mandatory {
+            return Err(())
+        }
+        Ok(check)
+    }
+}
+
+/// How a `match` block tests a response header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderTest {
+    Present,
+    /// `header ! Name;`
+    Absent,
+    Equals(String),
+    NotEquals(String),
+    Matches(String),
+    NotMatches(String),
+}
+
+/// Test of a `match` block, all of them must pass for a response to be healthy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchCondition {
+    /// Inclusive status ranges, `status 200 204;` or `status ! 500-599;`
+    Status { negated: bool, ranges: Vec<(u16, u16)> },
+    Header { name: String, test: HeaderTest },
+    /// Regex tested against the start of the body, `body ~ "ok";`
+    Body { negated: bool, pattern: String },
+    /// Every variable must be non-empty and not "0"
+    Require { variables: Vec<String> },
+}
+
+impl MatchCondition {
+    fn parse(args: &[&str]) -> Result<Self, ()> {
+        match args {
+            ["status", rest @ ..] => {
+                let (negated, codes) = match rest {
+                    ["!", codes @ ..] => (true, codes),
+                    codes => (false, codes),
+                };
+                if codes.is_empty() {
+                    return Err(())
+                }
+                let ranges = codes
+                    .iter()
+                    .map(|code| {
+                        let (from, to) = code.split_once('-').unwrap_or((code, code));
+                        Ok((from.parse().map_err(|_| ())?, to.parse().map_err(|_| ())?))
+                    })
+                    .collect::<Result<Vec<_>, ()>>()?;
+                Ok(Self::Status { negated, ranges })
+            }
+            ["header", "!", name] => Ok(Self::Header { name: name.to_string(), test: HeaderTest::Absent }),
+            ["header", name] => Ok(Self::Header { name: name.to_string(), test: HeaderTest::Present }),
+            ["header", name, operator, value] => {
+                let value = value.to_string();
+                let test = match *operator {
+                    "=" => HeaderTest::Equals(value),
+                    "!=" => HeaderTest::NotEquals(value),
+                    "~" => HeaderTest::Matches(value),
+                    "!~" => HeaderTest::NotMatches(value),
+                    _ => return Err(()),
+                };
+                Ok(Self::Header { name: name.to_string(), test })
+            }
+            ["body", "~", pattern] => Ok(Self::Body { negated: false, pattern: pattern.to_string() }),
+            ["body", "!~", pattern] => Ok(Self::Body { negated: true, pattern: pattern.to_string() }),
+            ["require", variables @ ..] if !variables.is_empty() => {
+                Ok(Self::Require { variables: variables.iter().map(|v| v.to_string()).collect() })
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+/// `match name { ... }` block referenced by `health_check match=name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthMatch {
+    pub name: String,
+    pub conditions: Vec<MatchCondition>,
+}
+
+/// Upstream timeouts in effect in a context, as inherited from the enclosing ones
+/// Timeouts that aren't set anywhere keep the nginx default of 60s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpstreamTimeouts {
+    pub connect: Duration,
+    pub send: Duration,
+    pub read: Duration,
+}
+
+impl Default for UpstreamTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(60),
+            send: Duration::from_secs(60),
+            read: Duration::from_secs(60),
+        }
+    }
+}
+
+impl UpstreamTimeouts {
+    /// The timeouts of merged contexts, see `effective::effective_config`
+    pub fn from_settings(settings: &EffectiveSettings) -> Self {
+        let mut timeouts = Self::default();
+        for name in [DirectiveName::ProxyConnectTimeout, DirectiveName::ProxySendTimeout, DirectiveName::ProxyReadTimeout] {
+            match settings.directives(name).last() {
+                Some(Directive::ProxyConnectTimeout { timeout }) => timeouts.connect = timeout,
+                Some(Directive::ProxySendTimeout { timeout }) => timeouts.send = timeout,
+                Some(Directive::ProxyReadTimeout { timeout }) => timeouts.read = timeout,
+                _ => {},
+            }
+        }
+        timeouts
+    }
+}
+
+/// True when the innermost of nested contexts, outermost first (e.g. `[http, server, location]`),
+/// passes to a `grpcs://` upstream but none of them sets a `grpc_ssl_trusted_certificate` to verify it against
+pub fn grpcs_without_trusted_certificate(contexts: &[&Structure]) -> bool {
+    let directives = |context: &&Structure| match context {
+        Structure::Block { children, .. } => children.iter().filter_map(|child| Directive::try_from(child).ok()).collect(),
+        Structure::Statement { .. } => Vec::new(),
+    };
+    let is_grpcs = contexts
+        .last()
+        .map(directives)
+        .unwrap_or_default()
+        .iter()
+        .any(|d| matches!(d, Directive::GrpcPass { secure: true, .. }));
+    let has_trusted = contexts
+        .iter()
+        .flat_map(directives)
+        .any(|d| matches!(d, Directive::GrpcSslTrustedCertificate { .. }));
+    is_grpcs && !has_trusted
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaplingIssue {
+    /// Stapling is on, but without a `resolver` the OCSP responder can't be looked up
+    MissingResolver,
+    /// `ssl_stapling_verify` is on, but there's no `ssl_trusted_certificate` to verify against
+    MissingTrustedCertificate,
+}
+
+/// Checks OCSP stapling settings across nested contexts, outermost first
+/// (e.g. `[http, server]`), since `resolver` is usually set at the `http` level
+pub fn stapling_issues(contexts: &[&Structure]) -> Vec<StaplingIssue> {
+    let mut stapling = false;
+    let mut verify = false;
+    let mut has_responder = false;
+    let mut has_resolver = false;
+    let mut has_trusted = false;
+    for context in contexts {
+        let Structure::Block { children, .. } = context else {
+            continue;
+        };
+        for child in children {
+            match Directive::try_from(child) {
+                Ok(Directive::Resolver { .. }) => has_resolver = true,
+                Ok(Directive::SslStapling { enabled }) => stapling = enabled,
+                Ok(Directive::SslStaplingVerify { enabled }) => verify = enabled,
+                Ok(Directive::SslStaplingFile { .. }) => has_responder = true,
+                Ok(Directive::SslTrustedCertificate { .. }) => has_trusted = true,
+                _ => {}
+            }
+        }
+    }
+    let mut issues = Vec::new();
+    if stapling && !has_resolver && !has_responder {
+        issues.push(StaplingIssue::MissingResolver);
+    }
+    if stapling && verify && !has_trusted {
+        issues.push(StaplingIssue::MissingTrustedCertificate);
+    }
+    issues
+}
+
+/// njs function reference: `http.hello`, or a bare `hello` for functions of the default module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsFunction {
+    pub module: Option<String>,
+    pub function: String,
+}
+
+impl FromStr for JsFunction {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (module, function) = match s.split_once('.') {
+            Some((module, function)) => (Some(module.to_string()), function),
+            None => (None, s),
+        };
+        if function.is_empty() || module.as_deref() == Some("") {
+            return Err(())
+        }
+        Ok(Self { module, function: function.to_string() })
+    }
+}
+
+/// `js_import http.js;` or `js_import main from lib/http.js;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsImport {
+    /// Explicit name given with `from`
+    pub name: Option<String>,
+    pub path: PathBuf,
+}
+
+impl JsImport {
+    /// Name functions refer to the module by, the file name without extension unless given explicitly
+    pub fn module_name(&self) -> Option<&str> {
+        self.name.as_deref().or_else(|| self.path.file_stem().and_then(|s| s.to_str()))
+    }
+
+    /// The module file, looked up in the `js_path` directories and then the nginx `prefix`
+    pub fn resolve(&self, js_paths: &[PathBuf], prefix: &Path) -> Option<PathBuf> {
+        if self.path.is_absolute() {
+            return Some(self.path.clone()).filter(|p| p.is_file())
+        }
+        js_paths
+            .iter()
+            .map(|dir| match dir.is_absolute() {
+                true => dir.join(&self.path),
+                false => prefix.join(dir).join(&self.path),
+            })
+            .chain(std::iter::once(prefix.join(&self.path)))
+            .find(|p| p.is_file())
+    }
+}
+
+/// Request or worker phase an OpenResty `*_by_lua*` handler runs in, from the directive name prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LuaPhase {
+    Init,
+    InitWorker,
+    ExitWorker,
+    Set,
+    ServerRewrite,
+    Rewrite,
+    Access,
+    Content,
+    Balancer,
+    HeaderFilter,
+    BodyFilter,
+    Log,
+    SslCertificate,
+    SslClientHello,
+    SslSessionFetch,
+    SslSessionStore,
+}
+
+impl FromStr for LuaPhase {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "init" => Ok(Self::Init),
+            "init_worker" => Ok(Self::InitWorker),
+            "exit_worker" => Ok(Self::ExitWorker),
+            "set" => Ok(Self::Set),
+            "server_rewrite" => Ok(Self::ServerRewrite),
+            "rewrite" => Ok(Self::Rewrite),
+            "access" => Ok(Self::Access),
+            "content" => Ok(Self::Content),
+            "balancer" => Ok(Self::Balancer),
+            "header_filter" => Ok(Self::HeaderFilter),
+            "body_filter" => Ok(Self::BodyFilter),
+            "log" => Ok(Self::Log),
+            "ssl_certificate" => Ok(Self::SslCertificate),
+            "ssl_client_hello" => Ok(Self::SslClientHello),
+            "ssl_session_fetch" => Ok(Self::SslSessionFetch),
+            "ssl_session_store" => Ok(Self::SslSessionStore),
+            _ => Err(()),
+        }
+    }
+}
+
+/// `lua_package_path` and `lua_package_cpath` search templates, `;;` stands for the default path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuaSearchPath {
+    pub templates: Vec<String>,
+    pub includes_default: bool,
+}
+
+impl FromStr for LuaSearchPath {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let templates = s.split(';').filter(|t| !t.is_empty()).map(str::to_string).collect();
+        Ok(Self { templates, includes_default: s.contains(";;") })
+    }
+}
+
+/// Handler of the `perl` and `perl_set` directives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PerlHandler {
+    /// `Module::function` defined in a module loaded with `perl_require`
+    Function(String),
+    /// Anonymous `sub { ... }` written in the config
+    Inline(String),
+}
+
+impl FromStr for PerlHandler {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "" => Err(()),
+            _ if s.starts_with("sub ") || s.starts_with("sub{") => Ok(Self::Inline(s.to_string())),
+            _ => Ok(Self::Function(s.to_string())),
+        }
+    }
+}
+
+/// Width or height of an `image_filter` resize or crop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageDimension {
+    /// `-`, the dimension isn't constrained
+    Any,
+    Pixels(u32),
+    Expression(String),
+}
+
+impl FromStr for ImageDimension {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "-" => Ok(Self::Any),
+            _ if s.contains('$') => Ok(Self::Expression(s.to_string())),
+            _ => s.parse().map(Self::Pixels).map_err(|_| ()),
+        }
+    }
+}
+
+/// `image_filter` transformation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageFilter {
+    Off,
+    /// Only lets JPEG, GIF, PNG and WebP responses through, others get 415
+    Test,
+    /// Replies with the image dimensions as JSON
+    Size,
+    Rotate(Dynamic<u16>),
+    Resize { width: ImageDimension, height: ImageDimension },
+    Crop { width: ImageDimension, height: ImageDimension },
+}
+
+impl ImageFilter {
+    fn parse(args: &[&str]) -> Result<Self, ()> {
+        match args {
+            ["off"] => Ok(Self::Off),
+            ["test"] => Ok(Self::Test),
+            ["size"] => Ok(Self::Size),
+            ["rotate", angle] => {
+                let angle = Dynamic::<u16>::from_str(angle)?;
+                match angle {
+                    Dynamic::Literal(90 | 180 | 270) | Dynamic::Expression(_) => Ok(Self::Rotate(angle)),
+                    Dynamic::Literal(_) => Err(()),
+                }
+            }
+            ["resize", width, height] => Ok(Self::Resize {
+                width: ImageDimension::from_str(width)?,
+                height: ImageDimension::from_str(height)?,
+            }),
+            ["crop", width, height] => Ok(Self::Crop {
+                width: ImageDimension::from_str(width)?,
+                height: ImageDimension::from_str(height)?,
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Kind of monitoring endpoint served by a location
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsKind {
+    /// `stub_status`, basic connection counters
+    StubStatus,
+    /// NGINX Plus `api`, `write` allows modifying upstreams and zones through it
+    Api { write: bool },
+}
+
+/// Location exposing server metrics, found by `MetricsEndpoint::discover`
+#[derive(Debug, Clone)]
+pub struct MetricsEndpoint {
+    pub kind: MetricsKind,
+    /// Arguments of the `server_name` directives of the enclosing server
+    pub server_names: Vec<String>,
+    pub listen: Vec<SocketAddr>,
+    /// `location` arguments as written, such as `= /status`
+    pub location: String,
+    /// `allow`/`deny` rules in effect for the location, inherited from the closest level defining any
+    pub access: AccessList,
+}
+
+impl MetricsEndpoint {
+    /// Finds every location with `stub_status` or `api` inside the `server` blocks of a config
+    pub fn discover(config: &Structure) -> Vec<Self> {
+        let mut endpoints = Vec::new();
+        Self::walk(config, &AccessList::default(), None, &mut endpoints);
+        endpoints
+    }
+
+    fn walk(block: &Structure, inherited: &AccessList, server: Option<&(Vec<String>, Vec<SocketAddr>)>, endpoints: &mut Vec<Self>) {
+        let Structure::Block { args, children } = block else {
+            return;
+        };
+        let own = AccessList::from_block(block);
+        let access = if own.rules.is_empty() { inherited } else { &own };
+        let name = args.first().map(|a| a.value());
+
+        let mut current = None;
+        if name == Some("server") {
+            let mut names = Vec::new();
+            let mut listen = Vec::new();
+            for child in children {
+                if let Structure::Statement { args } = child {
+                    if args.first().map(|a| a.value()) == Some("server_name") {
+                        names.extend(args[1..].iter().map(|a| a.value().to_string()));
+                    }
+                }
+                if let Ok(Directive::Listen { sock_addr, .. }) = Directive::try_from(child) {
+                    listen.push(sock_addr);
+                }
+            }
+            current = Some((names, listen));
+        }
+        let server = current.as_ref().or(server);
+
+        if let (Some("location"), Some((server_names, listen))) = (name, server) {
+            for child in children {
+                let kind = match Directive::try_from(child) {
+                    Ok(Directive::StubStatus) => MetricsKind::StubStatus,
+                    Ok(Directive::Api { write }) => MetricsKind::Api { write },
+                    _ => continue,
+                };
+                endpoints.push(Self {
+                    kind,
+                    server_names: server_names.clone(),
+                    listen: listen.clone(),
+                    location: args[1..].iter().map(|a| a.value()).collect::<Vec<_>>().join(" "),
+                    access: access.clone(),
+                });
+            }
+        }
+        for child in children {
+            Self::walk(child, access, server, endpoints);
+        }
+    }
+
+    /// Whether some clients are denied access to the endpoint
+    pub fn is_restricted(&self) -> bool {
+        self.access.rules.iter().any(|rule| rule.access == Access::Deny)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Directive {
+    AbsoluteRedirect {
+        enabled: bool
+    },
+    /// `None` for `charset off`
+    Charset {
+        charset: Option<String>
+    },
+    CharsetTypes {
+        mime_types: Vec<String>
+    },
+    ClientBodyBufferSize {
+        size: Size
+    },
+    ClientBodyTimeout {
+        timeout: Duration
+    },
+    ClientHeaderBufferSize {
+        size: Size
+    },
+    ClientHeaderTimeout {
+        timeout: Duration
+    },
+    /// 0 disables the request body size check
+    ClientMaxBodySize {
+        size: Size
+    },
+    DefaultType {
+        mime_type: String
+    },
+    Daemon {
+        enabled: bool
+    },
+    Deny {
+        target: AccessTarget
+    },
+    /// Variable kept from the parent environment, or set when `value` is given
+    Env {
+        name: String,
+        value: Option<String>
+    },
+    /// `None` for `directio off`
+    Directio {
+        min_size: Option<Size>
+    },
+    DirectioAlignment {
+        size: Size
+    },
     ErrorLog {
-        path: PathBuf,
-        level: Option<String>
+        target: LogTarget,
+        level: Option<LogLevel>
+    },
+    Aio(Aio),
+    Autoindex {
+        enabled: bool
+    },
+    AutoindexExactSize {
+        enabled: bool
+    },
+    AutoindexFormat {
+        format: AutoindexFormat
+    },
+    AutoindexLocaltime {
+        enabled: bool
     },
+    /// `target` is `None` for `access_log off`
     AccessLog {
-        path: PathBuf,
-        compression_enabled: bool
+        target: Option<LogTarget>,
+        format: Option<String>,
+        buffer: Option<Size>,
+        compression_enabled: bool,
+        compression_level: Option<u8>,
+        flush: Option<Duration>,
+        /// `if=` condition, the request isn't logged when it evaluates to "" or "0"
+        condition: Option<String>
     },
     AddHeader{
         name: String,
-        value: String
+        value: String,
+        /// Also added to error responses, not only 2xx/3xx
+        always: bool
+    },
+    /// Replaces the matched location prefix (or the whole path for regex locations) in file lookups
+    Alias {
+        path: String
+    },
+    Allow {
+        target: AccessTarget
     },
+    /// NGINX Plus REST API, read-only unless `write=on`
+    Api {
+        write: bool
+    },
+    /// `None` for `auth_basic off`
     AuthBasic {
-        realm: String
+        realm: Option<String>
     },
     AuthBasicUserFile {
         file: PathBuf  
     },
-    Http2 {
+    /// `None` for `auth_jwt off`
+    AuthJwt {
+        realm: Option<String>,
+        /// Variable holding the token instead of the `Authorization` header
+        token: Option<String>
+    },
+    AuthJwtKeyFile {
+        file: PathBuf
+    },
+    AuthJwtKeyRequest {
+        uri: String
+    },
+    /// `None` for `auth_request off`
+    AuthRequest {
+        uri: Option<String>
+    },
+    AuthRequestSet {
+        variable: String,
+        value: String
+    },
+    Brotli {
+        enabled: bool
+    },
+    BrotliCompLevel {
+        level: u8
+    },
+    BrotliMinLength {
+        length: Size
+    },
+    BrotliStatic {
+        mode: StaticCompression
+    },
+    BrotliTypes {
+        mime_types: Vec<String>
+    },
+    /// Stops processing the rewrite module directives of the context
+    Break,
+    Etag {
+        enabled: bool
+    },
+    /// `target` is a URI, a named location or a URL to redirect the client to
+    ErrorPage {
+        codes: Vec<u16>,
+        status: ErrorPageStatus,
+        target: String
+    },
+    Expires(Expires),
+    FastcgiPass {
+        target: PassTarget
+    },
+    /// Upstream responses with status 300 and above go through `error_page` instead of being passed on
+    FastcgiInterceptErrors {
+        enabled: bool
+    },
+    GeoIpCity {
+        database: PathBuf
+    },
+    GeoIpCountry {
+        database: PathBuf
+    },
+    GeoIpOrg {
+        database: PathBuf
+    },
+    /// Trusted address whose `X-Forwarded-For` is used for the lookup
+    GeoIpProxy {
+        target: AccessTarget
+    },
+    /// `geoip2 db.mmdb { ... }` block from ngx_http_geoip2_module
+    GeoIp2 {
+        database: PathBuf,
+        auto_reload: Option<Duration>,
+        variables: Vec<GeoIp2Variable>
+    },
+    GrpcPass {
+        target: PassTarget,
+        secure: bool
+    },
+    GrpcSetHeader {
+        header_name: String,
+        header_value: String
+    },
+    GrpcSslTrustedCertificate {
+        path: PathBuf
+    },
+    GrpcSslVerify {
+        enabled: bool
+    },
+    Gzip {
+        enabled: bool
+    },
+    GzipCompLevel {
+        level: u8
+    },
+    GzipMinLength {
+        length: Size
+    },
+    /// Empty for `gzip_proxied off`
+    GzipProxied {
+        conditions: Vec<GzipProxied>
+    },
+    GzipStatic {
+        mode: StaticCompression
+    },
+    GzipTypes {
+        mime_types: Vec<String>
+    },
+    GzipVary {
+        enabled: bool
+    },
+    HealthCheck(HealthCheck),
+    Http2 {
+        enabled: bool
+    },
+    Http3 {
+        enabled: bool
+    },
+    Http3Hq {
+        enabled: bool
+    },
+    Http2BodyPrereadSize {
+        size: Size
+    },
+    Http2MaxConcurrentStreams {
+        streams: u32
+    },
+    IgnoreInvalidHeaders {
+        enabled: bool
+    },
+    /// Browsers keep-alive is disabled for, empty for `none`
+    KeepaliveDisable {
+        browsers: Vec<String>
+    },
+    KeepaliveRequests {
+        requests: u32
+    },
+    /// 0 disables keep-alive, `header_timeout` is sent as `Keep-Alive: timeout=...`
+    KeepaliveTimeout {
+        timeout: Duration,
+        header_timeout: Option<Duration>
+    },
+    LargeClientHeaderBuffers {
+        number: u32,
+        size: Size
+    },
+    LimitConn {
+        zone: String,
+        connections: u32
+    },
+    LimitConnStatus {
+        code: u16
+    },
+    LimitConnZone(LimitConnZone),
+    /// Bytes per second, 0 disables the limit
+    LimitRate {
+        rate: Dynamic<Size>
+    },
+    LimitRateAfter {
+        size: Dynamic<Size>
+    },
+    LimitReq(LimitReq),
+    LimitReqStatus {
+        code: u16
+    },
+    LimitReqZone(LimitReqZone),
+    LoadModule {
+        path: PathBuf
+    },
+    LogFormat(LogFormat),
+    /// `*_by_lua_block { ... }` or the older `*_by_lua '...'`, `variable` is only set for `set_by_lua*`
+    LuaCode {
+        phase: LuaPhase,
+        variable: Option<String>,
+        code: String
+    },
+    /// `*_by_lua_file`, `set_by_lua_file` can pass extra arguments to the script
+    LuaFile {
+        phase: LuaPhase,
+        variable: Option<String>,
+        path: PathBuf,
+        args: Vec<String>
+    },
+    LuaPackageCpath(LuaSearchPath),
+    LuaPackagePath(LuaSearchPath),
+    LuaSharedDict {
+        name: String,
+        size: Size
+    },
+    Map(Map),
+    MasterProcess {
+        enabled: bool
+    },
+    MergeSlashes {
+        enabled: bool
+    },
+    /// `None` for `mirror off`
+    Mirror {
+        uri: Option<String>
+    },
+    MirrorRequestBody {
+        enabled: bool
+    },
+    MultiAccept {
+        enabled: bool
+    },
+    /// `None` for `open_file_cache off`
+    OpenFileCache(Option<OpenFileCache>),
+    OpenFileCacheErrors {
+        enabled: bool
+    },
+    OpenFileCacheMinUses {
+        uses: u32
+    },
+    OpenFileCacheValid {
+        time: Duration
+    },
+    /// `None` for `open_log_file_cache off`
+    OpenLogFileCache(Option<OpenLogFileCache>),
+    /// `if (...) {}`, the directives of the block are left in the parse tree
+    If {
+        condition: Condition
+    },
+    IfModifiedSince {
+        mode: IfModifiedSince
+    },
+    ImageFilter(ImageFilter),
+    Index {
+        files: Vec<String>
+    },
+    /// Largest image the filter reads, bigger ones get 415
+    ImageFilterBuffer {
+        size: Size
+    },
+    /// Location only reachable through internal redirects and subrequests
+    Internal,
+    /// `resolved` stays `None` until filled in by `include::resolve_directive`,
+    /// `Some(vec![])` then means the pattern matched no files
+    Include {
+        pattern: String,
+        is_glob: bool,
+        resolved: Option<Vec<PathBuf>>
+    },
+    /// `buffer_type=buffer` passes the body as a `Buffer` instead of a string
+    JsBodyFilter {
+        function: JsFunction,
+        buffer: bool
+    },
+    JsContent {
+        function: JsFunction
+    },
+    JsHeaderFilter {
+        function: JsFunction
+    },
+    JsImport(JsImport),
+    JsPath {
+        path: PathBuf
+    },
+    /// `nocache` evaluates the function every time the variable is referenced
+    JsSet {
+        variable: String,
+        function: JsFunction,
+        nocache: bool
+    },
+    Listen {
+        sock_addr: SocketAddr,
+        is_default: bool,
+        is_http2: bool,
+        /// `quic` parameter (or `http3` from the early QUIC branch)
+        is_http3: bool
+    },
+    OutputBuffers {
+        number: u32,
+        size: Size
+    },
+    OverrideCharset {
+        enabled: bool
+    },
+    Perl {
+        handler: PerlHandler
+    },
+    PerlModules {
+        path: PathBuf
+    },
+    PerlRequire {
+        module: String
+    },
+    PerlSet {
+        variable: String,
+        handler: PerlHandler
+    },
+    Pid {
+        path: PathBuf
+    },
+    PortInRedirect {
+        enabled: bool
+    },
+    ProxyBuffering {
+        enabled: bool
+    },
+    ProxyBuffers {
+        number: u32,
+        size: Size
+    },
+    ProxyBufferSize {
+        size: Size
+    },
+    ProxyBusyBuffersSize {
+        size: Size
+    },
+    ProxyHttpVersion {
+        version: String
+    },
+    /// Upstream responses with status 300 and above go through `error_page` instead of being passed on
+    ProxyInterceptErrors {
+        enabled: bool
+    },
+    ProxyNextUpstream(NextUpstream),
+    ProxyNextUpstreamTries {
+        tries: u32
+    },
+    ProxyNextUpstreamTimeout {
+        timeout: Duration
+    },
+    ProxyMaxTempFileSize {
+        size: Size
+    },
+    ProxyPass {
+        addr: Url
+    },
+    ProxyConnectTimeout {
+        timeout: Duration
+    },
+    ProxyReadTimeout {
+        timeout: Duration
+    },
+    ProxySendTimeout {
+        timeout: Duration
+    },
+    ProxySetHeader {
+        header_name: String,
+        header_value: String
+    },
+    ProxyHideHeader {
+        header_name: String  
+    },
+    ProxySslCertificate {
+        path: PathBuf
+    },
+    ProxySslCertificateKey {
+        path: PathBuf
+    },
+    ProxySslName {
+        name: String
+    },
+    ProxySslProtocols(TlsProtocols),
+    ProxySslServerName {
+        enabled: bool
+    },
+    ProxySslTrustedCertificate {
+        path: PathBuf
+    },
+    ProxySslVerify {
+        enabled: bool
+    },
+    QuicGso {
+        enabled: bool
+    },
+    QuicRetry {
+        enabled: bool
+    },
+    /// An `error_page` target may itself redirect through `error_page` again
+    RecursiveErrorPages {
+        enabled: bool
+    },
+    Resolver {
+        servers: Vec<ResolverAddr>,
+        /// Overrides the TTL of cached answers
+        valid: Option<Duration>,
+        ipv4: Option<bool>,
+        ipv6: Option<bool>,
+        status_zone: Option<String>
+    },
+    ResolverTimeout {
+        timeout: Duration
+    },
+    /// `return URL;` leaves `code` unset and redirects with 302,
+    /// `content` is the URL for redirect codes and the body text otherwise
+    Return {
+        code: Option<u16>,
+        content: Option<String>
+    },
+    Rewrite {
+        regex: Regex,
+        replacement: String,
+        flag: Option<RewriteFlag>
+    },
+    /// Unlike `alias`, the full request path is appended
+    Root {
+        path: String
+    },
+    Satisfy {
+        mode: SatisfyMode
+    },
+    ScgiParam {
+        name: String,
+        value: String,
+        if_not_empty: bool
+    },
+    ScgiPass {
+        target: PassTarget
+    },
+    Sendfile {
+        enabled: bool
+    },
+    /// 0 disables the limit
+    SendfileMaxChunk {
+        size: Size
+    },
+    SendTimeout {
+        timeout: Duration
+    },
+    /// `set $variable value;`, variable named without the `$`
+    Set {
+        variable: String,
+        value: String
+    },
+    ServerName {
+        names: Vec<String>
+    },
+    /// Use the primary server_name instead of the Host header in redirects
+    ServerNameInRedirect {
+        enabled: bool
+    },
+    /// `build` also counts as enabled, it adds the build name to the version
+    ServerTokens {
+        enabled: bool
+    },
+    /// Expressions holding the link hash and the optional expiry timestamp
+    SecureLink {
+        hash: String,
+        expires: Option<String>,
+    },
+    /// The string whose md5 the client must compute, as written and split into variables
+    SecureLinkMd5 {
+        expression: String,
+        template: Vec<TemplateSegment>,
+    },
+    SecureLinkSecret {
+        secret: String
+    },
+    /// 0 disables slicing
+    Slice {
+        size: Size
+    },
+    SourceCharset {
+        charset: String
+    },
+    SslCertificate {
+        path: PathBuf
+    },
+    TcpNodelay {
+        enabled: bool
+    },
+    TcpNopush {
+        enabled: bool
+    },
+    TryFiles {
+        files: Vec<String>,
+        fallback: TryFilesFallback
+    },
+    Types(MimeTypes),
+    Match(HealthMatch),
+    /// NGINX Plus file keeping the servers of an upstream changed through the API
+    State {
+        path: PathBuf
+    },
+    /// NGINX Plus zone collecting per-server, location or upstream statistics
+    StatusZone {
+        zone: String
+    },
+    /// Basic status page, the legacy `stub_status on` form is accepted too
+    StubStatus,
+    SubFilter {
+        pattern: String,
+        replacement: String
+    },
+    SubFilterLastModified {
+        enabled: bool
+    },
+    SubFilterOnce {
+        enabled: bool
+    },
+    SubFilterTypes {
+        mime_types: Vec<String>
+    },
+    SslCertificateKey {
+        path: PathBuf
+    },
+    SslCiphers(CipherList),
+    SslDhparam {
+        path: PathBuf
+    },
+    SslEarlyData {
+        enabled: bool
+    },
+    /// `None` for `ssl_ecdh_curve auto`
+    SslEcdhCurve {
+        curves: Option<Vec<String>>
+    },
+    SslPasswordFile {
+        path: PathBuf
+    },
+    SslPreferServerCiphers {
+        enabled: bool
+    },
+    SslSessionCache {
+        caches: Vec<SessionCache>
+    },
+    SslSessionTickets {
+        enabled: bool
+    },
+    SslSessionTimeout {
+        timeout: Duration
+    },
+    SslProtocols(TlsProtocols),
+    SslRejectHandshake {
+        enabled: bool
+    },
+    SslStapling {
+        enabled: bool
+    },
+    /// Stapled OCSP response read from a file instead of querying the responder
+    SslStaplingFile {
+        path: PathBuf
+    },
+    SslStaplingVerify {
+        enabled: bool
+    },
+    SslTrustedCertificate {
+        path: PathBuf
+    },
+    /// Accept header names with underscores instead of dropping them
+    UnderscoresInHeaders {
         enabled: bool
     },
-    Listen {
-        sock_addr: SocketAddr,
-        is_default: bool,
-        is_http2: bool,
-        is_http3: bool
+    Use {
+        method: EventMethod
     },
-    ProxyHttpVersion {
-        version: String
+    ValidReferers(ValidReferers),
+    /// Group defaults to the user name
+    User {
+        name: String,
+        group: Option<String>
     },
-    ProxyPass {
-        addr: Url
+    /// `auto` binds workers to available CPUs, optionally limited by the first mask
+    WorkerCpuAffinity {
+        auto: bool,
+        masks: Vec<String>
     },
-    ProxyReadTimeout {
-        timeout: Duration
+    WorkerConnections {
+        connections: u32
     },
-    ProxySetHeader {
-        header_name: String,
-        header_value: String
+    WorkingDirectory {
+        path: PathBuf
     },
-    ProxyHideHeader {
-        header_name: String  
+    WorkerProcesses {
+        processes: WorkerProcesses
     },
-    Return {
-        code: Option<u16>,
-        content: Option<String>
+    WorkerRlimitNofile {
+        limit: u32
     },
-    ServerName {
-        name: String
-    },   
-    ServerTokens {
-        enabled: bool
+    UwsgiParam {
+        name: String,
+        value: String,
+        if_not_empty: bool
     },
-    SslCertificate {
-        path: PathBuf
+    UwsgiPass {
+        target: PassTarget,
+        secure: bool
     },
-    SslCertificateKey {
-        path: PathBuf
+    /// Stylesheets apply in order, `parameters` are `name=value` pairs passed to the stylesheet
+    XsltStylesheet {
+        path: PathBuf,
+        parameters: Vec<(String, String)>
     },
-    SslEarlyData {
-        enabled: bool
+    XsltTypes {
+        mime_types: Vec<String>
+    },
+    /// Shared memory zone of an upstream, the size can be left out when another `zone` already set it
+    Zone {
+        name: String,
+        size: Option<Size>
     },
     Location(Location),
 }
 
-impl<'l> TryFrom<crate::Structure<'l>> for Directive {
+impl Directive {
+    /// URI or `@name` this directive may internally redirect to, resolve it with `LocationSet::redirect`
+    pub fn internal_redirect(&self) -> Option<&str> {
+        match self {
+            Self::TryFiles { fallback: TryFilesFallback::Uri(target) | TryFilesFallback::Named(target), .. } => Some(target),
+            Self::ErrorPage { target, .. } if target.starts_with(['/', '@']) => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Variables this directive makes available to the rest of the config, with the leading `$`
+    pub fn defined_variables(&self) -> Vec<String> {
+        let variables: &[&str] = match self {
+            Self::GeoIpCountry { .. } => &["$geoip_country_code", "$geoip_country_code3", "$geoip_country_name"],
+            Self::GeoIpCity { .. } => &[
+                "$geoip_area_code", "$geoip_city_continent_code", "$geoip_city_country_code",
+                "$geoip_city_country_code3", "$geoip_city_country_name", "$geoip_dma_code",
+                "$geoip_latitude", "$geoip_longitude", "$geoip_region", "$geoip_region_name",
+                "$geoip_city", "$geoip_postal_code",
+            ],
+            Self::GeoIpOrg { .. } => &["$geoip_org"],
+            Self::GeoIp2 { variables, .. } => return variables.iter().map(|v| v.variable.clone()).collect(),
+            _ => &[],
+        };
+        variables.iter().map(|v| v.to_string()).collect()
+    }
+}
+
+impl TryFrom<&Structure<'_>> for Directive {
     type Error = ();
-    fn try_from(value: crate::Structure) -> Result<Self, Self::Error> {
+    fn try_from(value: &Structure) -> Result<Self, Self::Error> {
+        if let Structure::Block { args, children } = value {
+            match args.first().and_then(|s| DirectiveName::from_str(s.value()).ok()) {
+                Some(DirectiveName::Geoip2) => {
+                    let database = PathBuf::from(args.get(1).ok_or(())?.value());
+                    let mut auto_reload = None;
+                    let mut variables = Vec::new();
+                    for child in children {
+                        let Structure::Statement { args } = child else {
+                            return Err(())
+                        };
+                        match args.first().map(|s| s.value()) {
+                            Some("auto_reload") => auto_reload = Some(parse_time(args.get(1))?),
+                            Some(variable) if variable.starts_with('$') => {
+                                let mut mapping = GeoIp2Variable { variable: variable.to_string(), ..Default::default() };
+                                for arg in args.get(1..).ok_or(())? {
+                                    let arg = arg.value();
+                                    if let Some(default) = arg.strip_prefix("default=") {
+                                        mapping.default = Some(default.to_string());
+                                    } else if let Some(source) = arg.strip_prefix("source=") {
+                                        mapping.source = Some(source.to_string());
+                                    } else {
+                                        mapping.path.push(arg.to_string());
+                                    }
+                                }
+                                variables.push(mapping);
+                            },
+                            _ => return Err(())
+                        }
+                    }
+                    return Ok(Self::GeoIp2 { database, auto_reload, variables })
+                },
+                Some(DirectiveName::Types) => {
+                    if args.len() != 1 {
+                        return Err(())
+                    }
+                    let mut types = MimeTypes::default();
+                    for child in children {
+                        let Structure::Statement { args } = child else {
+                            return Err(())
+                        };
+                        let mime_type = args.first().ok_or(())?.value();
+                        let extensions = args.get(1..).filter(|e| !e.is_empty()).ok_or(())?;
+                        for extension in extensions {
+                            types.types.insert(extension.value().to_ascii_lowercase(), mime_type.to_string());
+                        }
+                    }
+                    return Ok(Self::Types(types))
+                },
+                Some(DirectiveName::Location) => {
+                    return Ok(Self::Location(Location::try_from(value)?))
+                },
+                Some(DirectiveName::If) => {
+                    return Ok(Self::If { condition: Condition::try_from(&args[1..])? })
+                },
+                Some(DirectiveName::Map) => {
+                    return Ok(Self::Map(Map::try_from(value)?))
+                },
+                Some(DirectiveName::Match) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    if args.len() != 2 {
+                        return Err(())
+                    }
+                    let conditions = children
+                        .iter()
+                        .map(|child| {
+                            let Structure::Statement { args } = child else {
+                                return Err(())
+                            };
+                            MatchCondition::parse(&args.iter().map(|s| s.value()).collect::<Vec<_>>())
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    return Ok(Self::Match(HealthMatch { name, conditions }))
+                },
+                _ => return Err(())
+            }
+        }
+
         if let Structure::Statement { args } = value {
-            match args.get(0).map(|s| format!("{}", s)).as_deref() {
-                Some("error_log") => {
-                    let path = args.get(1).ok_or(())?.to_string();
-                    let path = PathBuf::from(path);
-                    let level = args.get(2).map(|s| s.to_string());
-                    return Ok(Self::ErrorLog { path, level })
-                },
-                Some("access_log") => {
-                    let path = args.get(1).ok_or(())?.to_string();
-                    let path = PathBuf::from(path);
-                    return Ok(Self::AccessLog { path, compression_enabled: false })                    
-                },
-                Some("add_header") => {
-                    let name = args.get(1).ok_or(())?.to_string();
-                    let value = args.get(2..).ok_or(())?.iter().map(|s| format!(" {}", s.to_string())).collect::<String>();
-                    return Ok(Self::AddHeader { name, value })
+            match args.first().and_then(|s| DirectiveName::from_str(s.value()).ok()) {
+                Some(DirectiveName::AbsoluteRedirect) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::AbsoluteRedirect { enabled })
+                },
+                Some(DirectiveName::ErrorLog) => {
+                    let target = LogTarget::from_str(args.get(1).ok_or(())?.value())?;
+                    let level = args.get(2).map(|s| LogLevel::from_str(s.value())).transpose()?;
+                    return Ok(Self::ErrorLog { target, level })
+                },
+                Some(DirectiveName::AccessLog) => {
+                    let target = match args.get(1).ok_or(())?.value() {
+                        "off" => None,
+                        target => Some(LogTarget::from_str(target)?),
+                    };
+                    let format = args.get(2).map(|s| s.value().to_string());
+                    let mut buffer = None;
+                    let mut compression_enabled = false;
+                    let mut compression_level = None;
+                    let mut flush = None;
+                    let mut condition = None;
+                    for arg in args.get(3..).unwrap_or_default() {
+                        let arg = arg.value();
+                        if let Some(size) = arg.strip_prefix("buffer=") {
+                            buffer = Some(Size::from_str(size)?);
+                        } else if arg == "gzip" {
+                            compression_enabled = true;
+                        } else if let Some(level) = arg.strip_prefix("gzip=") {
+                            compression_enabled = true;
+                            compression_level = Some(level.parse::<u8>().ok().filter(|l| (1..=9).contains(l)).ok_or(())?);
+                        } else if let Some(time) = arg.strip_prefix("flush=") {
+                            flush = Some(NginxTime::from_str(time)?.duration());
+                        } else if let Some(expression) = arg.strip_prefix("if=") {
+                            condition = Some(expression.to_string());
+                        } else {
+                            return Err(())
+                        }
+                    }
+                    if target.is_none() && format.is_some() {
+                        return Err(())
+                    }
+                    return Ok(Self::AccessLog { target, format, buffer, compression_enabled, compression_level, flush, condition })
+                },
+                Some(DirectiveName::AddHeader) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    let value = args.get(2).ok_or(())?.value().to_string();
+                    let always = match args.get(3).map(|s| s.value()) {
+                        None => false,
+                        Some("always") if args.len() == 4 => true,
+                        _ => return Err(()),
+                    };
+                    return Ok(Self::AddHeader { name, value, always })
+                },
+                Some(DirectiveName::Allow) => {
+                    let target = AccessTarget::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::Allow { target })
+                },
+                Some(DirectiveName::Charset) => {
+                    let charset = match args.get(1).ok_or(())?.value() {
+                        "off" => None,
+                        charset => Some(charset.to_string()),
+                    };
+                    return Ok(Self::Charset { charset })
+                },
+                Some(DirectiveName::CharsetTypes) => {
+                    let mime_types = args.get(1..).ok_or(())?.iter().map(|s| s.value().to_string()).collect::<Vec<_>>();
+                    if mime_types.is_empty() {
+                        return Err(())
+                    }
+                    return Ok(Self::CharsetTypes { mime_types })
+                },
+                Some(DirectiveName::FastcgiInterceptErrors) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::FastcgiInterceptErrors { enabled })
+                },
+                Some(DirectiveName::IgnoreInvalidHeaders) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::IgnoreInvalidHeaders { enabled })
+                },
+                Some(DirectiveName::MergeSlashes) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::MergeSlashes { enabled })
+                },
+                Some(DirectiveName::OverrideCharset) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::OverrideCharset { enabled })
+                },
+                Some(DirectiveName::PortInRedirect) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::PortInRedirect { enabled })
+                },
+                Some(DirectiveName::ProxyInterceptErrors) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::ProxyInterceptErrors { enabled })
                 },
-                Some("auth_basic") => {
-                    let realm = args.get(1) .ok_or(())?.to_string();
+                Some(DirectiveName::RecursiveErrorPages) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::RecursiveErrorPages { enabled })
+                },
+                Some(DirectiveName::ServerNameInRedirect) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::ServerNameInRedirect { enabled })
+                },
+                Some(DirectiveName::ServerTokens) => {
+                    let enabled = match args.get(1).map(Token::value) {
+                        Some("build") => true,
+                        _ => parse_switch(args.get(1))?,
+                    };
+                    return Ok(Self::ServerTokens { enabled })
+                },
+                Some(DirectiveName::SourceCharset) => {
+                    let charset = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::SourceCharset { charset })
+                },
+                Some(DirectiveName::ClientBodyBufferSize) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::ClientBodyBufferSize { size })
+                },
+                Some(DirectiveName::ClientBodyTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::ClientBodyTimeout { timeout })
+                },
+                Some(DirectiveName::ClientHeaderBufferSize) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::ClientHeaderBufferSize { size })
+                },
+                Some(DirectiveName::ClientHeaderTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::ClientHeaderTimeout { timeout })
+                },
+                Some(DirectiveName::ClientMaxBodySize) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::ClientMaxBodySize { size })
+                },
+                Some(DirectiveName::KeepaliveDisable) => {
+                    let mut browsers = Vec::new();
+                    for browser in args.get(1..).filter(|b| !b.is_empty()).ok_or(())? {
+                        match browser.value() {
+                            "none" => browsers.clear(),
+                            browser @ ("msie6" | "safari") => browsers.push(browser.to_string()),
+                            _ => return Err(()),
+                        }
+                    }
+                    return Ok(Self::KeepaliveDisable { browsers })
+                },
+                Some(DirectiveName::KeepaliveRequests) => {
+                    let requests = parse_number(args.get(1))?;
+                    return Ok(Self::KeepaliveRequests { requests })
+                },
+                Some(DirectiveName::KeepaliveTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    let header_timeout = args.get(2).map(|t| parse_time(Some(t))).transpose()?;
+                    return Ok(Self::KeepaliveTimeout { timeout, header_timeout })
+                },
+                Some(DirectiveName::LargeClientHeaderBuffers) => {
+                    let number = parse_number(args.get(1))?;
+                    let size = parse_size(args.get(2))?;
+                    return Ok(Self::LargeClientHeaderBuffers { number, size })
+                },
+                Some(DirectiveName::Autoindex) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::Autoindex { enabled })
+                },
+                Some(DirectiveName::AutoindexExactSize) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::AutoindexExactSize { enabled })
+                },
+                Some(DirectiveName::AutoindexFormat) => {
+                    let format = match args.get(1).ok_or(())?.value() {
+                        "html" => AutoindexFormat::Html,
+                        "xml" => AutoindexFormat::Xml,
+                        "json" => AutoindexFormat::Json,
+                        "jsonp" => AutoindexFormat::Jsonp,
+                        _ => return Err(()),
+                    };
+                    return Ok(Self::AutoindexFormat { format })
+                },
+                Some(DirectiveName::AutoindexLocaltime) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::AutoindexLocaltime { enabled })
+                },
+                Some(DirectiveName::Aio) => {
+                    let aio = match args.get(1).ok_or(())?.value() {
+                        "off" => Aio::Off,
+                        "on" => Aio::On,
+                        "threads" => Aio::Threads(None),
+                        value => match value.strip_prefix("threads=") {
+                            Some(pool) if !pool.is_empty() => Aio::Threads(Some(pool.to_string())),
+                            _ => return Err(()),
+                        },
+                    };
+                    return Ok(Self::Aio(aio))
+                },
+                Some(DirectiveName::Directio) => {
+                    let min_size = match args.get(1).ok_or(())?.value() {
+                        "off" => None,
+                        _ => Some(parse_size(args.get(1))?),
+                    };
+                    return Ok(Self::Directio { min_size })
+                },
+                Some(DirectiveName::DirectioAlignment) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::DirectioAlignment { size })
+                },
+                Some(DirectiveName::OutputBuffers) => {
+                    let number = parse_number(args.get(1))?;
+                    let size = parse_size(args.get(2))?;
+                    return Ok(Self::OutputBuffers { number, size })
+                },
+                Some(DirectiveName::DefaultType) => {
+                    let mime_type = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::DefaultType { mime_type })
+                },
+                Some(DirectiveName::Deny) => {
+                    let target = AccessTarget::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::Deny { target })
+                },
+                Some(DirectiveName::AuthBasic) => {
+                    let realm = match args.get(1).ok_or(())?.value() {
+                        "off" => None,
+                        realm => Some(realm.to_string()),
+                    };
                     return Ok(Self::AuthBasic { realm })
                 },
-                Some("auth_basic_user_file") => {
-                    let file = args.get(1) .ok_or(())?.to_string();
+                Some(DirectiveName::AuthJwt) => {
+                    let (realm, token) = match args.get(1).ok_or(())?.value() {
+                        "off" => (None, None),
+                        realm => {
+                            let token = match args.get(2) {
+                                Some(token) => Some(token.value().strip_prefix("token=").ok_or(())?.to_string()),
+                                None => None,
+                            };
+                            (Some(realm.to_string()), token)
+                        }
+                    };
+                    return Ok(Self::AuthJwt { realm, token })
+                },
+                Some(DirectiveName::AuthJwtKeyFile) => {
+                    let file = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::AuthJwtKeyFile { file })
+                },
+                Some(DirectiveName::AuthJwtKeyRequest) => {
+                    let uri = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::AuthJwtKeyRequest { uri })
+                },
+                Some(DirectiveName::AuthRequest) => {
+                    let uri = match args.get(1).ok_or(())?.value() {
+                        "off" => None,
+                        uri => Some(uri.to_string()),
+                    };
+                    return Ok(Self::AuthRequest { uri })
+                },
+                Some(DirectiveName::AuthRequestSet) => {
+                    let variable = args.get(1).ok_or(())?.value().to_string();
+                    let value = args.get(2).ok_or(())?.value().to_string();
+                    if !variable.starts_with('$') {
+                        return Err(())
+                    }
+                    return Ok(Self::AuthRequestSet { variable, value })
+                },
+                Some(DirectiveName::Satisfy) => {
+                    let mode = match args.get(1).ok_or(())?.value() {
+                        "all" => SatisfyMode::All,
+                        "any" => SatisfyMode::Any,
+                        _ => return Err(()),
+                    };
+                    return Ok(Self::Satisfy { mode })
+                },
+                Some(DirectiveName::AuthBasicUserFile) => {
+                    let file = args.get(1) .ok_or(())?.value().to_string();
                     let file = PathBuf::from(file);
                     return Ok(Self::AuthBasicUserFile { file })                    
                 },
-                Some("http2") => {
-                    let enabled = args.get(1).ok_or(())?.to_string().eq_ignore_ascii_case("on")
-                                     || !args.get(1).ok_or(())?.to_string().eq_ignore_ascii_case("off");
+                Some(DirectiveName::Brotli) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::Brotli { enabled })
+                },
+                Some(DirectiveName::BrotliCompLevel) => {
+                    let level = parse_number(args.get(1))?;
+                    if level > 11 {
+                        return Err(())
+                    }
+                    return Ok(Self::BrotliCompLevel { level })
+                },
+                Some(DirectiveName::BrotliMinLength) => {
+                    let length = parse_size(args.get(1))?;
+                    return Ok(Self::BrotliMinLength { length })
+                },
+                Some(DirectiveName::BrotliStatic) => {
+                    let mode = StaticCompression::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::BrotliStatic { mode })
+                },
+                Some(DirectiveName::BrotliTypes) => {
+                    let mime_types = args.get(1..).ok_or(())?.iter().map(|s| s.value().to_string()).collect::<Vec<_>>();
+                    if mime_types.is_empty() {
+                        return Err(())
+                    }
+                    return Ok(Self::BrotliTypes { mime_types })
+                },
+                Some(DirectiveName::Etag) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::Etag { enabled })
+                },
+                Some(DirectiveName::StubStatus) => {
+                    match args.get(1).map(|s| s.value()) {
+                        None | Some("on") if args.len() <= 2 => return Ok(Self::StubStatus),
+                        _ => return Err(()),
+                    }
+                },
+                Some(DirectiveName::Api) => {
+                    let write = match args.get(1).map(|s| s.value()) {
+                        None => false,
+                        Some(arg) => switch(arg.strip_prefix("write=").ok_or(())?)?,
+                    };
+                    return Ok(Self::Api { write })
+                },
+                Some(DirectiveName::StatusZone) => {
+                    let zone = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::StatusZone { zone })
+                },
+                Some(DirectiveName::JsBodyFilter) => {
+                    let function = JsFunction::from_str(args.get(1).ok_or(())?.value())?;
+                    let buffer = match args.get(2).map(|s| s.value()) {
+                        None | Some("buffer_type=string") => false,
+                        Some("buffer_type=buffer") => true,
+                        _ => return Err(()),
+                    };
+                    return Ok(Self::JsBodyFilter { function, buffer })
+                },
+                Some(DirectiveName::JsContent) => {
+                    let function = JsFunction::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::JsContent { function })
+                },
+                Some(DirectiveName::JsHeaderFilter) => {
+                    let function = JsFunction::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::JsHeaderFilter { function })
+                },
+                Some(DirectiveName::JsImport) => {
+                    let values = args[1..].iter().map(|s| s.value()).collect::<Vec<_>>();
+                    let (name, path) = match values.as_slice() {
+                        [path] => (None, path),
+                        [name, "from", path] => (Some(name.to_string()), path),
+                        _ => return Err(()),
+                    };
+                    return Ok(Self::JsImport(JsImport { name, path: PathBuf::from(path) }))
+                },
+                Some(DirectiveName::JsPath) => {
+                    let path = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::JsPath { path })
+                },
+                Some(DirectiveName::JsSet) => {
+                    let variable = args.get(1).ok_or(())?.value().to_string();
+                    if !variable.starts_with('$') {
+                        return Err(())
+                    }
+                    let function = JsFunction::from_str(args.get(2).ok_or(())?.value())?;
+                    let nocache = match args.get(3).map(|s| s.value()) {
+                        None => false,
+                        Some("nocache") => true,
+                        _ => return Err(()),
+                    };
+                    return Ok(Self::JsSet { variable, function, nocache })
+                },
+                Some(DirectiveName::ErrorPage) => {
+                    let (target, rest) = args[1..].split_last().ok_or(())?;
+                    let (status, codes) = match rest.split_last() {
+                        Some((status, codes)) if status.value().starts_with('=') => {
+                            let status = match &status.value()[1..] {
+                                "" => ErrorPageStatus::FromTarget,
+                                code => ErrorPageStatus::Code(code.parse().map_err(|_| ())?),
+                            };
+                            (status, codes)
+                        },
+                        _ => (ErrorPageStatus::Original, rest),
+                    };
+                    let codes = codes.iter().map(|c| c.value().parse::<u16>().map_err(|_| ())).collect::<Result<Vec<_>, _>>()?;
+                    if codes.is_empty() || codes.iter().any(|c| !(300..=599).contains(c)) {
+                        return Err(())
+                    }
+                    let target = target.value().to_string();
+                    return Ok(Self::ErrorPage { codes, status, target })
+                },
+                Some(DirectiveName::TryFiles) => {
+                    let (fallback, files) = args[1..].split_last().ok_or(())?;
+                    if files.is_empty() {
+                        return Err(())
+                    }
+                    let fallback = TryFilesFallback::from_str(fallback.value())?;
+                    let files = files.iter().map(|f| f.value().to_string()).collect();
+                    return Ok(Self::TryFiles { files, fallback })
+                },
+                Some(DirectiveName::Alias) => {
+                    let path = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::Alias { path })
+                },
+                Some(DirectiveName::FastcgiPass) => {
+                    let target = PassTarget::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::FastcgiPass { target })
+                },
+                Some(DirectiveName::Index) => {
+                    let files = args.get(1..).filter(|f| !f.is_empty()).ok_or(())?.iter().map(|s| s.value().to_string()).collect();
+                    return Ok(Self::Index { files })
+                },
+                Some(DirectiveName::Return) => {
+                    let (code, content) = match (args.get(1).map(|s| s.value()), args.get(2).map(|s| s.value())) {
+                        (Some(code), content) if code.bytes().all(|b| b.is_ascii_digit()) => {
+                            let code = code.parse::<u16>().map_err(|_| ())?;
+                            (Some(code), content.map(str::to_string))
+                        },
+                        (Some(url), None) if ["http://", "https://", "$scheme"].iter().any(|p| url.starts_with(p)) => {
+                            (None, Some(url.to_string()))
+                        },
+                        _ => return Err(()),
+                    };
+                    if args.len() > 3 || code.is_some_and(|c| c > 999) {
+                        return Err(())
+                    }
+                    return Ok(Self::Return { code, content })
+                },
+                Some(DirectiveName::Rewrite) => {
+                    let regex = Regex::new(args.get(1).ok_or(())?.value()).map_err(|_| ())?;
+                    let replacement = args.get(2).ok_or(())?.value().to_string();
+                    let flag = args.get(3).map(|f| RewriteFlag::from_str(f.value())).transpose()?;
+                    if args.len() > 4 {
+                        return Err(())
+                    }
+                    return Ok(Self::Rewrite { regex, replacement, flag })
+                },
+                Some(DirectiveName::Root) => {
+                    let path = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::Root { path })
+                },
+                Some(DirectiveName::Internal) => {
+                    if args.len() != 1 {
+                        return Err(())
+                    }
+                    return Ok(Self::Internal)
+                },
+                Some(DirectiveName::Break) => {
+                    if args.len() != 1 {
+                        return Err(())
+                    }
+                    return Ok(Self::Break)
+                },
+                Some(DirectiveName::Set) => {
+                    let variable = args.get(1).ok_or(())?.value().strip_prefix('$').ok_or(())?.to_string();
+                    let value = args.get(2).ok_or(())?.value().to_string();
+                    if args.len() != 3 {
+                        return Err(())
+                    }
+                    return Ok(Self::Set { variable, value })
+                },
+                Some(DirectiveName::Mirror) => {
+                    let uri = match args.get(1).ok_or(())?.value() {
+                        "off" => None,
+                        uri => Some(uri.to_string()),
+                    };
+                    return Ok(Self::Mirror { uri })
+                },
+                Some(DirectiveName::MirrorRequestBody) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::MirrorRequestBody { enabled })
+                },
+                Some(DirectiveName::Slice) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::Slice { size })
+                },
+                Some(DirectiveName::Include) => {
+                    let pattern = args.get(1).ok_or(())?.value().to_string();
+                    let is_glob = crate::include::is_glob(&pattern);
+                    return Ok(Self::Include { pattern, is_glob, resolved: None })
+                },
+                Some(DirectiveName::IfModifiedSince) => {
+                    let mode = match args.get(1).ok_or(())?.value() {
+                        "off" => IfModifiedSince::Off,
+                        "exact" => IfModifiedSince::Exact,
+                        "before" => IfModifiedSince::Before,
+                        _ => return Err(()),
+                    };
+                    return Ok(Self::IfModifiedSince { mode })
+                },
+                Some(DirectiveName::Expires) => {
+                    let expires = Expires::parse(args.get(1..).ok_or(())?)?;
+                    return Ok(Self::Expires(expires))
+                },
+                Some(DirectiveName::GeoipCity) => {
+                    let database = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::GeoIpCity { database })
+                },
+                Some(DirectiveName::GeoipCountry) => {
+                    let database = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::GeoIpCountry { database })
+                },
+                Some(DirectiveName::GeoipOrg) => {
+                    let database = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::GeoIpOrg { database })
+                },
+                Some(DirectiveName::GeoipProxy) => {
+                    let target = AccessTarget::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::GeoIpProxy { target })
+                },
+                Some(DirectiveName::GrpcPass) => {
+                    let target = args.get(1).ok_or(())?.value().to_string();
+                    let secure = target.starts_with("grpcs://");
+                    let target = target.strip_prefix("grpc://").or_else(|| target.strip_prefix("grpcs://")).unwrap_or(&target);
+                    let target = PassTarget::from_str(target)?;
+                    return Ok(Self::GrpcPass { target, secure })
+                },
+                Some(DirectiveName::GrpcSetHeader) => {
+                    let header_name = args.get(1).ok_or(())?.value().to_string();
+                    let header_value = args.get(2).ok_or(())?.value().to_string();
+                    return Ok(Self::GrpcSetHeader { header_name, header_value })
+                },
+                Some(DirectiveName::GrpcSslTrustedCertificate) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
+                    return Ok(Self::GrpcSslTrustedCertificate { path })
+                },
+                Some(DirectiveName::GrpcSslVerify) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::GrpcSslVerify { enabled })
+                },
+                Some(DirectiveName::Gzip) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::Gzip { enabled })
+                },
+                Some(DirectiveName::GzipCompLevel) => {
+                    let level = parse_number(args.get(1))?;
+                    if !(1..=9).contains(&level) {
+                        return Err(())
+                    }
+                    return Ok(Self::GzipCompLevel { level })
+                },
+                Some(DirectiveName::GzipMinLength) => {
+                    let length = parse_size(args.get(1))?;
+                    return Ok(Self::GzipMinLength { length })
+                },
+                Some(DirectiveName::GzipProxied) => {
+                    let mut conditions = Vec::new();
+                    for arg in args.get(1..).ok_or(())? {
+                        match arg.value() {
+                            "off" => conditions.clear(),
+                            condition => conditions.push(GzipProxied::from_str(condition)?),
+                        }
+                    }
+                    return Ok(Self::GzipProxied { conditions })
+                },
+                Some(DirectiveName::GzipStatic) => {
+                    let mode = StaticCompression::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::GzipStatic { mode })
+                },
+                Some(DirectiveName::GzipTypes) => {
+                    let mime_types = args.get(1..).ok_or(())?.iter().map(|s| s.value().to_string()).collect::<Vec<_>>();
+                    if mime_types.is_empty() {
+                        return Err(())
+                    }
+                    return Ok(Self::GzipTypes { mime_types })
+                },
+                Some(DirectiveName::GzipVary) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::GzipVary { enabled })
+                },
+                Some(DirectiveName::Http2) => {
+                    let enabled = parse_switch(args.get(1))?;
                     return Ok(Self::Http2 { enabled })
                 },
-                Some("proxy_http_version") => {
-                    let version = args.get(1).ok_or(())?.to_string();
+                Some(DirectiveName::Http3) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::Http3 { enabled })
+                },
+                Some(DirectiveName::Http3Hq) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::Http3Hq { enabled })
+                },
+                Some(DirectiveName::QuicGso) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::QuicGso { enabled })
+                },
+                Some(DirectiveName::QuicRetry) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::QuicRetry { enabled })
+                },
+                Some(DirectiveName::Http2BodyPrereadSize) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::Http2BodyPrereadSize { size })
+                },
+                Some(DirectiveName::Http2MaxConcurrentStreams) => {
+                    let streams = parse_number(args.get(1))?;
+                    return Ok(Self::Http2MaxConcurrentStreams { streams })
+                },
+                Some(DirectiveName::ProxyBuffering) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::ProxyBuffering { enabled })
+                },
+                Some(DirectiveName::ProxyBuffers) => {
+                    let number = parse_number(args.get(1))?;
+                    let size = parse_size(args.get(2))?;
+                    return Ok(Self::ProxyBuffers { number, size })
+                },
+                Some(DirectiveName::ProxyBufferSize) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::ProxyBufferSize { size })
+                },
+                Some(DirectiveName::ProxyBusyBuffersSize) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::ProxyBusyBuffersSize { size })
+                },
+                Some(DirectiveName::ProxyMaxTempFileSize) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::ProxyMaxTempFileSize { size })
+                },
+                Some(DirectiveName::ProxyHttpVersion) => {
+                    let version = args.get(1).ok_or(())?.value().to_string();
                     return Ok(Self::ProxyHttpVersion { version })
                 },
-                Some("proxy_pass") => {
-                    let addr = args.get(1).ok_or(())?.to_string();
+                Some(DirectiveName::ProxyNextUpstream) => {
+                    let mut next_upstream = NextUpstream::default();
+                    for arg in args.get(1..).ok_or(())? {
+                        match arg.value() {
+                            "off" => next_upstream.conditions.clear(),
+                            condition => {
+                                let condition = NextUpstreamCondition::from_str(condition)?;
+                                if !next_upstream.retries_on(condition) {
+                                    next_upstream.conditions.push(condition);
+                                }
+                            }
+                        }
+                    }
+                    return Ok(Self::ProxyNextUpstream(next_upstream))
+                },
+                Some(DirectiveName::ProxyNextUpstreamTries) => {
+                    let tries = parse_number(args.get(1))?;
+                    return Ok(Self::ProxyNextUpstreamTries { tries })
+                },
+                Some(DirectiveName::ProxyNextUpstreamTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::ProxyNextUpstreamTimeout { timeout })
+                },
+                Some(DirectiveName::ProxyPass) => {
+                    let addr = args.get(1).ok_or(())?.value().to_string();
                     let addr = Url::parse(&addr).map_err(|_| ())?;
                     return Ok(Self::ProxyPass { addr })
                 },
-                Some("proxy_hide_header") => {
-                    let header_name = args.get(1).ok_or(())?.to_string();
+                Some(DirectiveName::ProxyConnectTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::ProxyConnectTimeout { timeout })
+                },
+                Some(DirectiveName::ProxyReadTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::ProxyReadTimeout { timeout })
+                },
+                Some(DirectiveName::ProxySendTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::ProxySendTimeout { timeout })
+                },
+                Some(DirectiveName::ProxyHideHeader) => {
+                    let header_name = args.get(1).ok_or(())?.value().to_string();
                     return Ok(Self::ProxyHideHeader { header_name })
                 },
-                Some("proxy_set_header") => {
-                    let header_name = args.get(1).ok_or(())?.to_string();
-                    let header_value = args.get(2).ok_or(())?.to_string();
+                Some(DirectiveName::ProxySslCertificate) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
+                    return Ok(Self::ProxySslCertificate { path })
+                },
+                Some(DirectiveName::ProxySslCertificateKey) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
+                    return Ok(Self::ProxySslCertificateKey { path })
+                },
+                Some(DirectiveName::ProxySslName) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::ProxySslName { name })
+                },
+                Some(DirectiveName::ProxySslProtocols) => {
+                    let protocols = parse_protocols(args.get(1..).ok_or(())?)?;
+                    return Ok(Self::ProxySslProtocols(protocols))
+                },
+                Some(DirectiveName::ProxySslServerName) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::ProxySslServerName { enabled })
+                },
+                Some(DirectiveName::ProxySslTrustedCertificate) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
+                    return Ok(Self::ProxySslTrustedCertificate { path })
+                },
+                Some(DirectiveName::ProxySslVerify) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::ProxySslVerify { enabled })
+                },
+                Some(DirectiveName::ProxySetHeader) => {
+                    let header_name = args.get(1).ok_or(())?.value().to_string();
+                    let header_value = args.get(2).ok_or(())?.value().to_string();
                     return Ok(Self::ProxySetHeader { header_name, header_value })
                 },
-                Some("server_name") => {
-                    let name = args.get(2..).ok_or(())?.iter().map(|s| format!("{}", s.to_string())).collect::<String>();
-                    return Ok(Self::ServerName { name })
+                Some(DirectiveName::Resolver) => {
+                    let mut servers = Vec::new();
+                    let (mut valid, mut ipv4, mut ipv6, mut status_zone) = (None, None, None, None);
+                    for arg in args.get(1..).ok_or(())? {
+                        let arg = arg.value();
+                        match arg.split_once('=') {
+                            Some(("valid", time)) => valid = Some(NginxTime::from_str(time)?.duration()),
+                            Some(("ipv4", value)) => ipv4 = Some(switch(value)?),
+                            Some(("ipv6", value)) => ipv6 = Some(switch(value)?),
+                            Some(("status_zone", zone)) => status_zone = Some(zone.to_string()),
+                            Some(_) => return Err(()),
+                            None => servers.push(ResolverAddr::from_str(arg)?),
+                        }
+                    }
+                    if servers.is_empty() {
+                        return Err(())
+                    }
+                    return Ok(Self::Resolver { servers, valid, ipv4, ipv6, status_zone })
+                },
+                Some(DirectiveName::ResolverTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::ResolverTimeout { timeout })
+                },
+                Some(DirectiveName::ScgiParam) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    let value = args.get(2).ok_or(())?.value().to_string();
+                    let if_not_empty = args.get(3).is_some_and(|s| s.value() == "if_not_empty");
+                    return Ok(Self::ScgiParam { name, value, if_not_empty })
+                },
+                Some(DirectiveName::ScgiPass) => {
+                    let target = PassTarget::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::ScgiPass { target })
+                },
+                Some(DirectiveName::SubFilter) => {
+                    let pattern = args.get(1).ok_or(())?.value().to_string();
+                    let replacement = args.get(2).ok_or(())?.value().to_string();
+                    return Ok(Self::SubFilter { pattern, replacement })
+                },
+                Some(DirectiveName::SubFilterLastModified) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::SubFilterLastModified { enabled })
+                },
+                Some(DirectiveName::SubFilterOnce) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::SubFilterOnce { enabled })
+                },
+                Some(DirectiveName::SubFilterTypes) => {
+                    let mime_types = args.get(1..).ok_or(())?.iter().map(|s| s.value().to_string()).collect::<Vec<_>>();
+                    if mime_types.is_empty() {
+                        return Err(())
+                    }
+                    return Ok(Self::SubFilterTypes { mime_types })
+                },
+                Some(DirectiveName::Env) => {
+                    let variable = args.get(1).ok_or(())?.value();
+                    let (name, value) = match variable.split_once('=') {
+                        Some((name, value)) => (name, Some(value.to_string())),
+                        None => (variable, None),
+                    };
+                    if name.is_empty() {
+                        return Err(())
+                    }
+                    return Ok(Self::Env { name: name.to_string(), value })
+                },
+                Some(DirectiveName::LoadModule) => {
+                    let path = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::LoadModule { path })
+                },
+                Some(DirectiveName::Daemon) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::Daemon { enabled })
+                },
+                Some(DirectiveName::MasterProcess) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::MasterProcess { enabled })
+                },
+                Some(DirectiveName::Pid) => {
+                    let path = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::Pid { path })
+                },
+                Some(DirectiveName::UnderscoresInHeaders) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::UnderscoresInHeaders { enabled })
+                },
+                Some(DirectiveName::User) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    let group = args.get(2).map(|s| s.value().to_string());
+                    return Ok(Self::User { name, group })
+                },
+                Some(DirectiveName::WorkingDirectory) => {
+                    let path = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::WorkingDirectory { path })
+                },
+                Some(DirectiveName::MultiAccept) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::MultiAccept { enabled })
+                },
+                Some(DirectiveName::ValidReferers) => {
+                    let patterns = args.get(1..).filter(|p| !p.is_empty()).ok_or(())?.iter().map(|s| RefererPattern::from_str(s.value())).collect::<Result<Vec<_>, _>>()?;
+                    return Ok(Self::ValidReferers(ValidReferers { patterns }))
+                },
+                Some(DirectiveName::SecureLink) => {
+                    let value = args.get(1).ok_or(())?.value();
+                    let (hash, expires) = match value.split_once(',') {
+                        Some((hash, expires)) => (hash, Some(expires.to_string())),
+                        None => (value, None),
+                    };
+                    if hash.is_empty() {
+                        return Err(())
+                    }
+                    return Ok(Self::SecureLink { hash: hash.to_string(), expires })
+                },
+                Some(DirectiveName::SecureLinkMd5) => {
+                    let expression = args.get(1).ok_or(())?.value().to_string();
+                    let template = parse_template(&[expression.as_str()])?;
+                    return Ok(Self::SecureLinkMd5 { expression, template })
+                },
+                Some(DirectiveName::SecureLinkSecret) => {
+                    let secret = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::SecureLinkSecret { secret })
+                },
+                Some(DirectiveName::Use) => {
+                    let method = EventMethod::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::Use { method })
+                },
+                Some(DirectiveName::WorkerCpuAffinity) => {
+                    let mut masks = args.get(1..).filter(|m| !m.is_empty()).ok_or(())?.iter().map(|s| s.value()).collect::<Vec<_>>();
+                    let auto = masks.first() == Some(&"auto");
+                    if auto {
+                        masks.remove(0);
+                    }
+                    if masks.iter().any(|m| m.is_empty() || !m.chars().all(|c| c == '0' || c == '1')) {
+                        return Err(())
+                    }
+                    let masks = masks.into_iter().map(|m| m.to_string()).collect();
+                    return Ok(Self::WorkerCpuAffinity { auto, masks })
+                },
+                Some(DirectiveName::WorkerConnections) => {
+                    let connections = parse_number(args.get(1))?;
+                    return Ok(Self::WorkerConnections { connections })
+                },
+                Some(DirectiveName::WorkerProcesses) => {
+                    let processes = match args.get(1).ok_or(())?.value() {
+                        "auto" => WorkerProcesses::Auto,
+                        _ => WorkerProcesses::Count(parse_number(args.get(1))?),
+                    };
+                    return Ok(Self::WorkerProcesses { processes })
+                },
+                Some(DirectiveName::WorkerRlimitNofile) => {
+                    let limit = parse_number(args.get(1))?;
+                    return Ok(Self::WorkerRlimitNofile { limit })
+                },
+                Some(DirectiveName::UwsgiParam) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    let value = args.get(2).ok_or(())?.value().to_string();
+                    let if_not_empty = args.get(3).is_some_and(|s| s.value() == "if_not_empty");
+                    return Ok(Self::UwsgiParam { name, value, if_not_empty })
+                },
+                Some(DirectiveName::UwsgiPass) => {
+                    let target = args.get(1).ok_or(())?.value().to_string();
+                    let secure = target.starts_with("suwsgi://");
+                    let target = target.strip_prefix("uwsgi://").or_else(|| target.strip_prefix("suwsgi://")).unwrap_or(&target);
+                    let target = PassTarget::from_str(target)?;
+                    return Ok(Self::UwsgiPass { target, secure })
+                },
+                Some(DirectiveName::Sendfile) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::Sendfile { enabled })
                 },
-                Some("ssl_cerificate") => {
-                    let path = PathBuf::from( args.get(1).ok_or(())?.to_string() );
+                Some(DirectiveName::SendfileMaxChunk) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::SendfileMaxChunk { size })
+                },
+                Some(DirectiveName::SendTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::SendTimeout { timeout })
+                },
+                Some(DirectiveName::TcpNodelay) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::TcpNodelay { enabled })
+                },
+                Some(DirectiveName::TcpNopush) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::TcpNopush { enabled })
+                },
+                Some(DirectiveName::ServerName) => {
+                    let names = args.get(1..).filter(|n| !n.is_empty()).ok_or(())?.iter().map(|s| s.value().to_string()).collect();
+                    return Ok(Self::ServerName { names })
+                },
+                Some(DirectiveName::SslCertificate) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
                     return Ok(Self::SslCertificate { path })
                 },
-                Some("ssl_certificate_key") => {
-                    let path = PathBuf::from( args.get(1).ok_or(())?.to_string() );
+                Some(DirectiveName::SslCiphers) => {
+                    let ciphers = args.get(1..).ok_or(())?.iter().map(|s| s.value()).collect::<Vec<_>>().join(":");
+                    return Ok(Self::SslCiphers(CipherList::from_str(&ciphers)?))
+                },
+                Some(DirectiveName::SslPreferServerCiphers) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::SslPreferServerCiphers { enabled })
+                },
+                Some(DirectiveName::SslDhparam) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
+                    return Ok(Self::SslDhparam { path })
+                },
+                Some(DirectiveName::SslEcdhCurve) => {
+                    let curves = match args.get(1).ok_or(())?.value() {
+                        "auto" => None,
+                        curves => Some(curves.split(':').filter(|c| !c.is_empty()).map(|c| c.to_string()).collect::<Vec<_>>()),
+                    };
+                    if curves.as_ref().is_some_and(|c| c.is_empty()) {
+                        return Err(())
+                    }
+                    return Ok(Self::SslEcdhCurve { curves })
+                },
+                Some(DirectiveName::SslEarlyData) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::SslEarlyData { enabled })
+                },
+                Some(DirectiveName::SslPasswordFile) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
+                    return Ok(Self::SslPasswordFile { path })
+                },
+                Some(DirectiveName::SslRejectHandshake) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::SslRejectHandshake { enabled })
+                },
+                Some(DirectiveName::SslSessionTickets) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::SslSessionTickets { enabled })
+                },
+                Some(DirectiveName::SslSessionCache) => {
+                    let caches = args.get(1..).ok_or(())?.iter().map(|s| SessionCache::from_str(s.value())).collect::<Result<Vec<_>, _>>()?;
+                    let is_exclusive = caches.iter().any(|c| matches!(c, SessionCache::Off | SessionCache::None));
+                    if caches.is_empty() || (is_exclusive && caches.len() > 1) {
+                        return Err(())
+                    }
+                    return Ok(Self::SslSessionCache { caches })
+                },
+                Some(DirectiveName::SslSessionTimeout) => {
+                    let timeout = parse_time(args.get(1))?;
+                    return Ok(Self::SslSessionTimeout { timeout })
+                },
+                Some(DirectiveName::SslStapling) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::SslStapling { enabled })
+                },
+                Some(DirectiveName::SslStaplingFile) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
+                    return Ok(Self::SslStaplingFile { path })
+                },
+                Some(DirectiveName::SslStaplingVerify) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::SslStaplingVerify { enabled })
+                },
+                Some(DirectiveName::SslTrustedCertificate) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
+                    return Ok(Self::SslTrustedCertificate { path })
+                },
+                Some(DirectiveName::SslProtocols) => {
+                    let protocols = parse_protocols(args.get(1..).ok_or(())?)?;
+                    return Ok(Self::SslProtocols(protocols))
+                },
+                Some(DirectiveName::SslCertificateKey) => {
+                    let path = PathBuf::from( args.get(1).ok_or(())?.value().to_string() );
                     return Ok(Self::SslCertificateKey { path })                    
                 },
-                Some("listen") => {
-                    let sock_addr = args.get(1).ok_or(())?.to_string();
-                    let sock_addr = SocketAddr::from_str(&sock_addr).map_err(|_|())?;
-                    let is_default = args.iter().any(|s| s.to_string().eq_ignore_ascii_case("default_server"));
-                    let is_http2 = args.iter().any(|s| s.to_string().eq_ignore_ascii_case("http2"));
-                    let is_http3 = args.iter().any(|s| s.to_string().eq_ignore_ascii_case("http3"));
+                Some(DirectiveName::LimitConn) => {
+                    let zone = args.get(1).ok_or(())?.value().to_string();
+                    let connections = parse_number(args.get(2))?;
+                    return Ok(Self::LimitConn { zone, connections })
+                },
+                Some(DirectiveName::LimitConnStatus) => {
+                    let code = parse_number(args.get(1))?;
+                    return Ok(Self::LimitConnStatus { code })
+                },
+                Some(DirectiveName::LimitConnZone) => {
+                    let key = args.get(1).ok_or(())?.value().to_string();
+                    let (zone, size) = parse_zone(args.get(2).ok_or(())?.value())?;
+                    return Ok(Self::LimitConnZone(LimitConnZone { key, zone, size }))
+                },
+                Some(DirectiveName::LimitRate) => {
+                    let rate = Dynamic::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::LimitRate { rate })
+                },
+                Some(DirectiveName::LimitRateAfter) => {
+                    let size = Dynamic::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::LimitRateAfter { size })
+                },
+                Some(DirectiveName::LimitReq) => {
+                    let mut zone = None;
+                    let mut burst = 0;
+                    let mut delay = ReqDelay::Delayed;
+                    for arg in args.get(1..).ok_or(())? {
+                        let arg = arg.value();
+                        if let Some(name) = arg.strip_prefix("zone=") {
+                            zone = Some(name.to_string());
+                        } else if let Some(number) = arg.strip_prefix("burst=") {
+                            burst = number.parse().map_err(|_| ())?;
+                        } else if let Some(number) = arg.strip_prefix("delay=") {
+                            delay = ReqDelay::After(number.parse().map_err(|_| ())?);
+                        } else if arg == "nodelay" {
+                            delay = ReqDelay::NoDelay;
+                        } else {
+                            return Err(())
+                        }
+                    }
+                    let zone = zone.filter(|z| !z.is_empty()).ok_or(())?;
+                    return Ok(Self::LimitReq(LimitReq { zone, burst, delay }))
+                },
+                Some(DirectiveName::LimitReqStatus) => {
+                    let code = parse_number(args.get(1))?;
+                    return Ok(Self::LimitReqStatus { code })
+                },
+                Some(DirectiveName::LimitReqZone) => {
+                    let key = args.get(1).ok_or(())?.value().to_string();
+                    let mut zone = None;
+                    let mut rate = None;
+                    let mut sync = false;
+                    for arg in args.get(2..).ok_or(())? {
+                        let arg = arg.value();
+                        if arg.starts_with("zone=") {
+                            zone = Some(parse_zone(arg)?);
+                        } else if let Some(value) = arg.strip_prefix("rate=") {
+                            rate = Some(Rate::from_str(value)?);
+                        } else if arg == "sync" {
+                            sync = true;
+                        } else {
+                            return Err(())
+                        }
+                    }
+                    let (zone, size) = zone.ok_or(())?;
+                    let rate = rate.ok_or(())?;
+                    return Ok(Self::LimitReqZone(LimitReqZone { key, zone, size, rate, sync }))
+                },
+                Some(DirectiveName::OpenFileCache) => {
+                    if args.get(1).ok_or(())?.value() == "off" && args.len() == 2 {
+                        return Ok(Self::OpenFileCache(None))
+                    }
+                    let mut max = None;
+                    let mut inactive = Duration::from_secs(60);
+                    for arg in &args[1..] {
+                        match arg.value().split_once('=').ok_or(())? {
+                            ("max", value) => max = Some(value.parse().map_err(|_| ())?),
+                            ("inactive", value) => inactive = NginxTime::from_str(value)?.duration(),
+                            _ => return Err(()),
+                        }
+                    }
+                    let max = max.ok_or(())?;
+                    return Ok(Self::OpenFileCache(Some(OpenFileCache { max, inactive })))
+                },
+                Some(DirectiveName::OpenFileCacheErrors) => {
+                    let enabled = parse_switch(args.get(1))?;
+                    return Ok(Self::OpenFileCacheErrors { enabled })
+                },
+                Some(DirectiveName::OpenFileCacheMinUses) => {
+                    let uses = parse_number(args.get(1))?;
+                    return Ok(Self::OpenFileCacheMinUses { uses })
+                },
+                Some(DirectiveName::OpenFileCacheValid) => {
+                    let time = parse_time(args.get(1))?;
+                    return Ok(Self::OpenFileCacheValid { time })
+                },
+                Some(DirectiveName::OpenLogFileCache) => {
+                    let cache = match args.get(1).ok_or(())?.value() {
+                        "off" if args.len() == 2 => None,
+                        _ => Some(OpenLogFileCache::parse(&args[1..])?),
+                    };
+                    return Ok(Self::OpenLogFileCache(cache))
+                },
+                Some(DirectiveName::LogFormat) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    let mut parts = args.get(2..).ok_or(())?;
+                    let mut escape = LogEscape::Default;
+                    if let Some(mode) = parts.first().and_then(|s| s.value().strip_prefix("escape=")) {
+                        escape = match mode {
+                            "default" => LogEscape::Default,
+                            "json" => LogEscape::Json,
+                            "none" => LogEscape::None,
+                            _ => return Err(()),
+                        };
+                        parts = &parts[1..];
+                    }
+                    let parts = parts.iter().map(|s| s.value()).collect::<Vec<_>>();
+                    if parts.is_empty() {
+                        return Err(())
+                    }
+                    let template = parse_template(&parts)?;
+                    return Ok(Self::LogFormat(LogFormat { name, escape, template }))
+                },
+                Some(DirectiveName::Listen) => {
+                    let sock_addr = parse_listen_addr(args.get(1).ok_or(())?.value())?;
+                    let is_default = args.iter().any(|s| s.value().eq_ignore_ascii_case("default_server"));
+                    let is_http2 = args.iter().any(|s| s.value().eq_ignore_ascii_case("http2"));
+                    let is_http3 = args.iter().any(|s| s.value().eq_ignore_ascii_case("quic") || s.value().eq_ignore_ascii_case("http3"));
                     return Ok(Self::Listen { sock_addr, is_default, is_http2, is_http3 })
                 },
+                Some(DirectiveName::HealthCheck) => {
+                    let values = args[1..].iter().map(|s| s.value()).collect::<Vec<_>>();
+                    return Ok(Self::HealthCheck(HealthCheck::parse(&values)?))
+                },
+                Some(DirectiveName::State) => {
+                    let path = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::State { path })
+                },
+                Some(DirectiveName::Zone) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    let size = args.get(2).map(|s| Size::from_str(s.value())).transpose()?;
+                    return Ok(Self::Zone { name, size })
+                },
+                Some(DirectiveName::ImageFilter) => {
+                    let values = args[1..].iter().map(|s| s.value()).collect::<Vec<_>>();
+                    return Ok(Self::ImageFilter(ImageFilter::parse(&values)?))
+                },
+                Some(DirectiveName::ImageFilterBuffer) => {
+                    let size = parse_size(args.get(1))?;
+                    return Ok(Self::ImageFilterBuffer { size })
+                },
+                Some(DirectiveName::XsltStylesheet) => {
+                    let path = PathBuf::from(args.get(1).ok_or(())?.value());
+                    let parameters = args[2..]
+                        .iter()
+                        .map(|s| s.value().split_once('=').map(|(n, v)| (n.to_string(), v.to_string())).ok_or(()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    return Ok(Self::XsltStylesheet { path, parameters })
+                },
+                Some(DirectiveName::XsltTypes) => {
+                    let mime_types = args.get(1..).ok_or(())?.iter().map(|s| s.value().to_string()).collect::<Vec<_>>();
+                    if mime_types.is_empty() {
+                        return Err(())
+                    }
+                    return Ok(Self::XsltTypes { mime_types })
+                },
+                Some(DirectiveName::Perl) => {
+                    let handler = PerlHandler::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::Perl { handler })
+                },
+                Some(DirectiveName::PerlModules) => {
+                    let path = PathBuf::from(args.get(1).ok_or(())?.value());
+                    return Ok(Self::PerlModules { path })
+                },
+                Some(DirectiveName::PerlRequire) => {
+                    let module = args.get(1).ok_or(())?.value().to_string();
+                    return Ok(Self::PerlRequire { module })
+                },
+                Some(DirectiveName::PerlSet) => {
+                    let variable = args.get(1).ok_or(())?.value().to_string();
+                    if !variable.starts_with('$') {
+                        return Err(())
+                    }
+                    let handler = PerlHandler::from_str(args.get(2).ok_or(())?.value())?;
+                    return Ok(Self::PerlSet { variable, handler })
+                },
+                Some(DirectiveName::LuaPackagePath) => {
+                    let path = LuaSearchPath::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::LuaPackagePath(path))
+                },
+                Some(DirectiveName::LuaPackageCpath) => {
+                    let path = LuaSearchPath::from_str(args.get(1).ok_or(())?.value())?;
+                    return Ok(Self::LuaPackageCpath(path))
+                },
+                Some(DirectiveName::LuaSharedDict) => {
+                    let name = args.get(1).ok_or(())?.value().to_string();
+                    let size = parse_size(args.get(2))?;
+                    return Ok(Self::LuaSharedDict { name, size })
+                },
+                Some(name) if name.as_str().contains("_by_lua") => {
+                    let (phase, form) = name.as_str().split_once("_by_lua").ok_or(())?;
+                    let phase = LuaPhase::from_str(phase)?;
+                    let mut values = args[1..].iter().map(|s| s.value().to_string());
+                    let variable = match phase {
+                        LuaPhase::Set => Some(values.next().filter(|v| v.starts_with('$')).ok_or(())?),
+                        _ => None,
+                    };
+                    match form {
+                        "" | "_block" => {
+                            let code = values.next().ok_or(())?;
+                            if values.next().is_some() {
+                                return Err(())
+                            }
+                            return Ok(Self::LuaCode { phase, variable, code })
+                        },
+                        "_file" => {
+                            let path = PathBuf::from(values.next().ok_or(())?);
+                            let args = values.collect::<Vec<_>>();
+                            if phase != LuaPhase::Set && !args.is_empty() {
+                                return Err(())
+                            }
+                            return Ok(Self::LuaFile { phase, variable, path, args })
+                        },
+                        _ => return Err(()),
+                    }
+                },
                 
                 // TODO
                 
-                _ => unreachable!()               
+                _ => return Err(())
             };
         }
         
         Err(())
     }
 }
-                
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(Size::from_str("512"), Ok(Size(512)));
+        assert_eq!(Size::from_str("8k"), Ok(Size(8 << 10)));
+        assert_eq!(Size::from_str("16M"), Ok(Size(16 << 20)));
+        assert_eq!(Size::from_str("1g"), Ok(Size(1 << 30)));
+        assert_eq!(Size::from_str(""), Err(()));
+        assert_eq!(Size::from_str("k"), Err(()));
+        assert_eq!(Size::from_str("99999999999999g"), Err(()));
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(NginxTime::from_str("30").map(|t| t.duration()), Ok(Duration::from_secs(30)));
+        assert_eq!(NginxTime::from_str("500ms").map(|t| t.duration()), Ok(Duration::from_millis(500)));
+        assert_eq!(NginxTime::from_str("1h30m").map(|t| t.duration()), Ok(Duration::from_secs(90 * 60)));
+        assert!(NginxTime::from_str("1x").is_err());
+        assert!(NginxTime::from_str("18446744073709551615y").is_err());
+        assert!(NginxTime::from_str("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn if_conditions_keep_regex_groups() {
+        let root = Structure::parse("if ($request_uri !~* ^/(a|b)/(c)$) { return 404; }").unwrap();
+        let Structure::Block { children, .. } = &root else { unreachable!() };
+        let Ok(Directive::If { condition: Condition::Match { variable, regex, negated } }) = Directive::try_from(&children[0]) else {
+            panic!("not a regex condition")
+        };
+        assert_eq!((variable.as_str(), regex.as_str(), negated), ("request_uri", "(?i)^/(a|b)/(c)$", true));
+        assert!(matches!(Condition::from_str("-f $request_filename"), Ok(Condition::File { test: FileTest::File, negated: false, .. })));
+        assert!(Condition::try_from(&[Token::Word("$a")][..]).is_err());
+    }
+
+    #[test]
+    fn oversized_arguments_are_invalid() {
+        let root = Structure::parse("client_max_body_size 99999999999999g;").unwrap();
+        let Structure::Block { children, .. } = &root else { unreachable!() };
+        assert!(Directive::try_from(&children[0]).is_err());
+    }
+
+    #[test]
+    fn grpcs_trusted_certificate_is_inherited() {
+        let root = Structure::parse(
+            "server { grpc_ssl_trusted_certificate ca.pem; location / { grpc_pass grpcs://[::1]:50051; } }\n\
+             server { location / { grpc_pass grpcs://[::1]:50051; } }",
+        )
+        .unwrap();
+        let Structure::Block { children: servers, .. } = &root else { unreachable!() };
+        for (server, unverified) in servers.iter().zip([false, true]) {
+            let Structure::Block { children, .. } = server else { unreachable!() };
+            assert_eq!(grpcs_without_trusted_certificate(&[server, &children[children.len() - 1]]), unverified);
+            assert!(!grpcs_without_trusted_certificate(&[server]));
+        }
+    }
+
+    #[test]
+    fn upstream_timeouts_are_inherited() {
+        let root = Structure::parse("server { proxy_read_timeout 5m; proxy_connect_timeout 5s; location / { proxy_connect_timeout 2s; proxy_pass http://app; } }").unwrap();
+        let Structure::Block { children, .. } = &root else { unreachable!() };
+        let Structure::Block { children: locations, .. } = &children[0] else { unreachable!() };
+        let timeouts = UpstreamTimeouts::from_settings(&crate::effective::effective_config(&[&children[0], &locations[2]]));
+        let expected = UpstreamTimeouts { connect: Duration::from_secs(2), send: Duration::from_secs(60), read: Duration::from_secs(300) };
+        assert_eq!(timeouts, expected);
+        assert_eq!(UpstreamTimeouts::from_settings(&Default::default()), UpstreamTimeouts::default());
+    }
+}
@@ -0,0 +1,1405 @@
+use std::{collections::{HashMap, HashSet}, fmt::Display, net::SocketAddr, ops::BitOr, str::FromStr};
+
+use crate::{
+    deprecated::{deprecations, Deprecation},
+    directives::{third_party_module, BUILTIN_VARIABLES, BUILTIN_VARIABLE_PREFIXES},
+    dns::DnsProblem,
+    effective::group,
+    file_checks::FileProblem,
+    names::DirectiveName,
+    types::{condition_words, Condition, NginxTime, ServerNamePattern, ServerSet, Size},
+    Structure, Token,
+};
+
+/// Where a directive sits, as nginx tells contexts apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+    Main,
+    Events,
+    Http,
+    Server,
+    Location,
+    IfInServer,
+    IfInLocation,
+    LimitExcept,
+    Upstream,
+    Mail,
+    MailServer,
+    Stream,
+    StreamServer,
+    StreamUpstream,
+}
+
+impl Context {
+    pub const ALL: &'static [Self] = &[
+        Self::Main,
+        Self::Events,
+        Self::Http,
+        Self::Server,
+        Self::Location,
+        Self::IfInServer,
+        Self::IfInLocation,
+        Self::LimitExcept,
+        Self::Upstream,
+        Self::Mail,
+        Self::MailServer,
+        Self::Stream,
+        Self::StreamServer,
+        Self::StreamUpstream,
+    ];
+
+    /// Context opened by a block of this directive inside `self`, `None` for blocks that aren't contexts
+    /// (`map`, `types`, `geo`...) whose content isn't made of directives
+    pub fn enter(self, block: DirectiveName) -> Option<Self> {
+        Some(match block {
+            DirectiveName::Events => Self::Events,
+            DirectiveName::Http => Self::Http,
+            DirectiveName::Mail => Self::Mail,
+            DirectiveName::Stream => Self::Stream,
+            DirectiveName::Server => match self {
+                Self::Mail => Self::MailServer,
+                Self::Stream => Self::StreamServer,
+                _ => Self::Server,
+            },
+            DirectiveName::Upstream => match self {
+                Self::Stream => Self::StreamUpstream,
+                _ => Self::Upstream,
+            },
+            DirectiveName::Location => Self::Location,
+            DirectiveName::If => match self {
+                Self::Server => Self::IfInServer,
+                _ => Self::IfInLocation,
+            },
+            DirectiveName::LimitExcept => Self::LimitExcept,
+            _ => return None,
+        })
+    }
+
+    /// The context as the nginx docs name it
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Main => "main",
+            Self::Events => "events",
+            Self::Http => "http",
+            Self::Server => "server",
+            Self::Location => "location",
+            Self::IfInServer => "if in server",
+            Self::IfInLocation => "if in location",
+            Self::LimitExcept => "limit_except",
+            Self::Upstream => "upstream",
+            Self::Mail => "mail",
+            Self::MailServer => "mail server",
+            Self::Stream => "stream",
+            Self::StreamServer => "stream server",
+            Self::StreamUpstream => "stream upstream",
+        }
+    }
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+impl Display for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Set of contexts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Contexts(u16);
+
+impl Contexts {
+    pub const MAIN: Self = Self(1 << Context::Main as u16);
+    pub const EVENTS: Self = Self(1 << Context::Events as u16);
+    pub const HTTP: Self = Self(1 << Context::Http as u16);
+    pub const SERVER: Self = Self(1 << Context::Server as u16);
+    pub const LOCATION: Self = Self(1 << Context::Location as u16);
+    pub const IF_IN_SERVER: Self = Self(1 << Context::IfInServer as u16);
+    pub const IF_IN_LOCATION: Self = Self(1 << Context::IfInLocation as u16);
+    pub const LIMIT_EXCEPT: Self = Self(1 << Context::LimitExcept as u16);
+    pub const UPSTREAM: Self = Self(1 << Context::Upstream as u16);
+    pub const MAIL: Self = Self(1 << Context::Mail as u16);
+    pub const MAIL_SERVER: Self = Self(1 << Context::MailServer as u16);
+    pub const STREAM: Self = Self(1 << Context::Stream as u16);
+    pub const STREAM_SERVER: Self = Self(1 << Context::StreamServer as u16);
+    pub const STREAM_UPSTREAM: Self = Self(1 << Context::StreamUpstream as u16);
+    pub const ANY: Self = Self((1 << Context::ALL.len()) - 1);
+
+    pub fn contains(&self, context: Context) -> bool {
+        self.0 & context.bit() != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Context> + '_ {
+        Context::ALL.iter().copied().filter(|context| self.contains(*context))
+    }
+}
+
+impl BitOr for Contexts {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl Display for Contexts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self.iter().map(|context| context.as_str()).collect::<Vec<_>>();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// nginx release, as in `1.25.1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl Version {
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self { major, minor, patch }
+    }
+}
+
+impl FromStr for Version {
+    type Err = ();
+    /// `1.25.1`, `1.25` or the `nginx/1.25.1` of `nginx -v`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches("nginx version: ").trim_start_matches("nginx/");
+        let mut parts = s.splitn(3, '.').map(|part| part.parse::<u16>().map_err(|_| ()));
+        let major = parts.next().ok_or(())??;
+        let minor = parts.next().ok_or(())??;
+        let patch = parts.next().transpose()?.unwrap_or(0);
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Release a directive was made obsolete in, and what replaces it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Obsolete {
+    pub since: Version,
+    pub use_instead: &'static str,
+}
+
+/// Arguments a directive takes, not counting its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub min: usize,
+    /// `None` when it takes any number of them
+    pub max: Option<usize>,
+    /// What every argument must look like
+    pub kind: ArgKind,
+}
+
+impl Signature {
+    pub fn accepts_count(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: usize| if n == 1 { "argument" } else { "arguments" };
+        match (self.min, self.max) {
+            (0, Some(0)) => write!(f, "takes no arguments"),
+            (min, Some(max)) if min == max => write!(f, "takes {min} {}", plural(min)),
+            (min, Some(max)) => write!(f, "takes {min} to {max} {}", plural(max)),
+            (min, None) => write!(f, "takes at least {min} {}", plural(min)),
+        }
+    }
+}
+
+/// Basic type of an argument, as nginx parses it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    /// Anything, or arguments of different kinds whose checks are left to the typed layer
+    Any,
+    /// `on` or `off`
+    Flag,
+    Number,
+    /// `worker_processes`
+    NumberOrAuto,
+    /// `512`, `8k`, `1m`
+    Size,
+    /// `30`, `500ms`, `1h30m`
+    Time,
+    /// A file or directory, not an URL
+    Path,
+    /// One of these keywords
+    OneOf(&'static [&'static str]),
+}
+
+impl ArgKind {
+    /// Whether an argument is of this kind, nginx compares keywords case-insensitively
+    /// Arguments with variables are evaluated per request and always accepted
+    pub fn accepts(&self, arg: &str) -> bool {
+        if arg.contains('$') {
+            return true
+        }
+        match self {
+            Self::Any => true,
+            Self::Flag => arg.eq_ignore_ascii_case("on") || arg.eq_ignore_ascii_case("off"),
+            Self::Number => arg.parse::<i64>().is_ok(),
+            Self::NumberOrAuto => arg == "auto" || arg.parse::<u64>().is_ok(),
+            Self::Size => Size::from_str(arg).is_ok(),
+            Self::Time => NginxTime::from_str(arg).is_ok(),
+            Self::Path => !arg.is_empty() && !arg.contains("://"),
+            Self::OneOf(keywords) => keywords.iter().any(|k| k.eq_ignore_ascii_case(arg)),
+        }
+    }
+}
+
+impl Display for ArgKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "argument"),
+            Self::Flag => write!(f, "`on` or `off`"),
+            Self::Number => write!(f, "number"),
+            Self::NumberOrAuto => write!(f, "number or `auto`"),
+            Self::Size => write!(f, "size"),
+            Self::Time => write!(f, "time"),
+            Self::Path => write!(f, "path"),
+            Self::OneOf(keywords) => {
+                let keywords = keywords.iter().map(|k| format!("`{k}`")).collect::<Vec<_>>();
+                write!(f, "one of {}", keywords.join(", "))
+            },
+        }
+    }
+}
+
+/// What's wrong with a statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The directive isn't allowed where it's written, `nginx -t` reports "directive is not allowed here"
+    NotAllowedHere { context: Context, allowed: Contexts },
+    /// Wrong number of arguments, "invalid number of arguments"
+    ArgumentCount { expected: Signature, got: usize },
+    /// An argument isn't of the kind the directive takes, "invalid value"
+    InvalidArgument { value: String, expected: ArgKind },
+    /// Not a directive the parser knows, "unknown directive", with the known one it's likely a typo of
+    /// and the module providing either
+    UnknownDirective { suggestion: Option<DirectiveName>, module: Option<&'static str> },
+    /// The target nginx version predates the directive
+    NotYetAvailable { since: Version, target: Version },
+    /// The directive is obsolete in the target nginx version
+    Obsolete { obsolete: Obsolete, target: Version },
+    /// Set again in the same context, "is duplicate", the earlier statement is the diagnostic's `related` one
+    /// `first` is the directive it conflicts with, as `alias` and `root` share their setting
+    Duplicate { first: DirectiveName },
+    /// Location of the same match written twice in a context, "duplicate location"
+    DuplicateLocation,
+    /// Upstream name used by two upstream blocks, "duplicate upstream"
+    DuplicateUpstream,
+    /// Name of another server listening on the same address, nginx warns "conflicting server name"
+    /// and only the server written first (the diagnostic's `related` one) gets the requests
+    ConflictingServerName { name: String, listen: SocketAddr },
+    /// `*_pass` to a name no `upstream` block defines, which isn't a dotted DNS name either,
+    /// nginx fails with "host not found in upstream" unless it resolves as a host
+    UnknownUpstream { name: String },
+    /// Variable nothing defines, nginx -t fails with "unknown variable" and
+    /// variables set only at runtime (`ngx.var`) expand to nothing
+    UndefinedVariable { variable: String },
+    /// A regex that doesn't compile, nginx fails to reload with "pcre2_compile() failed"
+    InvalidRegex { pattern: String, error: String },
+    /// A regex using a PCRE feature the `regex` crate lacks, nginx takes it but the analysis can't follow it
+    UnsupportedRegex { pattern: String, feature: &'static str },
+    /// Syntax nginx deprecated, `deprecated::modernize` rewrites it
+    Deprecated(Deprecation),
+    /// From the opt-in `file_checks::check_files`
+    File(FileProblem),
+    /// From the opt-in `dns::check_dns`
+    Dns(DnsProblem),
+}
+
+impl Problem {
+    /// Identifier of the kind of problem, such as `unknown-directive`, as lint findings have their rule's
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::NotAllowedHere { .. } => "not-allowed-here",
+            Self::ArgumentCount { .. } => "argument-count",
+            Self::InvalidArgument { .. } => "invalid-argument",
+            Self::UnknownDirective { .. } => "unknown-directive",
+            Self::NotYetAvailable { .. } => "not-yet-available",
+            Self::Obsolete { .. } => "obsolete",
+            Self::Duplicate { .. } => "duplicate",
+            Self::DuplicateLocation => "duplicate-location",
+            Self::DuplicateUpstream => "duplicate-upstream",
+            Self::ConflictingServerName { .. } => "conflicting-server-name",
+            Self::UnknownUpstream { .. } => "unknown-upstream",
+            Self::UndefinedVariable { .. } => "undefined-variable",
+            Self::InvalidRegex { .. } => "invalid-regex",
+            Self::UnsupportedRegex { .. } => "unsupported-regex",
+            Self::Deprecated(_) => "deprecated",
+            Self::File(_) => "file",
+            Self::Dns(_) => "dns",
+        }
+    }
+
+    /// Whether nginx refuses the config, it only warns about (or takes) the other problems
+    pub fn is_error(&self) -> bool {
+        match self {
+            Self::ConflictingServerName { .. } | Self::UnsupportedRegex { .. } | Self::Deprecated(_) => false,
+            // nginx looks the name up as a host when it starts, which may well work (`app` in Docker Compose)
+            Self::UnknownUpstream { .. } => false,
+            Self::File(FileProblem::WorldReadable { .. }) => false,
+            Self::File(FileProblem::NoMatch { pattern }) => !pattern.contains(['*', '?', '[']),
+            Self::Dns(problem) => matches!(problem, DnsProblem::Unresolvable { .. }),
+            _ => true,
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAllowedHere { context, allowed } => {
+                write!(f, "not allowed in {context}, only in {allowed}")
+            },
+            Self::ArgumentCount { expected, got } => write!(f, "{expected}, got {got}"),
+            Self::InvalidArgument { value, expected: expected @ (ArgKind::OneOf(_) | ArgKind::Flag) } => {
+                write!(f, "`{value}` isn't {expected}")
+            },
+            Self::InvalidArgument { value, expected } => write!(f, "`{value}` isn't a valid {expected}"),
+            Self::UnknownDirective { suggestion: Some(suggestion), module: Some(module) } => {
+                write!(f, "is unknown, did you mean `{suggestion}` (requires {module})?")
+            },
+            Self::UnknownDirective { suggestion: Some(suggestion), module: None } => {
+                write!(f, "is unknown, did you mean `{suggestion}`?")
+            },
+            Self::UnknownDirective { suggestion: None, module: Some(module) } => {
+                write!(f, "is unknown, it comes from {module}")
+            },
+            Self::UnknownDirective { suggestion: None, module: None } => write!(f, "is unknown"),
+            Self::NotYetAvailable { since, target } => write!(f, "requires nginx {since}, targeting {target}"),
+            Self::Duplicate { first } => write!(f, "is duplicate, `{first}` was specified earlier"),
+            Self::DuplicateLocation => write!(f, "is a duplicate location"),
+            Self::DuplicateUpstream => write!(f, "is a duplicate upstream"),
+            Self::InvalidRegex { pattern, error } => write!(f, "regex `{pattern}` doesn't compile: {error}"),
+            Self::UnsupportedRegex { pattern, feature } => {
+                write!(f, "regex `{pattern}` uses {feature}, which the analysis can't emulate")
+            },
+            Self::Deprecated(deprecation) => write!(f, "{deprecation}"),
+            Self::File(problem) => write!(f, "{problem}"),
+            Self::Dns(problem) => write!(f, "{problem}"),
+            Self::UndefinedVariable { variable } => write!(f, "uses `${variable}`, which nothing defines"),
+            Self::UnknownUpstream { name } => write!(f, "refers to `{name}`, which no upstream defines, it has to resolve as a host name"),
+            Self::ConflictingServerName { name, listen } => {
+                write!(f, "`{name}` conflicts on {listen} with an earlier server, which gets its requests")
+            },
+            Self::Obsolete { obsolete, target } => {
+                write!(f, "is obsolete since nginx {}, targeting {target}, use {}", obsolete.since, obsolete.use_instead)
+            },
+        }
+    }
+}
+
+/// A problem found in a statement or block of the config
+#[derive(Debug, Clone)]
+pub struct Diagnostic<'a> {
+    /// Name of the directive as written
+    pub directive: &'a str,
+    pub statement: &'a Structure<'a>,
+    pub problem: Problem,
+    /// Another statement the problem is about, such as the first of duplicates
+    pub related: Option<&'a Structure<'a>>,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(directive: &'a str, statement: &'a Structure<'a>, problem: Problem) -> Self {
+        Self { directive, statement, problem, related: None }
+    }
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` {}", self.directive, self.problem)
+    }
+}
+
+/// Checks a whole config, as returned by `Structure::parse`, the way `nginx -t` would
+pub fn validate<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = check_unknown(root);
+    diagnostics.extend(check_contexts(root));
+    diagnostics.extend(check_arguments(root));
+    diagnostics.extend(check_duplicates(root));
+    diagnostics.extend(check_server_names(root));
+    diagnostics.extend(check_upstream_references(root));
+    diagnostics.extend(check_variables(root));
+    diagnostics.extend(check_regexes(root));
+    diagnostics
+}
+
+/// `validate` plus the checks against the nginx version the config is deployed to
+pub fn validate_for<'a>(root: &'a Structure<'a>, target: Version) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = validate(root);
+    diagnostics.extend(check_version(root, target));
+    diagnostics.extend(deprecations(root, target));
+    diagnostics
+}
+
+/// Directives `target` doesn't have yet (`http3` before 1.25.0) or made obsolete (`ssl`, `spdy_*`, `http2_push`)
+pub fn check_version<'a>(root: &'a Structure<'a>, target: Version) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    walk(root, Context::Main, &mut |statement, name, directive, _| {
+        let Some(directive) = directive else {
+            return
+        };
+        let problem = match (directive.since(), directive.obsolete()) {
+            (Some(since), _) if target < since => Problem::NotYetAvailable { since, target },
+            (_, Some(obsolete)) if target >= obsolete.since => Problem::Obsolete { obsolete, target },
+            _ => return,
+        };
+        diagnostics.push(Diagnostic::new(name, statement, problem));
+    });
+    diagnostics
+}
+
+/// Directives written in contexts they aren't allowed in, such as `proxy_pass` in `http` or `listen` in a location
+/// Directives of unknown contexts, and the content of blocks that aren't contexts, go unchecked
+pub fn check_contexts<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    walk(root, Context::Main, &mut |statement, name, directive, context| {
+        let Some(directive) = directive else {
+            return
+        };
+        if let Some(allowed) = allowed_contexts(statement, directive) {
+            if !allowed.contains(context) {
+                let problem = Problem::NotAllowedHere { context, allowed };
+                diagnostics.push(Diagnostic::new(name, statement, problem));
+            }
+        }
+    });
+    diagnostics
+}
+
+/// Directives with the wrong number of arguments (`worker_processes 1 2 3;`),
+/// or arguments that aren't what they take (`sendfile yes;`, `client_max_body_size 10mb;`)
+/// Only the first wrong argument of a statement is reported, as `nginx -t` does
+pub fn check_arguments<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    walk(root, Context::Main, &mut |statement, name, directive, _| {
+        let Some(signature) = directive.and_then(|directive| signature(statement, directive)) else {
+            return
+        };
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+        let args = &args[1..];
+        let problem = if !signature.accepts_count(args.len()) {
+            Problem::ArgumentCount { expected: signature, got: args.len() }
+        } else if let Some(arg) = args.iter().map(Token::value).find(|arg| !signature.kind.accepts(arg)) {
+            Problem::InvalidArgument { value: arg.to_string(), expected: signature.kind }
+        } else {
+            return
+        };
+        diagnostics.push(Diagnostic::new(name, statement, problem));
+    });
+    diagnostics
+}
+
+/// Directives set twice in the same context where nginx keeps a single value (`root`, `default_type`, flags, sizes...),
+/// and locations or upstreams defined twice
+/// Directives that legitimately repeat (`add_header`, `listen`, `proxy_set_header`...) aren't reported
+pub fn check_duplicates<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    let mut upstreams = HashMap::new();
+    check_block_duplicates(root, Context::Main, &mut upstreams, &mut diagnostics);
+    diagnostics
+}
+
+fn check_block_duplicates<'a>(
+    block: &'a Structure<'a>,
+    context: Context,
+    upstreams: &mut HashMap<(Context, &'a str), &'a Structure<'a>>,
+    diagnostics: &mut Vec<Diagnostic<'a>>,
+) {
+    let Structure::Block { children, .. } = block else {
+        return
+    };
+    let mut settings = HashMap::<DirectiveName, &'a Structure<'a>>::new();
+    let mut locations = HashMap::<Vec<&'a str>, &'a Structure<'a>>::new();
+    for child in children {
+        let Some(directive) = child.name() else {
+            continue;
+        };
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = child;
+        let name = args[0].value();
+        let first = match directive {
+            // nginx only tells exact and prefix locations apart, regex ones are tried one after another,
+            // and `^~` doesn't make a prefix another one
+            DirectiveName::Location if !matches!(args.get(1).map(Token::value), Some("~" | "~*")) => {
+                let priority = args.get(1).is_some_and(|modifier| modifier.value() == "^~");
+                let pattern = args[1 + usize::from(priority)..].iter().map(Token::value).collect::<Vec<_>>();
+                locations.insert(pattern, child).map(|first| (first, Problem::DuplicateLocation))
+            },
+            DirectiveName::Upstream if args.len() > 1 => upstreams
+                .insert((context, args[1].value()), child)
+                .map(|first| (first, Problem::DuplicateUpstream)),
+            directive if is_single_valued(child, directive) => settings
+                .insert(group(directive), child)
+                .map(|first| (first, Problem::Duplicate { first: first.name().unwrap_or(directive) })),
+            _ => None,
+        };
+        if let Some((first, problem)) = first {
+            diagnostics.push(Diagnostic { related: Some(first), ..Diagnostic::new(name, child, problem) });
+        }
+        if let (Structure::Block { .. }, Some(inner)) = (child, context.enter(directive)) {
+            check_block_duplicates(child, inner, upstreams, diagnostics);
+        }
+    }
+}
+
+/// Whether nginx keeps a single value for the directive and rejects setting it twice in a context
+fn is_single_valued(statement: &Structure, directive: DirectiveName) -> bool {
+    use DirectiveName::*;
+    // flags, numbers, sizes and times are stored in a single slot
+    let typed = signature(statement, directive).is_some_and(|s| !matches!(s.kind, ArgKind::Any | ArgKind::Path));
+    typed
+        || matches!(
+            directive,
+            Root | Alias
+                | DefaultType
+                | TryFiles
+                | ProxyPass
+                | FastcgiPass
+                | UwsgiPass
+                | ScgiPass
+                | GrpcPass
+                | MemcachedPass
+                | ProxyCache
+                | ProxyCacheKey
+                | FastcgiCacheKey
+                | FastcgiIndex
+                | ProxyMethod
+                | ProxyBind
+                | ProxyBuffers
+                | FastcgiBuffers
+                | GzipBuffers
+                | OutputBuffers
+                | ClientBodyTempPath
+                | ProxyTempPath
+                | OpenFileCache
+                | SslCiphers
+                | SslEcdhCurve
+                | SslDhparam
+                | SslSessionCache
+                | SslTrustedCertificate
+                | SslClientCertificate
+                | SslPasswordFile
+                | Charset
+                | Resolver
+                | ServerTokens
+                | AuthBasic
+                | AuthBasicUserFile
+                | AuthRequest
+                | Expires
+                | LimitExcept
+                | Pid
+                | User
+                | WorkerCpuAffinity
+                | WorkingDirectory
+                | LockFile
+        )
+}
+
+/// Names claimed by two servers of an `http` block listening on the same address, as happens
+/// when merging `conf.d` fragments: the later server never gets requests for the name
+/// `.example.com` claims both `example.com` and `*.example.com`, regex names never conflict
+pub fn check_server_names<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    let Structure::Block { children, .. } = root else {
+        return diagnostics
+    };
+    for http in children.iter().filter(|child| child.name() == Some(DirectiveName::Http)) {
+        let servers = ServerSet::from_block(http);
+        let mut claimed = HashMap::<(SocketAddr, String), &'a Structure<'a>>::new();
+        for server in &servers.servers {
+            for (listen, _) in &server.listen {
+                for pattern in &server.names {
+                    for name in claimed_names(pattern) {
+                        let statement = server_name_statement(server.block, pattern);
+                        let Some(first) = claimed.get(&(*listen, name.clone())) else {
+                            claimed.insert((*listen, name), statement);
+                            continue;
+                        };
+                        if std::ptr::eq(*first, statement) {
+                            continue;
+                        }
+                        let problem = Problem::ConflictingServerName { name, listen: *listen };
+                        diagnostics.push(Diagnostic { related: Some(first), ..Diagnostic::new("server_name", statement, problem) });
+                    }
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
+/// `proxy_pass http://backend`, `fastcgi_pass php` and the like whose target is neither an upstream
+/// of the same `http` or `stream` block nor an address: IPs, unix sockets, dotted host names and targets
+/// with a port resolve on their own, targets with variables are resolved per request
+/// Blocks setting a `resolver` are left alone, their names are meant to be looked up
+pub fn check_upstream_references<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    let Structure::Block { children, .. } = root else {
+        return diagnostics
+    };
+    for block in children {
+        let context = match block.name() {
+            Some(DirectiveName::Http) => Context::Http,
+            Some(DirectiveName::Stream) => Context::Stream,
+            _ => continue,
+        };
+        let Structure::Block { children, .. } = block else {
+            continue;
+        };
+        let mut has_resolver = false;
+        walk(block, context, &mut |_, _, directive, _| has_resolver |= directive == Some(DirectiveName::Resolver));
+        if has_resolver {
+            continue;
+        }
+        let upstreams = children
+            .iter()
+            .filter(|child| child.name() == Some(DirectiveName::Upstream))
+            .filter_map(|child| match child {
+                Structure::Block { args, .. } => args.get(1).map(|name| name.value().to_ascii_lowercase()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        walk(block, context, &mut |statement, name, directive, _| {
+            let Some(host) = directive.and_then(|directive| pass_upstream(statement, directive)) else {
+                return
+            };
+            let resolvable = host.contains('.') || host.contains(':') || host.eq_ignore_ascii_case("localhost");
+            if !resolvable && !upstreams.contains(&host.to_ascii_lowercase()) {
+                diagnostics.push(Diagnostic::new(name, statement, Problem::UnknownUpstream { name: host.to_string() }));
+            }
+        });
+    }
+    diagnostics
+}
+
+/// Uses of variables that are neither built in nor defined anywhere in the config, by `set`, `map`, `geo`,
+/// `split_clients`, `perl_set`, `js_set`, `set_by_lua*`, `auth_request_set`... or as a named regex capture
+/// Regexes, Lua and Perl code aren't looked into, their `$` isn't a variable
+pub fn check_variables<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut defined = HashSet::new();
+    named_captures(root, &mut defined);
+    walk(root, Context::Main, &mut |statement, _, directive, _| {
+        defined.extend(defined_variables(statement, directive).into_iter().map(|v| v.to_ascii_lowercase()));
+    });
+    let is_defined = |variable: &str| {
+        let variable = variable.to_ascii_lowercase();
+        variable.parse::<usize>().is_ok()
+            || defined.contains(&variable)
+            || BUILTIN_VARIABLES.contains(&variable.as_str())
+            || BUILTIN_VARIABLE_PREFIXES.iter().any(|prefix| variable.starts_with(prefix))
+    };
+    let mut diagnostics = Vec::new();
+    walk(root, Context::Main, &mut |statement, name, directive, _| {
+        let mut reported = HashSet::new();
+        for variable in used_variables(statement, directive) {
+            if !is_defined(&variable) && reported.insert(variable.clone()) {
+                let problem = Problem::UndefinedVariable { variable };
+                diagnostics.push(Diagnostic::new(name, statement, problem));
+            }
+        }
+    });
+    diagnostics
+}
+
+/// Regexes of `location ~`, `~` keys of `map`, `rewrite` and `if (... ~ ...)` that the `regex` crate can't compile,
+/// either broken or using PCRE features such as backreferences and lookarounds
+pub fn check_regexes<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    walk(root, Context::Main, &mut |statement, name, directive, _| {
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+        let values = args.iter().skip(1).map(Token::value).collect::<Vec<_>>();
+        let mut check = |statement: &'a Structure<'a>, pattern: &str| {
+            if let Some(problem) = regex_problem(pattern) {
+                diagnostics.push(Diagnostic::new(name, statement, problem));
+            }
+        };
+        match (directive, statement) {
+            (Some(DirectiveName::Location), Structure::Block { .. }) => {
+                let pattern = match values.as_slice() {
+                    ["~" | "~*", pattern] => Some(*pattern),
+                    [path] => path.strip_prefix("~*").or_else(|| path.strip_prefix('~')),
+                    _ => None,
+                };
+                pattern.into_iter().for_each(|pattern| check(statement, pattern));
+            },
+            (Some(DirectiveName::Map), Structure::Block { children, .. }) => {
+                for entry in children {
+                    let (Structure::Statement { args } | Structure::Block { args, .. }) = entry;
+                    let key = args.first().map(Token::value).unwrap_or_default();
+                    if let Some(pattern) = key.strip_prefix("~*").or_else(|| key.strip_prefix('~')) {
+                        check(entry, pattern);
+                    }
+                }
+            },
+            (Some(DirectiveName::Rewrite), _) => values.first().into_iter().for_each(|pattern| check(statement, pattern)),
+            (Some(DirectiveName::If), _) => {
+                let Some(condition @ Token::BracedString(_)) = args.get(1) else {
+                    return
+                };
+                if let [_, operator, pattern] = condition_words(condition.value()).unwrap_or_default().as_slice() {
+                    if matches!(operator.as_str(), "~" | "~*" | "!~" | "!~*") {
+                        check(statement, pattern);
+                    }
+                }
+            },
+            _ => {},
+        }
+    });
+    diagnostics
+}
+
+fn regex_problem(pattern: &str) -> Option<Problem> {
+    if let Some(feature) = pcre_feature(pattern) {
+        return Some(Problem::UnsupportedRegex { pattern: pattern.to_string(), feature })
+    }
+    let error = regex::Regex::new(pattern).err()?;
+    // syntax errors show the pattern with a caret, then the actual error on the last line
+    let error = error.to_string();
+    let error = error.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    let error = error.trim().strip_prefix("error: ").unwrap_or(error.trim()).to_string();
+    Some(Problem::InvalidRegex { pattern: pattern.to_string(), error })
+}
+
+/// First PCRE-only feature of a pattern, out of character classes
+fn pcre_feature(pattern: &str) -> Option<&'static str> {
+    let bytes = pattern.as_bytes();
+    let mut in_class = false;
+    let mut quantified = false;
+    let mut at = 0;
+    while at < bytes.len() {
+        let rest = &pattern[at..];
+        let mut quantifier = false;
+        match bytes[at] {
+            b'\\' => {
+                match bytes.get(at + 1) {
+                    Some(b'1'..=b'9' | b'g' | b'k') if !in_class => return Some("backreferences"),
+                    Some(b'K') if !in_class => return Some("`\\K`"),
+                    Some(b'Z') if !in_class => return Some("`\\Z`"),
+                    _ => {},
+                }
+                at += 2;
+                quantified = false;
+                continue;
+            },
+            b'[' if !in_class => in_class = true,
+            b']' => in_class = false,
+            _ if in_class => {},
+            b'(' => {
+                let group = rest.get(1..).unwrap_or_default();
+                if ["?=", "?!", "?<=", "?<!"].iter().any(|p| group.starts_with(p)) {
+                    return Some("lookarounds")
+                }
+                if group.starts_with("?>") {
+                    return Some("atomic groups")
+                }
+                if group.starts_with("?R") || group.starts_with("?&") || group.starts_with("?P>")
+                    || group.strip_prefix('?').is_some_and(|g| g.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-'))
+                {
+                    return Some("recursion")
+                }
+                if group.starts_with("?|") {
+                    return Some("branch reset groups")
+                }
+                if group.starts_with("?'") {
+                    return Some("`(?'name')` groups")
+                }
+                if group.starts_with("?#") {
+                    return Some("comments")
+                }
+            },
+            b'+' if quantified => return Some("possessive quantifiers"),
+            b'*' | b'+' | b'?' | b'}' => quantifier = true,
+            _ => {},
+        }
+        quantified = quantifier;
+        at += 1;
+    }
+    None
+}
+
+/// Variables a directive defines, without the `$`
+fn defined_variables<'a>(statement: &'a Structure<'a>, directive: Option<DirectiveName>) -> Vec<&'a str> {
+    use DirectiveName::*;
+    let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+    let arg = |index: usize| args.get(index).map(Token::value).and_then(|a| a.strip_prefix('$'));
+    let Some(directive) = directive else {
+        return Vec::new()
+    };
+    match directive {
+        Set | PerlSet | JsSet | JsVar | AuthRequestSet | AuthJwtClaimSet | AuthJwtHeaderSet | SetByLua | SetByLuaBlock
+        | SetByLuaFile => arg(1).into_iter().collect(),
+        Map | SplitClients | Keyval => arg(2).into_iter().collect(),
+        // `geo $variable {}` or `geo $source $variable {}`
+        Geo => arg(args.len() - 1).into_iter().collect(),
+        // `geoip2 db { $variable path...; }`
+        Geoip2 => match statement {
+            Structure::Block { children, .. } => children
+                .iter()
+                .filter_map(|child| match child {
+                    Structure::Statement { args } => args.first()?.value().strip_prefix('$'),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Variables a directive reads, without the `$`
+fn used_variables(statement: &Structure, directive: Option<DirectiveName>) -> Vec<String> {
+    use DirectiveName::*;
+    let (Structure::Statement { args } | Structure::Block { args, .. }) = statement;
+    let Some(directive) = directive else {
+        return Vec::new()
+    };
+    if directive.as_str().contains("_by_lua") {
+        return Vec::new()
+    }
+    let range = match directive {
+        Location | ServerName | Perl | PerlSet | Geoip2 => return Vec::new(),
+        If => {
+            let Ok(condition) = Condition::try_from(&args[1..]) else {
+                return Vec::new()
+            };
+            return match condition {
+                Condition::Variable(variable) | Condition::Match { variable, .. } => vec![variable],
+                Condition::Compare { variable, value, .. } => {
+                    let mut used = vec![variable];
+                    used.extend(variables_in(&value).into_iter().map(String::from));
+                    used
+                },
+                Condition::File { path, .. } => variables_in(&path).into_iter().map(String::from).collect(),
+            }
+        },
+        // the regex, or the defined variable itself
+        Rewrite | Set | JsSet | JsVar | AuthRequestSet | AuthJwtClaimSet | AuthJwtHeaderSet => 2..args.len(),
+        // the source
+        Map | SplitClients | Keyval | Geo if args.len() > 2 => 1..2,
+        Map | SplitClients | Keyval | Geo => return Vec::new(),
+        _ => 1..args.len(),
+    };
+    let mut used = args
+        .get(range)
+        .unwrap_or_default()
+        .iter()
+        .filter(|arg| !matches!(arg, Token::LuaBlock(_)) && !arg.value().starts_with('~'))
+        .flat_map(|arg| variables_in(arg.value()))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    if let (Map, Structure::Block { children, .. }) = (directive, statement) {
+        for child in children {
+            if let Structure::Statement { args } = child {
+                used.extend(args.get(1).into_iter().flat_map(|value| variables_in(value.value())).map(String::from));
+            }
+        }
+    }
+    used
+}
+
+/// Names of the `$variable` and `${variable}` in an argument
+fn variables_in(arg: &str) -> Vec<&str> {
+    let mut variables = Vec::new();
+    let mut rest = arg;
+    while let Some(at) = rest.find('$') {
+        rest = &rest[at + 1..];
+        let (name, after) = match rest.strip_prefix('{') {
+            Some(braced) => braced.split_once('}').unwrap_or((braced, "")),
+            None => {
+                let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+                rest.split_at(end)
+            },
+        };
+        if !name.is_empty() {
+            variables.push(name);
+        }
+        rest = after;
+    }
+    variables
+}
+
+/// Names of the `(?<name>...)` and `(?P<name>...)` groups of the config's regexes, they become variables
+fn named_captures(block: &Structure, names: &mut HashSet<String>) {
+    let (Structure::Statement { args } | Structure::Block { args, .. }) = block;
+    for arg in args {
+        let mut rest = arg.value();
+        while let Some(at) = rest.find("(?") {
+            rest = &rest[at + 2..];
+            let group = rest.strip_prefix("P<").or_else(|| rest.strip_prefix('<')).or_else(|| rest.strip_prefix('\''));
+            if let Some(name) = group.and_then(|g| g.split_once(['>', '\'']).map(|(name, _)| name)) {
+                if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    names.insert(name.to_ascii_lowercase());
+                }
+            }
+        }
+    }
+    if let Structure::Block { children, .. } = block {
+        for child in children {
+            named_captures(child, names);
+        }
+    }
+}
+
+/// Host of a `*_pass` target, without scheme, port or URI, `None` for unix sockets and targets with variables
+/// An IPv6 address keeps its colons
+pub(crate) fn pass_host<'a>(statement: &'a Structure<'a>, directive: DirectiveName) -> Option<&'a str> {
+    pass_address(statement, directive).map(|(host, _)| host)
+}
+
+/// Upstream block a `*_pass` target can refer to, targets with a port are host names or addresses
+pub(crate) fn pass_upstream<'a>(statement: &'a Structure<'a>, directive: DirectiveName) -> Option<&'a str> {
+    match pass_address(statement, directive)? {
+        (host, None) => Some(host),
+        (_, Some(_)) => None,
+    }
+}
+
+/// Host and port of a `*_pass` target
+fn pass_address<'a>(statement: &'a Structure<'a>, directive: DirectiveName) -> Option<(&'a str, Option<&'a str>)> {
+    use DirectiveName::*;
+    if !matches!(directive, ProxyPass | FastcgiPass | UwsgiPass | ScgiPass | GrpcPass | MemcachedPass) {
+        return None
+    }
+    let Structure::Statement { args } = statement else {
+        return None
+    };
+    let target = args.get(1)?.value();
+    if target.contains('$') || target.starts_with("unix:") {
+        return None
+    }
+    let target = target.split_once("://").map_or(target, |(_, rest)| rest);
+    if target.starts_with("unix:") {
+        return None
+    }
+    let authority = target.split('/').next()?;
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(ipv6) => {
+            let (host, rest) = ipv6.split_once(']')?;
+            (host, rest.strip_prefix(':'))
+        },
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    (!host.is_empty()).then_some((host, port))
+}
+
+/// Names nginx registers a server under for a `server_name` pattern
+fn claimed_names(pattern: &ServerNamePattern) -> Vec<String> {
+    match pattern {
+        ServerNamePattern::Exact(name) => vec![name.clone()],
+        ServerNamePattern::LeadingWildcard { suffix, include_bare: true } => vec![format!("*{suffix}"), suffix[1..].to_string()],
+        ServerNamePattern::LeadingWildcard { suffix, .. } => vec![format!("*{suffix}")],
+        ServerNamePattern::TrailingWildcard(prefix) => vec![format!("{prefix}*")],
+        ServerNamePattern::Regex(_) => Vec::new(),
+    }
+}
+
+/// The `server_name` statement of a server giving a pattern, the server block itself when none does
+/// (the implicit `""` name)
+fn server_name_statement<'a>(server: &'a Structure<'a>, pattern: &ServerNamePattern) -> &'a Structure<'a> {
+    let Structure::Block { children, .. } = server else {
+        return server
+    };
+    let gives_pattern = |args: &[Token]| {
+        args.iter().skip(1).any(|arg| {
+            ServerNamePattern::from_str(arg.value()).is_ok_and(|p| claimed_names(&p) == claimed_names(pattern))
+        })
+    };
+    children
+        .iter()
+        .find(|child| matches!(child, Structure::Statement { args } if child.name() == Some(DirectiveName::ServerName) && gives_pattern(args)))
+        .unwrap_or(server)
+}
+
+/// Directives the parser doesn't know, with the closest known one when it looks like a typo (`ssl_cerificate`)
+/// The content of unknown blocks goes unchecked, it may not be made of directives
+pub fn check_unknown<'a>(root: &'a Structure<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    walk(root, Context::Main, &mut |statement, name, directive, _| {
+        if directive.is_some() {
+            return
+        }
+        let problem = match third_party_module(name) {
+            Some(module) => Problem::UnknownDirective { suggestion: None, module: Some(module) },
+            None => {
+                let suggestion = closest_directive(name);
+                Problem::UnknownDirective { suggestion, module: suggestion.and_then(|s| s.module()) }
+            },
+        };
+        diagnostics.push(Diagnostic::new(name, statement, problem));
+    });
+    diagnostics
+}
+
+/// Known directive within a few typos of `name`, the closest one
+fn closest_directive(name: &str) -> Option<DirectiveName> {
+    let allowed = (name.len() / 4).max(1);
+    DirectiveName::ALL
+        .iter()
+        .map(|directive| (edit_distance(name, directive.as_str()), *directive))
+        .filter(|(distance, _)| *distance <= allowed)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, directive)| directive)
+}
+
+/// Edits (insertions, deletions, substitutions and swaps of adjacent characters) turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substitution.min(rows[i - 1][j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Visits the directives of `block` and the contexts nested in it, with their name as written,
+/// the known directive when it's one and the context each sits in
+pub(crate) fn walk<'a>(
+    block: &'a Structure<'a>,
+    context: Context,
+    visit: &mut impl FnMut(&'a Structure<'a>, &'a str, Option<DirectiveName>, Context),
+) {
+    let Structure::Block { children, .. } = block else {
+        return
+    };
+    for child in children {
+        let (Structure::Statement { args } | Structure::Block { args, .. }) = child;
+        let Some(name) = args.first().map(Token::value) else {
+            continue;
+        };
+        let directive = child.name();
+        visit(child, name, directive, context);
+        if let (Structure::Block { .. }, Some(inner)) = (child, directive.and_then(|d| context.enter(d))) {
+            walk(child, inner, visit);
+        }
+    }
+}
+
+/// `server` opens a context in `http`, `mail` and `stream`, but is a statement listing a peer in upstreams
+fn allowed_contexts(statement: &Structure, directive: DirectiveName) -> Option<Contexts> {
+    match (directive, statement) {
+        (DirectiveName::Server, Structure::Block { .. }) => Some(Contexts::HTTP | Contexts::MAIL | Contexts::STREAM),
+        (DirectiveName::Server, Structure::Statement { .. }) => Some(Contexts::UPSTREAM | Contexts::STREAM_UPSTREAM),
+        _ => directive.contexts(),
+    }
+}
+
+fn signature(statement: &Structure, directive: DirectiveName) -> Option<Signature> {
+    match (directive, statement) {
+        (DirectiveName::Server, Structure::Block { .. }) => Some(Signature { min: 0, max: Some(0), kind: ArgKind::Any }),
+        (DirectiveName::Server, Structure::Statement { .. }) => Some(Signature { min: 1, max: None, kind: ArgKind::Any }),
+        _ => directive.signature(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Line and message of each diagnostic of the check
+    fn problems(check: for<'a> fn(&'a Structure<'a>) -> Vec<Diagnostic<'a>>, source: &str) -> Vec<(usize, String)> {
+        let root = Structure::parse(source).unwrap();
+        check(&root).iter().map(|d| (d.statement.position(source).unwrap().line, format!("`{}` {}", d.directive, d.problem))).collect()
+    }
+
+    fn unknown_upstreams(source: &str) -> Vec<String> {
+        let root = Structure::parse(source).unwrap();
+        check_upstream_references(&root).iter().map(|d| d.problem.to_string()).collect()
+    }
+
+    #[test]
+    fn pass_targets_with_a_port_are_hosts() {
+        let http = "http { upstream backend { server 127.0.0.1; } server { location / { \
+                    proxy_pass http://app:3000; fastcgi_pass php-fpm:9000; proxy_pass http://backend/x; proxy_pass http://$up; \
+                    proxy_pass http://typo; } } }";
+        assert_eq!(unknown_upstreams(http), ["refers to `typo`, which no upstream defines, it has to resolve as a host name"]);
+        assert!(!Problem::UnknownUpstream { name: "typo".to_string() }.is_error());
+    }
+
+    #[test]
+    fn if_regexes_are_checked_whole() {
+        let problems = |source: &str| {
+            let root = Structure::parse(source).unwrap();
+            check_regexes(&root).iter().map(|d| d.problem.to_string()).collect::<Vec<_>>()
+        };
+        assert!(problems("if ($uri ~ ^/(a|b)(/.*)?$) { return 404; }").is_empty());
+        assert_eq!(problems("if ($uri ~* ^/(a|[b)$) { return 404; }").len(), 1);
+        assert_eq!(problems(r"if ($uri ~ ^/(\w+)/\1$) { return 404; }").len(), 1);
+    }
+
+    #[test]
+    fn names_are_left_to_a_resolver() {
+        assert!(unknown_upstreams("http { resolver 127.0.0.11; server { location / { proxy_pass http://app; } } }").is_empty());
+    }
+
+    #[test]
+    fn priority_prefixes_duplicate_plain_ones() {
+        let source = "server {\n\
+            location /a { }\n\
+            location ^~ /a { }\n\
+            location = /a { }\n\
+            location ~ /a { }\n\
+            location ~ /a { }\n\
+            }";
+        let root = Structure::parse(source).unwrap();
+        let lines = check_duplicates(&root)
+            .iter()
+            .filter(|d| d.problem == Problem::DuplicateLocation)
+            .map(|d| (d.statement.position(source).unwrap().line, d.related.and_then(|first| first.position(source)).unwrap().line))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [(3, 2)]);
+    }
+
+    #[test]
+    fn directives_in_the_wrong_context() {
+        let source = "\
+proxy_pass http://app;
+http {
+    listen 80;
+    proxy_set_header Host $host;
+    server {
+        listen 80;
+        location / { proxy_pass http://app; if ($args) { proxy_pass http://other; } }
+        location /b { worker_processes 2; }
+    }
+}
+stream { server { listen 5432; proxy_pass db:5432; } }
+events { worker_connections 1024; }
+";
+        let problems = problems(check_contexts, source);
+        assert_eq!(problems.iter().map(|(line, _)| *line).collect::<Vec<_>>(), [1, 3, 8]);
+        assert_eq!(problems[1].1, "`listen` not allowed in http, only in server, mail server, stream server");
+        assert_eq!(DirectiveName::Listen.contexts(), Some(Contexts::SERVER | Contexts::MAIL_SERVER | Contexts::STREAM_SERVER));
+        assert_eq!(Context::Stream.enter(DirectiveName::Server), Some(Context::StreamServer));
+        assert_eq!(Context::Server.enter(DirectiveName::If), Some(Context::IfInServer));
+        assert_eq!(Context::Http.enter(DirectiveName::Map), None);
+    }
+
+    #[test]
+    fn argument_counts_and_kinds() {
+        let source = "\
+worker_processes 1 2 3;
+worker_processes auto;
+http {
+    sendfile maybe;
+    gzip on;
+    client_max_body_size 10q;
+    keepalive_timeout 75s 60s;
+    proxy_read_timeout $timeout;
+    ssl_protocols TLSv1.2 TLSv1.4;
+    autoindex;
+}
+";
+        let problems = problems(check_arguments, source);
+        let lines = problems.iter().map(|(line, _)| *line).collect::<Vec<_>>();
+        assert_eq!(lines, [1, 4, 6, 9, 10]);
+        assert_eq!(problems[0].1, "`worker_processes` takes 1 argument, got 3");
+        assert!(ArgKind::Time.accepts("1h30m") && !ArgKind::Time.accepts("soon") && ArgKind::Size.accepts("$size"));
+        assert!(ArgKind::Flag.accepts("OFF") && !ArgKind::Path.accepts("http://app") && ArgKind::NumberOrAuto.accepts("auto"));
+    }
+
+    #[test]
+    fn unknown_directives_suggest_known_ones() {
+        let source = "\
+http {
+    ssl_cerificate /etc/ssl/site.pem;
+    brotly on;
+    more_set_headers \"Server: app\";
+    completely_made_up_directive 1;
+    gzip on;
+    unknown_block { anything goes here; }
+}
+";
+        let root = Structure::parse(source).unwrap();
+        let unknown = check_unknown(&root)
+            .into_iter()
+            .map(|d| match d.problem {
+                Problem::UnknownDirective { suggestion, module } => (d.directive, suggestion, module),
+                problem => panic!("{problem}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unknown,
+            [
+                ("ssl_cerificate", Some(DirectiveName::SslCertificate), None),
+                ("brotly", Some(DirectiveName::Brotli), Some("ngx_brotli")),
+                ("more_set_headers", None, Some("headers-more-nginx-module")),
+                ("completely_made_up_directive", None, None),
+                ("unknown_block", None, None),
+            ]
+        );
+        assert_eq!(edit_distance("ssl_cerificate", "ssl_certificate"), 1);
+        assert_eq!(edit_distance("gizp", "gzip"), 1);
+    }
+
+    #[test]
+    fn directives_of_the_target_version() {
+        let source = "http {\n    ssl on;\n    http3 on;\n    http2 on;\n    http2_push_preload on;\n    gzip on;\n}\n";
+        let root = Structure::parse(source).unwrap();
+        let at = |version: &str| {
+            let target = Version::from_str(version).unwrap();
+            check_version(&root, target).iter().map(|d| (d.directive, d.problem.id())).collect::<Vec<_>>()
+        };
+        assert_eq!(at("1.24.0"), [("ssl", "obsolete"), ("http3", "not-yet-available"), ("http2", "not-yet-available")]);
+        assert_eq!(at("nginx/1.25.1"), [("ssl", "obsolete"), ("http2_push_preload", "obsolete")]);
+        assert_eq!(at("1.14"), [("http3", "not-yet-available"), ("http2", "not-yet-available")]);
+        assert_eq!(Version::from_str("nginx version: nginx/1.25.1"), Ok(Version::new(1, 25, 1)));
+        assert!(Version::from_str("1").is_err() && Version::from_str("1.x").is_err());
+    }
+
+    #[test]
+    fn single_valued_directives_set_twice() {
+        let source = "\
+http {
+    upstream app { server 10.0.0.1; }
+    upstream app { server 10.0.0.2; }
+    server {
+        listen 80;
+        listen 443 ssl;
+        add_header X-A a;
+        add_header X-B b;
+        location / {
+            root /srv/a;
+            alias /srv/b/;
+            default_type text/plain;
+            default_type text/html;
+            gzip on;
+            gzip off;
+        }
+        location /other { root /srv/c; }
+    }
+}
+";
+        let root = Structure::parse(source).unwrap();
+        let duplicates = check_duplicates(&root)
+            .iter()
+            .map(|d| (d.statement.position(source).unwrap().line, d.related.and_then(|first| first.position(source)).unwrap().line, d.problem.to_string()))
+            .collect::<Vec<_>>();
+        let expected = [
+            (3, 2, "is a duplicate upstream"),
+            (11, 10, "is duplicate, `root` was specified earlier"),
+            (13, 12, "is duplicate, `default_type` was specified earlier"),
+            (15, 14, "is duplicate, `gzip` was specified earlier"),
+        ];
+        assert_eq!(duplicates, expected.map(|(line, first, problem)| (line, first, problem.to_string())));
+    }
+
+    #[test]
+    fn server_names_claimed_twice_on_a_socket() {
+        let source = "\
+http {
+    server { listen 80; server_name example.com www.example.com; }
+    server { listen 80; server_name www.example.com; }
+    server { listen 8080; server_name example.com; }
+    server { listen 80; server_name .example.com; }
+    server { listen 80; server_name ~^(?<sub>.+)\\.example\\.com$; }
+}
+";
+        let root = Structure::parse(source).unwrap();
+        let conflicts = check_server_names(&root)
+            .iter()
+            .map(|d| (d.statement.position(source).unwrap().line, d.related.and_then(|first| first.position(source)).unwrap().line, d.problem.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(conflicts.iter().map(|(line, first, _)| (*line, *first)).collect::<Vec<_>>(), [(3, 2), (5, 2)]);
+        assert_eq!(conflicts[0].2, "`www.example.com` conflicts on 0.0.0.0:80 with an earlier server, which gets its requests");
+    }
+
+    #[test]
+    fn stream_passes_refer_to_stream_upstreams() {
+        let stream = "stream { upstream db { server 10.0.0.1:5432; } server { listen 5432; proxy_pass db; } \
+                      server { listen 5433; proxy_pass dbs; } server { listen 5434; proxy_pass unix:/run/db.sock; } }\n\
+                      http { server { location / { proxy_pass http://db; } } }";
+        assert_eq!(
+            unknown_upstreams(stream),
+            ["refers to `dbs`, which no upstream defines, it has to resolve as a host name", "refers to `db`, which no upstream defines, it has to resolve as a host name"]
+        );
+    }
+
+    #[test]
+    fn variables_nothing_defines() {
+        let source = r#"
+http {
+    map $http_upgrade $connection_upgrade { default upgrade; "" close; }
+    geo $trusted { default 0; 10.0.0.0/8 1; }
+    split_clients $remote_addr $variant { 50% a; * b; }
+    server {
+        set $backend app;
+        location ~ ^/users/(?<user>[^/]+)/(\d+)$ {
+            add_header X-User $user;
+            add_header X-Id $2;
+            proxy_set_header Connection $connection_upgrade;
+            proxy_set_header X-Variant "$variant-$trusted";
+            proxy_pass http://$backend$request_uri$is_args$args;
+            add_header X-Agent $http_user_agent;
+            add_header X-Typo $reqest_uri;
+            return 200 "${undefined}x $undefined";
+        }
+    }
+}
+"#;
+        let undefined = problems(check_variables, source);
+        assert_eq!(undefined, [(15, "`add_header` uses `$reqest_uri`, which nothing defines".to_string()), (16, "`return` uses `$undefined`, which nothing defines".to_string())]);
+    }
+
+    #[test]
+    fn regexes_of_locations_maps_and_rewrites() {
+        let source = r#"
+http {
+    map $uri $legacy { default 0; ~^/old/(?=x) 1; ~*^/ok/ 1; /plain[ 1; }
+    server {
+        location ~ ^/(a|b$ { }
+        location ~*\.(png|jpe?g)$ { }
+        location /literal[ { }
+        rewrite ^/(\w+)/\1$ /dup last;
+        rewrite ^/(.*)$ /index.php?q=$1 last;
+        rewrite "^/a{2,1}$" /b;
+    }
+}
+"#;
+        let root = Structure::parse(source).unwrap();
+        let problems = check_regexes(&root)
+            .iter()
+            .map(|d| {
+                let line = d.statement.position(source).unwrap().line;
+                match &d.problem {
+                    Problem::UnsupportedRegex { pattern, feature } => (line, pattern.clone(), feature.to_string()),
+                    Problem::InvalidRegex { pattern, .. } => (line, pattern.clone(), "invalid".to_string()),
+                    problem => panic!("{problem}"),
+                }
+            })
+            .collect::<Vec<_>>();
+        let expected = [
+            (3, r"^/old/(?=x)", "lookarounds"),
+            (5, r"^/(a|b$", "invalid"),
+            (8, r"^/(\w+)/\1$", "backreferences"),
+            (10, r"^/a{2,1}$", "invalid"),
+        ];
+        assert_eq!(problems, expected.map(|(line, pattern, feature)| (line, pattern.to_string(), feature.to_string())));
+        assert!(pcre_feature(r"[\1]").is_none() && pcre_feature(r"^a++$").is_some());
+    }
+}